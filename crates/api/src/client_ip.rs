@@ -0,0 +1,100 @@
+use axum::http::HeaderMap;
+use std::net::IpAddr;
+
+use app_core::config::ServerConfig;
+
+const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Proxies whose `X-Forwarded-For` is believed, from `server.tls_policy.trusted_proxies`,
+/// the same peers trusted to report the TLS version
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    proxies: Vec<IpAddr>,
+}
+
+impl TrustedProxies {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        let proxies = config.tls_policy.as_ref().map(|policy| policy.trusted_proxies.clone()).unwrap_or_default();
+        Self { proxies }
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.proxies.contains(ip)
+    }
+
+    /// Address of the client behind `peer`. Requests from a trusted proxy are attributed to
+    /// the nearest `X-Forwarded-For` hop that isn't itself a trusted proxy; anything further
+    /// left was written by the client and can't be believed. Other peers are the client.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(&peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        let hops = headers
+            .get_all(FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+        for hop in hops.into_iter().rev() {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.contains(&ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn proxies(proxies: &[&str]) -> TrustedProxies {
+        TrustedProxies { proxies: proxies.iter().map(|ip| ip.parse().unwrap()).collect() }
+    }
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(FORWARDED_FOR, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn untrusted_peers_are_the_client_whatever_they_forward() {
+        let trusted = proxies(&["127.0.0.1"]);
+        assert_eq!(trusted.client_ip(ip("203.0.113.9"), &forwarded(&["198.51.100.1"])), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn trusted_proxies_are_resolved_to_the_forwarded_client() {
+        let trusted = proxies(&["127.0.0.1"]);
+        assert_eq!(trusted.client_ip(ip("127.0.0.1"), &forwarded(&["198.51.100.1"])), ip("198.51.100.1"));
+        // Without the header the proxy is all there is to go on
+        assert_eq!(trusted.client_ip(ip("127.0.0.1"), &HeaderMap::new()), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn spoofed_hops_left_of_the_first_untrusted_one_are_ignored() {
+        let trusted = proxies(&["127.0.0.1", "10.0.0.2"]);
+        let headers = forwarded(&["6.6.6.6, 198.51.100.1", "10.0.0.2"]);
+        assert_eq!(trusted.client_ip(ip("127.0.0.1"), &headers), ip("198.51.100.1"));
+    }
+
+    #[test]
+    fn unparseable_hops_stop_the_walk() {
+        let trusted = proxies(&["127.0.0.1"]);
+        assert_eq!(trusted.client_ip(ip("127.0.0.1"), &forwarded(&["198.51.100.1, junk"])), ip("127.0.0.1"));
+    }
+}
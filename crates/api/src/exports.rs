@@ -0,0 +1,265 @@
+use futures_util::TryStreamExt;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::reports::{audit_csv_stream, audit_report_file, audit_summary_pdf};
+use app_core::config::ServerConfig;
+use app_core::enterprise::{AuditReportFormat, ExportJob, ExportRequest, ExportStatus};
+use app_core::error::{ApiError, Result};
+use database::ExportJobs;
+use monitoring::AuditService;
+
+/// Suffix of files still being written; renamed away once complete
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Context the download link key is derived under, so the configured secret can't be
+/// used to forge anything else
+const LINK_KEY_CONTEXT: &[u8] = b"export-download-links";
+
+/// Exports waiting for, or produced by, the `process_exports` background job.
+///
+/// Jobs are kept in the database and files in `export_dir`, which every instance must
+/// share, so any instance can generate an export or serve it. Download links are signed
+/// with a key derived from `export_url_secret`, so a link issued by one instance works on
+/// the others and across restarts, and can't be forged or used after it expires.
+#[derive(Clone)]
+pub struct ExportQueue {
+    dir: PathBuf,
+    key: Arc<[u8; 32]>,
+    url_ttl: Duration,
+    retention: Duration,
+    jobs: ExportJobs,
+}
+
+impl ExportQueue {
+    pub async fn from_config(config: &ServerConfig, jobs: ExportJobs) -> Result<Self> {
+        let secret = config
+            .export_url_secret
+            .as_deref()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("server.export_url_secret must be set to sign export download links"))?;
+
+        let dir = config
+            .export_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::temp_dir().join("api-exports"));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create export directory {}: {}", dir.display(), e))?;
+        remove_orphaned_files(&dir, &jobs).await;
+
+        Ok(Self {
+            dir,
+            key: Arc::new(link_key(secret)),
+            url_ttl: Duration::seconds(config.export_url_ttl as i64),
+            retention: Duration::seconds(config.export_retention as i64),
+            jobs,
+        })
+    }
+
+    pub async fn enqueue(&self, requested_by: Uuid, request: ExportRequest) -> Result<ExportJob> {
+        let (filename, content_type) = match &request {
+            ExportRequest::AuditReport(query) => audit_report_file(query),
+        };
+
+        let job = ExportJob {
+            id: Uuid::new_v4(),
+            requested_by,
+            request,
+            status: ExportStatus::Pending,
+            created_at: OffsetDateTime::now_utc(),
+            completed_at: None,
+            expires_at: None,
+            error: None,
+            filename,
+            content_type: content_type.to_string(),
+        };
+        self.jobs.insert(&job).await?;
+        Ok(job)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<ExportJob>> {
+        self.jobs.get(id).await
+    }
+
+    /// Where a job's file is written, named after the job with the download's extension
+    pub fn path(&self, job: &ExportJob) -> PathBuf {
+        let extension = job.filename.rsplit('.').next().unwrap_or("bin");
+        self.dir.join(format!("{}.{}", job.id, extension))
+    }
+
+    async fn finish(&self, id: Uuid, outcome: &Result<()>) -> Result<()> {
+        // Failed jobs stay visible for as long as a file would have
+        let expires_at = OffsetDateTime::now_utc() + self.retention;
+        let error = outcome.as_ref().err().map(|e| e.to_string());
+        self.jobs.finish(id, error.as_deref(), expires_at).await
+    }
+
+    /// Fail jobs left running by an instance that died, then forget jobs past their
+    /// retention and delete their files, returning how many were deleted
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let now = OffsetDateTime::now_utc();
+        let abandoned = self.jobs.fail_abandoned(now - self.retention, now + self.retention).await?;
+        if abandoned > 0 {
+            warn!("Failed {} export(s) abandoned while running", abandoned);
+        }
+
+        let expired = self.jobs.delete_expired(now).await?;
+        for job in &expired {
+            let path = self.path(job);
+            for path in [partial_path(&path), path] {
+                if let Err(e) = tokio::fs::remove_file(&path).await {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        warn!("Failed to delete expired export {}: {}", job.id, e);
+                    }
+                }
+            }
+        }
+        Ok(expired.len())
+    }
+
+    /// Link to download a ready export, valid for `url_ttl` or until the file expires
+    pub fn download_url(&self, public_url: &str, job: &ExportJob) -> Option<String> {
+        let file_expires_at = job.expires_at.filter(|_| job.status == ExportStatus::Ready)?;
+        let expires = (OffsetDateTime::now_utc() + self.url_ttl).min(file_expires_at).unix_timestamp();
+
+        Some(format!(
+            "{}/api/v1/exports/{}/download?expires={}&signature={}",
+            public_url.trim_end_matches('/'),
+            job.id,
+            expires,
+            hex::encode(self.mac(job.id, expires).finalize().into_bytes())
+        ))
+    }
+
+    /// The ready export a download link points at, if the link is authentic and unexpired
+    pub async fn verify_download(&self, id: Uuid, expires: i64, signature: &str) -> Result<ExportJob> {
+        let invalid = || ApiError::Forbidden("Invalid or expired download link".to_string());
+
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        self.mac(id, expires).verify_slice(&signature).map_err(|_| invalid())?;
+        if expires < OffsetDateTime::now_utc().unix_timestamp() {
+            return Err(invalid());
+        }
+
+        self.get(id)
+            .await?
+            .filter(|job| job.status == ExportStatus::Ready)
+            .ok_or_else(|| ApiError::NotFound("Export not found".to_string()))
+    }
+
+    fn mac(&self, id: Uuid, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.key.as_slice()).expect("HMAC accepts any key length");
+        mac.update(format!("{}:{}", id, expires).as_bytes());
+        mac
+    }
+}
+
+fn link_key(secret: &str) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(LINK_KEY_CONTEXT);
+    mac.finalize().into_bytes().into()
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    path.with_extension(format!(
+        "{}{}",
+        path.extension().and_then(|e| e.to_str()).unwrap_or_default(),
+        PARTIAL_SUFFIX
+    ))
+}
+
+/// Run every pending export this instance claims, one at a time. Returns (succeeded, failed).
+pub async fn process_pending(queue: &ExportQueue, audit: Arc<dyn AuditService>) -> Result<(usize, usize)> {
+    let (mut succeeded, mut failed) = (0, 0);
+
+    for job in queue.jobs.claim_pending().await? {
+        let outcome = write_export(&job, &queue.path(&job), audit.clone()).await;
+        match &outcome {
+            Ok(()) => {
+                info!("Export {} ready", job.id);
+                succeeded += 1;
+            }
+            Err(e) => {
+                error!("Export {} failed: {}", job.id, e);
+                failed += 1;
+            }
+        }
+        if let Err(e) = queue.finish(job.id, &outcome).await {
+            // Left running, so it is failed as abandoned once past retention
+            error!("Failed to record the outcome of export {}: {}", job.id, e);
+        }
+    }
+
+    Ok((succeeded, failed))
+}
+
+/// Write the export next to its final path and only move it into place once complete
+async fn write_export(job: &ExportJob, path: &Path, audit: Arc<dyn AuditService>) -> Result<()> {
+    let partial = partial_path(path);
+
+    let written = async {
+        let mut file = tokio::fs::File::create(&partial).await.map_err(anyhow::Error::from)?;
+        match &job.request {
+            ExportRequest::AuditReport(query) => match query.format {
+                AuditReportFormat::Csv => {
+                    let mut rows = std::pin::pin!(audit_csv_stream(audit, query.clone()));
+                    while let Some(chunk) = rows.try_next().await? {
+                        file.write_all(&chunk).await.map_err(anyhow::Error::from)?;
+                    }
+                }
+                AuditReportFormat::Pdf => {
+                    let summary = audit.get_audit_report_summary(query).await?;
+                    file.write_all(&audit_summary_pdf(query, &summary)).await.map_err(anyhow::Error::from)?;
+                }
+            },
+        }
+        file.flush().await.map_err(anyhow::Error::from)?;
+        Ok::<_, ApiError>(())
+    }
+    .await;
+
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&partial, path).await.map_err(anyhow::Error::from)?;
+    Ok(())
+}
+
+/// Delete files whose jobs no longer exist, e.g. when an instance stopped between
+/// deleting an expired job and its file. Only names this queue produces are touched,
+/// in case the directory holds anything else.
+async fn remove_orphaned_files(dir: &Path, jobs: &ExportJobs) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(stem) = name.to_str().and_then(|name| name.split('.').next()) else {
+            continue;
+        };
+        let Ok(id) = Uuid::parse_str(stem) else {
+            continue;
+        };
+        match jobs.exists(id).await {
+            Ok(true) => {}
+            Ok(false) => {
+                if let Err(e) = std::fs::remove_file(entry.path()) {
+                    warn!("Failed to delete orphaned export {}: {}", entry.path().display(), e);
+                }
+            }
+            Err(e) => {
+                warn!("Keeping export files; failed to check for their jobs: {}", e);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,846 @@
+use axum::{
+    async_trait,
+    body::BodyDataStream,
+    extract::{rejection::JsonRejection, ConnectInfo, FromRequest, FromRequestParts, Path, Query, Request},
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use futures_util::StreamExt;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Deref;
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use app_core::{config::UnknownFieldPolicy, error::ApiError, models::{Fieldset, TenantScope}};
+use auth::{Claims, Permission};
+
+use crate::state::AppState;
+
+/// UUID path parameter that rejects malformed ids with the standard error envelope
+/// instead of axum's plain-text `Path` rejection
+#[derive(Debug, Clone, Copy)]
+pub struct IdPath(pub Uuid);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IdPath
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(id) = Path::<Uuid>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| ApiError::BadRequest("invalid id format".to_string()))?;
+
+        Ok(Self(id))
+    }
+}
+
+/// Address of the client that sent the request, resolved through
+/// `server.tls_policy.trusted_proxies` like the rate limiters do
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ClientIp {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .copied()
+            .ok_or_else(|| ApiError::Internal(anyhow::anyhow!("Peer address is unavailable")))?;
+
+        Ok(Self(state.trusted_proxies.client_ip(peer.ip(), &parts.headers)))
+    }
+}
+
+/// Header a super-admin sets to `true` to operate across all tenants
+pub const ALL_TENANTS_HEADER: &str = "x-all-tenants";
+
+/// Tenant scope of the authenticated caller, for handing to repositories.
+/// Asking for all tenants without being a super-admin is rejected rather than ignored.
+#[derive(Debug, Clone, Copy)]
+pub struct Tenant(pub TenantScope);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Tenant
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let claims = parts
+            .extensions
+            .get::<Claims>()
+            .ok_or_else(|| ApiError::Unauthorized("Missing authentication".to_string()))?;
+
+        let all_tenants = parts
+            .headers
+            .get(ALL_TENANTS_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+
+        if all_tenants && !claims.is_super_admin() {
+            return Err(ApiError::Unauthorized("Cross-tenant access requires super-admin".to_string()));
+        }
+
+        Ok(Self(claims.tenant_scope(all_tenants)))
+    }
+}
+
+/// JSON request body. With `server.unknown_json_fields: reject`, fields `T` doesn't
+/// declare are refused with 400 naming each one, instead of being silently dropped.
+#[derive(Debug, Clone)]
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest<Arc<AppState>> for JsonBody<T>
+where
+    T: DeserializeOwned + Send,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        if state.config.server.unknown_json_fields == UnknownFieldPolicy::Ignore {
+            let Json(body) = Json::<T>::from_request(request, state).await.map_err(json_rejection)?;
+            return Ok(Self(body));
+        }
+
+        // Deserialize from a parsed value so every ignored field can be collected with its path
+        let Json(value) = Json::<Value>::from_request(request, state).await.map_err(json_rejection)?;
+        let mut unknown = Vec::new();
+        let body = serde_ignored::deserialize(value, |path| unknown.push(path.to_string()))
+            .map_err(|e| ApiError::BadRequest(format!("Invalid request body: {}", e)))?;
+
+        if !unknown.is_empty() {
+            return Err(ApiError::BadRequest(format!("Unknown field(s) in request body: {}", unknown.join(", "))));
+        }
+        Ok(Self(body))
+    }
+}
+
+/// axum's limit on buffered request bodies
+const BUFFERED_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// The standard error envelope for axum's plain-text `Json` rejections
+fn json_rejection(rejection: JsonRejection) -> ApiError {
+    match rejection {
+        JsonRejection::JsonDataError(e) => ApiError::Validation(e.body_text()),
+        JsonRejection::MissingJsonContentType(_) => {
+            ApiError::BadRequest("Expected an application/json request body".to_string())
+        }
+        rejection if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            ApiError::PayloadTooLarge(BUFFERED_BODY_LIMIT)
+        }
+        rejection => ApiError::BadRequest(rejection.body_text()),
+    }
+}
+
+/// Authenticated caller holding the permission `P`, checked against the configured
+/// role→permission mapping. Derefs to the caller's `Claims`.
+pub struct Authorized<P> {
+    pub claims: Claims,
+    _permission: PhantomData<P>,
+}
+
+impl<P> fmt::Debug for Authorized<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Authorized").field(&self.claims).finish()
+    }
+}
+
+impl<P> Deref for Authorized<P> {
+    type Target = Claims;
+
+    fn deref(&self) -> &Claims {
+        &self.claims
+    }
+}
+
+#[async_trait]
+impl<P: Permission> FromRequestParts<Arc<AppState>> for Authorized<P> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let claims = parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .ok_or_else(|| ApiError::Unauthorized("Missing authentication".to_string()))?;
+
+        if !state.permissions.has_permission(&claims, P::NAME) {
+            warn!(user_id = %claims.sub, permission = P::NAME, "Permission denied");
+            return Err(ApiError::Unauthorized("Insufficient permissions".to_string()));
+        }
+
+        Ok(Self { claims, _permission: PhantomData })
+    }
+}
+
+#[derive(Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+/// JSON:API-style sparse fieldset from `?fields=id,username`, validated against `T`'s
+/// selectable fields. Without the parameter every field is kept.
+pub struct Fields<T> {
+    selected: Option<Vec<String>>,
+    _type: PhantomData<T>,
+}
+
+impl<T> fmt::Debug for Fields<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Fields").field(&self.selected).finish()
+    }
+}
+
+impl<T: Fieldset> Fields<T> {
+    /// Serialize `item` keeping only the selected fields
+    pub fn project(&self, item: &T) -> Result<Value, ApiError> {
+        let mut value = serde_json::to_value(item).map_err(anyhow::Error::from)?;
+
+        if let (Some(selected), Value::Object(map)) = (&self.selected, &mut value) {
+            map.retain(|name, _| selected.iter().any(|field| field == name));
+        }
+        Ok(value)
+    }
+
+    pub fn project_all(&self, items: &[T]) -> Result<Vec<Value>, ApiError> {
+        items.iter().map(|item| self.project(item)).collect()
+    }
+}
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for Fields<T>
+where
+    S: Send + Sync,
+    T: Fieldset,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<FieldsQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::BadRequest(e.body_text()))?;
+
+        let Some(fields) = query.fields else {
+            return Ok(Self { selected: None, _type: PhantomData });
+        };
+
+        let selected: Vec<String> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if selected.is_empty() {
+            return Err(ApiError::BadRequest("fields must name at least one field".to_string()));
+        }
+
+        let unknown: Vec<&str> = selected
+            .iter()
+            .map(String::as_str)
+            .filter(|field| !T::FIELDS.contains(field))
+            .collect();
+        if !unknown.is_empty() {
+            return Err(ApiError::BadRequest(format!(
+                "Unknown field(s): {}. Available fields: {}",
+                unknown.join(", "),
+                T::FIELDS.join(", ")
+            )));
+        }
+
+        Ok(Self { selected: Some(selected), _type: PhantomData })
+    }
+}
+
+/// Request body holding a top-level JSON array, deserialized one element at a time
+/// as the body arrives instead of buffering the whole array.
+/// Errors name the index of the element that failed to parse.
+pub struct JsonArrayStream {
+    body: BodyDataStream,
+    /// Bytes read so far and the most allowed (`server.max_streamed_body_bytes`)
+    received: usize,
+    limit: usize,
+    splitter: ArraySplitter,
+    pending: VecDeque<Vec<u8>>,
+    /// Malformed input found by the splitter, reported once earlier elements are consumed
+    failure: Option<&'static str>,
+    index: usize,
+}
+
+impl JsonArrayStream {
+    /// Next element of the array, or `None` once the closing `]` has been read
+    pub async fn next<T: DeserializeOwned>(&mut self) -> Result<Option<T>, ApiError> {
+        loop {
+            if let Some(raw) = self.pending.pop_front() {
+                if raw.len() > MAX_JSON_ELEMENT_BYTES {
+                    return Err(ApiError::PayloadTooLarge(MAX_JSON_ELEMENT_BYTES));
+                }
+                let index = self.index;
+                self.index += 1;
+
+                return serde_json::from_slice(&raw).map(Some).map_err(|e| {
+                    ApiError::BadRequest(format!("Invalid JSON at item {}: {}", index, e))
+                });
+            }
+
+            if let Some(reason) = self.failure {
+                return Err(self.parse_error(reason));
+            }
+
+            if self.splitter.finished {
+                return Ok(None);
+            }
+
+            match self.body.next().await {
+                Some(Ok(chunk)) => {
+                    self.received += chunk.len();
+                    if self.received > self.limit {
+                        return Err(ApiError::PayloadTooLarge(self.limit));
+                    }
+                    if let Err(reason) = self.splitter.feed(&chunk, &mut self.pending) {
+                        self.failure = Some(reason);
+                    }
+                    // An element still being read can't grow past the cap either
+                    if self.splitter.current.len() > MAX_JSON_ELEMENT_BYTES {
+                        return Err(ApiError::PayloadTooLarge(MAX_JSON_ELEMENT_BYTES));
+                    }
+                }
+                Some(Err(e)) => {
+                    return Err(ApiError::BadRequest(format!("Failed to read request body: {}", e)))
+                }
+                None => self.failure = Some("unexpected end of input"),
+            }
+        }
+    }
+
+    fn parse_error(&self, reason: &str) -> ApiError {
+        ApiError::BadRequest(format!("Invalid JSON at item {}: {}", self.index, reason))
+    }
+}
+
+#[async_trait]
+impl FromRequest<Arc<AppState>> for JsonArrayStream {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            body: request.into_body().into_data_stream(),
+            received: 0,
+            limit: state.config.server.max_streamed_body_bytes,
+            splitter: ArraySplitter::default(),
+            pending: VecDeque::new(),
+            failure: None,
+            index: 0,
+        })
+    }
+}
+
+/// Largest single element of a streamed JSON array
+const MAX_JSON_ELEMENT_BYTES: usize = 1024 * 1024;
+
+/// Incremental scanner that cuts a top-level JSON array into the raw bytes of its
+/// elements; element contents are validated later by serde
+#[derive(Default)]
+struct ArraySplitter {
+    started: bool,
+    finished: bool,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    after_comma: bool,
+    current: Vec<u8>,
+}
+
+impl ArraySplitter {
+    fn feed(&mut self, chunk: &[u8], out: &mut VecDeque<Vec<u8>>) -> Result<(), &'static str> {
+        for &byte in chunk {
+            if self.finished {
+                if !byte.is_ascii_whitespace() {
+                    return Err("unexpected data after the array");
+                }
+                continue;
+            }
+
+            if !self.started {
+                match byte {
+                    b'[' => self.started = true,
+                    b if b.is_ascii_whitespace() => {}
+                    _ => return Err("expected a JSON array"),
+                }
+                continue;
+            }
+
+            if self.in_string {
+                self.current.push(byte);
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    self.current.push(byte);
+                }
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    self.current.push(byte);
+                }
+                b'}' | b']' if self.depth > 0 => {
+                    self.depth -= 1;
+                    self.current.push(byte);
+                }
+                b',' | b']' if self.depth == 0 => {
+                    let item = std::mem::take(&mut self.current);
+                    if item.iter().all(u8::is_ascii_whitespace) {
+                        // `[]` is fine; `[,` `[1,,2]` and `[1,]` are not
+                        if byte == b',' || self.after_comma {
+                            return Err("expected an array element");
+                        }
+                    } else {
+                        out.push_back(item);
+                    }
+
+                    self.after_comma = byte == b',';
+                    self.finished = byte == b']';
+                }
+                b'}' => return Err("unbalanced closing brace"),
+                _ => self.current.push(byte),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Largest single CSV record accepted, so an unterminated quote can't buffer the whole body
+const MAX_CSV_RECORD_BYTES: usize = 64 * 1024;
+
+/// One record of a CSV body
+#[derive(Debug)]
+pub struct CsvRecord {
+    /// 1-based line the record starts on
+    pub line: u64,
+    /// Field values, or why the record couldn't be parsed
+    pub fields: Result<Vec<String>, &'static str>,
+}
+
+/// `text/csv` request body (RFC 4180), parsed one record at a time as the body arrives.
+/// A malformed record is returned as such rather than failing the stream, so callers
+/// decide whether to skip it.
+pub struct CsvStream {
+    body: BodyDataStream,
+    /// Bytes read so far and the most allowed (`server.max_streamed_body_bytes`)
+    received: usize,
+    limit: usize,
+    splitter: CsvSplitter,
+    pending: VecDeque<CsvRecord>,
+    finished: bool,
+}
+
+impl CsvStream {
+    /// Next record, or `None` at the end of the body
+    pub async fn next(&mut self) -> Result<Option<CsvRecord>, ApiError> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Ok(Some(record));
+            }
+
+            if self.finished {
+                return Ok(None);
+            }
+
+            match self.body.next().await {
+                Some(Ok(chunk)) => {
+                    self.received += chunk.len();
+                    if self.received > self.limit {
+                        return Err(ApiError::PayloadTooLarge(self.limit));
+                    }
+                    self.splitter.feed(&chunk, &mut self.pending)?
+                }
+                Some(Err(e)) => {
+                    return Err(ApiError::BadRequest(format!("Failed to read request body: {}", e)))
+                }
+                None => {
+                    self.splitter.finish(&mut self.pending);
+                    self.finished = true;
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequest<Arc<AppState>> for CsvStream {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &Arc<AppState>) -> Result<Self, Self::Rejection> {
+        let is_csv = request
+            .headers()
+            .get(axum::http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("text/csv"));
+        if !is_csv {
+            return Err(ApiError::BadRequest("Expected a text/csv request body".to_string()));
+        }
+
+        Ok(Self {
+            body: request.into_body().into_data_stream(),
+            received: 0,
+            limit: state.config.server.max_streamed_body_bytes,
+            splitter: CsvSplitter::default(),
+            pending: VecDeque::new(),
+            finished: false,
+        })
+    }
+}
+
+/// Incremental CSV scanner: comma-separated, `"`-quoted fields with `""` escapes,
+/// records ending in LF or CRLF
+#[derive(Default)]
+struct CsvSplitter {
+    /// Line breaks consumed so far, including ones inside quoted fields
+    lines: u64,
+    record_line: u64,
+    record_bytes: usize,
+    fields: Vec<Vec<u8>>,
+    current: Vec<u8>,
+    in_quotes: bool,
+    /// A `"` inside a quoted field: either the first half of `""` or the closing quote
+    quote_pending: bool,
+    after_closing_quote: bool,
+    malformed: Option<&'static str>,
+}
+
+impl CsvSplitter {
+    fn feed(&mut self, chunk: &[u8], out: &mut VecDeque<CsvRecord>) -> Result<(), ApiError> {
+        for &byte in chunk {
+            self.record_bytes += 1;
+            if self.record_bytes > MAX_CSV_RECORD_BYTES {
+                return Err(ApiError::BadRequest(format!(
+                    "CSV record at line {} exceeds {} bytes",
+                    self.record_line + 1,
+                    MAX_CSV_RECORD_BYTES
+                )));
+            }
+
+            if self.in_quotes {
+                if self.quote_pending {
+                    self.quote_pending = false;
+                    if byte == b'"' {
+                        self.current.push(byte);
+                        continue;
+                    }
+                    self.in_quotes = false;
+                    self.after_closing_quote = true;
+                } else {
+                    match byte {
+                        b'"' => self.quote_pending = true,
+                        b'\n' => {
+                            self.lines += 1;
+                            self.current.push(byte);
+                        }
+                        _ => self.current.push(byte),
+                    }
+                    continue;
+                }
+            }
+
+            match byte {
+                b',' => self.end_field(),
+                b'\n' => {
+                    self.lines += 1;
+                    self.end_record(out);
+                }
+                // Part of a CRLF line ending
+                b'\r' => {}
+                b'"' if self.current.is_empty() && !self.after_closing_quote => self.in_quotes = true,
+                b'"' => {
+                    self.malformed.get_or_insert("unexpected quote in unquoted field");
+                }
+                _ if self.after_closing_quote => {
+                    self.malformed.get_or_insert("unexpected data after closing quote");
+                }
+                _ => self.current.push(byte),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Flush the last record when the body ends without a trailing line break
+    fn finish(&mut self, out: &mut VecDeque<CsvRecord>) {
+        if self.in_quotes && !self.quote_pending {
+            self.malformed.get_or_insert("unterminated quoted field");
+        }
+        if self.record_bytes > 0 {
+            self.end_record(out);
+        }
+    }
+
+    fn end_field(&mut self) {
+        self.fields.push(std::mem::take(&mut self.current));
+        self.in_quotes = false;
+        self.quote_pending = false;
+        self.after_closing_quote = false;
+    }
+
+    fn end_record(&mut self, out: &mut VecDeque<CsvRecord>) {
+        self.end_field();
+        let raw = std::mem::take(&mut self.fields);
+
+        let fields = match self.malformed.take() {
+            Some(reason) => Err(reason),
+            None => raw
+                .into_iter()
+                .map(String::from_utf8)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| "invalid UTF-8"),
+        };
+
+        out.push_back(CsvRecord {
+            line: self.record_line + 1,
+            fields,
+        });
+        self.record_line = self.lines;
+        self.record_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    type Parsed = Vec<(u64, Result<Vec<String>, &'static str>)>;
+
+    fn split(chunks: &[&[u8]]) -> Parsed {
+        let mut splitter = CsvSplitter::default();
+        let mut out = VecDeque::new();
+        for chunk in chunks {
+            splitter.feed(chunk, &mut out).unwrap();
+        }
+        splitter.finish(&mut out);
+        out.into_iter().map(|record| (record.line, record.fields)).collect()
+    }
+
+    fn row(fields: &[&str]) -> Result<Vec<String>, &'static str> {
+        Ok(fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    #[test]
+    fn records_end_at_lf_crlf_or_the_end_of_the_body() {
+        assert_eq!(
+            split(&[b"name,price\r\nWidget,100\nGadget,250"]),
+            vec![(1, row(&["name", "price"])), (2, row(&["Widget", "100"])), (3, row(&["Gadget", "250"]))]
+        );
+        assert_eq!(split(&[b"a,,\n"]), vec![(1, row(&["a", "", ""]))]);
+    }
+
+    #[test]
+    fn quoted_fields_keep_commas_escaped_quotes_and_line_breaks() {
+        let csv = b"\"Widget, large\",\"6\"\" pipe\",\"two\nlines\"\nnext,1\n";
+        assert_eq!(
+            split(&[csv]),
+            vec![(1, row(&["Widget, large", "6\" pipe", "two\nlines"])), (3, row(&["next", "1"]))]
+        );
+    }
+
+    #[test]
+    fn chunk_boundaries_anywhere_give_the_same_records() {
+        let csv: &[u8] = b"name,notes\r\n\"a\"\"b\",\"x,\r\ny\"\"\"\nc,\"\"\n";
+        let whole = split(&[csv]);
+        assert_eq!(whole, vec![(1, row(&["name", "notes"])), (2, row(&["a\"b", "x,\r\ny\""])), (4, row(&["c", ""]))]);
+
+        for at in 1..csv.len() {
+            assert_eq!(split(&[&csv[..at], &csv[at..]]), whole, "split at byte {}", at);
+        }
+        let bytes: Vec<&[u8]> = csv.chunks(1).collect();
+        assert_eq!(split(&bytes), whole);
+    }
+
+    #[test]
+    fn malformed_records_are_reported_and_parsing_continues() {
+        assert_eq!(
+            split(&[b"a\"b,1\n\"a\"b,2\nok,3\n\xff,4\n\"open,5"]),
+            vec![
+                (1, Err("unexpected quote in unquoted field")),
+                (2, Err("unexpected data after closing quote")),
+                (3, row(&["ok", "3"])),
+                (4, Err("invalid UTF-8")),
+                (5, Err("unterminated quoted field")),
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_records_are_refused() {
+        let mut splitter = CsvSplitter::default();
+        let mut out = VecDeque::new();
+        let record = vec![b'a'; MAX_CSV_RECORD_BYTES + 1];
+        assert!(matches!(splitter.feed(&record, &mut out), Err(ApiError::BadRequest(_))));
+    }
+
+    fn csv_stream(chunks: &[&'static str], limit: usize) -> CsvStream {
+        let chunks: Vec<Result<&'static str, std::io::Error>> = chunks.iter().copied().map(Ok).collect();
+        CsvStream {
+            body: Body::from_stream(futures_util::stream::iter(chunks)).into_data_stream(),
+            received: 0,
+            limit,
+            splitter: CsvSplitter::default(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn csv_streams_yield_records_split_across_body_chunks() {
+        let mut csv = csv_stream(&["name,notes\n\"quoted, ", "field\",1\nlast,", "2"], 1024);
+
+        let mut records = Vec::new();
+        while let Some(record) = csv.next().await.unwrap() {
+            records.push((record.line, record.fields));
+        }
+        assert_eq!(
+            records,
+            vec![(1, row(&["name", "notes"])), (2, row(&["quoted, field", "1"])), (3, row(&["last", "2"]))]
+        );
+    }
+
+    #[tokio::test]
+    async fn csv_streams_stop_at_the_body_limit() {
+        let mut csv = csv_stream(&["a,1\n", "b,2\n", "c,3\n"], 10);
+
+        assert!(csv.next().await.unwrap().is_some());
+        assert!(csv.next().await.unwrap().is_some());
+        assert!(matches!(csv.next().await, Err(ApiError::PayloadTooLarge(10))));
+    }
+
+    fn elements(chunks: &[&[u8]]) -> Result<Vec<String>, &'static str> {
+        let mut splitter = ArraySplitter::default();
+        let mut out = VecDeque::new();
+        for chunk in chunks {
+            splitter.feed(chunk, &mut out)?;
+        }
+        if !splitter.finished {
+            return Err("unexpected end of input");
+        }
+        Ok(out.into_iter().map(|raw| String::from_utf8(raw).unwrap().trim().to_string()).collect())
+    }
+
+    #[test]
+    fn nested_arrays_and_objects_stay_whole() {
+        assert_eq!(
+            elements(&[br#"[{"a": [1, {"b": []}]}, [2, [3]], 4, {}]"#]).unwrap(),
+            [r#"{"a": [1, {"b": []}]}"#, "[2, [3]]", "4", "{}"]
+        );
+        assert_eq!(elements(&[b" [ ] "]).unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn brackets_commas_and_escaped_quotes_inside_strings_are_content() {
+        assert_eq!(
+            elements(&[br#"["a]b", {"c,d": "}"}, "say \"hi\"], ok", "back\\", "\\\"]"]"#]).unwrap(),
+            [r#""a]b""#, r#"{"c,d": "}"}"#, r#""say \"hi\"], ok""#, r#""back\\""#, r#""\\\"]""#]
+        );
+    }
+
+    #[test]
+    fn array_chunk_boundaries_anywhere_give_the_same_elements() {
+        let json: &[u8] = br#"[{"name": "a]\",b", "tags": ["x", "y,z"]}, [[1], {"k": "\\"}], "end"]"#;
+        let whole = elements(&[json]).unwrap();
+        assert_eq!(whole.len(), 3);
+
+        for at in 1..json.len() {
+            assert_eq!(elements(&[&json[..at], &json[at..]]).unwrap(), whole, "split at byte {}", at);
+        }
+        let bytes: Vec<&[u8]> = json.chunks(1).collect();
+        assert_eq!(elements(&bytes).unwrap(), whole);
+    }
+
+    #[test]
+    fn malformed_arrays_are_rejected() {
+        assert_eq!(elements(&[b"{}"]), Err("expected a JSON array"));
+        assert_eq!(elements(&[b"[1,,2]"]), Err("expected an array element"));
+        assert_eq!(elements(&[b"[1,]"]), Err("expected an array element"));
+        assert_eq!(elements(&[b"[,1]"]), Err("expected an array element"));
+        assert_eq!(elements(&[b"[1}"]), Err("unbalanced closing brace"));
+        assert_eq!(elements(&[b"[1] 2"]), Err("unexpected data after the array"));
+        assert_eq!(elements(&[b"[1, \"]"]), Err("unexpected end of input"));
+    }
+
+    fn json_array_stream(chunks: &[&'static str]) -> JsonArrayStream {
+        let chunks: Vec<Result<&'static str, std::io::Error>> = chunks.iter().copied().map(Ok).collect();
+        JsonArrayStream {
+            body: Body::from_stream(futures_util::stream::iter(chunks)).into_data_stream(),
+            received: 0,
+            limit: 1024,
+            splitter: ArraySplitter::default(),
+            pending: VecDeque::new(),
+            failure: None,
+            index: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn json_array_streams_yield_elements_then_report_errors_by_index() {
+        let mut items = json_array_stream(&[r#"[{"n": 1}, {"n""#, r#": 2}, {"n": "#]);
+
+        assert_eq!(items.next::<Value>().await.unwrap(), Some(serde_json::json!({"n": 1})));
+        assert_eq!(items.next::<Value>().await.unwrap(), Some(serde_json::json!({"n": 2})));
+        match items.next::<Value>().await {
+            Err(ApiError::BadRequest(message)) => {
+                assert_eq!(message, "Invalid JSON at item 2: unexpected end of input")
+            }
+            other => panic!("expected a bad request, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Named {
+        #[allow(dead_code)]
+        name: String,
+    }
+
+    async fn buffered_json(content_type: &str, body: impl Into<Body>) -> ApiError {
+        let request = Request::builder()
+            .header(axum::http::header::CONTENT_TYPE, content_type)
+            .body(body.into())
+            .unwrap();
+        json_rejection(Json::<Named>::from_request(request, &()).await.unwrap_err())
+    }
+
+    #[tokio::test]
+    async fn json_rejections_use_the_error_envelope() {
+        assert!(matches!(buffered_json("application/json", "{").await, ApiError::BadRequest(_)));
+        assert!(matches!(buffered_json("application/json", r#"{"name": 1}"#).await, ApiError::Validation(_)));
+        match buffered_json("text/plain", r#"{"name": "a"}"#).await {
+            ApiError::BadRequest(message) => assert_eq!(message, "Expected an application/json request body"),
+            other => panic!("expected a bad request, got {:?}", other),
+        }
+        let oversized = format!(r#"{{"name": "{}"}}"#, "a".repeat(BUFFERED_BODY_LIMIT));
+        assert!(matches!(
+            buffered_json("application/json", oversized).await,
+            ApiError::PayloadTooLarge(BUFFERED_BODY_LIMIT)
+        ));
+    }
+}
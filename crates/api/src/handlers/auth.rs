@@ -1,87 +1,336 @@
 use axum::{
     extract::State,
-    response::Json,
+    Extension,
 };
 use std::sync::Arc;
 use tracing::{info, instrument, warn};
 use validator::Validate;
 
+use crate::extractors::{ClientIp, JsonBody};
+use crate::one_time_token;
 use crate::state::AppState;
-use auth::{LoginRequest, LoginResponse, UserInfo};
+use auth::{
+    mfa, ChangePasswordRequest, Claims, IntrospectRequest, IntrospectionResponse, LoginOutcome, LoginRequest,
+    LoginResponse, LoginVerification, MfaChallengeResponse, MfaConfirmRequest, MfaEnrollmentResponse,
+    MfaVerifyRequest, PasswordResetConfirmRequest, PasswordResetRequest, RefreshTokenRequest, TokenResponse,
+    UserInfo,
+};
+use app_core::enterprise::{AuditCategory, AuditSeverity};
 use app_core::error::{ApiError, Result};
-use database::UserRepositoryTrait;
+use app_core::timestamp::Json;
+use app_core::models::{TenantScope, User};
+use database::{UserRepository, UserRepositoryTrait};
+use monitoring::sanitize::sanitize_str;
+use monitoring::Notification;
 
 #[instrument(skip(state, request))]
 pub async fn login(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<LoginRequest>,
-) -> Result<Json<LoginResponse>> {
+    JsonBody(request): JsonBody<LoginRequest>,
+) -> Result<Json<LoginOutcome>> {
     // Validate request
-    request.validate()
-        .map_err(|e| ApiError::Validation(format!("Validation failed: {}", e)))?;
+    request.validate()?;
 
-    let user_repo = state.db_pool.user_repository();
+    // Bound concurrent logins so a flood can't turn password hashing into a CPU DoS
+    let _login_permit = state.auth_service.try_acquire_login_permit().inspect_err(|_| {
+        state.metrics_service.increment_auth_events("login_throttled", false);
+    })?;
 
-    // Find user by email
-    let user = user_repo
-        .find_by_email(&request.email)
-        .await?
-        .ok_or_else(|| {
-            warn!("Login attempt with non-existent email: {}", request.email);
+    // Emails are unique across tenants, so login resolves the user before any tenant is known
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+
+    // Find user by email. The password is verified whether or not the account exists or
+    // is active, so neither can be told apart by response time.
+    let user = user_repo.find_by_email(&request.email).await?;
+    let verification = state.auth_service.verify_login(&request.password, user.as_ref())?;
+    let user = match (verification, user) {
+        (LoginVerification::Verified, Some(user)) => user,
+        (LoginVerification::Inactive, Some(user)) => {
+            warn!("Login attempt for inactive user: {}", sanitize_str(user.email.as_str()));
+            state.metrics_service.increment_auth_events("login", false);
+            return Err(ApiError::Unauthorized("Account is deactivated".to_string()));
+        }
+        (LoginVerification::InvalidPassword, Some(user)) => {
+            warn!("Invalid password for user: {}", sanitize_str(user.email.as_str()));
+            state.metrics_service.increment_auth_events("login", false);
+            return Err(ApiError::Unauthorized("Invalid credentials".to_string()));
+        }
+        _ => {
+            warn!("Login attempt with non-existent email: {}", sanitize_str(request.email.as_str()));
             state.metrics_service.increment_auth_events("login", false);
-            ApiError::Unauthorized("Invalid credentials".to_string())
-        })?;
+            return Err(ApiError::Unauthorized("Invalid credentials".to_string()));
+        }
+    };
 
-    // Check if user is active
-    if !user.is_active {
-        warn!("Login attempt for inactive user: {}", user.email);
-        state.metrics_service.increment_auth_events("login", false);
-        return Err(ApiError::Unauthorized("Account is deactivated".to_string()));
+    // Transparently migrate legacy hashes to the current algorithm
+    if state.auth_service.needs_rehash(&user.password_hash) {
+        let password_hash = state.auth_service.hash_password(&request.password)?;
+        user_repo.update_password_hash(user.id, password_hash).await?;
+        info!("Rehashed legacy password for user: {}", user.id);
     }
 
-    // Verify password
-    let password_valid = state
-        .auth_service
-        .verify_password(&request.password, &user.password_hash)?;
+    // With MFA enabled the password only earns a challenge; tokens follow a valid code
+    let mfa_enabled = user_repo.mfa_settings(user.id).await?.is_some_and(|settings| settings.enabled);
+    if mfa_enabled {
+        let challenge_token = one_time_token::generate();
+        let ttl = state.config.auth.mfa_challenge_ttl;
+        user_repo
+            .create_mfa_challenge(
+                user.id,
+                &one_time_token::hash(&challenge_token),
+                time::OffsetDateTime::now_utc() + time::Duration::seconds(ttl as i64),
+            )
+            .await?;
 
-    if !password_valid {
-        warn!("Invalid password for user: {}", user.email);
-        state.metrics_service.increment_auth_events("login", false);
-        return Err(ApiError::Unauthorized("Invalid credentials".to_string()));
+        state.metrics_service.increment_auth_events("login_mfa_challenge", true);
+        info!("MFA challenge issued for user: {}", user.id);
+
+        return Ok(Json(LoginOutcome::MfaRequired(MfaChallengeResponse {
+            mfa_required: true,
+            challenge_token,
+            expires_in: ttl,
+        })));
+    }
+
+    let response = start_session(&state, &user_repo, user).await?;
+    Ok(Json(LoginOutcome::Authenticated(response)))
+}
+
+/// Record a session for a user who has passed every login step and issue their tokens
+async fn start_session(state: &AppState, user_repo: &UserRepository, user: User) -> Result<LoginResponse> {
+    let roles = user_repo.roles(user.id).await?;
+
+    // Admins are exempt from the per-user session limit
+    let session_limit = state
+        .config
+        .auth
+        .max_sessions_per_user
+        .filter(|_| !roles.iter().any(|role| role == "admin"))
+        .map(|max_sessions| (max_sessions, state.config.auth.session_limit_policy));
+
+    let refresh_token = one_time_token::generate();
+    let session_ttl = time::Duration::seconds(state.config.auth.session_ttl as i64);
+    let Some(session) = user_repo
+        .create_session(
+            user.id,
+            &one_time_token::hash(&refresh_token),
+            time::OffsetDateTime::now_utc() + session_ttl,
+            session_limit,
+        )
+        .await?
+    else {
+        warn!("Login refused for user at session limit: {}", sanitize_str(user.email.as_str()));
+        state.metrics_service.increment_auth_events("login_session_limit", false);
+        return Err(ApiError::Forbidden(format!(
+            "Maximum of {} active sessions reached; sign out on another device and try again",
+            session_limit.map_or(0, |(max_sessions, _)| max_sessions)
+        )));
+    };
+
+    if !session.evicted.is_empty() {
+        info!(
+            user_id = %user.id,
+            evicted = session.evicted.len(),
+            "Revoked oldest sessions to stay within the session limit"
+        );
+        state.metrics_service.increment_counter_by("sessions_evicted_total", session.evicted.len() as u64, &[]);
+
+        if let Err(e) = state.audit_service.log_action(
+            Some(user.id),
+            "sessions_evicted",
+            AuditCategory::Security,
+            AuditSeverity::Info,
+            "session",
+            Some(session.session_id),
+            "127.0.0.1",
+            None,
+            serde_json::json!({ "revoked_sessions": session.evicted }),
+        ).await {
+            warn!("Failed to audit session eviction: {}", e);
+        }
     }
 
     // Generate JWT token
-    let roles = vec!["user".to_string()]; // In a real app, fetch from database
     let token = state.auth_service.generate_token(
         user.id,
-        user.username.clone(),
-        user.email.clone(),
+        user.username.to_string(),
+        user.email.to_string(),
         roles.clone(),
+        user.tenant_id,
+        session.session_id,
     )?;
 
+    // The session and tokens already exist, so a failed bookkeeping write mustn't fail the login
+    if let Err(e) = user_repo.record_login(user.id).await {
+        warn!("Failed to record login for user {}: {}", user.id, e);
+    }
+
     state.metrics_service.increment_auth_events("login", true);
-    info!("User logged in successfully: {}", user.email);
+    info!("User logged in successfully: {}", sanitize_str(user.email.as_str()));
 
-    Ok(Json(LoginResponse {
+    Ok(LoginResponse {
         access_token: token,
+        refresh_token,
         token_type: "Bearer".to_string(),
         expires_in: state.auth_service.jwt_expiration(),
         user: UserInfo {
             id: user.id,
-            username: user.username,
-            email: user.email,
+            username: user.username.into(),
+            email: user.email.into(),
             roles,
         },
-    }))
+    })
+}
+
+/// Complete a login that returned an MFA challenge by supplying a code from the
+/// authenticator app. A challenge allows `mfa_max_attempts` wrong codes.
+#[instrument(skip(state, request))]
+pub async fn verify_mfa(
+    State(state): State<Arc<AppState>>,
+    JsonBody(request): JsonBody<MfaVerifyRequest>,
+) -> Result<Json<LoginResponse>> {
+    request.validate()?;
+
+    let rejected = |message: &str| {
+        state.metrics_service.increment_auth_events("mfa_verify", false);
+        ApiError::Unauthorized(message.to_string())
+    };
+
+    let challenge_hash = one_time_token::hash(&request.challenge_token);
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let user_id = user_repo
+        .find_mfa_challenge(&challenge_hash)
+        .await?
+        .ok_or_else(|| rejected("Invalid or expired MFA challenge"))?;
+
+    let user = user_repo
+        .find_by_id(user_id)
+        .await?
+        .filter(|user| user.is_active)
+        .ok_or_else(|| rejected("Invalid or expired MFA challenge"))?;
+    let secret = user_repo
+        .mfa_settings(user.id)
+        .await?
+        .filter(|settings| settings.enabled)
+        .and_then(|settings| settings.secret)
+        .ok_or_else(|| rejected("Invalid or expired MFA challenge"))?;
+
+    let step = match mfa::verify_totp(&secret, &request.code)? {
+        Some(step) if user_repo.record_mfa_step(user.id, step).await? => step,
+        _ => {
+            user_repo.fail_mfa_challenge(&challenge_hash, state.config.auth.mfa_max_attempts).await?;
+            warn!("Invalid MFA code for user: {}", user.id);
+            return Err(rejected("Invalid MFA code"));
+        }
+    };
+
+    // Consumed last so a wrong code leaves the challenge usable for another attempt
+    user_repo
+        .consume_mfa_challenge(&challenge_hash)
+        .await?
+        .ok_or_else(|| rejected("Invalid or expired MFA challenge"))?;
+
+    state.metrics_service.increment_auth_events("mfa_verify", true);
+    info!(user_id = %user.id, step, "MFA challenge passed");
+
+    Ok(Json(start_session(&state, &user_repo, user).await?))
+}
+
+/// Start MFA enrollment with a new TOTP secret. Logins don't require it until a code
+/// from it is confirmed at `/auth/mfa/enroll/confirm`; enrolling again replaces it.
+#[instrument(skip(state, claims), fields(user_id = %claims.sub))]
+pub async fn enroll_mfa(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<MfaEnrollmentResponse>> {
+    if claims.impersonated_by.is_some() {
+        return Err(ApiError::Unauthorized("MFA cannot be enrolled while impersonating".to_string()));
+    }
+
+    let secret = mfa::generate_totp_secret();
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    if !user_repo.set_pending_mfa_secret(claims.sub, &secret).await? {
+        return Err(ApiError::Conflict("MFA is already enabled".to_string()));
+    }
+
+    let provisioning_uri = mfa::provisioning_uri(&secret, &claims.email, &state.config.auth.jwt_issuer);
+    state.metrics_service.increment_auth_events("mfa_enroll", true);
+    info!("MFA enrollment started");
+
+    Ok(Json(MfaEnrollmentResponse { secret, provisioning_uri }))
+}
+
+/// Enable MFA once a code shows the enrolled secret was saved in an authenticator app
+#[instrument(skip(state, claims, request), fields(user_id = %claims.sub))]
+pub async fn confirm_mfa_enrollment(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<MfaConfirmRequest>,
+) -> Result<Json<serde_json::Value>> {
+    request.validate()?;
+
+    if claims.impersonated_by.is_some() {
+        return Err(ApiError::Unauthorized("MFA cannot be enrolled while impersonating".to_string()));
+    }
+
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let settings = user_repo
+        .mfa_settings(claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
+    if settings.enabled {
+        return Err(ApiError::Conflict("MFA is already enabled".to_string()));
+    }
+    let secret = settings
+        .secret
+        .ok_or_else(|| ApiError::BadRequest("Start MFA enrollment first".to_string()))?;
+
+    match mfa::verify_totp(&secret, &request.code)? {
+        Some(step) if user_repo.record_mfa_step(claims.sub, step).await? => {}
+        _ => {
+            state.metrics_service.increment_auth_events("mfa_enroll_confirm", false);
+            return Err(ApiError::Validation("Invalid MFA code".to_string()));
+        }
+    }
+
+    if !user_repo.enable_mfa(claims.sub).await? {
+        return Err(ApiError::NotFound("User not found".to_string()));
+    }
+
+    let _ = state.audit_service.log_action(
+        Some(claims.sub),
+        "mfa_enabled",
+        AuditCategory::Security,
+        AuditSeverity::Warning,
+        "user",
+        Some(claims.sub),
+        "127.0.0.1",
+        None,
+        serde_json::json!({}),
+    ).await;
+
+    state.metrics_service.increment_auth_events("mfa_enroll_confirm", true);
+    info!("MFA enabled");
+
+    Ok(Json(serde_json::json!({
+        "message": "MFA enabled"
+    })))
 }
 
-#[instrument(skip(state))]
+/// Revoke the token the request was made with, so it stops working before it expires
+#[instrument(skip(state, claims), fields(user_id = %claims.sub))]
 pub async fn logout(
     State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
 ) -> Result<Json<serde_json::Value>> {
-    // In a real implementation, you might want to blacklist the token
-    // For now, we'll just log the logout event
-    state.metrics_service.increment_auth_events("logout", true);
+    let revoked = state.auth_service.revoke_token(&claims).await?;
+
+    // Ending the session as well stops its refresh token from minting new access tokens
+    if let Some(session_id) = claims.sid {
+        let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+        user_repo.revoke_session(claims.sub, session_id).await?;
+    }
+    state.metrics_service.increment_auth_events("logout", revoked);
     info!("User logged out");
 
     Ok(Json(serde_json::json!({
@@ -89,15 +338,280 @@ pub async fn logout(
     })))
 }
 
-#[instrument(skip(state))]
+/// Issue a new access token for the session a refresh token identifies. The session's
+/// expiry is checked with `refresh_token_leeway` of grace for skewed client clocks.
+#[instrument(skip(state, request))]
 pub async fn refresh_token(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<serde_json::Value>> {
-    // In a real implementation, you would handle refresh tokens
-    // This is a placeholder for the refresh token logic
+    JsonBody(request): JsonBody<RefreshTokenRequest>,
+) -> Result<Json<TokenResponse>> {
+    let rejected = || {
+        state.metrics_service.increment_auth_events("refresh", false);
+        ApiError::Unauthorized("Invalid or expired refresh token".to_string())
+    };
+
+    let leeway = time::Duration::seconds(state.config.auth.refresh_token_leeway as i64);
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let session = user_repo
+        .find_session(&one_time_token::hash(&request.refresh_token), time::OffsetDateTime::now_utc() - leeway)
+        .await?
+        .ok_or_else(rejected)?;
+
+    let user = user_repo
+        .find_by_id(session.user_id)
+        .await?
+        .filter(|user| user.is_active)
+        .ok_or_else(rejected)?;
+
+    // Reloaded so a role granted or withdrawn since login applies from this token on
+    let roles = user_repo.roles(user.id).await?;
+    let access_token = state.auth_service.generate_token(
+        user.id,
+        user.username.to_string(),
+        user.email.to_string(),
+        roles,
+        user.tenant_id,
+        session.session_id,
+    )?;
+
     state.metrics_service.increment_auth_events("refresh", true);
 
+    Ok(Json(TokenResponse {
+        access_token,
+        refresh_token: None,
+        token_type: "Bearer".to_string(),
+        expires_in: state.auth_service.jwt_expiration(),
+    }))
+}
+
+/// Change the caller's own password after re-verifying the current one
+#[instrument(skip(state, request))]
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<ChangePasswordRequest>,
+) -> Result<Json<serde_json::Value>> {
+    request.validate()?;
+
+    if claims.impersonated_by.is_some() {
+        return Err(ApiError::Unauthorized("Passwords cannot be changed while impersonating".to_string()));
+    }
+
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let user = user_repo
+        .find_by_id(claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
+
+    if !state.auth_service.verify_password(&request.current_password, &user.password_hash)? {
+        state.metrics_service.increment_auth_events("change_password", false);
+        return Err(ApiError::Unauthorized("Invalid credentials".to_string()));
+    }
+
+    ensure_password_not_reused(&state, &user, &request.new_password).await?;
+
+    let password_hash = state.auth_service.hash_password(&request.new_password)?;
+    let history_size = state.config.auth.password_history_size as i64;
+    if !user_repo.change_password(user.id, password_hash, history_size).await? {
+        return Err(ApiError::NotFound("User not found".to_string()));
+    }
+
+    let _ = state.audit_service.log_action(
+        Some(user.id),
+        "password_changed",
+        AuditCategory::Security,
+        AuditSeverity::Info,
+        "user",
+        Some(user.id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({}),
+    ).await;
+
+    state.metrics_service.increment_auth_events("change_password", true);
+    info!("Password changed for user: {}", user.id);
+
     Ok(Json(serde_json::json!({
-        "message": "Token refresh not implemented yet"
+        "message": "Password changed successfully"
     })))
 }
+
+/// Email a single-use password reset link. Always answers the same way, whether or not
+/// the email belongs to an active account, so it can't be used to discover accounts.
+#[instrument(skip(state, request))]
+pub async fn request_password_reset(
+    State(state): State<Arc<AppState>>,
+    ClientIp(client): ClientIp,
+    JsonBody(request): JsonBody<PasswordResetRequest>,
+) -> Result<Json<serde_json::Value>> {
+    request.validate()?;
+
+    let response = Json(serde_json::json!({
+        "message": "If an account exists for this email, a password reset link has been sent"
+    }));
+
+    // Over quota: answer as usual, but neither issue a token nor send anything
+    if let Err(limit) = state.email_send_limiter.check(request.email.as_str(), client) {
+        warn!(
+            client = %client,
+            limit,
+            "Suppressed password reset email to {}: send limit reached",
+            sanitize_str(request.email.as_str())
+        );
+        state.metrics_service.increment_counter("email_sends_suppressed_total", &[("limit", limit)]);
+        return Ok(response);
+    }
+
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let user = user_repo
+        .find_by_email(&request.email)
+        .await?
+        .filter(|user| user.is_active);
+
+    if let Some(user) = &user {
+        let token = one_time_token::generate();
+        let ttl = state.config.auth.password_reset_token_ttl;
+        let expires_at = time::OffsetDateTime::now_utc() + time::Duration::seconds(ttl as i64);
+        user_repo
+            .request_password_reset(user.id, &one_time_token::hash(&token), expires_at)
+            .await?;
+
+        let link = format!(
+            "{}/reset-password?token={}",
+            state.config.server.public_url.trim_end_matches('/'),
+            token
+        );
+        state.notifications.notify_user(
+            user.email.as_str(),
+            Notification::new(
+                "Reset your password",
+                format!(
+                    "Open this link within {} minutes to choose a new password:\n{}\n\nIf you did not ask to reset your password, ignore this email.",
+                    ttl / 60,
+                    link
+                ),
+            ),
+        );
+
+        let _ = state.audit_service.log_action(
+            Some(user.id),
+            "password_reset_requested",
+            AuditCategory::Security,
+            AuditSeverity::Info,
+            "user",
+            Some(user.id),
+            "127.0.0.1",
+            None,
+            serde_json::json!({}),
+        ).await;
+
+        info!("Password reset requested for user: {}", user.id);
+    } else {
+        warn!("Password reset requested for unknown or inactive email: {}", sanitize_str(request.email.as_str()));
+    }
+
+    state.metrics_service.increment_auth_events("password_reset_request", user.is_some());
+
+    Ok(response)
+}
+
+/// Set a new password from an emailed reset token. The token works once, and every
+/// session of the user is signed out.
+#[instrument(skip(state, request))]
+pub async fn confirm_password_reset(
+    State(state): State<Arc<AppState>>,
+    JsonBody(request): JsonBody<PasswordResetConfirmRequest>,
+) -> Result<Json<serde_json::Value>> {
+    request.validate()?;
+
+    let rejected = || {
+        state.metrics_service.increment_auth_events("password_reset", false);
+        ApiError::BadRequest("Invalid or expired password reset token".to_string())
+    };
+
+    let token_hash = one_time_token::hash(&request.token);
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let user_id = user_repo
+        .find_password_reset(&token_hash)
+        .await?
+        .ok_or_else(rejected)?;
+
+    let user = user_repo
+        .find_by_id(user_id)
+        .await?
+        .filter(|user| user.is_active)
+        .ok_or_else(rejected)?;
+
+    ensure_password_not_reused(&state, &user, &request.new_password).await?;
+
+    let password_hash = state.auth_service.hash_password(&request.new_password)?;
+    let history_size = state.config.auth.password_history_size as i64;
+    // The token may have been used or expired since it was looked up
+    user_repo
+        .update_password(&token_hash, password_hash, history_size)
+        .await?
+        .ok_or_else(rejected)?;
+
+    let _ = state.audit_service.log_action(
+        Some(user.id),
+        "password_reset",
+        AuditCategory::Security,
+        AuditSeverity::Warning,
+        "user",
+        Some(user.id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({}),
+    ).await;
+
+    state.metrics_service.increment_auth_events("password_reset", true);
+    info!("Password reset for user: {}", user.id);
+
+    Ok(Json(serde_json::json!({
+        "message": "Password has been reset"
+    })))
+}
+
+/// Reject `password` if it matches the current password or one of the last
+/// `password_history_size` previous ones
+async fn ensure_password_not_reused(state: &AppState, user: &User, password: &str) -> Result<()> {
+    let history_size = state.config.auth.password_history_size;
+    if history_size == 0 {
+        return Ok(());
+    }
+
+    let previous = state
+        .db_pool
+        .user_repository(TenantScope::all_tenants(None))
+        .password_history(user.id, history_size as i64)
+        .await?;
+
+    for hash in std::iter::once(&user.password_hash).chain(previous.iter()) {
+        if state.auth_service.verify_password(password, hash)? {
+            return Err(ApiError::Validation(format!(
+                "Password matches one of your last {} passwords",
+                history_size
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Report whether a token is currently valid along with its claims, for gateways and
+/// services that delegate token verification to this service
+#[instrument(skip(state, request))]
+pub async fn introspect_token(
+    State(state): State<Arc<AppState>>,
+    JsonBody(request): JsonBody<IntrospectRequest>,
+) -> Result<Json<IntrospectionResponse>> {
+    request.validate()?;
+
+    let response = match state.auth_service.validate_token(&request.token).await {
+        Ok(claims) => IntrospectionResponse::active(claims),
+        Err(_) => IntrospectionResponse::inactive(),
+    };
+
+    state.metrics_service.increment_auth_events("introspect", response.active);
+    Ok(Json(response))
+}
@@ -1,35 +1,69 @@
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    response::Json,
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Extension,
 };
+use futures_util::TryStreamExt;
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use tracing::{info, instrument};
-use uuid::Uuid;
+use std::time::Instant;
+use tracing::{error, info, instrument, warn};
+use validator::Validate;
 
+use crate::extractors::{IdPath, JsonArrayStream, JsonBody};
+use crate::middleware::in_flight::InFlightSnapshot;
+use crate::middleware::rate_limit::RequestCost;
+use crate::reports::{audit_csv_stream, audit_report_file, audit_summary_pdf, label};
 use crate::state::AppState;
-use auth::Claims;
+use auth::{Claims, KeyRotation, SigningKeyInfo};
 use app_core::error::{ApiError, Result};
-use app_core::enterprise::{AuditLog, FeatureFlag, PerformanceMetrics};
-use monitoring::{audit_action, feature_enabled};
+use app_core::timestamp::Json;
+use app_core::models::User;
+use app_core::enterprise::{
+    AggregateStats, AuditCategory, AuditFilter, AuditLog, AuditReportFormat, AuditReportQuery, AuditSeverity,
+    BulkFlagEvaluationRequest, CreateFeatureFlagRequest, FeatureFlag, FlagDependencies, FlagEvaluation,
+    FlagEvaluationQuery, PerformanceMetrics, RolloutSimulation, RolloutSimulationRequest, UpdateFeatureFlagRequest,
+};
+use database::UserRepositoryTrait;
+use monitoring::{audit_action, feature_enabled, CircuitBreakerSnapshot, DependencyReport};
+use monitoring::audit::is_audit_flag;
+use monitoring::feature_flags::{assign_variant, dependency_graph, in_rollout};
+use monitoring::sanitize::sanitize_str;
+
+/// Maximum audit entries returned by a single query
+const AUDIT_QUERY_LIMIT: i64 = 100;
+
+/// Maximum flag evaluations returned by a single history query
+const FLAG_EVALUATION_QUERY_LIMIT: i64 = 1000;
+
+/// Maximum flags evaluated by a single bulk request
+const MAX_BULK_FLAG_EVALUATIONS: usize = 100;
+
+/// Users sampled by a rollout simulation unless fewer are requested
+const ROLLOUT_SIMULATION_MAX_SAMPLE: i64 = 100_000;
 
-/// Get audit trail for a specific user (admin only)
+/// Audit entries loaded per `COPY` during a backfill
+const AUDIT_BACKFILL_BATCH: usize = 5000;
+
+/// Get audit trail for a specific user, optionally filtered by category/severity (admin only)
 #[instrument(skip(state))]
 pub async fn get_user_audit_trail(
     State(state): State<Arc<AppState>>,
-    Path(user_id): Path<Uuid>,
+    IdPath(user_id): IdPath,
+    Query(filter): Query<AuditFilter>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<Vec<AuditLog>>> {
-    // Check admin permission
-    if !claims.has_role("admin") {
-        return Err(ApiError::Unauthorized("Admin access required".to_string()));
-    }
+    claims.require_admin()?;
 
     // Log this admin action
     let _ = audit_action!(
         state.audit_service,
         Some(claims.sub),
         "view_audit_trail",
+        AuditCategory::Security,
+        AuditSeverity::Info,
         "user",
         Some(user_id),
         "127.0.0.1", // In real implementation, extract from request
@@ -37,7 +71,7 @@ pub async fn get_user_audit_trail(
         serde_json::json!({"target_user": user_id})
     );
 
-    let audit_logs = state.audit_service.get_user_audit_trail(user_id, 100).await?;
+    let audit_logs = state.audit_service.get_user_audit_trail(user_id, &filter, AUDIT_QUERY_LIMIT).await?;
 
     state.metrics_service.increment_counter("audit_trail_requests_total", &[
         ("requested_by", &claims.sub.to_string()),
@@ -47,15 +81,216 @@ pub async fn get_user_audit_trail(
     Ok(Json(audit_logs))
 }
 
+/// Recent security-category audit events across all users (admin only)
+#[instrument(skip(state))]
+pub async fn get_security_events(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<AuditLog>>> {
+    claims.require_admin()?;
+
+    let filter = AuditFilter {
+        category: Some(AuditCategory::Security),
+        min_severity: None,
+    };
+    let events = state.audit_service.get_audit_events(&filter, AUDIT_QUERY_LIMIT).await?;
+
+    state.metrics_service.increment_counter("security_events_requests_total", &[]);
+    Ok(Json(events))
+}
+
+/// Download the audit entries in a date range as CSV, or a PDF summary of them (admin only)
+#[instrument(skip(state))]
+pub async fn get_audit_report(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditReportQuery>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Response> {
+    authorize_audit_report(&claims, &query)?;
+    state.request_costs.charge(&claims, RequestCost::Report)?;
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "export_audit_report",
+        AuditCategory::Security,
+        AuditSeverity::Warning,
+        "audit_log",
+        None,
+        "127.0.0.1",
+        None,
+        serde_json::to_value(&query).unwrap_or_default()
+    );
+
+    let (filename, content_type) = audit_report_file(&query);
+    let body = match query.format {
+        AuditReportFormat::Csv => {
+            let rows = audit_csv_stream(state.audit_service.clone(), query.clone())
+                .inspect_err(|e| error!("Audit report stream failed: {}", e));
+            Body::from_stream(rows)
+        }
+        AuditReportFormat::Pdf => {
+            let summary = state.audit_service.get_audit_report_summary(&query).await?;
+            Body::from(audit_summary_pdf(&query, &summary))
+        }
+    };
+
+    state.metrics_service.increment_counter("audit_reports_total", &[("format", &label(&query.format))]);
+
+    Ok(attachment(body, content_type, &filename))
+}
+
+/// Load historical audit entries, e.g. when migrating from another system (admin only).
+///
+/// The body is a JSON array of complete entries, streamed and loaded with `COPY` in
+/// batches of `AUDIT_BACKFILL_BATCH`. Each batch commits on its own, so after a failure
+/// the entries reported in the log as loaded are already stored.
+#[instrument(skip(state, entries))]
+pub async fn backfill_audit_logs(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    mut entries: JsonArrayStream,
+) -> Result<Json<serde_json::Value>> {
+    // The audit log spans every tenant, so tenant admins can't write to it
+    claims.require_cross_tenant_admin()?;
+
+    let started = Instant::now();
+    let mut loaded = 0u64;
+    let mut batch = Vec::with_capacity(AUDIT_BACKFILL_BATCH);
+    loop {
+        let entry = entries.next::<AuditLog>().await.inspect_err(|e| {
+            warn!("Audit backfill stopped after {} entries: {}", loaded, e);
+        })?;
+        let done = entry.is_none();
+        batch.extend(entry);
+
+        if batch.len() == AUDIT_BACKFILL_BATCH || (done && !batch.is_empty()) {
+            loaded += state.audit_store.bulk_load(&batch).await.inspect_err(|e| {
+                warn!("Audit backfill stopped after {} entries: {}", loaded, e);
+            })?;
+            batch.clear();
+        }
+        if done {
+            break;
+        }
+    }
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "backfill_audit_logs",
+        AuditCategory::Admin,
+        AuditSeverity::Warning,
+        "audit_log",
+        None,
+        "127.0.0.1",
+        None,
+        serde_json::json!({ "loaded": loaded })
+    );
+
+    state.metrics_service.increment_counter_by("audit_entries_backfilled_total", loaded, &[]);
+    info!("Backfilled {} audit entries in {:?}", loaded, started.elapsed());
+
+    Ok(Json(serde_json::json!({ "loaded": loaded })))
+}
+
+/// Admins may export the audit log; being cross-tenant, tenant admins also need super-admin
+pub(crate) fn authorize_audit_report(claims: &Claims, query: &AuditReportQuery) -> Result<()> {
+    // The audit log spans every tenant, so tenant admins can't export it
+    claims.require_cross_tenant_admin()?;
+
+    if query.from >= query.to {
+        return Err(ApiError::BadRequest("`from` must be before `to`".to_string()));
+    }
+    Ok(())
+}
+
+/// `body` served as a download named `filename`
+pub(crate) fn attachment(body: Body, content_type: &str, filename: &str) -> Response {
+    let mut response = body.into_response();
+    let headers = response.headers_mut();
+    if let Ok(content_type) = HeaderValue::from_str(content_type) {
+        headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    if let Ok(disposition) = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", filename)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+    response
+}
+
+/// Get precomputed aggregate stats (admin only)
+#[instrument(skip(state))]
+pub async fn get_stats(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<AggregateStats>> {
+    // Totals span every tenant, so tenant admins can't see them
+    claims.require_cross_tenant_admin()?;
+
+    // Served from the snapshot maintained by the background refresh job
+    let stats = state
+        .stats_cache
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| ApiError::NotFound("Stats have not been computed yet".to_string()))?;
+
+    Ok(Json(stats))
+}
+
+/// List JWT signing keys and their rotation status (admin only)
+#[instrument(skip(state))]
+pub async fn list_signing_keys(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<SigningKeyInfo>>> {
+    // Signing keys are shared by every tenant
+    claims.require_cross_tenant_admin()?;
+
+    Ok(Json(state.auth_service.signing_keys()))
+}
+
+/// Promote the standby JWT key to primary; the previous key is retired by the
+/// scheduler once its grace period ends (admin only)
+#[instrument(skip(state))]
+pub async fn promote_signing_key(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<KeyRotation>> {
+    claims.require_cross_tenant_admin()?;
+
+    let rotation = state.auth_service.promote_standby_key().await?;
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "signing_key_promoted",
+        AuditCategory::Security,
+        AuditSeverity::Critical,
+        "signing_key",
+        None,
+        "127.0.0.1",
+        None,
+        serde_json::json!({
+            "primary_key_id": rotation.primary_key_id,
+            "retiring_key_id": rotation.retiring_key_id,
+            "retires_at": rotation.retires_at,
+        })
+    );
+
+    state.metrics_service.increment_counter("jwt_key_rotations_total", &[]);
+    info!("Signing key '{}' promoted by admin {}", rotation.primary_key_id, claims.sub);
+
+    Ok(Json(rotation))
+}
+
 /// Get all feature flags (admin only)
 #[instrument(skip(state))]
 pub async fn list_feature_flags(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<Vec<FeatureFlag>>> {
-    if !claims.has_role("admin") {
-        return Err(ApiError::Unauthorized("Admin access required".to_string()));
-    }
+    claims.require_admin()?;
 
     let flags = state.feature_flags.list_flags().await?;
 
@@ -64,6 +299,8 @@ pub async fn list_feature_flags(
         state.audit_service,
         Some(claims.sub),
         "list_feature_flags",
+        AuditCategory::Access,
+        AuditSeverity::Info,
         "feature_flag",
         None,
         "127.0.0.1",
@@ -73,6 +310,158 @@ pub async fn list_feature_flags(
     Ok(Json(flags))
 }
 
+/// Every flag with its prerequisites and dependents (admin only)
+#[instrument(skip(state))]
+pub async fn get_feature_flag_dependencies(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<FlagDependencies>>> {
+    claims.require_admin()?;
+
+    let flags = state.feature_flags.list_flags().await?;
+    Ok(Json(dependency_graph(&flags)))
+}
+
+/// `audit.*` flags decide what every tenant's audit log records, so tenant admins may not
+/// change them
+fn ensure_flag_writable(claims: &Claims, flag_name: &str) -> Result<()> {
+    if is_audit_flag(flag_name) && claims.is_tenant_bound() {
+        return Err(ApiError::Unauthorized("Audit flags can only be changed by a super-admin".to_string()));
+    }
+    Ok(())
+}
+
+/// Changes to `audit.*` flags are critical so the audit gate can never drop them
+fn flag_change_severity(flag_name: &str) -> AuditSeverity {
+    if is_audit_flag(flag_name) {
+        AuditSeverity::Critical
+    } else {
+        AuditSeverity::Warning
+    }
+}
+
+/// Create a feature flag (admin only)
+#[instrument(skip(state))]
+pub async fn create_feature_flag(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<CreateFeatureFlagRequest>,
+) -> Result<(StatusCode, Json<FeatureFlag>)> {
+    claims.require_admin()?;
+    request.validate()?;
+    ensure_flag_writable(&claims, &request.name)?;
+
+    let now = time::OffsetDateTime::now_utc();
+    let flag = FeatureFlag {
+        name: request.name,
+        enabled: request.enabled,
+        rollout_percentage: request.rollout_percentage,
+        conditions: request.conditions,
+        depends_on: request.depends_on,
+        variants: request.variants,
+        created_at: now,
+        updated_at: now,
+    };
+    if !state.feature_flags.create_flag(flag.clone()).await? {
+        return Err(ApiError::Conflict(format!("Feature flag {} already exists", flag.name)));
+    }
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "create_feature_flag",
+        AuditCategory::Admin,
+        flag_change_severity(&flag.name),
+        "feature_flag",
+        None,
+        "127.0.0.1",
+        None,
+        serde_json::to_value(&flag).unwrap_or_default()
+    );
+
+    info!("Feature flag '{}' created", sanitize_str(&flag.name));
+
+    Ok((StatusCode::CREATED, Json(flag)))
+}
+
+/// Replace a feature flag's rollout, conditions, dependencies and variants (admin only)
+#[instrument(skip(state))]
+pub async fn update_feature_flag(
+    State(state): State<Arc<AppState>>,
+    Path(flag_name): Path<String>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<UpdateFeatureFlagRequest>,
+) -> Result<Json<FeatureFlag>> {
+    claims.require_admin()?;
+    request.validate()?;
+    ensure_flag_writable(&claims, &flag_name)?;
+
+    let mut flag = state.feature_flags.get_flag(&flag_name).await?
+        .ok_or_else(|| ApiError::NotFound("Feature flag not found".to_string()))?;
+    let previous = serde_json::to_value(&flag).unwrap_or_default();
+
+    flag.rollout_percentage = request.rollout_percentage;
+    flag.conditions = request.conditions;
+    flag.depends_on = request.depends_on;
+    flag.variants = request.variants;
+    flag.updated_at = time::OffsetDateTime::now_utc();
+
+    state.feature_flags.set_flag(flag.clone()).await?;
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "update_feature_flag",
+        AuditCategory::Admin,
+        flag_change_severity(&flag_name),
+        "feature_flag",
+        None,
+        "127.0.0.1",
+        None,
+        serde_json::json!({
+            "flag_name": flag_name,
+            "previous": previous,
+            "updated": flag
+        })
+    );
+
+    info!("Feature flag '{}' updated", sanitize_str(&flag_name));
+
+    Ok(Json(flag))
+}
+
+/// Delete a feature flag (admin only). Refused while other flags depend on it.
+#[instrument(skip(state))]
+pub async fn delete_feature_flag(
+    State(state): State<Arc<AppState>>,
+    Path(flag_name): Path<String>,
+    Extension(claims): Extension<Claims>,
+) -> Result<StatusCode> {
+    claims.require_admin()?;
+    ensure_flag_writable(&claims, &flag_name)?;
+
+    if !state.feature_flags.delete_flag(&flag_name).await? {
+        return Err(ApiError::NotFound("Feature flag not found".to_string()));
+    }
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "delete_feature_flag",
+        AuditCategory::Admin,
+        flag_change_severity(&flag_name),
+        "feature_flag",
+        None,
+        "127.0.0.1",
+        None,
+        serde_json::json!({ "flag_name": flag_name })
+    );
+
+    info!("Feature flag '{}' deleted", sanitize_str(&flag_name));
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Toggle a feature flag (admin only)
 #[instrument(skip(state))]
 pub async fn toggle_feature_flag(
@@ -80,9 +469,8 @@ pub async fn toggle_feature_flag(
     Path(flag_name): Path<String>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<FeatureFlag>> {
-    if !claims.has_role("admin") {
-        return Err(ApiError::Unauthorized("Admin access required".to_string()));
-    }
+    claims.require_admin()?;
+    ensure_flag_writable(&claims, &flag_name)?;
 
     let mut flag = state.feature_flags.get_flag(&flag_name).await?
         .ok_or_else(|| ApiError::NotFound("Feature flag not found".to_string()))?;
@@ -97,6 +485,8 @@ pub async fn toggle_feature_flag(
         state.audit_service,
         Some(claims.sub),
         "toggle_feature_flag",
+        AuditCategory::Admin,
+        flag_change_severity(&flag_name),
         "feature_flag",
         None,
         "127.0.0.1",
@@ -107,11 +497,118 @@ pub async fn toggle_feature_flag(
         })
     );
 
-    info!("Feature flag '{}' toggled to: {}", flag_name, flag.enabled);
+    info!("Feature flag '{}' toggled to: {}", sanitize_str(&flag_name), flag.enabled);
 
     Ok(Json(flag))
 }
 
+/// Preview how many recently active users a new rollout percentage would add or remove,
+/// bucketing them exactly as evaluations do; the live flag is not changed (admin only)
+#[instrument(skip(state))]
+pub async fn simulate_feature_flag_rollout(
+    State(state): State<Arc<AppState>>,
+    Path(flag_name): Path<String>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<RolloutSimulationRequest>,
+) -> Result<Json<RolloutSimulation>> {
+    claims.require_admin()?;
+
+    if !(0.0..=100.0).contains(&request.rollout_percentage) {
+        return Err(ApiError::Validation("rollout_percentage must be between 0 and 100".to_string()));
+    }
+
+    let flag = state.feature_flags.get_flag(&flag_name).await?
+        .ok_or_else(|| ApiError::NotFound("Feature flag not found".to_string()))?;
+
+    let sample_size = request
+        .sample_size
+        .unwrap_or(ROLLOUT_SIMULATION_MAX_SAMPLE)
+        .clamp(1, ROLLOUT_SIMULATION_MAX_SAMPLE);
+    // Tenant admins preview against their own users; super-admins against everyone
+    let user_ids = state
+        .db_pool
+        .user_repository(claims.tenant_scope(true))
+        .recently_active_ids(sample_size)
+        .await?;
+
+    let mut simulation = RolloutSimulation {
+        flag_name: flag.name,
+        current_percentage: flag.rollout_percentage,
+        proposed_percentage: request.rollout_percentage,
+        sampled_users: user_ids.len(),
+        in_rollout_now: 0,
+        in_rollout_after: 0,
+        newly_enabled: 0,
+        newly_disabled: 0,
+    };
+    for user_id in &user_ids {
+        let user_id = user_id.to_string();
+        let now = in_rollout(&simulation.flag_name, simulation.current_percentage, &user_id);
+        let after = in_rollout(&simulation.flag_name, simulation.proposed_percentage, &user_id);
+
+        simulation.in_rollout_now += usize::from(now);
+        simulation.in_rollout_after += usize::from(after);
+        simulation.newly_enabled += usize::from(!now && after);
+        simulation.newly_disabled += usize::from(now && !after);
+    }
+
+    Ok(Json(simulation))
+}
+
+/// Recorded flag evaluations for a user within a time range, newest first (admin only).
+/// Empty unless `monitoring.flag_evaluation_log` was on at the time; only a sample of
+/// evaluations is recorded, so a missing entry doesn't mean the flag wasn't evaluated.
+#[instrument(skip(state))]
+pub async fn get_flag_evaluation_history(
+    State(state): State<Arc<AppState>>,
+    IdPath(user_id): IdPath,
+    Query(query): Query<FlagEvaluationQuery>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<FlagEvaluation>>> {
+    claims.require_admin()?;
+
+    if query.from >= query.to {
+        return Err(ApiError::BadRequest("`from` must be before `to`".to_string()));
+    }
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        "view_flag_evaluations",
+        AuditCategory::Access,
+        AuditSeverity::Info,
+        "user",
+        Some(user_id),
+        "127.0.0.1",
+        None,
+        serde_json::to_value(&query).unwrap_or_default()
+    );
+
+    let evaluations = state
+        .flag_evaluations
+        .history(&user_id.to_string(), &query, FLAG_EVALUATION_QUERY_LIMIT)
+        .await?;
+
+    Ok(Json(evaluations))
+}
+
+/// Attributes of the caller that flag conditions are evaluated against, on top of any
+/// the client supplies in `extra`; the caller's own attributes can't be overridden
+fn flag_context(claims: &Claims, extra: Option<serde_json::Value>) -> Result<serde_json::Value> {
+    let mut context = match extra {
+        None => serde_json::Map::new(),
+        Some(serde_json::Value::Object(extra)) => extra,
+        Some(_) => return Err(ApiError::BadRequest("context must be a JSON object".to_string())),
+    };
+
+    context.insert(
+        "user_tier".to_string(),
+        serde_json::json!(if claims.has_role("premium") { "premium" } else { "basic" }),
+    );
+    context.insert("user_id".to_string(), serde_json::json!(claims.sub));
+    Ok(serde_json::Value::Object(context))
+}
+
 /// Check if a feature is enabled for the current user
 #[instrument(skip(state))]
 pub async fn check_feature_flag(
@@ -120,22 +617,97 @@ pub async fn check_feature_flag(
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<serde_json::Value>> {
     // Create user context for feature flag evaluation
-    let context = serde_json::json!({
-        "user_tier": if claims.has_role("premium") { "premium" } else { "basic" },
-        "user_id": claims.sub
-    });
+    let context = flag_context(&claims, None)?;
 
-    let enabled = state.feature_flags.is_enabled(
-        &flag_name,
-        Some(&claims.sub.to_string()),
-        Some(&context),
-    ).await;
+    let user_id = claims.sub.to_string();
+    let enabled = state.feature_flags.is_enabled(&flag_name, Some(&user_id), Some(&context)).await;
 
-    Ok(Json(serde_json::json!({
+    let mut response = serde_json::json!({
         "flag_name": flag_name,
         "enabled": enabled,
         "user_id": claims.sub
-    })))
+    });
+    // Multivariate flags also report which variant the user is in. The flag was just
+    // evaluated, so assign from it directly rather than evaluating (and logging) it again.
+    if enabled {
+        if let Some(flag) = state.feature_flags.get_flag(&flag_name).await? {
+            if let Some(variant) = assign_variant(&flag.name, &flag.variants, Some(&user_id)) {
+                response["variant"] = serde_json::json!(variant.name);
+            }
+        }
+    }
+
+    Ok(Json(response))
+}
+
+/// Evaluate several flags for the current user in one request, e.g. on page load.
+/// Unknown flags are reported as disabled.
+#[instrument(skip(state, request))]
+pub async fn evaluate_feature_flags(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<BulkFlagEvaluationRequest>,
+) -> Result<Json<BTreeMap<String, bool>>> {
+    if request.flags.len() > MAX_BULK_FLAG_EVALUATIONS {
+        return Err(ApiError::BadRequest(format!(
+            "At most {} flags can be evaluated per request",
+            MAX_BULK_FLAG_EVALUATIONS
+        )));
+    }
+
+    let context = flag_context(&claims, request.context)?;
+    let user_id = claims.sub.to_string();
+
+    let mut results = BTreeMap::new();
+    for flag_name in request.flags {
+        if results.contains_key(&flag_name) {
+            continue;
+        }
+        let enabled = state.feature_flags.is_enabled(&flag_name, Some(&user_id), Some(&context)).await;
+        results.insert(flag_name, enabled);
+    }
+
+    Ok(Json(results))
+}
+
+/// Name the simulated service behind the demo circuit breaker is tracked under
+pub const DEMO_DEPENDENCY: &str = "demo_service";
+
+/// Name the enhanced profile's analytics lookups are tracked under
+pub const PROFILE_ANALYTICS_DEPENDENCY: &str = "profile_analytics";
+
+/// Success rate, p99 latency and circuit state of each downstream dependency (admin only)
+#[instrument(skip(state))]
+pub async fn get_dependency_health(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<DependencyReport>>> {
+    claims.require_admin()?;
+
+    Ok(Json(state.dependency_health.report().await))
+}
+
+/// State and failure count of every circuit breaker, by downstream service (admin only)
+#[instrument(skip(state))]
+pub async fn list_circuit_breakers(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<CircuitBreakerSnapshot>>> {
+    claims.require_admin()?;
+
+    Ok(Json(state.circuit_breakers.snapshot().await))
+}
+
+/// Requests currently being handled and the oldest of them (admin only).
+/// A steadily growing oldest age points at a deadlock or a stuck dependency.
+#[instrument(skip(state))]
+pub async fn get_in_flight_requests(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<InFlightSnapshot>> {
+    claims.require_admin()?;
+
+    Ok(Json(state.in_flight.snapshot()))
 }
 
 /// Demonstrate circuit breaker functionality
@@ -144,8 +716,11 @@ pub async fn circuit_breaker_demo(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<serde_json::Value>> {
+    let start = Instant::now();
+
     // Use circuit breaker to protect a potentially failing operation
-    let result = state.circuit_breaker.call(|| {
+    let breaker = state.circuit_breakers.get(DEMO_DEPENDENCY);
+    let result = breaker.call(|| {
         // Simulate a service call that might fail
         use rand::Rng;
         let mut rng = rand::thread_rng();
@@ -155,9 +730,10 @@ pub async fn circuit_breaker_demo(
             Ok("Service call successful")
         }
     }).await;
+    state.dependency_health.record(DEMO_DEPENDENCY, start.elapsed(), result.is_ok());
 
-    let circuit_state = state.circuit_breaker.get_state().await;
-    let failure_count = state.circuit_breaker.get_failure_count();
+    let circuit_state = breaker.get_state().await;
+    let failure_count = breaker.get_failure_count();
 
     match result {
         Ok(message) => Ok(Json(serde_json::json!({
@@ -181,7 +757,24 @@ pub async fn get_enhanced_profile(
     State(state): State<Arc<AppState>>,
     Extension(claims): Extension<Claims>,
 ) -> Result<Json<serde_json::Value>> {
-    let user_repo = state.db_pool.user_repository();
+    // This endpoint is heavier than a normal GET, so it has its own per-user quota
+    if let Err(e) = state.enhanced_profile_limiter.check(&claims) {
+        let _ = audit_action!(
+            state.audit_service,
+            Some(claims.sub),
+            "enhanced_profile_rate_limited",
+            AuditCategory::Security,
+            AuditSeverity::Warning,
+            "user",
+            Some(claims.sub),
+            "127.0.0.1",
+            None
+        );
+        return Err(e);
+    }
+
+    let start_time = Instant::now();
+    let user_repo = state.db_pool.user_repository(claims.tenant_scope(false));
     let user = user_repo.find_by_id(claims.sub).await?
         .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
 
@@ -204,6 +797,8 @@ pub async fn get_enhanced_profile(
         state.audit_service,
         Some(claims.sub),
         "view_enhanced_profile",
+        AuditCategory::Access,
+        AuditSeverity::Info,
         "user",
         Some(user.id),
         "127.0.0.1",
@@ -233,14 +828,62 @@ pub async fn get_enhanced_profile(
         });
     }
 
-    // Add analytics if enabled
+    // Add analytics if enabled; the profile is still served without them when the
+    // lookup is unavailable
     if analytics_enabled {
-        response["analytics"] = serde_json::json!({
+        match load_profile_analytics(&state, &user_repo, &user).await {
+            Ok(analytics) => response["analytics"] = analytics,
+            Err(reason) => {
+                record_degraded_response(&state, "enhanced_profile", reason);
+                response["degraded"] = serde_json::json!(true);
+            }
+        }
+    }
+
+    state.metrics_service.record_histogram(
+        "enhanced_profile_duration_milliseconds",
+        start_time.elapsed().as_millis() as f64,
+        &[],
+    );
+
+    Ok(Json(response))
+}
+
+/// Analytics section of the enhanced profile, or why it couldn't be loaded
+async fn load_profile_analytics(
+    state: &AppState,
+    user_repo: &dyn UserRepositoryTrait,
+    user: &User,
+) -> std::result::Result<serde_json::Value, &'static str> {
+    // Enrichment lookups have their own breaker; the profile degrades without them
+    let breaker = state.circuit_breakers.get(PROFILE_ANALYTICS_DEPENDENCY);
+    if !breaker.allow_request().await {
+        return Err("circuit_open");
+    }
+
+    let started = Instant::now();
+    let active_sessions = user_repo.count_active_sessions(user.id).await;
+    let healthy = active_sessions.is_ok();
+    breaker.record_outcome(healthy).await;
+    state.dependency_health.record(PROFILE_ANALYTICS_DEPENDENCY, started.elapsed(), healthy);
+
+    match active_sessions {
+        Ok(active_sessions) => Ok(serde_json::json!({
             "login_count": 42,
+            "active_sessions": active_sessions,
             "last_active": user.updated_at,
             "engagement_score": 85.5
-        });
+        })),
+        Err(e) => {
+            warn!("Enhanced profile analytics lookup failed: {}", e);
+            Err("enrichment_failed")
+        }
     }
+}
 
-    Ok(Json(response))
+/// Count and log a response served without some of its content because a dependency
+/// was unavailable
+fn record_degraded_response(state: &AppState, endpoint: &str, reason: &str) {
+    state.metrics_service.increment_counter("degraded_responses_total", &[("endpoint", endpoint)]);
+    info!(endpoint, reason, "Serving degraded response");
 }
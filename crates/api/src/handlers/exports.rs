@@ -0,0 +1,114 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::StatusCode,
+    response::Response,
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+use tracing::{info, instrument};
+
+use crate::extractors::{IdPath, JsonBody};
+use crate::handlers::enterprise::{attachment, authorize_audit_report};
+use crate::middleware::rate_limit::RequestCost;
+use crate::reports::label;
+use crate::state::AppState;
+use auth::Claims;
+use app_core::enterprise::{AuditCategory, AuditSeverity, ExportJob, ExportRequest};
+use app_core::error::{ApiError, Result};
+use app_core::timestamp::Json;
+use monitoring::audit_action;
+
+/// An export's progress, with a signed download link once it is ready
+#[derive(Debug, Serialize)]
+pub struct ExportResponse {
+    #[serde(flatten)]
+    pub job: ExportJob,
+    pub download_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+fn export_response(state: &AppState, job: ExportJob) -> ExportResponse {
+    let download_url = state.exports.download_url(&state.config.server.public_url, &job);
+    ExportResponse { job, download_url }
+}
+
+/// Queue an export to be generated in the background. Poll the returned export until
+/// it is `ready`, then fetch its `download_url`.
+#[instrument(skip(state, request))]
+pub async fn create_export(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<ExportRequest>,
+) -> Result<(StatusCode, Json<ExportResponse>)> {
+    let (action, format, details) = match &request {
+        ExportRequest::AuditReport(query) => {
+            authorize_audit_report(&claims, query)?;
+            ("export_audit_report", label(&query.format), serde_json::to_value(query).unwrap_or_default())
+        }
+    };
+
+    state.request_costs.charge(&claims, RequestCost::Export)?;
+    let job = state.exports.enqueue(claims.sub, request).await?;
+
+    let _ = audit_action!(
+        state.audit_service,
+        Some(claims.sub),
+        action,
+        AuditCategory::Security,
+        AuditSeverity::Warning,
+        "export",
+        Some(job.id),
+        "127.0.0.1",
+        None,
+        details
+    );
+
+    state.metrics_service.increment_counter("exports_requested_total", &[("format", &format)]);
+    info!("Export {} queued by user {}", job.id, claims.sub);
+
+    Ok((StatusCode::ACCEPTED, Json(export_response(&state, job))))
+}
+
+/// Progress of one of the caller's exports
+#[instrument(skip(state))]
+pub async fn get_export(
+    State(state): State<Arc<AppState>>,
+    IdPath(id): IdPath,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ExportResponse>> {
+    // Other users' exports are indistinguishable from missing ones
+    let job = state
+        .exports
+        .get(id)
+        .await?
+        .filter(|job| job.requested_by == claims.sub)
+        .ok_or_else(|| ApiError::NotFound("Export not found".to_string()))?;
+
+    Ok(Json(export_response(&state, job)))
+}
+
+/// Stream a ready export. Authenticated by the link's signature rather than a token,
+/// so the link works in a browser or download manager until it expires.
+#[instrument(skip(state, query))]
+pub async fn download_export(
+    State(state): State<Arc<AppState>>,
+    IdPath(id): IdPath,
+    Query(query): Query<DownloadQuery>,
+) -> Result<Response> {
+    let job = state.exports.verify_download(id, query.expires, &query.signature).await?;
+
+    let file = tokio::fs::File::open(state.exports.path(&job)).await.map_err(|e| {
+        ApiError::Internal(anyhow::anyhow!("Failed to open export {}: {}", job.id, e))
+    })?;
+
+    state.metrics_service.increment_counter("exports_downloaded_total", &[]);
+    Ok(attachment(Body::from_stream(ReaderStream::new(file)), &job.content_type, &job.filename))
+}
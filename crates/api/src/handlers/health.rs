@@ -1,30 +1,99 @@
-use axum::{extract::State, response::Json};
+use axum::extract::State;
+use futures_util::future::OptionFuture;
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use crate::state::AppState;
 use app_core::error::Result;
+use app_core::timestamp::Json;
+use database::RedisCache;
+
+/// Upper bound for any single dependency probe
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Name the database is tracked under in dependency health
+pub const DATABASE_DEPENDENCY: &str = "database";
+
+/// Health of a single dependency, including how long the probe took
+#[derive(Debug, Serialize)]
+pub struct DependencyHealth {
+    pub status: &'static str,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DependencyHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.status == "healthy"
+    }
+}
+
+/// Run a dependency probe with its own timeout, recording latency and any error
+pub async fn probe<F>(check: F) -> DependencyHealth
+where
+    F: Future<Output = Result<()>>,
+{
+    let start = Instant::now();
+    let outcome = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, check).await;
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    match outcome {
+        Ok(Ok(())) => DependencyHealth { status: "healthy", latency_ms, error: None },
+        Ok(Err(e)) => DependencyHealth {
+            status: "unhealthy",
+            latency_ms,
+            error: Some(e.to_string()),
+        },
+        Err(_) => DependencyHealth {
+            status: "unhealthy",
+            latency_ms,
+            error: Some(format!("Timed out after {:?}", HEALTH_CHECK_TIMEOUT)),
+        },
+    }
+}
 
 /// Health check endpoint that verifies all services are operational
 pub async fn health_check(State(state): State<Arc<AppState>>) -> Result<Json<Value>> {
-    // Check database connectivity
-    let db_healthy = state.db_pool.health_check().await.is_ok();
+    // Probe every dependency concurrently; the cache only when one is configured
+    let redis_cache = state.products.cache();
+    let (database, cache) = tokio::join!(
+        probe(state.db_pool.health_check()),
+        OptionFuture::from(redis_cache.map(|cache| probe(cache.health_check()))),
+    );
+    state.dependency_health.record(
+        DATABASE_DEPENDENCY,
+        Duration::from_millis(database.latency_ms),
+        database.is_healthy(),
+    );
 
-    // Check Redis connectivity (if configured)
-    let cache_healthy = true; // Simplified for example
+    // A stalled critical job (crashed or hung) means work is silently piling up
+    let jobs = state.scheduler.job_statuses();
+    let jobs_healthy = jobs.iter().all(|job| job.healthy || !job.critical);
 
-    let status = if db_healthy && cache_healthy {
+    // Requests still succeed while a fail-open cache is down, so it's reported but
+    // doesn't make the instance unhealthy
+    let cache_healthy = cache.as_ref().is_none_or(DependencyHealth::is_healthy)
+        || redis_cache.is_some_and(RedisCache::fails_open);
+
+    let status = if database.is_healthy() && cache_healthy && jobs_healthy {
         "healthy"
     } else {
         "unhealthy"
     };
 
+    let mut services = json!({ "database": database });
+    if let Some(cache) = cache {
+        services["cache"] = json!(cache);
+    }
+
     Ok(Json(json!({
         "status": status,
         "timestamp": time::OffsetDateTime::now_utc(),
-        "services": {
-            "database": if db_healthy { "healthy" } else { "unhealthy" },
-            "cache": if cache_healthy { "healthy" } else { "unhealthy" }
-        },
+        "services": services,
+        "jobs": jobs,
         "version": env!("CARGO_PKG_VERSION")
     })))
 }
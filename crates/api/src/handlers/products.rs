@@ -1,34 +1,40 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Query, State},
     http::StatusCode,
-    response::Json,
-    Extension,
 };
+use std::collections::HashSet;
 use std::sync::Arc;
-use tracing::{info, instrument};
 use uuid::Uuid;
+use tracing::{error, info, instrument};
 use validator::Validate;
 
+use crate::extractors::{Authorized, CsvRecord, CsvStream, Fields, IdPath, JsonBody, Tenant};
+use crate::middleware::rate_limit::RequestCost;
 use crate::state::AppState;
-use auth::Claims;
+use auth::{ProductsDelete, ProductsWrite};
+use app_core::enterprise::{AuditCategory, AuditSeverity};
 use app_core::error::{ApiError, Result};
-use app_core::models::{Product, CreateProductRequest, PaginationParams, ListResponse};
+use app_core::timestamp::Json;
+use app_core::models::{
+    Product, Created, CreateProductRequest, UpdateProductRequest, BulkDeleteRequest, BulkPriceUpdateRequest, PriceUpdate,
+    PaginationParams, ListResponse, MultiStatus, ImportRowIssue, ProductImportParams, ProductImportSummary,
+};
+use database::ProductRepositoryTrait;
+use monitoring::audit_diff;
 
 #[instrument(skip(state))]
 pub async fn list_products(
     State(state): State<Arc<AppState>>,
+    Tenant(scope): Tenant,
     Query(pagination): Query<PaginationParams>,
-) -> Result<Json<ListResponse<Product>>> {
-    // In a real implementation, you would have a product repository
-    // For now, return empty list with proper pagination
+    fields: Fields<Product>,
+) -> Result<Json<ListResponse<serde_json::Value>>> {
+    let product_repo = state.products.repository(scope);
+    let products = product_repo.list(pagination).await?;
+
     let response = ListResponse {
-        data: vec![],
-        pagination: core::models::PaginationMetadata {
-            page: pagination.page.unwrap_or(1),
-            per_page: pagination.per_page.unwrap_or(20),
-            total: 0,
-            total_pages: 0,
-        },
+        data: fields.project_all(&products.data)?,
+        pagination: products.pagination,
     };
 
     state.metrics_service.increment_counter("products_listed_total", &[]);
@@ -38,63 +44,678 @@ pub async fn list_products(
 #[instrument(skip(state))]
 pub async fn get_product(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
+    IdPath(id): IdPath,
+    Tenant(scope): Tenant,
 ) -> Result<Json<Product>> {
-    // Placeholder implementation
+    let product_repo = state.products.repository(scope);
+    let product = product_repo.find_by_id(id).await?
+        .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
     state.metrics_service.increment_counter("product_retrieved_total", &[]);
-    Err(ApiError::NotFound("Product not found".to_string()))
+    Ok(Json(product))
 }
 
 #[instrument(skip(state, request))]
 pub async fn create_product(
     State(state): State<Arc<AppState>>,
-    Extension(claims): Extension<Claims>,
-    Json(request): Json<CreateProductRequest>,
-) -> Result<Json<Product>> {
+    claims: Authorized<ProductsWrite>,
+    Tenant(scope): Tenant,
+    JsonBody(request): JsonBody<CreateProductRequest>,
+) -> Result<Created<Product>> {
     // Validate request
-    request.validate()
-        .map_err(|e| ApiError::Validation(format!("Validation failed: {}", e)))?;
+    request.validate()?;
 
-    // Check if user has permission to create products
-    if !claims.has_role("admin") && !claims.has_role("merchant") {
-        return Err(ApiError::Unauthorized("Insufficient permissions".to_string()));
-    }
+    let product_repo = state.products.repository(scope);
+    let product = product_repo.create(request).await?;
 
     state.metrics_service.increment_counter("product_created_total", &[]);
-    info!("Product creation attempted by user: {}", claims.sub);
+    info!("Product {} created by user: {}", product.id, claims.sub);
 
-    // Placeholder implementation
-    Err(ApiError::NotFound("Product creation not implemented yet".to_string()))
+    Ok(Created::new(format!("/api/v1/products/{}", product.id), product))
 }
 
-#[instrument(skip(state))]
+#[instrument(skip(state, request))]
 pub async fn update_product(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
-    Extension(claims): Extension<Claims>,
+    IdPath(id): IdPath,
+    claims: Authorized<ProductsWrite>,
+    Tenant(scope): Tenant,
+    JsonBody(request): JsonBody<UpdateProductRequest>,
 ) -> Result<Json<Product>> {
-    // Check permissions
-    if !claims.has_role("admin") && !claims.has_role("merchant") {
-        return Err(ApiError::Unauthorized("Insufficient permissions".to_string()));
+    // Validate request
+    request.validate()?;
+
+    let product_repo = state.products.repository(scope);
+    let before = product_repo.find_by_id(id).await?
+        .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+    let product = product_repo.update(id, request).await?
+        .ok_or_else(|| ApiError::NotFound("Product not found".to_string()))?;
+
+    let changes = audit_diff(&before, &product);
+    if changes.as_object().is_some_and(|changes| !changes.is_empty()) {
+        if let Err(e) = state.audit_service.log_action(
+            Some(claims.sub),
+            "update_product",
+            AuditCategory::DataChange,
+            AuditSeverity::Info,
+            "product",
+            Some(product.id),
+            "127.0.0.1",
+            None,
+            serde_json::json!({ "changes": changes }),
+        ).await {
+            error!("Failed to audit product update: {}", e);
+        }
     }
 
     state.metrics_service.increment_counter("product_updated_total", &[]);
-    Err(ApiError::NotFound("Product update not implemented yet".to_string()))
+    info!("Product updated successfully: {}", product.id);
+
+    Ok(Json(product))
 }
 
 #[instrument(skip(state))]
 pub async fn delete_product(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
-    Extension(claims): Extension<Claims>,
+    IdPath(id): IdPath,
+    claims: Authorized<ProductsDelete>,
+    Tenant(scope): Tenant,
 ) -> Result<StatusCode> {
-    // Check permissions
-    if !claims.has_role("admin") {
-        return Err(ApiError::Unauthorized("Only admins can delete products".to_string()));
+    let product_repo = state.products.repository(scope);
+    if !product_repo.soft_delete(id).await? {
+        return Err(ApiError::NotFound("Product not found".to_string()));
     }
 
     state.metrics_service.increment_counter("product_deleted_total", &[]);
-    info!("Product deletion attempted by admin: {}", claims.sub);
+    info!("Product {} deleted by admin: {}", id, claims.sub);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Undo a soft delete (requires `products:delete`)
+#[instrument(skip(state))]
+pub async fn restore_product(
+    State(state): State<Arc<AppState>>,
+    IdPath(id): IdPath,
+    claims: Authorized<ProductsDelete>,
+    Tenant(scope): Tenant,
+) -> Result<Json<Product>> {
+    let product_repo = state.products.repository(scope);
+    let Some(product) = product_repo.restore(id).await? else {
+        return Err(match product_repo.find_by_id_including_deleted(id).await? {
+            Some(_) => ApiError::Conflict("Product is not deleted".to_string()),
+            None => ApiError::NotFound("Product not found".to_string()),
+        });
+    };
+
+    let _ = state.audit_service.log_action(
+        Some(claims.sub),
+        "restore_product",
+        AuditCategory::Admin,
+        AuditSeverity::Warning,
+        "product",
+        Some(product.id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({}),
+    ).await;
+
+    state.metrics_service.increment_counter("product_restored_total", &[]);
+    info!("Product restored successfully: {}", product.id);
+
+    Ok(Json(product))
+}
+
+/// Maximum number of products deleted by a single bulk request
+const MAX_BULK_DELETES: usize = 500;
+
+/// Soft delete many products at once (requires `products:delete`). Requires `confirm: true`.
+#[instrument(skip(state, request))]
+pub async fn bulk_delete_products(
+    State(state): State<Arc<AppState>>,
+    claims: Authorized<ProductsDelete>,
+    Tenant(scope): Tenant,
+    JsonBody(request): JsonBody<BulkDeleteRequest>,
+) -> Result<MultiStatus> {
+    if !request.confirm {
+        return Err(ApiError::BadRequest("Bulk delete requires \"confirm\": true".to_string()));
+    }
+
+    if request.ids.len() > MAX_BULK_DELETES {
+        return Err(ApiError::BadRequest(format!(
+            "Bulk deletes are limited to {} items per request",
+            MAX_BULK_DELETES
+        )));
+    }
+    state.request_costs.charge(&claims, RequestCost::BulkWrite(request.ids.len()))?;
+
+    let mut seen = HashSet::new();
+    let unique: Vec<bool> = request.ids.iter().map(|id| seen.insert(*id)).collect();
+    let ids: Vec<_> = seen.into_iter().collect();
+
+    let product_repo = state.products.repository(scope);
+    let deleted = product_repo.delete_many(&ids).await?;
+    let deleted: HashSet<_> = ids.iter()
+        .zip(deleted)
+        .filter(|(_, deleted)| *deleted)
+        .map(|(id, _)| *id)
+        .collect();
+
+    let mut results = MultiStatus::new();
+    for (index, (id, unique)) in request.ids.iter().zip(unique).enumerate() {
+        if !unique {
+            results.push_error(index, &ApiError::BadRequest("Duplicate product id in batch".to_string()));
+        } else if deleted.contains(id) {
+            results.push_success(index, StatusCode::NO_CONTENT, *id);
+        } else {
+            results.push_error(index, &ApiError::NotFound("Product not found".to_string()));
+        }
+    }
+
+    if !deleted.is_empty() {
+        // One audit event for the whole batch
+        if let Err(e) = state.audit_service.log_action(
+            Some(claims.sub),
+            "bulk_delete",
+            AuditCategory::Admin,
+            AuditSeverity::Warning,
+            "product",
+            None,
+            "127.0.0.1",
+            None,
+            serde_json::json!({
+                "product_ids": deleted,
+                "count": deleted.len(),
+            }),
+        ).await {
+            error!("Failed to audit bulk product delete: {}", e);
+        }
+
+        state.metrics_service.increment_counter_by(
+            "product_deleted_total",
+            deleted.len() as u64,
+            &[],
+        );
+    }
+
+    info!(
+        "Bulk product delete by admin {}: {} requested, {} deleted",
+        claims.sub,
+        request.ids.len(),
+        deleted.len()
+    );
+
+    Ok(results)
+}
+
+/// Maximum number of price changes accepted in a single bulk request
+const MAX_BULK_PRICE_UPDATES: usize = 500;
+
+#[instrument(skip(state, request))]
+pub async fn bulk_update_prices(
+    State(state): State<Arc<AppState>>,
+    claims: Authorized<ProductsWrite>,
+    Tenant(scope): Tenant,
+    JsonBody(request): JsonBody<BulkPriceUpdateRequest>,
+) -> Result<MultiStatus> {
+    if request.updates.len() > MAX_BULK_PRICE_UPDATES {
+        return Err(ApiError::BadRequest(format!(
+            "Bulk price updates are limited to {} items per request",
+            MAX_BULK_PRICE_UPDATES
+        )));
+    }
+    state.request_costs.charge(&claims, RequestCost::BulkWrite(request.updates.len()))?;
+
+    // Validate every item up front; duplicate ids would make the outcome order-dependent
+    let mut seen = HashSet::new();
+    let invalid: Vec<Option<ApiError>> = request.updates.iter().map(|update| {
+        if let Err(e) = update.validate() {
+            Some(ApiError::from(e))
+        } else if !seen.insert(update.id) {
+            Some(ApiError::BadRequest("Duplicate product id in batch".to_string()))
+        } else {
+            None
+        }
+    }).collect();
+
+    let mut results = MultiStatus::new();
+
+    if request.atomic && invalid.iter().any(Option::is_some) {
+        for (index, (update, error)) in request.updates.iter().zip(&invalid).enumerate() {
+            match error {
+                Some(e) => results.push_error(index, e),
+                None => results.push_failed_dependency(index, update.id),
+            }
+        }
+        return Ok(results);
+    }
+
+    let valid: Vec<PriceUpdate> = request.updates.iter()
+        .zip(&invalid)
+        .filter(|(_, error)| error.is_none())
+        .map(|(update, _)| update.clone())
+        .collect();
+
+    let product_repo = state.products.repository(scope);
+    let applied = product_repo.update_prices(&valid, request.atomic).await?;
+    let rolled_back = request.atomic && applied.contains(&false);
+
+    let mut applied = applied.into_iter();
+    let mut updated_ids = Vec::new();
+    for (index, (update, error)) in request.updates.iter().zip(&invalid).enumerate() {
+        if let Some(e) = error {
+            results.push_error(index, e);
+            continue;
+        }
+
+        match applied.next() {
+            Some(true) if rolled_back => results.push_failed_dependency(index, update.id),
+            Some(true) => {
+                updated_ids.push(update.id);
+                results.push_success(index, StatusCode::OK, update.id);
+            }
+            _ => results.push_error(index, &ApiError::NotFound("Product not found".to_string())),
+        }
+    }
+
+    if !updated_ids.is_empty() {
+        // One audit event for the whole batch
+        if let Err(e) = state.audit_service.log_action(
+            Some(claims.sub),
+            "bulk_price_update",
+            AuditCategory::DataChange,
+            AuditSeverity::Info,
+            "product",
+            None,
+            "127.0.0.1",
+            None,
+            serde_json::json!({
+                "product_ids": updated_ids,
+                "count": updated_ids.len(),
+                "atomic": request.atomic,
+            }),
+        ).await {
+            error!("Failed to audit bulk price update: {}", e);
+        }
+
+        state.metrics_service.increment_counter_by(
+            "product_prices_updated_total",
+            updated_ids.len() as u64,
+            &[],
+        );
+    }
+
+    info!(
+        "Bulk price update by {} completed: {} items, {} failed",
+        claims.sub,
+        results.results.len(),
+        results.failed_count()
+    );
+
+    Ok(results)
+}
+
+/// Rows inserted per transaction during a CSV import
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/// Data rows accepted by a single CSV import
+const MAX_IMPORT_ROWS: usize = 10_000;
+
+/// Create products from a streamed `text/csv` body (requires `products:write`).
+///
+/// The header row names the columns: `name`, `price` (in cents) and `category_id`, plus an
+/// optional `description`. Valid rows are inserted in batches while the body is still
+/// arriving and bad rows are reported by line number. With `?strict=true` any bad row
+/// fails the request and nothing is created.
+#[instrument(skip(state, csv))]
+pub async fn import_products(
+    State(state): State<Arc<AppState>>,
+    claims: Authorized<ProductsWrite>,
+    Tenant(scope): Tenant,
+    Query(params): Query<ProductImportParams>,
+    mut csv: CsvStream,
+) -> Result<Json<ProductImportSummary>> {
+    state.request_costs.charge(&claims, RequestCost::Import)?;
+
+    let columns = match csv.next().await? {
+        Some(CsvRecord { fields: Ok(header), .. }) => ImportColumns::from_header(&header)?,
+        Some(CsvRecord { fields: Err(reason), .. }) => {
+            return Err(ApiError::BadRequest(format!("Invalid CSV header: {}", reason)));
+        }
+        None => return Err(ApiError::BadRequest("CSV body is empty".to_string())),
+    };
 
-    Err(ApiError::NotFound("Product deletion not implemented yet".to_string()))
+    let product_repo = state.products.repository(scope);
+    let mut summary = ProductImportSummary::default();
+    let mut seen = HashSet::new();
+    let mut batch = Vec::new();
+    let mut rows = 0;
+    let mut saved = Ok(());
+
+    while let Some(record) = csv.next().await? {
+        let fields = match record.fields {
+            Ok(fields) if fields.iter().all(|field| field.trim().is_empty()) => {
+                summary.skipped.push(import_issue(record.line, "blank line"));
+                continue;
+            }
+            Ok(fields) => fields,
+            Err(reason) => {
+                reject_import_row(&mut summary, params.strict, record.line, format!("malformed CSV: {}", reason))?;
+                continue;
+            }
+        };
+
+        rows += 1;
+        if rows > MAX_IMPORT_ROWS {
+            reject_import_row(
+                &mut summary,
+                params.strict,
+                record.line,
+                format!("imports are limited to {} rows; the rest of the file was not read", MAX_IMPORT_ROWS),
+            )?;
+            break;
+        }
+
+        let request = match columns.parse(&fields) {
+            Ok(request) => request,
+            Err(reason) => {
+                reject_import_row(&mut summary, params.strict, record.line, reason)?;
+                continue;
+            }
+        };
+
+        if !seen.insert((request.name.clone(), request.category_id)) {
+            summary.skipped.push(import_issue(record.line, "repeats an earlier row"));
+            continue;
+        }
+
+        batch.push((record.line, request));
+        // Strict imports go in as a single transaction once the whole file has been read
+        if !params.strict && batch.len() >= IMPORT_BATCH_SIZE {
+            saved = flush_import_batch(product_repo.as_ref(), &mut batch, false, &mut summary).await;
+            if saved.is_err() {
+                break;
+            }
+        }
+    }
+    if saved.is_ok() {
+        saved = flush_import_batch(product_repo.as_ref(), &mut batch, params.strict, &mut summary).await;
+    }
+
+    // A strict import was rolled back as a whole; otherwise report the rows saved before the failure
+    if let Err(e) = saved {
+        if params.strict {
+            return Err(e);
+        }
+        error!("Product import by {} stopped after a failed write: {}", claims.sub, e);
+    }
+
+    if summary.created > 0 {
+        if let Err(e) = state.audit_service.log_action(
+            Some(claims.sub),
+            "import_products",
+            AuditCategory::DataChange,
+            AuditSeverity::Info,
+            "product",
+            None,
+            "127.0.0.1",
+            None,
+            serde_json::json!({
+                "created": summary.created,
+                "skipped": summary.skipped.len(),
+                "errors": summary.errors.len(),
+                "strict": params.strict,
+            }),
+        ).await {
+            error!("Failed to audit product import: {}", e);
+        }
+
+        state.metrics_service.increment_counter_by(
+            "product_created_total",
+            summary.created as u64,
+            &[],
+        );
+    }
+
+    info!(
+        "Product import by {}: {} created, {} skipped, {} errors",
+        claims.sub,
+        summary.created,
+        summary.skipped.len(),
+        summary.errors.len()
+    );
+
+    Ok(Json(summary))
+}
+
+/// Positions of the import columns in the header row
+struct ImportColumns {
+    name: usize,
+    description: Option<usize>,
+    price: usize,
+    category_id: usize,
+    count: usize,
+}
+
+impl ImportColumns {
+    fn from_header(header: &[String]) -> Result<Self> {
+        let position = |column: &str| header.iter().position(|name| name.trim().eq_ignore_ascii_case(column));
+
+        let (Some(name), Some(price), Some(category_id)) = (position("name"), position("price"), position("category_id")) else {
+            let missing: Vec<&str> = ["name", "price", "category_id"]
+                .into_iter()
+                .filter(|column| position(column).is_none())
+                .collect();
+            return Err(ApiError::BadRequest(format!("CSV header is missing column(s): {}", missing.join(", "))));
+        };
+
+        Ok(Self {
+            name,
+            description: position("description"),
+            price,
+            category_id,
+            count: header.len(),
+        })
+    }
+
+    /// Build and validate the create request for one data row
+    fn parse(&self, fields: &[String]) -> std::result::Result<CreateProductRequest, String> {
+        if fields.len() != self.count {
+            return Err(format!("expected {} fields, found {}", self.count, fields.len()));
+        }
+
+        let request = CreateProductRequest {
+            name: fields[self.name].trim().to_string(),
+            description: self
+                .description
+                .map(|index| fields[index].trim())
+                .filter(|description| !description.is_empty())
+                .map(str::to_string),
+            price: fields[self.price]
+                .trim()
+                .parse()
+                .map_err(|_| "price must be a whole number of cents".to_string())?,
+            category_id: Uuid::parse_str(fields[self.category_id].trim())
+                .map_err(|_| "category_id must be a UUID".to_string())?,
+        };
+
+        request.validate().map_err(|e| ApiError::from(e).parts().1)?;
+        Ok(request)
+    }
+}
+
+fn import_issue(line: u64, reason: impl Into<String>) -> ImportRowIssue {
+    ImportRowIssue { line, reason: reason.into() }
+}
+
+/// Record a bad row, or fail the whole import in strict mode
+fn reject_import_row(summary: &mut ProductImportSummary, strict: bool, line: u64, reason: String) -> Result<()> {
+    if strict {
+        return Err(ApiError::Validation(format!("Line {}: {}; nothing was imported", line, reason)));
+    }
+    summary.errors.push(import_issue(line, reason));
+    Ok(())
+}
+
+/// Insert the buffered rows in one transaction; in strict mode any failure rolls it back.
+/// Outside strict mode a failed write is recorded against each of the batch's rows.
+async fn flush_import_batch(
+    product_repo: &dyn ProductRepositoryTrait,
+    batch: &mut Vec<(u64, CreateProductRequest)>,
+    strict: bool,
+    summary: &mut ProductImportSummary,
+) -> Result<()> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+
+    let (lines, requests): (Vec<u64>, Vec<CreateProductRequest>) = batch.drain(..).unzip();
+    let created = match product_repo.create_many(&requests, strict).await {
+        Ok(created) => created,
+        Err(e) => {
+            if !strict {
+                summary.errors.extend(lines.into_iter().map(|line| {
+                    import_issue(line, "could not be saved; the rest of the file was not imported")
+                }));
+            }
+            return Err(e);
+        }
+    };
+    for (line, product) in lines.into_iter().zip(created) {
+        match product {
+            Some(_) => summary.created += 1,
+            None => reject_import_row(summary, strict, line, "category does not exist".to_string())?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_core::traits::SoftDeleteRepository;
+    use axum::async_trait;
+
+    /// Creates every row whose name isn't "missing category", or fails every write
+    struct ImportRepository {
+        available: bool,
+    }
+
+    #[async_trait]
+    impl ProductRepositoryTrait for ImportRepository {
+        async fn create(&self, _: CreateProductRequest) -> Result<Product> {
+            unimplemented!()
+        }
+
+        async fn create_many(&self, requests: &[CreateProductRequest], _: bool) -> Result<Vec<Option<Product>>> {
+            if !self.available {
+                return Err(ApiError::ServiceUnavailable("database is down".to_string()));
+            }
+            Ok(requests
+                .iter()
+                .map(|request| {
+                    (request.name != "missing category").then(|| Product {
+                        id: Uuid::new_v4(),
+                        tenant_id: None,
+                        name: request.name.clone(),
+                        description: None,
+                        price: request.price,
+                        category_id: request.category_id,
+                        is_active: true,
+                        created_at: time::OffsetDateTime::now_utc(),
+                        updated_at: time::OffsetDateTime::now_utc(),
+                        deleted_at: None,
+                    })
+                })
+                .collect())
+        }
+
+        async fn find_by_id(&self, _: Uuid) -> Result<Option<Product>> {
+            unimplemented!()
+        }
+
+        async fn list(&self, _: PaginationParams) -> Result<ListResponse<Product>> {
+            unimplemented!()
+        }
+
+        async fn update(&self, _: Uuid, _: UpdateProductRequest) -> Result<Option<Product>> {
+            unimplemented!()
+        }
+
+        async fn update_prices(&self, _: &[PriceUpdate], _: bool) -> Result<Vec<bool>> {
+            unimplemented!()
+        }
+
+        async fn delete_many(&self, _: &[Uuid]) -> Result<Vec<bool>> {
+            unimplemented!()
+        }
+    }
+
+    #[async_trait]
+    impl SoftDeleteRepository<Product, Uuid> for ImportRepository {
+        async fn soft_delete(&self, _: Uuid) -> Result<bool> {
+            unimplemented!()
+        }
+
+        async fn restore(&self, _: Uuid) -> Result<Option<Product>> {
+            unimplemented!()
+        }
+
+        async fn find_by_id_including_deleted(&self, _: Uuid) -> Result<Option<Product>> {
+            unimplemented!()
+        }
+
+        async fn list_deleted(&self, _: PaginationParams) -> Result<ListResponse<Product>> {
+            unimplemented!()
+        }
+    }
+
+    fn batch(names: &[&str]) -> Vec<(u64, CreateProductRequest)> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let request = CreateProductRequest {
+                    name: name.to_string(),
+                    description: None,
+                    price: 100,
+                    category_id: Uuid::nil(),
+                };
+                (i as u64 + 2, request)
+            })
+            .collect()
+    }
+
+    fn lines(issues: &[ImportRowIssue]) -> Vec<u64> {
+        issues.iter().map(|issue| issue.line).collect()
+    }
+
+    #[tokio::test]
+    async fn rows_without_a_category_are_reported_outside_strict_mode() {
+        let repo = ImportRepository { available: true };
+        let mut summary = ProductImportSummary::default();
+
+        flush_import_batch(&repo, &mut batch(&["a", "missing category", "b"]), false, &mut summary).await.unwrap();
+        assert_eq!(summary.created, 2);
+        assert_eq!(lines(&summary.errors), [3]);
+
+        let mut summary = ProductImportSummary::default();
+        let strict = flush_import_batch(&repo, &mut batch(&["a", "missing category"]), true, &mut summary).await;
+        assert!(matches!(strict, Err(ApiError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn a_failed_write_is_recorded_against_each_row_of_the_batch() {
+        let repo = ImportRepository { available: false };
+        let mut summary = ProductImportSummary { created: 3, ..Default::default() };
+
+        let result = flush_import_batch(&repo, &mut batch(&["a", "b"]), false, &mut summary).await;
+        assert!(result.is_err());
+        assert_eq!(summary.created, 3);
+        assert_eq!(lines(&summary.errors), [2, 3]);
+
+        let mut summary = ProductImportSummary::default();
+        assert!(flush_import_batch(&repo, &mut batch(&["a"]), true, &mut summary).await.is_err());
+        assert!(summary.errors.is_empty());
+    }
 }
@@ -1,30 +1,39 @@
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Query, State},
     http::StatusCode,
-    response::Json,
+    response::Response,
     Extension,
 };
 use std::sync::Arc;
-use tracing::{info, instrument};
-use uuid::Uuid;
+use tracing::{info, instrument, warn};
 use validator::Validate;
 
+use crate::extractors::{ClientIp, Fields, IdPath, JsonArrayStream, JsonBody, Tenant};
+use crate::one_time_token;
 use crate::state::AppState;
+use crate::versioning::ApiVersion;
+use app_core::enterprise::{ApiResponse, AuditCategory, AuditSeverity, ResponseMetadata};
 use app_core::error::{ApiError, Result};
-use app_core::models::{CreateUserRequest, UpdateUserRequest, UserResponse, PaginationParams, ListResponse};
-use auth::Claims;
+use app_core::timestamp::Json;
+use app_core::traits::SoftDeleteRepository;
+use app_core::models::{ChangeEmailRequest, ConfirmEmailChangeQuery, Created, CreateUserRequest, TenantScope, UpdateUserRequest, UserResponse, PaginationParams, ListResponse, MultiStatus};
+use auth::{Claims, TokenResponse};
 use database::UserRepositoryTrait;
+use monitoring::{audit_diff, Notification};
 
 #[instrument(skip(state))]
 pub async fn list_users(
     State(state): State<Arc<AppState>>,
+    Tenant(scope): Tenant,
     Query(pagination): Query<PaginationParams>,
-) -> Result<Json<ListResponse<UserResponse>>> {
-    let user_repo = state.db_pool.user_repository();
+    fields: Fields<UserResponse>,
+) -> Result<Json<ListResponse<serde_json::Value>>> {
+    let user_repo = state.db_pool.user_repository(scope);
     let users_result = user_repo.list(pagination).await?;
 
+    let users: Vec<UserResponse> = users_result.data.into_iter().map(UserResponse::from).collect();
     let response = ListResponse {
-        data: users_result.data.into_iter().map(UserResponse::from).collect(),
+        data: fields.project_all(&users)?,
         pagination: users_result.pagination,
     };
 
@@ -32,36 +41,56 @@ pub async fn list_users(
     Ok(Json(response))
 }
 
+/// Serves v1 (bare user) or v2 (user wrapped with response metadata) based on
+/// the negotiated `Accept` version
 #[instrument(skip(state))]
 pub async fn get_user(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
-) -> Result<Json<UserResponse>> {
-    let user_repo = state.db_pool.user_repository();
+    IdPath(id): IdPath,
+    Tenant(scope): Tenant,
+    version: ApiVersion,
+    fields: Fields<UserResponse>,
+    request_id: Option<Extension<String>>,
+) -> Result<Response> {
+    let user_repo = state.db_pool.user_repository(scope);
     let user = user_repo.find_by_id(id).await?
         .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
 
     state.metrics_service.increment_counter("user_retrieved_total", &[]);
-    Ok(Json(UserResponse::from(user)))
+
+    let user = fields.project(&UserResponse::from(user))?;
+    Ok(match version {
+        ApiVersion::V1 => version.json(user),
+        _ => version.json(ApiResponse {
+            data: user,
+            meta: ResponseMetadata {
+                timestamp: time::OffsetDateTime::now_utc(),
+                request_id: request_id.map(|Extension(id)| id).unwrap_or_default(),
+                version: version.0.to_string(),
+                rate_limit: None,
+            },
+        }),
+    })
 }
 
 #[instrument(skip(state, request))]
 pub async fn create_user(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<CreateUserRequest>,
-) -> Result<Json<UserResponse>> {
+    Tenant(scope): Tenant,
+    JsonBody(request): JsonBody<CreateUserRequest>,
+) -> Result<Created<UserResponse>> {
     // Validate request
-    request.validate()
-        .map_err(|e| ApiError::Validation(format!("Validation failed: {}", e)))?;
+    request.validate()?;
 
-    let user_repo = state.db_pool.user_repository();
+    let user_repo = state.db_pool.user_repository(scope);
 
-    // Check if user already exists
-    if user_repo.find_by_email(&request.email).await?.is_some() {
+    // Emails and usernames are unique across tenants, so conflicts are checked globally
+    let all_users = state.db_pool.user_repository(TenantScope::all_tenants(scope.tenant_id()));
+    if all_users.find_by_email(&request.email).await?.is_some() {
         return Err(ApiError::Conflict("User with this email already exists".to_string()));
     }
 
-    if user_repo.find_by_username(&request.username).await?.is_some() {
+    if all_users.find_by_username(&request.username).await?.is_some() {
         return Err(ApiError::Conflict("User with this username already exists".to_string()));
     }
 
@@ -74,30 +103,90 @@ pub async fn create_user(
     state.metrics_service.increment_counter("user_created_total", &[]);
     info!("User created successfully: {}", user.id);
 
-    Ok(Json(UserResponse::from(user)))
+    Ok(Created::new(format!("/api/v1/users/{}", user.id), UserResponse::from(user)))
+}
+
+/// Maximum number of users accepted in a single bulk import
+const MAX_BULK_USERS: usize = 10_000;
+
+/// Bulk import streamed item by item, so large arrays are never buffered whole and
+/// inserts start as soon as the first users arrive
+#[instrument(skip(state, users))]
+pub async fn bulk_create_users(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    tenant: Tenant,
+    mut users: JsonArrayStream,
+) -> Result<MultiStatus> {
+    claims.require_admin()?;
+
+    let mut results = MultiStatus::new();
+    let mut index = 0;
+    loop {
+        let request = match users.next::<CreateUserRequest>().await {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(e) if index == 0 => return Err(e),
+            Err(e) => {
+                // Parsing can't resume past malformed input; report where it stopped
+                results.push_error(index, &e);
+                break;
+            }
+        };
+
+        if index >= MAX_BULK_USERS {
+            results.push_error(index, &ApiError::BadRequest(format!(
+                "Bulk import is limited to {} users per request",
+                MAX_BULK_USERS
+            )));
+            break;
+        }
+
+        match create_user(State(state.clone()), tenant, JsonBody(request)).await {
+            Ok(created) => results.push_success(index, StatusCode::CREATED, created.body.id),
+            Err(e) => results.push_error(index, &e),
+        }
+        index += 1;
+    }
+
+    info!(
+        "Bulk user import completed: {} items, {} failed",
+        results.results.len(),
+        results.failed_count()
+    );
+
+    Ok(results)
 }
 
 #[instrument(skip(state, request))]
 pub async fn update_user(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
+    IdPath(id): IdPath,
     Extension(claims): Extension<Claims>,
-    Json(request): Json<UpdateUserRequest>,
+    Tenant(scope): Tenant,
+    JsonBody(request): JsonBody<UpdateUserRequest>,
 ) -> Result<Json<UserResponse>> {
     // Validate request
-    request.validate()
-        .map_err(|e| ApiError::Validation(format!("Validation failed: {}", e)))?;
+    request.validate()?;
 
     // Check if user can update this profile (own profile or admin)
     if claims.sub != id && !claims.is_admin() {
         return Err(ApiError::Unauthorized("Cannot update other user's profile".to_string()));
     }
 
-    let user_repo = state.db_pool.user_repository();
+    // A hijacked session must not be able to silently move the recovery address
+    if request.email.is_some() && !claims.is_admin() {
+        return Err(ApiError::Validation(
+            "Email changes must be verified; use POST /users/{id}/email".to_string(),
+        ));
+    }
+
+    let user_repo = state.db_pool.user_repository(scope);
+    let all_users = state.db_pool.user_repository(TenantScope::all_tenants(scope.tenant_id()));
 
-    // Check for conflicts if updating email or username
+    // Check for conflicts if updating email or username (unique across tenants)
     if let Some(ref email) = request.email {
-        if let Some(existing) = user_repo.find_by_email(email).await? {
+        if let Some(existing) = all_users.find_by_email(email).await? {
             if existing.id != id {
                 return Err(ApiError::Conflict("Email already in use".to_string()));
             }
@@ -105,35 +194,186 @@ pub async fn update_user(
     }
 
     if let Some(ref username) = request.username {
-        if let Some(existing) = user_repo.find_by_username(username).await? {
+        if let Some(existing) = all_users.find_by_username(username).await? {
             if existing.id != id {
                 return Err(ApiError::Conflict("Username already in use".to_string()));
             }
         }
     }
 
+    let before = user_repo.find_by_id(id).await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
     let user = user_repo.update(id, request).await?
         .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
 
+    let changes = audit_diff(&before, &user);
+    if changes.as_object().is_some_and(|changes| !changes.is_empty()) {
+        if let Err(e) = state.audit_service.log_action(
+            Some(claims.sub),
+            "update_user",
+            AuditCategory::DataChange,
+            AuditSeverity::Info,
+            "user",
+            Some(user.id),
+            "127.0.0.1",
+            None,
+            serde_json::json!({ "changes": changes }),
+        ).await {
+            warn!("Failed to audit user update: {}", e);
+        }
+    }
+
     state.metrics_service.increment_counter("user_updated_total", &[]);
     info!("User updated successfully: {}", user.id);
 
     Ok(Json(UserResponse::from(user)))
 }
 
+/// Start a self-service email change: the new address is stored as pending and sent a
+/// verification link; the account email is unchanged until the link is used
+#[instrument(skip(state, request))]
+pub async fn request_email_change(
+    State(state): State<Arc<AppState>>,
+    IdPath(id): IdPath,
+    ClientIp(client): ClientIp,
+    Extension(claims): Extension<Claims>,
+    JsonBody(request): JsonBody<ChangeEmailRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>)> {
+    request.validate()?;
+
+    if claims.sub != id || claims.impersonated_by.is_some() {
+        return Err(ApiError::Unauthorized("Cannot change another user's email".to_string()));
+    }
+
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let user = user_repo.find_by_id(id).await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
+
+    if user.email == request.new_email {
+        return Err(ApiError::Validation("New email matches the current email".to_string()));
+    }
+    if user_repo.find_by_email(&request.new_email).await?.is_some() {
+        return Err(ApiError::Conflict("Email already in use".to_string()));
+    }
+
+    let accepted = (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "message": "Verification email sent to the new address"
+        })),
+    );
+
+    // Over quota: answer as usual, but neither record the change nor send anything
+    if let Err(limit) = state.email_send_limiter.check(request.new_email.as_str(), client) {
+        warn!(
+            client = %client,
+            limit,
+            "Suppressed email change verification for user {}: send limit reached",
+            id
+        );
+        state.metrics_service.increment_counter("email_sends_suppressed_total", &[("limit", limit)]);
+        return Ok(accepted);
+    }
+
+    let token = one_time_token::generate();
+    let ttl = state.config.auth.email_change_token_ttl;
+    let expires_at = time::OffsetDateTime::now_utc() + time::Duration::seconds(ttl as i64);
+    user_repo
+        .request_email_change(id, &request.new_email, &one_time_token::hash(&token), expires_at)
+        .await?;
+
+    let link = format!(
+        "{}/api/v1/users/email/confirm?token={}",
+        state.config.server.public_url.trim_end_matches('/'),
+        token
+    );
+    state.notifications.notify_user(
+        request.new_email.as_str(),
+        Notification::new(
+            "Confirm your new email address",
+            format!(
+                "Open this link within {} hours to confirm your new email address:\n{}\n\nIf you did not request this change, ignore this email.",
+                ttl / 3600,
+                link
+            ),
+        ),
+    );
+
+    let _ = state.audit_service.log_action(
+        Some(claims.sub),
+        "email_change_requested",
+        AuditCategory::Security,
+        AuditSeverity::Warning,
+        "user",
+        Some(id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({ "new_email": request.new_email }),
+    ).await;
+
+    info!("Email change requested for user: {}", id);
+
+    Ok(accepted)
+}
+
+/// Complete an email change from the emailed link; the previous address is told about it
+#[instrument(skip(state, query))]
+pub async fn confirm_email_change(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ConfirmEmailChangeQuery>,
+) -> Result<Json<UserResponse>> {
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let (user, previous_email) = user_repo
+        .confirm_email_change(&one_time_token::hash(&query.token))
+        .await?
+        .ok_or_else(|| ApiError::BadRequest("Invalid or expired email change token".to_string()))?;
+
+    state.notifications.notify_user(
+        previous_email.as_str(),
+        Notification::new(
+            "Your email address was changed",
+            format!(
+                "The email address on your account was changed to {}. If you did not make this change, contact support immediately.",
+                user.email
+            ),
+        ),
+    );
+
+    let _ = state.audit_service.log_action(
+        Some(user.id),
+        "email_changed",
+        AuditCategory::Security,
+        AuditSeverity::Warning,
+        "user",
+        Some(user.id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({
+            "previous_email": previous_email,
+            "new_email": user.email
+        }),
+    ).await;
+
+    state.metrics_service.increment_counter("user_email_changed_total", &[]);
+    info!("Email change confirmed for user: {}", user.id);
+
+    Ok(Json(UserResponse::from(user)))
+}
+
 #[instrument(skip(state))]
 pub async fn delete_user(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
+    IdPath(id): IdPath,
     Extension(claims): Extension<Claims>,
+    Tenant(scope): Tenant,
 ) -> Result<StatusCode> {
     // Check if user can delete this profile (own profile or admin)
     if claims.sub != id && !claims.is_admin() {
         return Err(ApiError::Unauthorized("Cannot delete other user's profile".to_string()));
     }
 
-    let user_repo = state.db_pool.user_repository();
-    let deleted = user_repo.delete(id).await?;
+    let user_repo = state.db_pool.user_repository(scope);
+    let deleted = user_repo.soft_delete(id).await?;
 
     if !deleted {
         return Err(ApiError::NotFound("User not found".to_string()));
@@ -145,18 +385,115 @@ pub async fn delete_user(
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Undo a soft delete (admin only)
+#[instrument(skip(state))]
+pub async fn restore_user(
+    State(state): State<Arc<AppState>>,
+    IdPath(id): IdPath,
+    Extension(claims): Extension<Claims>,
+    Tenant(scope): Tenant,
+) -> Result<Json<UserResponse>> {
+    claims.require_admin()?;
+
+    let user_repo = state.db_pool.user_repository(scope);
+    let Some(user) = user_repo.restore(id).await? else {
+        return Err(match user_repo.find_by_id_including_deleted(id).await? {
+            Some(_) => ApiError::Conflict("User is not deleted".to_string()),
+            None => ApiError::NotFound("User not found".to_string()),
+        });
+    };
+
+    let _ = state.audit_service.log_action(
+        Some(claims.sub),
+        "restore_user",
+        AuditCategory::Admin,
+        AuditSeverity::Warning,
+        "user",
+        Some(user.id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({}),
+    ).await;
+
+    state.metrics_service.increment_counter("user_restored_total", &[]);
+    info!("User restored successfully: {}", user.id);
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+/// Issue a short-lived token acting as the target user for support investigations (admin only)
+#[instrument(skip(state))]
+pub async fn impersonate_user(
+    State(state): State<Arc<AppState>>,
+    IdPath(id): IdPath,
+    Extension(claims): Extension<Claims>,
+    Tenant(scope): Tenant,
+) -> Result<Json<TokenResponse>> {
+    // Impersonation tokens never carry the admin role, so they can't chain
+    claims.require_admin()?;
+
+    if claims.sub == id {
+        return Err(ApiError::BadRequest("Cannot impersonate yourself".to_string()));
+    }
+
+    let user_repo = state.db_pool.user_repository(scope);
+    let user = user_repo.find_by_id(id).await?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
+
+    if !user.is_active {
+        return Err(ApiError::BadRequest("Cannot impersonate an inactive user".to_string()));
+    }
+
+    let roles = vec!["user".to_string()]; // In a real app, fetch from database
+    let access_token = state.auth_service.generate_impersonation_token(
+        user.id,
+        user.username.to_string(),
+        user.email.to_string(),
+        roles,
+        user.tenant_id,
+        claims.sub,
+    )?;
+
+    state.audit_service.log_action(
+        Some(claims.sub),
+        "impersonation_started",
+        AuditCategory::Security,
+        AuditSeverity::Critical,
+        "user",
+        Some(user.id),
+        "127.0.0.1",
+        None,
+        serde_json::json!({
+            "admin_id": claims.sub,
+            "impersonated_user_id": user.id,
+            "impersonated_username": user.username,
+        }),
+    ).await?;
+
+    state.metrics_service.increment_counter("impersonation_tokens_issued_total", &[]);
+    warn!("Admin {} started impersonating user {}", claims.sub, user.id);
+
+    Ok(Json(TokenResponse {
+        access_token,
+        refresh_token: None,
+        token_type: "Bearer".to_string(),
+        expires_in: state.auth_service.impersonation_token_ttl(),
+    }))
+}
+
 #[instrument(skip(state))]
 pub async fn get_user_profile(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
+    IdPath(id): IdPath,
     Extension(claims): Extension<Claims>,
+    Tenant(scope): Tenant,
 ) -> Result<Json<UserResponse>> {
     // Users can only view their own profile unless they're admin
     if claims.sub != id && !claims.is_admin() {
         return Err(ApiError::Unauthorized("Cannot view other user's profile".to_string()));
     }
 
-    let user_repo = state.db_pool.user_repository();
+    let user_repo = state.db_pool.user_repository(scope);
     let user = user_repo.find_by_id(id).await?
         .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
 
@@ -166,9 +503,10 @@ pub async fn get_user_profile(
 #[instrument(skip(state, request))]
 pub async fn update_user_profile(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<Uuid>,
+    IdPath(id): IdPath,
     Extension(claims): Extension<Claims>,
-    Json(request): Json<UpdateUserRequest>,
+    tenant: Tenant,
+    JsonBody(request): JsonBody<UpdateUserRequest>,
 ) -> Result<Json<UserResponse>> {
     // Users can only update their own profile
     if claims.sub != id {
@@ -176,5 +514,5 @@ pub async fn update_user_profile(
     }
 
     // Reuse the update_user logic
-    update_user(State(state), Path(id), Extension(claims), Json(request)).await
+    update_user(State(state), IdPath(id), Extension(claims), tenant, JsonBody(request)).await
 }
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use crate::exports;
+use crate::handlers::health::{probe, DATABASE_DEPENDENCY};
+use crate::state::AppState;
+use app_core::enterprise::{AuditCategory, AuditSeverity};
+use app_core::error::Result;
+use app_core::models::TenantScope;
+use database::{LogDispatcher, OutboxDispatcher, UserRepositoryTrait};
+use monitoring::Notification;
+
+/// How often the inactivity deactivation job runs
+const INACTIVITY_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often idle per-user rate limiter, concurrency and correlation tracking state is discarded
+const RATE_LIMITER_PRUNE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How often demoted JWT signing keys are checked for retirement
+const KEY_RETIREMENT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often each instance checks the shared key store for a key promoted elsewhere
+const KEY_SYNC_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often buffered flag evaluations are written to the database
+const FLAG_EVALUATION_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often flag evaluations past their retention are deleted
+const FLAG_EVALUATION_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the in-flight request gauges are refreshed
+const IN_FLIGHT_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Register all periodic background jobs with the scheduler
+pub async fn register_jobs(state: &Arc<AppState>) {
+    let stats_state = state.clone();
+    state
+        .scheduler
+        .register(
+            "refresh_aggregate_stats",
+            Duration::from_secs(state.config.monitoring.stats_refresh_interval),
+            move || refresh_aggregate_stats(stats_state.clone()),
+        )
+        .await;
+
+    let flag_cache = state.flag_cache.clone();
+    state
+        .scheduler
+        .register_critical(
+            "refresh_feature_flags",
+            Duration::from_secs(state.config.monitoring.feature_flag_refresh_interval),
+            move || {
+                let flag_cache = flag_cache.clone();
+                async move { flag_cache.refresh().await }
+            },
+        )
+        .await;
+
+    let retry_schedule = state.config.monitoring.outbox_retry_schedule.iter().copied().map(Duration::from_secs).collect();
+    let relay = state.db_pool.outbox_relay(state.config.monitoring.outbox_batch_size, retry_schedule);
+    let dispatcher: Arc<dyn OutboxDispatcher> = Arc::new(LogDispatcher);
+    let relay_metrics = state.metrics_service.clone();
+    let relay_notifications = state.notifications.clone();
+    state
+        .scheduler
+        .register_critical_singleton(
+            "relay_outbox",
+            Duration::from_secs(state.config.monitoring.outbox_relay_interval),
+            move || {
+                let relay = relay.clone();
+                let dispatcher = dispatcher.clone();
+                let metrics = relay_metrics.clone();
+                let notifications = relay_notifications.clone();
+                async move {
+                    let outcome = relay.relay(dispatcher.as_ref()).await?;
+                    metrics.increment_counter_by("outbox_events_dispatched_total", outcome.dispatched as u64, &[]);
+                    metrics.increment_counter_by(
+                        "outbox_dispatch_failures_total",
+                        (outcome.failed + outcome.dead_lettered) as u64,
+                        &[],
+                    );
+                    if outcome.dead_lettered > 0 {
+                        metrics.increment_counter_by("outbox_events_dead_lettered_total", outcome.dead_lettered as u64, &[]);
+                        notifications.notify(Notification::new(
+                            "Outbox events dead-lettered",
+                            format!(
+                                "{} event(s) failed every scheduled retry and will not be dispatched again",
+                                outcome.dead_lettered
+                            ),
+                        ));
+                    }
+                    Ok(())
+                }
+            },
+        )
+        .await;
+
+    let dependency_state = state.clone();
+    state
+        .scheduler
+        .register(
+            "check_dependencies",
+            Duration::from_secs(state.config.monitoring.dependency_check_interval),
+            move || check_dependencies(dependency_state.clone()),
+        )
+        .await;
+
+    let limiter = state.enhanced_profile_limiter.clone();
+    let client_limiter = state.rate_limiter.clone();
+    let resource_limiter = state.resource_rate_limiter.clone();
+    let email_send_limiter = state.email_send_limiter.clone();
+    let request_costs = state.request_costs.clone();
+    let correlation_tracker = state.correlation_tracker.clone();
+    let user_concurrency = state.user_concurrency.clone();
+    state
+        .scheduler
+        .register("prune_rate_limiters", RATE_LIMITER_PRUNE_INTERVAL, move || {
+            let limiter = limiter.clone();
+            let client_limiter = client_limiter.clone();
+            let resource_limiter = resource_limiter.clone();
+            let email_send_limiter = email_send_limiter.clone();
+            let request_costs = request_costs.clone();
+            let correlation_tracker = correlation_tracker.clone();
+            let user_concurrency = user_concurrency.clone();
+            async move {
+                limiter.prune();
+                if let Some(client_limiter) = &client_limiter {
+                    client_limiter.prune();
+                }
+                resource_limiter.prune();
+                email_send_limiter.prune();
+                request_costs.prune();
+                correlation_tracker.prune();
+                user_concurrency.prune().await;
+                Ok(())
+            }
+        })
+        .await;
+
+    // The oldest request's age keeps growing while it hangs, so it's sampled on a timer
+    // rather than only when requests start or finish
+    let in_flight = state.in_flight.clone();
+    let in_flight_metrics = state.metrics_service.clone();
+    state
+        .scheduler
+        .register("report_in_flight_requests", IN_FLIGHT_REPORT_INTERVAL, move || {
+            let snapshot = in_flight.snapshot();
+            let metrics = in_flight_metrics.clone();
+            async move {
+                metrics.set_gauge("http_requests_in_flight", snapshot.count as f64, &[]);
+                metrics.set_gauge(
+                    "http_oldest_request_age_seconds",
+                    snapshot.oldest.map_or(0.0, |oldest| oldest.age_ms as f64 / 1000.0),
+                    &[],
+                );
+                Ok(())
+            }
+        })
+        .await;
+
+    let auth_service = state.auth_service.clone();
+    state
+        .scheduler
+        .register("retire_signing_keys", KEY_RETIREMENT_INTERVAL, move || {
+            let auth_service = auth_service.clone();
+            async move {
+                for key_id in auth_service.retire_expired_keys() {
+                    info!("Retired JWT signing key '{}'", key_id);
+                }
+                Ok(())
+            }
+        })
+        .await;
+
+    // Every instance syncs, so this isn't a singleton
+    let auth_service = state.auth_service.clone();
+    state
+        .scheduler
+        .register("sync_signing_keys", KEY_SYNC_INTERVAL, move || {
+            let auth_service = auth_service.clone();
+            async move {
+                auth_service.sync_signing_keys().await?;
+                Ok(())
+            }
+        })
+        .await;
+
+    if state.config.monitoring.flag_evaluation_log {
+        let flush_state = state.clone();
+        state
+            .scheduler
+            .register("flush_flag_evaluations", FLAG_EVALUATION_FLUSH_INTERVAL, move || {
+                flush_flag_evaluations(flush_state.clone())
+            })
+            .await;
+    }
+
+    // Purged even while logging is off, so history recorded earlier still expires
+    let purge_state = state.clone();
+    state
+        .scheduler
+        .register_singleton("purge_flag_evaluations", FLAG_EVALUATION_PURGE_INTERVAL, move || {
+            purge_flag_evaluations(purge_state.clone())
+        })
+        .await;
+
+    let export_state = state.clone();
+    state
+        .scheduler
+        .register(
+            "process_exports",
+            Duration::from_secs(state.config.server.export_poll_interval),
+            move || process_exports(export_state.clone()),
+        )
+        .await;
+
+    if state.config.auth.inactivity_deactivation_days.is_some() {
+        let job_state = state.clone();
+        state
+            .scheduler
+            .register_singleton("deactivate_inactive_accounts", INACTIVITY_JOB_INTERVAL, move || {
+                deactivate_inactive_accounts(job_state.clone())
+            })
+            .await;
+    }
+}
+
+/// Delete expired exports, then generate the queued ones
+async fn process_exports(state: Arc<AppState>) -> Result<()> {
+    let purged = state.exports.purge_expired().await?;
+    if purged > 0 {
+        info!("Deleted {} expired export(s)", purged);
+    }
+
+    let (succeeded, failed) = exports::process_pending(&state.exports, state.audit_service.clone()).await?;
+    let metrics = &state.metrics_service;
+    metrics.increment_counter_by("exports_completed_total", succeeded as u64, &[("outcome", "success")]);
+    metrics.increment_counter_by("exports_completed_total", failed as u64, &[("outcome", "failure")]);
+    Ok(())
+}
+
+/// Write the flag evaluations buffered since the last flush
+async fn flush_flag_evaluations(state: Arc<AppState>) -> Result<()> {
+    let outcome = state.flag_evaluations.flush().await?;
+    let metrics = &state.metrics_service;
+    metrics.increment_counter_by("flag_evaluations_recorded_total", outcome.written as u64, &[]);
+    metrics.increment_counter_by("flag_evaluations_dropped_total", outcome.dropped as u64, &[]);
+    Ok(())
+}
+
+/// Delete flag evaluations older than `flag_evaluation_retention`
+async fn purge_flag_evaluations(state: Arc<AppState>) -> Result<()> {
+    let retention = time::Duration::seconds(state.config.monitoring.flag_evaluation_retention as i64);
+    let purged = state.flag_evaluations.purge(time::OffsetDateTime::now_utc() - retention).await?;
+    if purged > 0 {
+        info!("Deleted {} expired flag evaluation(s)", purged);
+    }
+    Ok(())
+}
+
+/// Recompute aggregate counts and replace the cached snapshot
+async fn refresh_aggregate_stats(state: Arc<AppState>) -> Result<()> {
+    let stats = state.db_pool.aggregate_stats().await?;
+    *state.stats_cache.write().await = Some(stats);
+    Ok(())
+}
+
+/// Probe the database so its health is tracked even without traffic, then alert on
+/// any dependency that crossed the degradation thresholds
+async fn check_dependencies(state: Arc<AppState>) -> Result<()> {
+    let database = probe(state.db_pool.health_check()).await;
+    state.dependency_health.record(
+        DATABASE_DEPENDENCY,
+        Duration::from_millis(database.latency_ms),
+        database.is_healthy(),
+    );
+
+    state.dependency_health.evaluate().await;
+    Ok(())
+}
+
+/// Deactivate accounts whose last login exceeds the configured threshold, in batches
+async fn deactivate_inactive_accounts(state: Arc<AppState>) -> Result<()> {
+    let auth_config = &state.config.auth;
+    let Some(days) = auth_config.inactivity_deactivation_days else {
+        return Ok(());
+    };
+
+    let inactive_since = time::OffsetDateTime::now_utc() - time::Duration::days(days as i64);
+    let batch_size = auth_config.inactivity_batch_size.max(1);
+    let user_repo = state.db_pool.user_repository(TenantScope::all_tenants(None));
+    let mut total = 0;
+
+    loop {
+        let deactivated = user_repo
+            .deactivate_inactive(
+                inactive_since,
+                &auth_config.inactivity_exempt_usernames,
+                batch_size,
+            )
+            .await?;
+
+        for (user_id, username) in &deactivated {
+            info!("Deactivated inactive account: {}", user_id);
+
+            let _ = state.audit_service.log_action(
+                None,
+                "auto_deactivate_inactive",
+                AuditCategory::Security,
+                AuditSeverity::Warning,
+                "user",
+                Some(*user_id),
+                "127.0.0.1",
+                None,
+                serde_json::json!({
+                    "username": username,
+                    "inactive_days": days
+                }),
+            ).await;
+        }
+
+        total += deactivated.len();
+        if (deactivated.len() as i64) < batch_size {
+            break;
+        }
+    }
+
+    if total > 0 {
+        info!("Inactivity job deactivated {} accounts", total);
+        state.notifications.notify(Notification::new(
+            "Inactive accounts deactivated",
+            format!("The inactivity job deactivated {} accounts", total),
+        ));
+    }
+
+    Ok(())
+}
@@ -1,24 +1,50 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
-use axum::{Router, routing::get, middleware as axum_middleware};
+use std::time::Duration;
+use axum::{Router, routing::{get, post}, middleware as axum_middleware};
 use tower::ServiceBuilder;
-use tower_http::{trace::TraceLayer, compression::CompressionLayer, cors::CorsLayer};
+use tower_http::{trace::TraceLayer, compression::CompressionLayer, cors::CorsLayer, timeout::RequestBodyTimeoutLayer};
 use anyhow;
 use tokio;
+use tokio::signal::unix::{signal, SignalKind};
 
-use auth::AuthService;
-use app_core::config::Config;
+use auth::{AuthService, PermissionResolver, TokenBlacklist};
+use app_core::config::{Config, FeatureFlagBackend, FeatureFlagDefault};
 use app_core::error::{ApiError, Result};
-use database::DatabasePool;
-use monitoring::{MetricsService, DatabaseAuditService, AuditService, init_tracing};
-use monitoring::feature_flags::{FeatureFlagService, InMemoryFeatureFlagService};
-use monitoring::CircuitBreaker;
+use database::{DatabasePool, ProductStore};
+use monitoring::{MetricsService, DatabaseAuditService, AuditService, GatedAuditService, init_tracing};
+use monitoring::audit::audit_flag_name;
+use monitoring::feature_flags::{
+    CachedFeatureFlagService, DatabaseFeatureFlagService, FeatureFlagService, InMemoryFeatureFlagService,
+};
+use monitoring::flag_evaluations::{FlagEvaluationLog, RecordingFeatureFlagService};
+use monitoring::{CircuitBreakerRegistry, DependencyHealthTracker, NotificationService, Scheduler};
+use monitoring::notifications::{mailer_from_config, notifier_from_config};
 use app_core::enterprise::CircuitBreakerConfig;
 
+mod client_ip;
+mod exports;
+mod extractors;
 mod handlers;
+mod jobs;
 mod routes;
 mod middleware;
+mod negotiation;
+mod reports;
+mod one_time_token;
+mod request_id;
 mod state;
+mod versioning;
 
+use exports::ExportQueue;
+use middleware::concurrency::UserConcurrencyLimiter;
+use middleware::enterprise::CorrelationTracker;
+use middleware::in_flight::InFlightRequests;
+use client_ip::TrustedProxies;
+use middleware::tls_policy::TlsPolicy;
+use middleware::rate_limit::{
+    ClientRateLimiter, EmailSendLimiter, RequestCostBudget, ResourceRateLimiter, UserRateLimiter,
+};
 use state::AppState;
 
 /// Main application struct
@@ -36,34 +62,156 @@ impl App {
         let db_pool = DatabasePool::new(&config.database).await?;
 
         // Initialize services
-        let auth_service = AuthService::new(&config.auth)?;
+        let permissions = Arc::new(PermissionResolver::new(&config.auth));
         let metrics_service = MetricsService::new()?;
 
+        // Per-action audit switches live alongside the other flags so they can be toggled at runtime
+        let mut default_flags = config.monitoring.default_feature_flags.clone();
+        default_flags.extend(config.monitoring.audited_actions.iter().map(|(action, audited)| FeatureFlagDefault {
+            name: audit_flag_name(action),
+            enabled: *audited,
+            rollout_percentage: 100.0,
+            conditions: None,
+            depends_on: Vec::new(),
+            variants: Vec::new(),
+        }));
+
+        // Initialize the flag store, seeding this environment's defaults for flags it lacks
+        let flag_store: Arc<dyn FeatureFlagService> = match config.monitoring.feature_flag_backend {
+            FeatureFlagBackend::Memory => {
+                let store = InMemoryFeatureFlagService::new();
+                store.initialize_default_flags(&default_flags).await?;
+                Arc::new(store)
+            }
+            FeatureFlagBackend::Database => {
+                let store = DatabaseFeatureFlagService::new(db_pool.pool().clone());
+                store.initialize_default_flags(&default_flags).await?;
+                Arc::new(store)
+            }
+        };
+
+        // Evaluate flags from a fail-static cache so a store outage doesn't disable them all
+        let flag_cache = Arc::new(CachedFeatureFlagService::new(
+            flag_store,
+            Duration::from_secs(config.monitoring.feature_flag_max_staleness),
+        ));
+        flag_cache.refresh().await?;
+
+        // Evaluation history is opt-in; when off, evaluations skip the recording layer entirely
+        let flag_evaluations = Arc::new(FlagEvaluationLog::new(
+            db_pool.pool().clone(),
+            config.monitoring.flag_evaluation_sample_rate,
+        ));
+        let feature_flags: Arc<dyn FeatureFlagService> = if config.monitoring.flag_evaluation_log {
+            Arc::new(RecordingFeatureFlagService::new(flag_cache.clone(), flag_evaluations.clone()))
+        } else {
+            flag_cache.clone()
+        };
+
         // Initialize enterprise services
-        let audit_service: Arc<dyn AuditService> = Arc::new(
-            DatabaseAuditService::new(db_pool.pool().clone())
+        let audit_store = Arc::new(DatabaseAuditService::new(db_pool.pool().clone()));
+        let audit_service: Arc<dyn AuditService> = Arc::new(GatedAuditService::new(
+            audit_store.clone(),
+            feature_flags.clone(),
+        ));
+
+        // Circuit breakers per downstream service
+        let circuit_breakers = Arc::new(CircuitBreakerRegistry::new(CircuitBreakerConfig::builder().build()?));
+
+        // Operator notifications (log, email or Slack depending on config)
+        let notifications = NotificationService::new(
+            notifier_from_config(&config.notifications)?,
+            mailer_from_config(&config.notifications)?,
         );
 
-        let feature_flags: Arc<dyn FeatureFlagService> = Arc::new(
-            InMemoryFeatureFlagService::new()
+        // Per-dependency success rate, latency and breaker state
+        let dependency_health = Arc::new(DependencyHealthTracker::new(
+            metrics_service.clone(),
+            notifications.clone(),
+            &config.monitoring,
+            circuit_breakers.clone(),
+        ));
+
+        let auth_service = AuthService::new(
+            &config.auth,
+            TokenBlacklist::new(
+                &config.redis,
+                Duration::from_millis(config.auth.token_validation_timeout_ms),
+                dependency_health.clone(),
+            )?,
+            Arc::new(db_pool.signing_key_store()),
+        )?;
+        // Sign with the key the other instances use, if one was promoted since our config
+        if let Err(e) = auth_service.sync_signing_keys().await {
+            tracing::warn!("Failed to sync signing keys at startup: {}", e);
+        }
+
+        let products = ProductStore::from_config(
+            &config.database.product_backend,
+            &config.redis,
+            &db_pool,
+            dependency_health.clone(),
+        )?;
+
+        // Initialize background job scheduler
+        let scheduler = Arc::new(
+            Scheduler::new(metrics_service.clone()).with_job_lock(Arc::new(db_pool.advisory_locks())),
         );
 
-        // Initialize feature flags with defaults
-        feature_flags.initialize_default_flags().await?;
+        let request_costs = RequestCostBudget::per_minute(
+            config.server.request_cost_budget_per_minute,
+            metrics_service.clone(),
+        );
 
-        // Initialize circuit breaker
-        let circuit_breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default()));
+        let exports = ExportQueue::from_config(&config.server, db_pool.export_jobs()).await?;
 
         let state = Arc::new(AppState {
             db_pool,
+            products,
             auth_service,
+            permissions,
             metrics_service,
             audit_service,
+            audit_store,
             feature_flags,
-            circuit_breaker,
+            flag_cache,
+            flag_evaluations,
+            circuit_breakers,
+            scheduler,
+            notifications,
+            dependency_health,
+            user_concurrency: UserConcurrencyLimiter::new(
+                config.server.max_concurrent_requests_per_user,
+            ),
+            enhanced_profile_limiter: UserRateLimiter::per_minute(
+                "enhanced_profile",
+                config.server.enhanced_profile_requests_per_minute,
+            ),
+            request_costs,
+            email_send_limiter: EmailSendLimiter::per_hour(
+                config.auth.email_sends_per_address_per_hour,
+                config.auth.email_sends_per_client_per_hour,
+            ),
+            rate_limiter: config.server.rate_limit.as_ref().map(ClientRateLimiter::from_config).transpose()?,
+            resource_rate_limiter: ResourceRateLimiter::from_config(&config.server.resource_rate_limits)?,
+            correlation_tracker: CorrelationTracker::new(
+                config.server.correlation_id_reuse_threshold,
+                config.server.correlation_id_tracking_capacity,
+            ),
+            in_flight: InFlightRequests::new(),
+            tls_policy: config.server.tls_policy.as_ref().map(TlsPolicy::from_config).transpose()?,
+            trusted_proxies: TrustedProxies::from_config(&config.server),
+            stats_cache: Arc::new(tokio::sync::RwLock::new(None)),
+            exports,
             config: config.clone(),
         });
 
+        // Register periodic background jobs
+        jobs::register_jobs(&state).await;
+
+        // Pick up role→permission changes without a restart
+        tokio::spawn(reload_on_sighup(state.clone()));
+
         Ok(Self { state, config })
     }
 
@@ -71,6 +219,24 @@ impl App {
     fn create_router(&self) -> Router {
         Router::new()
             .nest("/api/v1", self.api_routes())
+            .route(
+                "/api/v1/auth/introspect",
+                post(handlers::auth::introspect_token).layer(axum_middleware::from_fn_with_state(
+                    self.state.clone(),
+                    middleware::service_auth::service_auth_middleware,
+                )),
+            )
+            // Reached from an emailed link, so authenticated by its one-time token instead
+            .route("/api/v1/users/email/confirm", get(handlers::users::confirm_email_change))
+            // Used by people who can't sign in, so authenticated by the emailed token if at all
+            .route("/api/v1/auth/password-reset/request", post(handlers::auth::request_password_reset))
+            .route("/api/v1/auth/password-reset/confirm", post(handlers::auth::confirm_password_reset))
+            // Second login step; the challenge token from the password step stands in for a token
+            .route("/api/v1/auth/mfa/verify", post(handlers::auth::verify_mfa))
+            // Called once the access token has expired, so authenticated by the refresh token
+            .route("/api/v1/auth/refresh", post(handlers::auth::refresh_token))
+            // Signed, expiring links; the signature stands in for a token
+            .route("/api/v1/exports/:id/download", get(handlers::exports::download_export))
             .route("/health", get(handlers::health::health_check))
             .route("/metrics", get(handlers::metrics::prometheus_metrics))
             .layer(
@@ -78,8 +244,32 @@ impl App {
                     .layer(TraceLayer::new_for_http())
                     .layer(CompressionLayer::new())
                     .layer(CorsLayer::permissive())
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::server_timing::server_timing_middleware,
+                    ))
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::in_flight::in_flight_middleware,
+                    ))
+                    // Inside compression so signatures cover the uncompressed body
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::signing::response_signing_middleware,
+                    ))
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::null_fields::null_fields_middleware,
+                    ))
                     .layer(axum_middleware::from_fn(middleware::enterprise::timeout_middleware))
                     .layer(axum_middleware::from_fn(middleware::enterprise::security_headers_middleware))
+                    .layer(axum_middleware::from_fn(middleware::problem::problem_json_middleware))
+                    // Inside problem+json so the error can be re-encoded, and inside null
+                    // stripping so oversized bodies aren't buffered again
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::response_size::response_size_middleware,
+                    ))
                     .layer(axum_middleware::from_fn_with_state(
                         self.state.clone(),
                         middleware::enterprise::correlation_middleware,
@@ -96,8 +286,25 @@ impl App {
                         self.state.clone(),
                         middleware::metrics::metrics_middleware,
                     ))
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::tls_policy::tls_policy_middleware,
+                    ))
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::checksum::checksum_middleware,
+                    ))
+                    // Innermost so the chosen format is in scope while handlers serialize
+                    .layer(axum_middleware::from_fn_with_state(
+                        self.state.clone(),
+                        middleware::timestamps::timestamp_format_middleware,
+                    ))
                     .into_inner(),
             )
+            // Idle timeout between body frames, separate from the total request timeout
+            .layer(RequestBodyTimeoutLayer::new(Duration::from_secs(
+                self.config.server.body_read_timeout,
+            )))
             .with_state(self.state.clone())
     }
 
@@ -108,10 +315,23 @@ impl App {
             .nest("/auth", routes::auth::router())
             .nest("/products", routes::products::router())
             .nest("/enterprise", routes::enterprise::router())
+            .nest("/exports", routes::exports::router())
+            .layer(axum_middleware::from_fn_with_state(
+                self.state.clone(),
+                middleware::concurrency::user_concurrency_middleware,
+            ))
             .layer(axum_middleware::from_fn_with_state(
                 self.state.clone(),
                 middleware::auth::auth_middleware,
             ))
+            .layer(axum_middleware::from_fn_with_state(
+                self.state.clone(),
+                middleware::load_shed::load_shed_middleware,
+            ))
+            .layer(axum_middleware::from_fn_with_state(
+                self.state.clone(),
+                middleware::accept::accept_middleware,
+            ))
     }
 
     /// Run the application
@@ -124,11 +344,52 @@ impl App {
 
         tracing::info!("Server running on {}:{}", self.config.server.host, self.config.server.port);
         
-        axum::serve(listener, router).await?;
+        // Peer addresses let the TLS policy tell trusted proxies from direct clients
+        axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(shutdown_signal())
+            .await?;
+
+        // Drain background jobs before exiting
+        self.state.scheduler.shutdown().await;
         Ok(())
     }
 }
 
+/// Resolves when the process receives Ctrl+C
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Failed to listen for shutdown signal: {}", e);
+    }
+    tracing::info!("Shutdown signal received");
+}
+
+/// Reload the role→permission mapping from config whenever SIGHUP is received
+async fn reload_on_sighup(state: Arc<AppState>) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            tracing::error!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        match Config::load() {
+            Ok(config) => {
+                state.permissions.reload(&config.auth);
+                if let Some(rotation) = state.auth_service.reload_keys(&config.auth).await {
+                    tracing::warn!(
+                        "Promoted signing key '{}' from config; key '{}' retires at {}",
+                        rotation.primary_key_id, rotation.retiring_key_id, rotation.retires_at
+                    );
+                }
+                tracing::info!("Reloaded role permissions and signing keys after SIGHUP");
+            }
+            Err(e) => tracing::error!("Failed to reload config after SIGHUP: {}", e),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     // Initialize tracing
@@ -0,0 +1,38 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use app_core::config::UnsupportedAcceptPolicy;
+
+use crate::negotiation::{negotiate, route_media_types, JSON_MEDIA_TYPES};
+use crate::state::AppState;
+
+/// Answer 406 Not Acceptable, listing the supported types, when the client's `Accept`
+/// rules out every representation the route produces: JSON, or the types listed for it
+/// in `route_media_types`. With `server.unsupported_accept: ignore` such requests are
+/// served anyway.
+pub async fn accept_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.config.server.unsupported_accept == UnsupportedAcceptPolicy::Ignore {
+        return next.run(request).await;
+    }
+
+    let available = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(JSON_MEDIA_TYPES, |route| route_media_types(route.as_str()));
+    let accept = request.headers().get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    if let Err(e) = negotiate(accept, available) {
+        state.metrics_service.increment_counter("requests_not_acceptable_total", &[]);
+        return e.into_response();
+    }
+
+    next.run(request).await
+}
@@ -1,14 +1,37 @@
 use axum::{
     extract::{Request, State},
-    http::HeaderMap,
+    http::{HeaderMap, HeaderValue},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
 };
+use serde_json::json;
 use std::sync::Arc;
 use tracing::{error, warn};
 
 use crate::state::AppState;
+use app_core::enterprise::{AuditCategory, AuditSeverity};
 use app_core::error::ApiError;
+use app_core::server_timing::PhaseTimer;
+
+/// `Server-Timing` phase covering token validation, including the revocation check
+const AUTH_PHASE: &str = "auth";
+
+/// Response header explaining why credentials were rejected (debug mode only)
+const AUTH_DEBUG_HEADER: &str = "x-auth-debug";
+
+/// 401 with the usual opaque message; when `expose_auth_debug` is on, the precise
+/// `reason` is added in `X-Auth-Debug`
+fn unauthorized(state: &AppState, message: &str, reason: &str) -> Response {
+    let mut response = ApiError::Unauthorized(message.to_string()).into_response();
+
+    if state.config.auth.expose_auth_debug {
+        if let Ok(value) = HeaderValue::from_str(reason) {
+            response.headers_mut().insert(AUTH_DEBUG_HEADER, value);
+        }
+    }
+
+    response
+}
 
 /// Authentication middleware that validates JWT tokens
 pub async fn auth_middleware(
@@ -18,30 +41,74 @@ pub async fn auth_middleware(
     next: Next,
 ) -> Result<Response, ApiError> {
     // Extract authorization header
-    let auth_header = headers
-        .get("authorization")
-        .and_then(|header| header.to_str().ok())
-        .ok_or_else(|| {
-            warn!("Missing authorization header");
-            ApiError::Unauthorized("Missing authorization header".to_string())
-        })?;
+    let Some(auth_header) = headers.get("authorization") else {
+        warn!("Missing authorization header");
+        return Ok(unauthorized(&state, "Missing authorization header", "missing authorization header"));
+    };
+
+    let Ok(auth_header) = auth_header.to_str() else {
+        warn!("Invalid authorization header format");
+        return Ok(unauthorized(&state, "Invalid authorization header format", "authorization header is not valid ASCII"));
+    };
 
     // Extract token from "Bearer <token>" format
-    let token = auth_header
-        .strip_prefix("Bearer ")
-        .ok_or_else(|| {
-            warn!("Invalid authorization header format");
-            ApiError::Unauthorized("Invalid authorization header format".to_string())
-        })?;
+    let Some(token) = auth_header.strip_prefix("Bearer ") else {
+        warn!("Invalid authorization header format");
+        return Ok(unauthorized(&state, "Invalid authorization header format", "expected 'Bearer <token>'"));
+    };
 
     // Validate token and extract user claims
-    let claims = state.auth_service.validate_token(token).await.map_err(|e| {
-        error!("Token validation failed: {}", e);
-        ApiError::Unauthorized("Invalid token".to_string())
-    })?;
+    let timer = PhaseTimer::start(AUTH_PHASE);
+    let validated = state.auth_service.validate_token(token).await;
+    drop(timer);
+    let claims = match validated {
+        Ok(claims) => claims,
+        // The revocation store being down isn't the caller's fault; don't send them to log in again
+        Err(e @ ApiError::ServiceUnavailable(_)) => {
+            warn!("Token validation unavailable: {}", e);
+            state.metrics_service.increment_counter("token_validation_unavailable_total", &[]);
+            return Err(e);
+        }
+        Err(e) => {
+            error!("Token validation failed: {}", e);
+            let (_, reason, _) = e.parts();
+            return Ok(unauthorized(&state, "Invalid token", &reason));
+        }
+    };
+
+    // Every request made under an impersonation token is audited against both
+    // the impersonated user and the real admin
+    let impersonation = claims.impersonated_by.map(|admin_id| {
+        (claims.sub, admin_id, request.method().to_string(), request.uri().path().to_string())
+    });
 
     // Add user information to request extensions for downstream handlers
     request.extensions_mut().insert(claims);
 
-    Ok(next.run(request).await)
+    let response = next.run(request).await;
+
+    if let Some((user_id, admin_id, method, path)) = impersonation {
+        warn!(%user_id, %admin_id, "Request made under impersonation: {} {}", method, path);
+
+        if let Err(e) = state.audit_service.log_action(
+            Some(user_id),
+            "impersonated_request",
+            AuditCategory::Security,
+            AuditSeverity::Warning,
+            "request",
+            None,
+            "127.0.0.1",
+            None,
+            json!({
+                "impersonated_by": admin_id,
+                "method": method,
+                "path": path,
+                "status": response.status().as_u16(),
+            }),
+        ).await {
+            error!("Failed to audit impersonated request: {}", e);
+        }
+    }
+
+    Ok(response)
 }
\ No newline at end of file
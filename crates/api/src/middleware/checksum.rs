@@ -0,0 +1,110 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderName},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::state::AppState;
+use app_core::error::ApiError;
+
+pub static CONTENT_MD5: HeaderName = HeaderName::from_static("content-md5");
+pub static X_CONTENT_SHA256: HeaderName = HeaderName::from_static("x-content-sha256");
+
+/// Largest body buffered for checksum verification
+const MAX_CHECKSUM_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// Verifies request bodies on configured routes against a client-supplied `Content-MD5`
+/// (base64, RFC 1864) or `X-Content-SHA256` (hex) header, rejecting mismatches with 400
+/// before the handler runs. Requests without either header pass through unchecked.
+pub async fn checksum_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if !state.config.server.checksum_verified_paths.iter().any(|prefix| path.starts_with(prefix.as_str())) {
+        return next.run(request).await;
+    }
+
+    let expected = match Checksum::from_headers(request.headers()) {
+        Ok(Some(expected)) => expected,
+        Ok(None) => return next.run(request).await,
+        Err(e) => return e.into_response(),
+    };
+
+    // The whole body has to be buffered to verify it before the handler sees any of it
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_CHECKSUM_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return ApiError::BadRequest(format!(
+                "Checksummed request bodies are limited to {} bytes",
+                MAX_CHECKSUM_BODY_BYTES
+            ))
+            .into_response();
+        }
+    };
+
+    if !expected.matches(&bytes) {
+        state.metrics_service.increment_counter("request_checksum_mismatch_total", &[]);
+        warn!(path = %parts.uri.path(), "Request body does not match its {} header", expected.header());
+        return ApiError::BadRequest(format!("Request body does not match {}", expected.header()))
+            .into_response();
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes))).await
+}
+
+/// Digest the client claims for the body
+enum Checksum {
+    Md5(Vec<u8>),
+    Sha256(Vec<u8>),
+}
+
+impl Checksum {
+    /// The checksum header sent, preferring SHA-256 when both are present
+    fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, ApiError> {
+        if let Some(value) = headers.get(&X_CONTENT_SHA256) {
+            let digest = value
+                .to_str()
+                .ok()
+                .and_then(|value| hex::decode(value.trim()).ok())
+                .filter(|digest| digest.len() == 32)
+                .ok_or_else(|| ApiError::BadRequest("X-Content-SHA256 must be a hex SHA-256 digest".to_string()))?;
+            return Ok(Some(Self::Sha256(digest)));
+        }
+
+        if let Some(value) = headers.get(&CONTENT_MD5) {
+            let digest = value
+                .to_str()
+                .ok()
+                .and_then(|value| STANDARD.decode(value.trim()).ok())
+                .filter(|digest| digest.len() == 16)
+                .ok_or_else(|| ApiError::BadRequest("Content-MD5 must be a base64 MD5 digest".to_string()))?;
+            return Ok(Some(Self::Md5(digest)));
+        }
+
+        Ok(None)
+    }
+
+    fn matches(&self, body: &[u8]) -> bool {
+        match self {
+            Self::Md5(expected) => Md5::digest(body).as_slice() == expected.as_slice(),
+            Self::Sha256(expected) => Sha256::digest(body).as_slice() == expected.as_slice(),
+        }
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            Self::Md5(_) => "Content-MD5",
+            Self::Sha256(_) => "X-Content-SHA256",
+        }
+    }
+}
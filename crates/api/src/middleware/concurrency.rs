@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{RwLock, Semaphore};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::state::AppState;
+use app_core::error::ApiError;
+use auth::Claims;
+
+/// Per-user in-flight request cap so a single caller can't monopolize the server
+#[derive(Clone)]
+pub struct UserConcurrencyLimiter {
+    limit: usize,
+    semaphores: Arc<RwLock<HashMap<Uuid, Arc<Semaphore>>>>,
+}
+
+impl UserConcurrencyLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            semaphores: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    async fn semaphore_for(&self, user_id: Uuid) -> Arc<Semaphore> {
+        if let Some(semaphore) = self.semaphores.read().await.get(&user_id) {
+            return semaphore.clone();
+        }
+
+        self.semaphores
+            .write()
+            .await
+            .entry(user_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit)))
+            .clone()
+    }
+
+    /// Forget users with no requests in flight. An entry is only dropped while no request
+    /// holds its semaphore, so a user can't end up with two.
+    pub async fn prune(&self) {
+        self.semaphores.write().await.retain(|_, semaphore| {
+            Arc::strong_count(semaphore) > 1 || semaphore.available_permits() < self.limit
+        });
+    }
+}
+
+/// Sheds a user's excess concurrent requests with 429 while other users proceed.
+/// Must run after `auth_middleware` so claims are available; admins bypass the cap.
+pub async fn user_concurrency_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let user_id = match request.extensions().get::<Claims>() {
+        Some(claims) if !claims.is_admin() => claims.sub,
+        _ => return Ok(next.run(request).await),
+    };
+
+    let semaphore = state.user_concurrency.semaphore_for(user_id).await;
+
+    // Held until the response is produced
+    let _permit = semaphore.try_acquire_owned().map_err(|_| {
+        warn!("Concurrent request limit reached for user: {}", user_id);
+        ApiError::RateLimitExceeded("Too many concurrent requests".to_string())
+    })?;
+
+    Ok(next.run(request).await)
+}
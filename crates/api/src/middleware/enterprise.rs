@@ -4,36 +4,116 @@ use axum::{
     middleware::Next,
     response::Response,
 };
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tracing::{info_span, Instrument};
-use uuid::Uuid;
-
+use crate::request_id;
 use crate::state::AppState;
 
 pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
 pub static X_CORRELATION_ID: HeaderName = HeaderName::from_static("x-correlation-id");
 
+/// Window over which requests sharing a correlation id are counted
+const CORRELATION_WINDOW: Duration = Duration::from_secs(60);
+
+/// Counts requests per client-supplied correlation id to spot clients reusing one
+/// id for unrelated traffic. At most `capacity` ids are counted; a new id beyond that
+/// replaces the one whose window started longest ago.
+#[derive(Clone)]
+pub struct CorrelationTracker {
+    threshold: u64,
+    capacity: usize,
+    windows: Arc<Mutex<HashMap<String, (Instant, u64)>>>,
+}
+
+impl CorrelationTracker {
+    pub fn new(threshold: u64, capacity: usize) -> Self {
+        Self {
+            threshold,
+            capacity,
+            windows: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Count one request for `correlation_id`; true once the id exceeds the threshold
+    /// within the current window
+    pub fn record(&self, correlation_id: &str) -> bool {
+        self.record_at(correlation_id, Instant::now())
+    }
+
+    fn record_at(&self, correlation_id: &str, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+
+        if windows.len() >= self.capacity && !windows.contains_key(correlation_id) {
+            windows.retain(|_, (started, _)| now.duration_since(*started) < CORRELATION_WINDOW);
+            if windows.len() >= self.capacity {
+                let oldest = windows
+                    .iter()
+                    .min_by_key(|(_, (started, _))| *started)
+                    .map(|(id, _)| id.clone());
+                if let Some(oldest) = oldest {
+                    windows.remove(&oldest);
+                }
+            }
+        }
+
+        let (started, count) = windows
+            .entry(correlation_id.to_string())
+            .or_insert((now, 0));
+        if now.duration_since(*started) >= CORRELATION_WINDOW {
+            *started = now;
+            *count = 0;
+        }
+        *count += 1;
+
+        // Warn once per window rather than on every excess request
+        if *count == self.threshold + 1 {
+            warn!(
+                correlation_id = %correlation_id,
+                threshold = self.threshold,
+                "Correlation id reused by an unusually high number of requests"
+            );
+        }
+
+        *count > self.threshold
+    }
+
+    /// Drop counts for ids not seen within the current window
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.windows
+            .lock()
+            .unwrap()
+            .retain(|_, (started, _)| now.duration_since(*started) < CORRELATION_WINDOW);
+    }
+}
+
 /// Correlation ID middleware that ensures every request has a unique identifier
-/// for distributed tracing and debugging
+/// for distributed tracing and debugging.
+///
+/// A client-supplied correlation id is honored so traces can span requests, but the
+/// request id is always generated here so it stays unique per request.
 pub async fn correlation_middleware(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
 ) -> Response {
-    // Get or generate correlation ID
-    let correlation_id = headers
-        .get(&X_CORRELATION_ID)
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
-
-    // Get or generate request ID (unique per request, correlation ID can span multiple requests)
-    let request_id = headers
-        .get(&X_REQUEST_ID)
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let id_format = state.config.server.request_id_format;
+
+    // Honor a supplied correlation ID, metering ids shared by too many requests
+    let correlation_id = match headers.get(&X_CORRELATION_ID).and_then(|v| v.to_str().ok()) {
+        Some(supplied) => {
+            if state.correlation_tracker.record(supplied) {
+                state.metrics_service.increment_counter("correlation_id_reuse_total", &[]);
+            }
+            supplied.to_string()
+        }
+        None => request_id::generate(id_format),
+    };
+
+    // Request ID is always ours: unique per request, while a correlation ID can span many
+    let request_id = request_id::generate(id_format);
 
     // Add IDs to request extensions for downstream handlers
     request.extensions_mut().insert(correlation_id.clone());
@@ -184,3 +264,53 @@ fn get_memory_usage() -> f64 {
     // For now, return a placeholder value
     std::process::id() as f64 / 1000.0 // Simplified placeholder
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracked(tracker: &CorrelationTracker) -> Vec<String> {
+        let mut ids: Vec<_> = tracker.windows.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn ids_over_the_threshold_are_flagged_within_a_window() {
+        let tracker = CorrelationTracker::new(2, 10);
+        let start = Instant::now();
+
+        assert!(!tracker.record_at("a", start));
+        assert!(!tracker.record_at("a", start));
+        assert!(tracker.record_at("a", start));
+        assert!(!tracker.record_at("a", start + CORRELATION_WINDOW));
+    }
+
+    #[test]
+    fn the_id_with_the_oldest_window_makes_room_at_capacity() {
+        let tracker = CorrelationTracker::new(10, 2);
+        let start = Instant::now();
+
+        tracker.record_at("a", start);
+        tracker.record_at("b", start + Duration::from_secs(1));
+        tracker.record_at("a", start + Duration::from_secs(2));
+        tracker.record_at("c", start + Duration::from_secs(3));
+        assert_eq!(tracked(&tracker), ["b", "c"]);
+
+        // Counting an id already tracked evicts nothing
+        tracker.record_at("b", start + Duration::from_secs(4));
+        assert_eq!(tracked(&tracker), ["b", "c"]);
+    }
+
+    #[test]
+    fn expired_windows_are_dropped_before_live_ones_at_capacity() {
+        let tracker = CorrelationTracker::new(10, 3);
+        let start = Instant::now();
+
+        tracker.record_at("a", start);
+        tracker.record_at("b", start);
+        tracker.record_at("c", start + Duration::from_secs(30));
+        tracker.record_at("d", start + CORRELATION_WINDOW);
+        assert_eq!(tracked(&tracker), ["c", "d"]);
+    }
+}
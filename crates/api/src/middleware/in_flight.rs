@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::state::AppState;
+
+/// The request that has been in flight the longest
+#[derive(Debug, Clone, Serialize)]
+pub struct OldestRequest {
+    pub method: String,
+    pub path: String,
+    pub age_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InFlightSnapshot {
+    pub count: usize,
+    pub oldest: Option<OldestRequest>,
+}
+
+struct InFlightEntry {
+    started: Instant,
+    method: String,
+    path: String,
+}
+
+/// Requests currently being handled. Entries are keyed by a sequence number taken at
+/// arrival, so the oldest request is always the first entry.
+#[derive(Clone, Default)]
+pub struct InFlightRequests {
+    next_id: Arc<AtomicU64>,
+    entries: Arc<Mutex<BTreeMap<u64, InFlightEntry>>>,
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track `request` until the returned guard is dropped
+    fn start(&self, request: &Request) -> InFlightGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.entries.lock().unwrap().insert(
+            id,
+            InFlightEntry {
+                started: Instant::now(),
+                method: request.method().to_string(),
+                path: request.uri().path().to_string(),
+            },
+        );
+
+        InFlightGuard { requests: self.clone(), id }
+    }
+
+    pub fn snapshot(&self) -> InFlightSnapshot {
+        let entries = self.entries.lock().unwrap();
+        InFlightSnapshot {
+            count: entries.len(),
+            oldest: entries.values().next().map(|entry| OldestRequest {
+                method: entry.method.clone(),
+                path: entry.path.clone(),
+                age_ms: entry.started.elapsed().as_millis() as u64,
+            }),
+        }
+    }
+}
+
+/// Removes its request from the in-flight set when the response is produced or the
+/// request is cancelled
+struct InFlightGuard {
+    requests: InFlightRequests,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.requests.entries.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Record every request in `state.in_flight` while it is being handled
+pub async fn in_flight_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _guard = state.in_flight.start(&request);
+    next.run(request).await
+}
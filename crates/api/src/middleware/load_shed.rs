@@ -0,0 +1,33 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+use crate::state::AppState;
+
+/// Fail fast with a retriable 503 when the database pool is exhausted instead of
+/// letting requests queue until `acquire_timeout` fires
+pub async fn load_shed_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Held until the response is produced
+    let _admission = match state.db_pool.try_admit() {
+        Ok(admission) => admission,
+        Err(e) => {
+            state.metrics_service.increment_counter("requests_shed_total", &[]);
+
+            let mut response = e.into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+            return response;
+        }
+    };
+
+    next.run(request).await
+}
@@ -2,4 +2,16 @@ pub mod auth;
 pub mod rate_limit;
 pub mod metrics;
 pub mod enterprise;
-
+pub mod concurrency;
+pub mod load_shed;
+pub mod problem;
+pub mod service_auth;
+pub mod signing;
+pub mod tls_policy;
+pub mod null_fields;
+pub mod response_size;
+pub mod checksum;
+pub mod in_flight;
+pub mod accept;
+pub mod timestamps;
+pub mod server_timing;
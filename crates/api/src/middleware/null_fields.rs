@@ -0,0 +1,69 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::sync::Arc;
+use tracing::error;
+
+use crate::state::AppState;
+use app_core::config::NullFieldPolicy;
+use app_core::error::ApiError;
+
+/// Strips `null`-valued fields from JSON responses when `server.null_fields` is `omit`.
+/// Must run inside response signing so signatures cover the rewritten body.
+pub async fn null_fields_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if state.config.server.null_fields == NullFieldPolicy::Include {
+        return response;
+    }
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to buffer response body for null stripping: {}", e);
+            return ApiError::Internal(anyhow::anyhow!("Response serialization failed")).into_response();
+        }
+    };
+
+    // Bodies that don't parse are passed through untouched
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    strip_nulls(&mut value);
+
+    let Ok(compact) = serde_json::to_vec(&value) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(compact))
+}
+
+/// Remove `null` object fields at any depth; `null` array elements are kept so indexes hold
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, field| !field.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
@@ -0,0 +1,46 @@
+use axum::{
+    extract::Request,
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::middleware::enterprise::X_CORRELATION_ID;
+use crate::negotiation::explicitly_accepts;
+use app_core::error::{ErrorDetails, PROBLEM_JSON_MEDIA_TYPE};
+
+/// Re-encodes error responses as RFC 7807 problem+json when the client asks for it
+/// via `Accept`; everyone else keeps the default error envelope.
+/// Must wrap `correlation_middleware` so the correlation id is available as `instance`.
+pub async fn problem_json_middleware(request: Request, next: Next) -> Response {
+    let wants_problem_json = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| explicitly_accepts(accept, PROBLEM_JSON_MEDIA_TYPE));
+
+    let response = next.run(request).await;
+    if !wants_problem_json {
+        return response;
+    }
+
+    let Some(details) = response.extensions().get::<ErrorDetails>().cloned() else {
+        return response;
+    };
+
+    let instance = response
+        .headers()
+        .get(&X_CORRELATION_ID)
+        .and_then(|v| v.to_str().ok());
+    let mut problem = details.to_problem_response(instance);
+
+    // Keep headers set further in (correlation ids, Retry-After, ...)
+    let (parts, _) = response.into_parts();
+    for (name, value) in parts.headers.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            problem.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+
+    problem
+}
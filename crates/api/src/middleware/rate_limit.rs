@@ -0,0 +1,610 @@
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration as ChronoDuration, LocalResult, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use governor::{
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
+    Quota, RateLimiter,
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+use app_core::config::{RateLimitConfig, RateLimitWindow, ResourceRateLimitConfig, WindowBoundary};
+use app_core::error::{ApiError, Result};
+use auth::Claims;
+use monitoring::MetricsService;
+
+use crate::state::AppState;
+
+/// In-memory GCRA limiter with a separate quota per key
+type KeyedLimiter<K> = RateLimiter<K, DefaultKeyedStateStore<K>, DefaultClock>;
+
+/// Per-user request quota for endpoints that are more expensive than a typical
+/// request. Admins are exempt.
+#[derive(Clone)]
+pub struct UserRateLimiter {
+    name: &'static str,
+    limiter: Arc<KeyedLimiter<Uuid>>,
+}
+
+impl UserRateLimiter {
+    pub fn per_minute(name: &'static str, requests: u32) -> Self {
+        let quota = Quota::per_minute(NonZeroU32::new(requests.max(1)).unwrap());
+
+        Self {
+            name,
+            limiter: Arc::new(RateLimiter::keyed(quota)),
+        }
+    }
+
+    pub fn check(&self, claims: &Claims) -> Result<()> {
+        if claims.is_admin() {
+            return Ok(());
+        }
+
+        self.limiter.check_key(&claims.sub).map_err(|_| {
+            warn!("Rate limit '{}' exceeded for user: {}", self.name, claims.sub);
+            ApiError::RateLimitExceeded("Too many requests, please slow down".to_string())
+        })
+    }
+
+    /// Drop state for users whose quota has fully replenished
+    pub fn prune(&self) {
+        self.limiter.retain_recent();
+    }
+}
+
+/// What an expensive operation costs against a user's request cost budget, in units
+/// where a typical request would cost one
+#[derive(Debug, Clone, Copy)]
+pub enum RequestCost {
+    /// Charged per item, on top of the request itself
+    BulkWrite(usize),
+    /// CSV import; the row count isn't known until the body has been read
+    Import,
+    Export,
+    Report,
+}
+
+impl RequestCost {
+    pub fn operation(&self) -> &'static str {
+        match self {
+            RequestCost::BulkWrite(_) => "bulk_write",
+            RequestCost::Import => "import",
+            RequestCost::Export => "export",
+            RequestCost::Report => "report",
+        }
+    }
+
+    pub fn units(&self) -> u32 {
+        match self {
+            RequestCost::BulkWrite(items) => u32::try_from(*items).unwrap_or(u32::MAX).saturating_add(1),
+            RequestCost::Import => 200,
+            RequestCost::Export => 100,
+            RequestCost::Report => 50,
+        }
+    }
+
+    /// Units taken from a budget of `budget` per minute. Capped at the whole budget so an
+    /// operation dearer than the budget can still run once the user's budget has fully
+    /// refilled.
+    fn charged_units(&self, budget: NonZeroU32) -> NonZeroU32 {
+        NonZeroU32::new(self.units().clamp(1, budget.get())).unwrap()
+    }
+}
+
+/// Per-user budget of cost units per minute, shared by every endpoint that declares a
+/// `RequestCost`. Units are always counted in `request_cost_units_total`; the budget is
+/// only enforced when `server.request_cost_budget_per_minute` is set. Admins are exempt.
+#[derive(Clone)]
+pub struct RequestCostBudget {
+    budget: Option<(NonZeroU32, Arc<KeyedLimiter<Uuid>>)>,
+    metrics: MetricsService,
+}
+
+impl RequestCostBudget {
+    pub fn per_minute(units: Option<u32>, metrics: MetricsService) -> Self {
+        let budget = units.and_then(NonZeroU32::new).map(|units| {
+            (units, Arc::new(RateLimiter::keyed(Quota::per_minute(units))))
+        });
+
+        Self { budget, metrics }
+    }
+
+    /// Charge `cost` to the caller, failing with 429 once their budget is spent
+    pub fn charge(&self, claims: &Claims, cost: RequestCost) -> Result<()> {
+        let operation = [("operation", cost.operation())];
+
+        if let Some((budget, limiter)) = &self.budget {
+            let exempt = claims.is_admin();
+            if !exempt && !matches!(limiter.check_key_n(&claims.sub, cost.charged_units(*budget)), Ok(Ok(()))) {
+                self.metrics.increment_counter("request_cost_budget_exceeded_total", &operation);
+                warn!("Request cost budget exceeded for user {} ({})", claims.sub, cost.operation());
+                return Err(ApiError::RateLimitExceeded(
+                    "Request cost budget exhausted, please retry later".to_string(),
+                ));
+            }
+        }
+
+        self.metrics.increment_counter_by("request_cost_units_total", u64::from(cost.units()), &operation);
+        Ok(())
+    }
+
+    /// Drop state for users whose budget has fully replenished
+    pub fn prune(&self) {
+        if let Some((_, limiter)) = &self.budget {
+            limiter.retain_recent();
+        }
+    }
+}
+
+/// Hourly quotas on emails sent at a user's request, such as password resets, per
+/// recipient and per client address. Guards against email-bombing a victim's inbox and
+/// runaway sending costs.
+#[derive(Clone)]
+pub struct EmailSendLimiter {
+    per_address: Arc<KeyedLimiter<String>>,
+    per_client: Arc<KeyedLimiter<IpAddr>>,
+}
+
+impl EmailSendLimiter {
+    pub fn per_hour(per_address: u32, per_client: u32) -> Self {
+        let quota = |sends: u32| Quota::per_hour(NonZeroU32::new(sends.max(1)).unwrap());
+
+        Self {
+            per_address: Arc::new(RateLimiter::keyed(quota(per_address))),
+            per_client: Arc::new(RateLimiter::keyed(quota(per_client))),
+        }
+    }
+
+    /// Charge one send to `address` requested by `client`. On `Err`, names the quota
+    /// that was exhausted; the send should be suppressed.
+    pub fn check(&self, address: &str, client: IpAddr) -> std::result::Result<(), &'static str> {
+        // The client is checked first so one client can't exhaust a victim's address quota
+        if self.per_client.check_key(&client).is_err() {
+            return Err("client");
+        }
+        if self.per_address.check_key(&address.to_lowercase()).is_err() {
+            return Err("address");
+        }
+        Ok(())
+    }
+
+    /// Drop state for addresses and clients whose quota has fully replenished
+    pub fn prune(&self) {
+        self.per_address.retain_recent();
+        self.per_client.retain_recent();
+    }
+}
+
+/// Outcome of charging one request against a client's quota
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    /// When the client's quota is next fully available
+    pub reset_at: DateTime<Utc>,
+}
+
+/// Per-client request quota applied by `rate_limit_middleware`, keyed by client address
+/// as resolved through `server.tls_policy.trusted_proxies`
+#[derive(Clone)]
+pub struct ClientRateLimiter {
+    limit: u32,
+    window: ClientWindow,
+}
+
+#[derive(Clone)]
+enum ClientWindow {
+    Sliding {
+        limiter: Arc<RateLimiter<IpAddr, DefaultKeyedStateStore<IpAddr>, DefaultClock, StateInformationMiddleware>>,
+        clock: DefaultClock,
+    },
+    Fixed(Arc<FixedWindow>),
+}
+
+/// Counters that reset together at calendar boundaries in a time zone
+struct FixedWindow {
+    boundary: WindowBoundary,
+    timezone: Tz,
+    /// Request count per client, alongside the end of the window it was counted in
+    counters: Mutex<HashMap<IpAddr, (DateTime<Utc>, u32)>>,
+}
+
+impl ClientRateLimiter {
+    pub fn from_config(config: &RateLimitConfig) -> Result<Self> {
+        let limit = NonZeroU32::new(config.requests)
+            .ok_or_else(|| anyhow::anyhow!("Rate limit requests must be at least 1"))?;
+
+        let window = match &config.window {
+            RateLimitWindow::Sliding { period } => {
+                if *period == 0 {
+                    return Err(anyhow::anyhow!("Sliding rate limit period must be at least 1 second").into());
+                }
+                let quota = Quota::with_period(Duration::from_secs(*period) / limit.get())
+                    .ok_or_else(|| anyhow::anyhow!("Sliding rate limit period is too short"))?
+                    .allow_burst(limit);
+                let clock = DefaultClock::default();
+                let limiter = RateLimiter::<_, _, _, StateInformationMiddleware>::new(
+                    quota,
+                    DefaultKeyedStateStore::default(),
+                    &clock,
+                );
+                ClientWindow::Sliding { limiter: Arc::new(limiter), clock }
+            }
+            RateLimitWindow::Fixed { boundary, timezone } => {
+                let timezone: Tz = timezone
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Unknown rate limit time zone: {}", timezone))?;
+                ClientWindow::Fixed(Arc::new(FixedWindow {
+                    boundary: *boundary,
+                    timezone,
+                    counters: Mutex::new(HashMap::new()),
+                }))
+            }
+        };
+
+        Ok(Self { limit: limit.get(), window })
+    }
+
+    /// Charge one request from `client` against its quota
+    pub fn check(&self, client: IpAddr) -> RateLimitDecision {
+        let now = Utc::now();
+        match &self.window {
+            ClientWindow::Sliding { limiter, clock } => match limiter.check_key(&client) {
+                Ok(snapshot) => {
+                    let remaining = snapshot.remaining_burst_capacity();
+                    let refill = snapshot.quota().replenish_interval() * (self.limit - remaining);
+                    self.decision(true, remaining, now + to_chrono(refill))
+                }
+                Err(not_until) => {
+                    let wait = not_until.wait_time_from(clock.now());
+                    self.decision(false, 0, now + to_chrono(wait))
+                }
+            },
+            ClientWindow::Fixed(window) => {
+                let mut counters = window.counters.lock().unwrap();
+                let (ends_at, count) = counters.entry(client).or_insert((now, 0));
+                if *ends_at <= now {
+                    *ends_at = window.end_of_window(now);
+                    *count = 0;
+                }
+
+                if *count >= self.limit {
+                    return self.decision(false, 0, *ends_at);
+                }
+                *count += 1;
+                self.decision(true, self.limit - *count, *ends_at)
+            }
+        }
+    }
+
+    fn decision(&self, allowed: bool, remaining: u32, reset_at: DateTime<Utc>) -> RateLimitDecision {
+        RateLimitDecision { allowed, limit: self.limit, remaining, reset_at }
+    }
+
+    /// Drop state for clients whose quota has fully replenished
+    pub fn prune(&self) {
+        match &self.window {
+            ClientWindow::Sliding { limiter, .. } => limiter.retain_recent(),
+            ClientWindow::Fixed(window) => {
+                let now = Utc::now();
+                window.counters.lock().unwrap().retain(|_, (ends_at, _)| *ends_at > now);
+            }
+        }
+    }
+}
+
+/// Per-client quotas on individual resources, chosen by the matched route and method and
+/// keyed like `ClientRateLimiter`
+#[derive(Clone, Default)]
+pub struct ResourceRateLimiter {
+    rules: Vec<ResourceRule>,
+}
+
+#[derive(Clone)]
+struct ResourceRule {
+    path: String,
+    /// Every method when empty
+    methods: Vec<Method>,
+    limiter: ClientRateLimiter,
+}
+
+impl ResourceRule {
+    fn matches(&self, route: &str, method: &Method) -> bool {
+        let covers_route = route
+            .strip_prefix(self.path.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        covers_route && (self.methods.is_empty() || self.methods.contains(method))
+    }
+}
+
+impl ResourceRateLimiter {
+    pub fn from_config(configs: &[ResourceRateLimitConfig]) -> Result<Self> {
+        let rules = configs
+            .iter()
+            .map(|config| {
+                let methods = config
+                    .methods
+                    .iter()
+                    .map(|method| {
+                        Method::from_bytes(method.to_uppercase().as_bytes())
+                            .map_err(|_| anyhow::anyhow!("Invalid rate limit method {} for {}", method, config.path))
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+
+                Ok(ResourceRule {
+                    path: config.path.trim_end_matches('/').to_string(),
+                    methods,
+                    limiter: ClientRateLimiter::from_config(&config.limit)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Charge one request from `client` to the first rule covering `route` and `method`;
+    /// `None` when no rule does
+    pub fn check(&self, route: &str, method: &Method, client: IpAddr) -> Option<RateLimitDecision> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(route, method))
+            .map(|rule| rule.limiter.check(client))
+    }
+
+    /// Drop state for clients whose quotas have fully replenished
+    pub fn prune(&self) {
+        self.rules.iter().for_each(|rule| rule.limiter.prune());
+    }
+}
+
+impl FixedWindow {
+    /// The first boundary after `now`, computed on the zone's wall clock so daily windows
+    /// follow local midnight across DST changes. A repeated hour when clocks go back
+    /// shares one window.
+    fn end_of_window(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let local = now.with_timezone(&self.timezone).naive_local();
+        let start = match self.boundary {
+            WindowBoundary::Hourly => local.date().and_hms_opt(local.hour(), 0, 0),
+            WindowBoundary::Daily => local.date().and_hms_opt(0, 0, 0),
+        }
+        .expect("start of hour is a valid time");
+        let step = match self.boundary {
+            WindowBoundary::Hourly => ChronoDuration::hours(1),
+            WindowBoundary::Daily => ChronoDuration::days(1),
+        };
+        self.resolve_local(start + step)
+    }
+
+    /// Map a wall-clock time to an instant; times skipped by a DST jump resolve to the
+    /// first minute that exists after the jump
+    fn resolve_local(&self, mut local: NaiveDateTime) -> DateTime<Utc> {
+        loop {
+            match self.timezone.from_local_datetime(&local) {
+                LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => return time.with_timezone(&Utc),
+                LocalResult::None => local += ChronoDuration::minutes(1),
+            }
+        }
+    }
+}
+
+fn to_chrono(duration: Duration) -> ChronoDuration {
+    ChronoDuration::from_std(duration).unwrap_or(ChronoDuration::MAX)
+}
+
+/// Routes polled by infrastructure, never limited
+const UNLIMITED_PATHS: &[&str] = &["/health", "/metrics"];
+
+/// Apply `server.rate_limit` and the `server.resource_rate_limits` entry matching the
+/// route per client address, reporting the tighter quota in `X-RateLimit-Limit`,
+/// `X-RateLimit-Remaining` and `X-RateLimit-Reset` (Unix seconds)
+pub async fn rate_limit_middleware(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if UNLIMITED_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let client = state.trusted_proxies.client_ip(peer.ip(), request.headers());
+    let global = state.rate_limiter.as_ref().map(|limiter| limiter.check(client));
+    let resource = request
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|route| state.resource_rate_limiter.check(route.as_str(), request.method(), client));
+
+    // A refusal wins, then whichever quota has less left
+    let Some(decision) = global.into_iter().chain(resource).min_by_key(|d| (d.allowed, d.remaining)) else {
+        return next.run(request).await;
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        let scope = if resource.is_some_and(|d| !d.allowed) { "resource" } else { "client" };
+        state.metrics_service.increment_counter("rate_limit_exceeded_total", &[("scope", scope)]);
+        warn!(client = %client, path = %request.uri().path(), scope, "Client rate limit exceeded");
+
+        let mut response =
+            ApiError::RateLimitExceeded("Too many requests, please slow down".to_string()).into_response();
+        let retry_after = (decision.reset_at - Utc::now()).num_seconds().max(1);
+        response.headers_mut().insert("retry-after", HeaderValue::from(retry_after));
+        response
+    };
+
+    set_rate_limit_headers(response.headers_mut(), &decision);
+    response
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert("x-ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(decision.remaining));
+    // Rounded up so a client waiting until the reset never arrives early
+    let reset = decision.reset_at.timestamp() + i64::from(decision.reset_at.timestamp_subsec_nanos() > 0);
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(boundary: WindowBoundary, timezone: &str) -> FixedWindow {
+        FixedWindow { boundary, timezone: timezone.parse().unwrap(), counters: Mutex::new(HashMap::new()) }
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn rule(path: &str, methods: &[&str], requests: u32) -> ResourceRateLimitConfig {
+        ResourceRateLimitConfig {
+            path: path.to_string(),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+            limit: RateLimitConfig { requests, window: RateLimitWindow::Sliding { period: 60 } },
+        }
+    }
+
+    #[test]
+    fn rules_cover_their_path_and_routes_below_it() {
+        let limiter = ResourceRateLimiter::from_config(&[rule("/api/v1/products/", &[], 1)]).unwrap();
+        let rule = &limiter.rules[0];
+
+        assert!(rule.matches("/api/v1/products", &Method::GET));
+        assert!(rule.matches("/api/v1/products/:id", &Method::DELETE));
+        assert!(!rule.matches("/api/v1/products-archive", &Method::GET));
+        assert!(!rule.matches("/api/v1", &Method::GET));
+    }
+
+    #[test]
+    fn rules_with_methods_cover_only_those_methods() {
+        let limiter = ResourceRateLimiter::from_config(&[rule("/api/v1/products", &["post", "DELETE"], 1)]).unwrap();
+        let rule = &limiter.rules[0];
+
+        assert!(rule.matches("/api/v1/products", &Method::POST));
+        assert!(rule.matches("/api/v1/products/:id", &Method::DELETE));
+        assert!(!rule.matches("/api/v1/products", &Method::GET));
+    }
+
+    #[test]
+    fn the_first_matching_rule_applies() {
+        let limiter = ResourceRateLimiter::from_config(&[
+            rule("/api/v1/products", &["POST"], 5),
+            rule("/api/v1/products", &[], 100),
+        ])
+        .unwrap();
+        let client: IpAddr = "198.51.100.1".parse().unwrap();
+
+        let write = limiter.check("/api/v1/products", &Method::POST, client).unwrap();
+        assert_eq!((write.limit, write.remaining), (5, 4));
+        let read = limiter.check("/api/v1/products/:id", &Method::GET, client).unwrap();
+        assert_eq!((read.limit, read.remaining), (100, 99));
+        assert!(limiter.check("/api/v1/users", &Method::POST, client).is_none());
+    }
+
+    #[test]
+    fn bulk_writes_cost_one_unit_per_item_plus_the_request() {
+        assert_eq!(RequestCost::BulkWrite(0).units(), 1);
+        assert_eq!(RequestCost::BulkWrite(25).units(), 26);
+        assert_eq!(RequestCost::BulkWrite(usize::MAX).units(), u32::MAX);
+        assert_eq!(RequestCost::Import.units(), 200);
+        assert_eq!(RequestCost::Export.units(), 100);
+        assert_eq!(RequestCost::Report.units(), 50);
+    }
+
+    #[test]
+    fn charges_are_capped_at_the_whole_budget() {
+        let budget = NonZeroU32::new(100).unwrap();
+
+        assert_eq!(RequestCost::Report.charged_units(budget).get(), 50);
+        assert_eq!(RequestCost::Export.charged_units(budget).get(), 100);
+        assert_eq!(RequestCost::Import.charged_units(budget).get(), 100);
+        assert_eq!(RequestCost::BulkWrite(usize::MAX).charged_units(budget).get(), 100);
+    }
+
+    #[test]
+    fn an_operation_dearer_than_the_budget_runs_once_the_budget_is_full() {
+        let budget = NonZeroU32::new(100).unwrap();
+        let limiter: KeyedLimiter<Uuid> = RateLimiter::keyed(Quota::per_minute(budget));
+        let user = Uuid::new_v4();
+
+        // Without the cap, 200 units could never fit in a budget of 100
+        assert!(limiter.check_key_n(&user, NonZeroU32::new(RequestCost::Import.units()).unwrap()).is_err());
+
+        assert!(matches!(limiter.check_key_n(&user, RequestCost::Import.charged_units(budget)), Ok(Ok(()))));
+        assert!(matches!(limiter.check_key_n(&user, RequestCost::Report.charged_units(budget)), Ok(Err(_))));
+    }
+
+    #[test]
+    fn email_sends_are_limited_per_address_regardless_of_case() {
+        let limiter = EmailSendLimiter::per_hour(2, 10);
+        let (first, second): (IpAddr, IpAddr) = ("198.51.100.1".parse().unwrap(), "198.51.100.2".parse().unwrap());
+
+        assert_eq!(limiter.check("victim@example.com", first), Ok(()));
+        assert_eq!(limiter.check("Victim@Example.com", second), Ok(()));
+        assert_eq!(limiter.check("victim@example.com", second), Err("address"));
+        assert_eq!(limiter.check("someone@example.com", first), Ok(()));
+    }
+
+    #[test]
+    fn email_sends_are_limited_per_client_before_the_address_is_charged() {
+        let limiter = EmailSendLimiter::per_hour(2, 3);
+        let (client, other): (IpAddr, IpAddr) = ("198.51.100.1".parse().unwrap(), "198.51.100.2".parse().unwrap());
+
+        for n in 0..3 {
+            assert_eq!(limiter.check(&format!("user{}@example.com", n), client), Ok(()));
+        }
+        assert_eq!(limiter.check("victim@example.com", client), Err("client"));
+        assert_eq!(limiter.check("victim@example.com", client), Err("client"));
+
+        // The refused sends didn't use up the victim's quota
+        assert_eq!(limiter.check("victim@example.com", other), Ok(()));
+        assert_eq!(limiter.check("victim@example.com", other), Ok(()));
+    }
+
+    #[test]
+    fn daily_windows_end_at_local_midnight_across_dst_changes() {
+        let daily = window(WindowBoundary::Daily, "America/New_York");
+
+        // Clocks go forward on 2024-03-10, so that day is 23 hours long
+        assert_eq!(daily.end_of_window(utc("2024-03-09T17:00:00Z")), utc("2024-03-10T05:00:00Z"));
+        assert_eq!(daily.end_of_window(utc("2024-03-10T17:00:00Z")), utc("2024-03-11T04:00:00Z"));
+
+        // and back on 2024-11-03, so that one is 25
+        assert_eq!(daily.end_of_window(utc("2024-11-03T12:00:00Z")), utc("2024-11-04T05:00:00Z"));
+        assert_eq!(daily.end_of_window(utc("2024-11-02T12:00:00Z")), utc("2024-11-03T04:00:00Z"));
+    }
+
+    #[test]
+    fn hourly_windows_skip_the_missing_hour_and_share_the_repeated_one() {
+        let hourly = window(WindowBoundary::Hourly, "America/New_York");
+
+        // 01:30 EST; 02:00 doesn't exist, so the window runs to 03:00 EDT
+        assert_eq!(hourly.end_of_window(utc("2024-03-10T06:30:00Z")), utc("2024-03-10T07:00:00Z"));
+
+        // 01:30 EDT and 01:30 EST both fall in the window ending at 02:00 EST
+        assert_eq!(hourly.end_of_window(utc("2024-11-03T05:30:00Z")), utc("2024-11-03T07:00:00Z"));
+        assert_eq!(hourly.end_of_window(utc("2024-11-03T06:30:00Z")), utc("2024-11-03T07:00:00Z"));
+    }
+
+    #[test]
+    fn a_skipped_midnight_resolves_to_the_first_time_after_the_jump() {
+        // Sao Paulo went from 23:59 straight to 01:00 on 2018-11-04
+        let daily = window(WindowBoundary::Daily, "America/Sao_Paulo");
+        assert_eq!(daily.end_of_window(utc("2018-11-03T15:00:00Z")), utc("2018-11-04T03:00:00Z"));
+    }
+}
@@ -0,0 +1,39 @@
+use axum::{
+    body::HttpBody,
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::state::AppState;
+use app_core::error::ApiError;
+
+/// Replaces responses larger than `server.max_response_bytes` with an error telling the
+/// client to page or stream. Only bodies of known size are checked, such as serialized
+/// JSON; streamed bodies like exports are passed through. Must run inside any middleware
+/// that buffers the body so the oversized one is dropped before it is copied.
+pub async fn response_size_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(limit) = state.config.server.max_response_bytes else {
+        return next.run(request).await;
+    };
+
+    let path = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    let Some(size) = response.body().size_hint().exact() else {
+        return response;
+    };
+    if size <= limit as u64 {
+        return response;
+    }
+
+    warn!(path = %path, size, limit, "Refusing to send oversized response");
+    state.metrics_service.increment_counter("responses_too_large_total", &[]);
+    ApiError::ResponseTooLarge(limit).into_response()
+}
@@ -0,0 +1,34 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use std::time::Instant;
+
+use app_core::server_timing::collect;
+
+use crate::state::AppState;
+
+/// With `server.server_timing` on, report where the request's time went in a
+/// `Server-Timing` header: token validation (`auth`), database work (`db`), writing
+/// JSON bodies (`serialize`) and the total. Time not in a phase was spent in handlers
+/// and middleware.
+pub async fn server_timing_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.config.server.server_timing {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let (mut response, timings) = collect(next.run(request)).await;
+
+    if let Ok(value) = HeaderValue::from_str(&timings.header_value(started.elapsed())) {
+        response.headers_mut().insert("server-timing", value);
+    }
+    response
+}
@@ -0,0 +1,49 @@
+use axum::{
+    extract::{Request, State},
+    http::HeaderName,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::state::AppState;
+use app_core::error::ApiError;
+
+pub static X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Restricts service-only endpoints to callers presenting a configured `X-API-Key`.
+/// With no keys configured every request is rejected.
+pub async fn service_auth_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let presented = request
+        .headers()
+        .get(&X_API_KEY)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| {
+            warn!("Missing service API key");
+            ApiError::Unauthorized("Missing API key".to_string())
+        })?;
+
+    let authorized = state
+        .config
+        .auth
+        .service_api_keys
+        .iter()
+        .any(|key| constant_time_eq(key.as_bytes(), presented.as_bytes()));
+
+    if !authorized {
+        warn!("Invalid service API key presented");
+        return Err(ApiError::Unauthorized("Invalid API key".to_string()));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compare without short-circuiting so key contents can't be probed by timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
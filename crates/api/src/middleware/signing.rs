@@ -0,0 +1,63 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use tracing::error;
+
+use crate::state::AppState;
+use app_core::error::ApiError;
+
+pub static X_BODY_SIGNATURE: HeaderName = HeaderName::from_static("x-body-signature");
+
+/// Signs response bodies on configured routes with HMAC-SHA256 so integrations holding
+/// the shared secret can verify integrity. The header value is `sha256=<hex digest>`
+/// computed over the uncompressed body.
+pub async fn response_signing_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let server = &state.config.server;
+    let Some(secret) = server.response_signing_secret.as_deref() else {
+        return next.run(request).await;
+    };
+
+    let path = request.uri().path();
+    if !server.signed_response_paths.iter().any(|prefix| path.starts_with(prefix.as_str())) {
+        return next.run(request).await;
+    }
+
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+
+    // The whole body has to be buffered to sign it
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to buffer response body for signing: {}", e);
+            return ApiError::Internal(anyhow::anyhow!("Response signing failed")).into_response();
+        }
+    };
+
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(e) => {
+            error!("Invalid response signing key: {}", e);
+            return ApiError::Internal(anyhow::anyhow!("Response signing failed")).into_response();
+        }
+    };
+    mac.update(&bytes);
+    let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+    if let Ok(value) = HeaderValue::from_str(&signature) {
+        parts.headers.insert(X_BODY_SIGNATURE.clone(), value);
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
@@ -0,0 +1,36 @@
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use app_core::config::TimestampFormat;
+use app_core::timestamp::with_format;
+
+use crate::negotiation::media_type_parameter;
+use crate::state::AppState;
+
+/// Write response timestamps in the format named by a `timestamps` parameter in
+/// `Accept`, e.g. `application/json; timestamps=unix_millis`, falling back to
+/// `server.timestamp_format`. Unknown values are ignored.
+pub async fn timestamp_format_middleware(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requested = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|accept| media_type_parameter(accept, "timestamps"))
+        .and_then(|format| match format {
+            "rfc3339" => Some(TimestampFormat::Rfc3339),
+            "unix_millis" => Some(TimestampFormat::UnixMillis),
+            _ => None,
+        });
+
+    let format = requested.unwrap_or(state.config.server.timestamp_format);
+    with_format(format, next.run(request)).await
+}
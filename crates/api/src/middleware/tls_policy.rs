@@ -0,0 +1,129 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderName,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
+
+use app_core::config::TlsPolicyConfig;
+use app_core::error::{ApiError, Result};
+
+use crate::state::AppState;
+
+/// Parsed TLS floor, checked against the version reported by a trusted proxy
+#[derive(Clone)]
+pub struct TlsPolicy {
+    header: HeaderName,
+    min_version: (u8, u8),
+    trusted_proxies: Vec<IpAddr>,
+    enforce: bool,
+}
+
+impl TlsPolicy {
+    pub fn from_config(config: &TlsPolicyConfig) -> Result<Self> {
+        let header = HeaderName::try_from(config.version_header.as_str())
+            .map_err(|_| anyhow::anyhow!("Invalid TLS version header: {}", config.version_header))?;
+        let min_version = parse_tls_version(&config.min_version)
+            .ok_or_else(|| anyhow::anyhow!("Invalid minimum TLS version: {}", config.min_version))?;
+
+        Ok(Self {
+            header,
+            min_version,
+            trusted_proxies: config.trusted_proxies.clone(),
+            enforce: config.enforce,
+        })
+    }
+}
+
+/// Parse `TLSv1.2`, `TLS1.3` or `1.2` into (major, minor)
+fn parse_tls_version(value: &str) -> Option<(u8, u8)> {
+    let value = value.trim();
+    let lower = value.to_ascii_lowercase();
+    let version = lower
+        .strip_prefix("tlsv")
+        .or_else(|| lower.strip_prefix("tls"))
+        .unwrap_or(&lower);
+
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Flag or reject requests whose proxy-reported TLS version is below the configured floor.
+/// The header is only honored from trusted proxies, since any client could otherwise set it.
+pub async fn tls_policy_middleware(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(policy) = state.tls_policy.as_ref() else {
+        return next.run(request).await;
+    };
+
+    if !policy.trusted_proxies.contains(&peer.ip()) {
+        return next.run(request).await;
+    }
+
+    let Some(reported) = request
+        .headers()
+        .get(&policy.header)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return next.run(request).await;
+    };
+
+    // An unparseable version is treated as outdated rather than silently trusted
+    let below_minimum = parse_tls_version(reported).is_none_or(|version| version < policy.min_version);
+    if below_minimum {
+        state.metrics_service.increment_counter("tls_below_minimum_total", &[]);
+        warn!(
+            tls_version = reported,
+            path = %request.uri().path(),
+            enforced = policy.enforce,
+            "Request negotiated TLS below the configured minimum"
+        );
+
+        if policy.enforce {
+            return ApiError::Forbidden(format!(
+                "TLS {}.{} or newer is required",
+                policy.min_version.0, policy.min_version.1
+            ))
+            .into_response();
+        }
+    }
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_parse_with_or_without_a_tls_prefix() {
+        assert_eq!(parse_tls_version("TLSv1.2"), Some((1, 2)));
+        assert_eq!(parse_tls_version("tlsv1.3"), Some((1, 3)));
+        assert_eq!(parse_tls_version("TLS1.0"), Some((1, 0)));
+        assert_eq!(parse_tls_version(" 1.1 "), Some((1, 1)));
+    }
+
+    #[test]
+    fn malformed_versions_are_not_parsed() {
+        for value in ["", "TLS", "TLSv1", "SSLv3", "TLSv1.x", "1.2.1", "TLSv-1.2", "TLS v1.2"] {
+            assert_eq!(parse_tls_version(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn versions_order_by_major_then_minor() {
+        let floor = parse_tls_version("TLSv1.2").unwrap();
+
+        assert!(parse_tls_version("TLSv1.1").unwrap() < floor);
+        assert!(parse_tls_version("TLSv1.3").unwrap() > floor);
+        // Compared numerically, so 1.10 isn't mistaken for 1.1
+        assert!(parse_tls_version("1.10").unwrap() > floor);
+    }
+}
@@ -0,0 +1,150 @@
+use app_core::error::{ApiError, Result, PROBLEM_JSON_MEDIA_TYPE};
+
+use crate::versioning::VENDOR_MEDIA_TYPE;
+
+/// Representations every JSON endpoint can produce, most preferred first
+pub const JSON_MEDIA_TYPES: &[&str] = &["application/json", VENDOR_MEDIA_TYPE, PROBLEM_JSON_MEDIA_TYPE];
+
+/// Representations of `GET /api/v1/enterprise/audit/report`, picked by its `format`
+pub const AUDIT_REPORT_MEDIA_TYPES: &[&str] = &["text/csv", "application/pdf"];
+
+/// Routes serving something other than JSON, by route pattern
+const ROUTE_MEDIA_TYPES: &[(&str, &[&str])] = &[("/api/v1/enterprise/audit/report", AUDIT_REPORT_MEDIA_TYPES)];
+
+/// Representations the route matching `route` can produce; JSON unless it is listed
+/// in `ROUTE_MEDIA_TYPES`
+pub fn route_media_types(route: &str) -> &'static [&'static str] {
+    ROUTE_MEDIA_TYPES
+        .iter()
+        .find(|(pattern, _)| *pattern == route)
+        .map_or(JSON_MEDIA_TYPES, |(_, media_types)| media_types)
+}
+
+/// One entry of an `Accept` header
+struct MediaRange<'a> {
+    kind: &'a str,
+    subtype: &'a str,
+    quality: f32,
+}
+
+impl<'a> MediaRange<'a> {
+    fn parse(range: &'a str) -> Option<Self> {
+        let mut params = range.split(';').map(str::trim);
+        let (kind, subtype) = params.next()?.split_once('/')?;
+        let quality = params
+            .find_map(|param| param.strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+
+        Some(Self { kind, subtype, quality })
+    }
+
+    /// How closely this range names `media_type`: 3 exact, 2 `type/*`, 1 `*/*`
+    fn specificity(&self, media_type: &str) -> Option<u8> {
+        let (kind, subtype) = media_type.split_once('/')?;
+        match (self.kind, self.subtype) {
+            ("*", "*") => Some(1),
+            (k, "*") if k.eq_ignore_ascii_case(kind) => Some(2),
+            (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => Some(3),
+            _ => None,
+        }
+    }
+}
+
+fn media_ranges(accept: &str) -> Vec<MediaRange<'_>> {
+    accept.split(',').filter_map(MediaRange::parse).collect()
+}
+
+/// Quality the client gives `media_type`, taken from the most specific matching range
+fn quality(ranges: &[MediaRange<'_>], media_type: &str) -> f32 {
+    ranges
+        .iter()
+        .filter_map(|range| range.specificity(media_type).map(|specificity| (specificity, range.quality)))
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, quality)| quality)
+}
+
+/// Pick the type from `available` the client prefers, breaking ties in `available` order.
+/// A missing or empty `Accept` takes the first type; when nothing acceptable can be
+/// served the error lists `available` for a 406.
+pub fn negotiate(accept: Option<&str>, available: &[&'static str]) -> Result<&'static str> {
+    let Some(accept) = accept.map(str::trim).filter(|accept| !accept.is_empty()) else {
+        return available
+            .first()
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("No media types to negotiate").into());
+    };
+
+    let ranges = media_ranges(accept);
+    let mut best: Option<(&'static str, f32)> = None;
+    for &media_type in available {
+        let quality = quality(&ranges, media_type);
+        if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best = Some((media_type, quality));
+        }
+    }
+
+    best.map(|(media_type, _)| media_type)
+        .ok_or_else(|| ApiError::NotAcceptable(available.to_vec()))
+}
+
+/// Whether `accept` names `media_type` itself, rather than only through a wildcard
+pub fn explicitly_accepts(accept: &str, media_type: &str) -> bool {
+    media_ranges(accept)
+        .iter()
+        .any(|range| range.quality > 0.0 && range.specificity(media_type) == Some(3))
+}
+
+/// Value of the parameter `name` on the first range in `accept` that has it, e.g.
+/// `unix_millis` for `timestamps` in `application/json; timestamps=unix_millis`
+pub fn media_type_parameter<'a>(accept: &'a str, name: &str) -> Option<&'a str> {
+    accept
+        .split(',')
+        .flat_map(|range| range.split(';').skip(1))
+        .find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim().trim_matches('"'))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV_OR_PDF: &[&str] = AUDIT_REPORT_MEDIA_TYPES;
+
+    #[test]
+    fn a_missing_or_empty_accept_takes_the_first_type() {
+        assert_eq!(negotiate(None, CSV_OR_PDF).unwrap(), "text/csv");
+        assert_eq!(negotiate(Some("  "), CSV_OR_PDF).unwrap(), "text/csv");
+    }
+
+    #[test]
+    fn the_highest_quality_wins_and_ties_go_to_the_first_available() {
+        assert_eq!(negotiate(Some("text/csv;q=0.5, application/pdf"), CSV_OR_PDF).unwrap(), "application/pdf");
+        assert_eq!(negotiate(Some("application/pdf;q=0.2, text/csv;q=0.9"), CSV_OR_PDF).unwrap(), "text/csv");
+        assert_eq!(negotiate(Some("application/pdf, text/csv"), CSV_OR_PDF).unwrap(), "text/csv");
+        assert_eq!(negotiate(Some("*/*"), CSV_OR_PDF).unwrap(), "text/csv");
+    }
+
+    #[test]
+    fn the_most_specific_range_sets_a_types_quality() {
+        // text/csv is refused outright even though */* would allow it
+        assert_eq!(negotiate(Some("text/csv;q=0, */*;q=0.1"), CSV_OR_PDF).unwrap(), "application/pdf");
+        assert_eq!(negotiate(Some("text/*;q=0.3, */*;q=0.5"), CSV_OR_PDF).unwrap(), "application/pdf");
+        assert_eq!(negotiate(Some("TEXT/CSV"), CSV_OR_PDF).unwrap(), "text/csv");
+    }
+
+    #[test]
+    fn ranges_with_unreadable_quality_are_ignored() {
+        assert_eq!(negotiate(Some("text/csv;q=high, application/pdf;q=0.1"), CSV_OR_PDF).unwrap(), "application/pdf");
+        assert!(negotiate(Some("text/csv;q=high"), CSV_OR_PDF).is_err());
+    }
+
+    #[test]
+    fn nothing_acceptable_lists_what_is_available() {
+        let Err(ApiError::NotAcceptable(available)) = negotiate(Some("image/png, text/csv;q=0"), CSV_OR_PDF) else {
+            panic!("expected 406");
+        };
+        assert_eq!(available, CSV_OR_PDF);
+    }
+}
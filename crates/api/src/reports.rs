@@ -0,0 +1,381 @@
+use axum::body::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use serde::Serialize;
+use std::sync::Arc;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use uuid::Uuid;
+
+use app_core::enterprise::{AuditLog, AuditReportFormat, AuditReportQuery, AuditReportSummary};
+use app_core::error::Result;
+use monitoring::AuditService;
+
+/// Entries fetched per query while streaming a report
+const REPORT_PAGE_SIZE: i64 = 1000;
+
+/// Lines per page of a PDF report
+const PDF_LINES_PER_PAGE: usize = 50;
+
+const AUDIT_CSV_COLUMNS: &[&str] = &[
+    "id", "created_at", "user_id", "action", "category", "severity",
+    "resource_type", "resource_id", "ip_address", "user_agent", "details",
+];
+
+/// Download filename and content type of the report `query` describes
+pub fn audit_report_file(query: &AuditReportQuery) -> (String, &'static str) {
+    let date = |time: OffsetDateTime| time.date().to_string().replace('-', "");
+    let (extension, content_type) = match query.format {
+        AuditReportFormat::Csv => ("csv", "text/csv; charset=utf-8"),
+        AuditReportFormat::Pdf => ("pdf", "application/pdf"),
+    };
+    (
+        format!("audit-report-{}-{}.{}", date(query.from), date(query.to), extension),
+        content_type,
+    )
+}
+
+/// CSV of every entry matching `query`, oldest first. Entries are fetched a page at a
+/// time as the client reads, so memory stays flat however large the range.
+pub fn audit_csv_stream(
+    audit: Arc<dyn AuditService>,
+    query: AuditReportQuery,
+) -> impl Stream<Item = Result<Bytes>> + Send {
+    let header = stream::once(async { Ok(Bytes::from(csv_row(AUDIT_CSV_COLUMNS.iter().copied()))) });
+
+    // `None` once the last page has been sent
+    let start: Option<Option<(OffsetDateTime, Uuid)>> = Some(None);
+    let rows = stream::try_unfold(start, move |cursor| {
+        let audit = audit.clone();
+        let query = query.clone();
+        async move {
+            let Some(after) = cursor else {
+                return Ok(None);
+            };
+
+            let page = audit.get_audit_report_page(&query, after, REPORT_PAGE_SIZE).await?;
+            let next = match page.last() {
+                Some(last) if page.len() as i64 == REPORT_PAGE_SIZE => Some(Some((last.created_at, last.id))),
+                _ => None,
+            };
+
+            let chunk: String = page.iter().map(audit_csv_row).collect();
+            Ok(Some((Bytes::from(chunk), next)))
+        }
+    });
+
+    header.chain(rows)
+}
+
+fn audit_csv_row(entry: &AuditLog) -> String {
+    let optional = |id: Option<Uuid>| id.map(|id| id.to_string()).unwrap_or_default();
+
+    csv_row([
+        entry.id.to_string(),
+        entry.created_at.format(&Rfc3339).unwrap_or_default(),
+        optional(entry.user_id),
+        entry.action.clone(),
+        label(&entry.category),
+        label(&entry.severity),
+        entry.resource_type.clone(),
+        optional(entry.resource_id),
+        entry.ip_address.ip().to_string(),
+        entry.user_agent.clone().unwrap_or_default(),
+        entry.details.to_string(),
+    ].iter().map(String::as_str))
+}
+
+/// Serialized name of a unit enum variant, e.g. `data_change`
+pub fn label(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+/// One RFC 4180 record. Cells that a spreadsheet would evaluate as a formula are
+/// prefixed with `'` so an exported report can't run attacker-supplied formulas.
+fn csv_row<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut row = fields
+        .into_iter()
+        .map(|field| {
+            let field = if field.starts_with(['=', '+', '-', '@']) {
+                format!("'{}", field)
+            } else {
+                field.to_string()
+            };
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// Single-font PDF summarising an audit report: the selection, then entry counts
+pub fn audit_summary_pdf(query: &AuditReportQuery, summary: &AuditReportSummary) -> Vec<u8> {
+    let format_time = |time: OffsetDateTime| time.format(&Rfc3339).unwrap_or_default();
+    let mut lines = vec![
+        "Audit report".to_string(),
+        String::new(),
+        format!("From: {}", format_time(query.from)),
+        format!("To: {}", format_time(query.to)),
+    ];
+    if let Some(user_id) = query.user_id {
+        lines.push(format!("User: {}", user_id));
+    }
+    if let Some(resource_type) = &query.resource_type {
+        lines.push(format!("Resource type: {}", resource_type));
+    }
+    if let Some(category) = &query.category {
+        lines.push(format!("Category: {}", label(category)));
+    }
+    lines.push(format!("Generated: {}", format_time(OffsetDateTime::now_utc())));
+    lines.push(String::new());
+    lines.push(format!("Total entries: {}", summary.total));
+
+    let mut section = |title: &str, counts: Vec<(String, i64)>| {
+        lines.push(String::new());
+        lines.push(title.to_string());
+        if counts.is_empty() {
+            lines.push("  (none)".to_string());
+        }
+        lines.extend(counts.into_iter().map(|(name, count)| format!("  {:<40} {:>10}", name, count)));
+    };
+    section(
+        "By category",
+        summary.by_category.iter().map(|(c, n)| (label(c), *n)).collect(),
+    );
+    section(
+        "By severity",
+        summary.by_severity.iter().map(|(s, n)| (label(s), *n)).collect(),
+    );
+    section("Most frequent actions", summary.top_actions.clone());
+
+    text_pdf(&lines)
+}
+
+/// Minimal PDF 1.4 with `lines` set in Courier, paginated
+fn text_pdf(lines: &[String]) -> Vec<u8> {
+    let pages: Vec<&[String]> = lines.chunks(PDF_LINES_PER_PAGE).collect();
+
+    // Objects 1-3 are the catalog, page tree and font; each page then takes two
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len()).map(|i| format!("{} 0 R", 4 + 2 * i)).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = String::from("BT /F1 10 Tf 14 TL 50 800 Td\n");
+        for line in page.iter() {
+            content.push_str(&format!("({}) Tj T*\n", pdf_escape(line)));
+        }
+        content.push_str("ET");
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            5 + 2 * i
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+
+    let xref = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    pdf.into_bytes()
+}
+
+/// Escape a line for a PDF string literal; the standard fonts only cover ASCII here
+fn pdf_escape(line: &str) -> String {
+    line.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_core::enterprise::{AuditCategory, AuditFilter, AuditSeverity};
+    use axum::async_trait;
+    use futures_util::TryStreamExt;
+    use std::sync::Mutex;
+
+    #[test]
+    fn csv_rows_quote_only_fields_that_need_it() {
+        assert_eq!(csv_row(["plain", "with space"]), "plain,with space\r\n");
+        assert_eq!(csv_row(["a,b", "say \"hi\"", "two\nlines"]), "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\r\n");
+        assert_eq!(csv_row([""]), "\r\n");
+    }
+
+    #[test]
+    fn csv_rows_neutralise_formulas() {
+        assert_eq!(csv_row(["=SUM(A1:A2)", "+1", "-1", "@cmd", "1-2"]), "'=SUM(A1:A2),'+1,'-1,'@cmd,1-2\r\n");
+        // Prefixed before quoting, so the quote stays inside the cell
+        assert_eq!(csv_row(["=HYPERLINK(\"x\",\"y\")"]), "\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\"\r\n");
+    }
+
+    #[test]
+    fn text_pdfs_paginate_and_index_every_object() {
+        let lines: Vec<String> = (0..PDF_LINES_PER_PAGE + 1).map(|n| format!("line {} (of many) \\ é", n)).collect();
+        let pdf = String::from_utf8(text_pdf(&lines)).unwrap();
+
+        assert!(pdf.starts_with("%PDF-1.4\n") && pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("(line 0 \\(of many\\) \\\\ ?) Tj"));
+
+        // Each xref entry points at the start of its object
+        let xref = pdf.rfind("\nxref\n").unwrap() + 1;
+        let offsets: Vec<usize> = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 3 + 2 * 2);
+        for (i, offset) in offsets.into_iter().enumerate() {
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+        let startxref: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        assert_eq!(startxref, xref);
+    }
+
+    /// Serves `entries` a page at a time, recording the cursor of each request
+    struct PagedAudit {
+        entries: Vec<AuditLog>,
+        cursors: Mutex<Vec<Option<(OffsetDateTime, Uuid)>>>,
+    }
+
+    #[async_trait]
+    impl AuditService for PagedAudit {
+        async fn log_action(
+            &self,
+            _: Option<Uuid>,
+            _: &str,
+            _: AuditCategory,
+            _: AuditSeverity,
+            _: &str,
+            _: Option<Uuid>,
+            _: &str,
+            _: Option<&str>,
+            _: serde_json::Value,
+        ) -> Result<()> {
+            unimplemented!()
+        }
+
+        async fn get_user_audit_trail(&self, _: Uuid, _: &AuditFilter, _: i64) -> Result<Vec<AuditLog>> {
+            unimplemented!()
+        }
+
+        async fn get_resource_audit_trail(&self, _: &str, _: Uuid, _: &AuditFilter, _: i64) -> Result<Vec<AuditLog>> {
+            unimplemented!()
+        }
+
+        async fn get_audit_events(&self, _: &AuditFilter, _: i64) -> Result<Vec<AuditLog>> {
+            unimplemented!()
+        }
+
+        async fn get_audit_report_page(
+            &self,
+            _: &AuditReportQuery,
+            after: Option<(OffsetDateTime, Uuid)>,
+            limit: i64,
+        ) -> Result<Vec<AuditLog>> {
+            self.cursors.lock().unwrap().push(after);
+            Ok(self
+                .entries
+                .iter()
+                .filter(|entry| after.is_none_or(|after| (entry.created_at, entry.id) > after))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn get_audit_report_summary(&self, _: &AuditReportQuery) -> Result<AuditReportSummary> {
+            unimplemented!()
+        }
+    }
+
+    fn entries(count: usize) -> Vec<AuditLog> {
+        let start = OffsetDateTime::now_utc();
+        (0..count)
+            .map(|n| {
+                serde_json::from_value(serde_json::json!({
+                    "id": Uuid::new_v4(),
+                    "user_id": null,
+                    "action": format!("action_{}", n),
+                    "category": "security",
+                    "severity": "info",
+                    "resource_type": "user",
+                    "resource_id": null,
+                    "ip_address": "127.0.0.1",
+                    "user_agent": null,
+                    "details": {},
+                    "created_at": (start + time::Duration::seconds(n as i64)).format(&Rfc3339).unwrap(),
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    async fn report(count: usize) -> (Vec<String>, usize) {
+        let now = OffsetDateTime::now_utc();
+        let query = AuditReportQuery {
+            from: now - time::Duration::days(1),
+            to: now + time::Duration::days(1),
+            format: AuditReportFormat::Csv,
+            user_id: None,
+            resource_type: None,
+            category: None,
+        };
+        let audit = Arc::new(PagedAudit { entries: entries(count), cursors: Mutex::new(Vec::new()) });
+
+        let chunks: Vec<Bytes> = audit_csv_stream(audit.clone(), query).try_collect().await.unwrap();
+        let csv = String::from_utf8(chunks.concat()).unwrap();
+        let pages = audit.cursors.lock().unwrap().len();
+        (csv.split_terminator("\r\n").map(str::to_string).collect(), pages)
+    }
+
+    #[tokio::test]
+    async fn csv_reports_page_through_every_entry_in_order() {
+        let (rows, pages) = report(2 * REPORT_PAGE_SIZE as usize + 1).await;
+
+        assert_eq!(rows[0], AUDIT_CSV_COLUMNS.join(","));
+        assert_eq!(rows.len(), 1 + 2 * REPORT_PAGE_SIZE as usize + 1);
+        assert_eq!(pages, 3);
+        assert!(rows[1].contains(",action_0,"));
+        assert!(rows.last().unwrap().contains(&format!(",action_{},", 2 * REPORT_PAGE_SIZE)));
+    }
+
+    #[tokio::test]
+    async fn a_full_last_page_is_followed_by_one_empty_fetch() {
+        let (rows, pages) = report(REPORT_PAGE_SIZE as usize).await;
+        assert_eq!((rows.len(), pages), (1 + REPORT_PAGE_SIZE as usize, 2));
+
+        let (rows, pages) = report(0).await;
+        assert_eq!((rows.len(), pages), (1, 1));
+    }
+}
@@ -0,0 +1,44 @@
+use rand::{rngs::OsRng, Rng};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+use app_core::config::RequestIdFormat;
+
+const CROCKFORD_BASE32: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Generate a fresh request/correlation id in the configured format
+pub fn generate(format: RequestIdFormat) -> String {
+    match format {
+        RequestIdFormat::UuidV4 => Uuid::new_v4().to_string(),
+        RequestIdFormat::Ulid => ulid(),
+        RequestIdFormat::Base62 => base62(OsRng.gen::<u128>()),
+    }
+}
+
+/// 26-char ULID: 48-bit millisecond timestamp then 80 random bits, so ids sort by time
+fn ulid() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let random = OsRng.gen::<u128>() & ((1u128 << 80) - 1);
+    let mut value = ((millis & ((1u128 << 48) - 1)) << 80) | random;
+
+    let mut encoded = [0u8; 26];
+    for slot in encoded.iter_mut().rev() {
+        *slot = CROCKFORD_BASE32[(value & 0x1f) as usize];
+        value >>= 5;
+    }
+    String::from_utf8_lossy(&encoded).into_owned()
+}
+
+/// Fixed-width (22 char) base62 encoding of 128 random bits
+fn base62(mut value: u128) -> String {
+    let mut encoded = [b'0'; 22];
+    for slot in encoded.iter_mut().rev() {
+        *slot = BASE62[(value % 62) as usize];
+        value /= 62;
+    }
+    String::from_utf8_lossy(&encoded).into_owned()
+}
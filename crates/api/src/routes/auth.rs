@@ -10,5 +10,7 @@ pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/login", post(auth::login))
         .route("/logout", post(auth::logout))
-        .route("/refresh", post(auth::refresh_token))
+        .route("/password", post(auth::change_password))
+        .route("/mfa/enroll", post(auth::enroll_mfa))
+        .route("/mfa/enroll/confirm", post(auth::confirm_mfa_enrollment))
 }
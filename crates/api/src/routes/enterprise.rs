@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
 use std::sync::Arc;
@@ -10,11 +10,36 @@ pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         // Admin-only audit trail endpoints
         .route("/audit/users/:user_id", get(enterprise::get_user_audit_trail))
+        .route("/audit/security", get(enterprise::get_security_events))
+        .route("/audit/report", get(enterprise::get_audit_report))
+        .route("/audit/backfill", post(enterprise::backfill_audit_logs))
+
+        // Cached aggregate stats (admin only)
+        .route("/stats", get(enterprise::get_stats))
+
+        // JWT signing key rotation (admin only)
+        .route("/auth/keys", get(enterprise::list_signing_keys))
+        .route("/auth/keys/promote", post(enterprise::promote_signing_key))
 
         // Feature flag management (admin only)
-        .route("/feature-flags", get(enterprise::list_feature_flags))
+        .route("/feature-flags", get(enterprise::list_feature_flags).post(enterprise::create_feature_flag))
+        .route("/feature-flags/dependencies", get(enterprise::get_feature_flag_dependencies))
+        .route("/feature-flags/evaluate", post(enterprise::evaluate_feature_flags))
+        .route(
+            "/feature-flags/:flag_name",
+            put(enterprise::update_feature_flag).delete(enterprise::delete_feature_flag),
+        )
         .route("/feature-flags/:flag_name/toggle", post(enterprise::toggle_feature_flag))
         .route("/feature-flags/:flag_name/check", get(enterprise::check_feature_flag))
+        .route("/feature-flags/:flag_name/simulate", post(enterprise::simulate_feature_flag_rollout))
+        .route("/feature-flags/evaluations/users/:user_id", get(enterprise::get_flag_evaluation_history))
+
+        // Downstream dependency health (admin only)
+        .route("/dependencies", get(enterprise::get_dependency_health))
+        .route("/circuit-breakers", get(enterprise::list_circuit_breakers))
+
+        // Requests currently being handled (admin only)
+        .route("/in-flight", get(enterprise::get_in_flight_requests))
 
         // Circuit breaker demonstration
         .route("/circuit-breaker/demo", get(enterprise::circuit_breaker_demo))
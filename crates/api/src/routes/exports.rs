@@ -0,0 +1,13 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::{handlers::exports, state::AppState};
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(exports::create_export))
+        .route("/:id", get(exports::get_export))
+}
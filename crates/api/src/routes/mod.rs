@@ -2,4 +2,4 @@ pub mod users;
 pub mod auth;
 pub mod products;
 pub mod enterprise;
-
+pub mod exports;
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post, put, delete},
+    routing::{get, post},
     Router,
 };
 use std::sync::Arc;
@@ -9,5 +9,9 @@ use crate::{handlers::products, state::AppState};
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(products::list_products).post(products::create_product))
-        .route("/:id", get(products::get_product).put(products::update_product).delete(products::delete_product))
+        .route("/bulk-price", post(products::bulk_update_prices))
+        .route("/bulk-delete", post(products::bulk_delete_products))
+        .route("/import", post(products::import_products))
+        .route("/:id/restore", post(products::restore_product))
+        .route("/:id", get(products::get_product).put(products::update_product).patch(products::update_product).delete(products::delete_product))
 }
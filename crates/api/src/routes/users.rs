@@ -9,6 +9,10 @@ use crate::{handlers::users, state::AppState};
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/", get(users::list_users).post(users::create_user))
+        .route("/bulk", post(users::bulk_create_users))
         .route("/:id", get(users::get_user).put(users::update_user).delete(users::delete_user))
+        .route("/:id/restore", post(users::restore_user))
+        .route("/:id/email", post(users::request_email_change))
+        .route("/:id/impersonate", post(users::impersonate_user))
         .route("/:id/profile", get(users::get_user_profile).put(users::update_user_profile))
 }
@@ -1,20 +1,62 @@
-use auth::AuthService;
+use auth::{AuthService, PermissionResolver};
 use app_core::config::Config;
-use database::DatabasePool;
+use database::{DatabasePool, ProductStore};
 use monitoring::{MetricsService, DatabaseAuditService, AuditService};
-use monitoring::feature_flags::{FeatureFlagService, InMemoryFeatureFlagService};
-use monitoring::CircuitBreaker;
-use app_core::enterprise::CircuitBreakerConfig;
+use monitoring::feature_flags::{CachedFeatureFlagService, FeatureFlagService, InMemoryFeatureFlagService};
+use monitoring::flag_evaluations::FlagEvaluationLog;
+use monitoring::{CircuitBreakerRegistry, DependencyHealthTracker, NotificationService, Scheduler};
+use app_core::enterprise::{AggregateStats, CircuitBreakerConfig};
 use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::client_ip::TrustedProxies;
+use crate::exports::ExportQueue;
+use crate::middleware::concurrency::UserConcurrencyLimiter;
+use crate::middleware::enterprise::CorrelationTracker;
+use crate::middleware::in_flight::InFlightRequests;
+use crate::middleware::rate_limit::{
+    ClientRateLimiter, EmailSendLimiter, RequestCostBudget, ResourceRateLimiter, UserRateLimiter,
+};
+use crate::middleware::tls_policy::TlsPolicy;
 
 /// Shared application state containing all services and dependencies
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: DatabasePool,
+    pub products: ProductStore,
     pub auth_service: AuthService,
+    pub permissions: Arc<PermissionResolver>,
     pub metrics_service: MetricsService,
     pub audit_service: Arc<dyn AuditService>,
+    /// Store behind `audit_service`, kept concrete for bulk loads
+    pub audit_store: Arc<DatabaseAuditService>,
     pub feature_flags: Arc<dyn FeatureFlagService>,
-    pub circuit_breaker: Arc<CircuitBreaker>,
+    /// Same service as `feature_flags`, kept concrete for the background refresh job
+    pub flag_cache: Arc<CachedFeatureFlagService>,
+    /// Recorded flag evaluations; only written to while `monitoring.flag_evaluation_log` is on
+    pub flag_evaluations: Arc<FlagEvaluationLog>,
+    /// One breaker per downstream service, keyed by the name its health is tracked under
+    pub circuit_breakers: Arc<CircuitBreakerRegistry>,
+    pub scheduler: Arc<Scheduler>,
+    pub notifications: NotificationService,
+    pub dependency_health: Arc<DependencyHealthTracker>,
+    pub user_concurrency: UserConcurrencyLimiter,
+    pub enhanced_profile_limiter: UserRateLimiter,
+    /// Per-user budget charged by imports, exports, reports and bulk writes
+    pub request_costs: RequestCostBudget,
+    /// Quotas on password reset and verification emails
+    pub email_send_limiter: EmailSendLimiter,
+    /// Global per-client quota; `None` when `server.rate_limit` is unset
+    pub rate_limiter: Option<ClientRateLimiter>,
+    /// Per-client quotas from `server.resource_rate_limits`
+    pub resource_rate_limiter: ResourceRateLimiter,
+    pub correlation_tracker: CorrelationTracker,
+    pub in_flight: InFlightRequests,
+    pub tls_policy: Option<TlsPolicy>,
+    /// Proxies whose `X-Forwarded-For` identifies the client to the rate limiters and `ClientIp`
+    pub trusted_proxies: TrustedProxies,
+    pub stats_cache: Arc<RwLock<Option<AggregateStats>>>,
+    /// Exports generated in the background and served through signed links
+    pub exports: ExportQueue,
     pub config: Config,
 }
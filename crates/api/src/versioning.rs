@@ -0,0 +1,74 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use app_core::error::ApiError;
+use app_core::timestamp::Json;
+
+/// Vendor media type used for per-endpoint version negotiation
+pub const VENDOR_MEDIA_TYPE: &str = "application/vnd.api+json";
+
+/// Response schema version requested via `Accept: application/vnd.api+json;version=N`.
+/// Defaults to the latest version when the client doesn't ask for one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApiVersion(pub u8);
+
+impl ApiVersion {
+    pub const V1: ApiVersion = ApiVersion(1);
+    pub const V2: ApiVersion = ApiVersion(2);
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    fn from_accept(accept: &str) -> Result<Self, ApiError> {
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';').map(str::trim);
+            if params.next() != Some(VENDOR_MEDIA_TYPE) {
+                continue;
+            }
+
+            let version = params.find_map(|param| param.strip_prefix("version="));
+            return match version {
+                None => Ok(Self::LATEST),
+                Some(v) => match v.trim_matches('"').parse::<u8>() {
+                    Ok(v) if (Self::V1.0..=Self::LATEST.0).contains(&v) => Ok(ApiVersion(v)),
+                    _ => Err(ApiError::BadRequest(format!("Unsupported API version: {}", v))),
+                },
+            };
+        }
+
+        Ok(Self::LATEST)
+    }
+
+    /// Serialize `body` as JSON tagged with the negotiated vendor media type
+    pub fn json<T: Serialize>(self, body: T) -> Response {
+        let mut response = Json(body).into_response();
+        let headers = response.headers_mut();
+
+        if let Ok(content_type) =
+            HeaderValue::from_str(&format!("{};version={}", VENDOR_MEDIA_TYPE, self.0))
+        {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        headers.insert(header::VARY, HeaderValue::from_static("accept"));
+
+        response
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ApiVersion
+where
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) {
+            Some(accept) => Self::from_accept(accept),
+            None => Ok(Self::LATEST),
+        }
+    }
+}
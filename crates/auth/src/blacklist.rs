@@ -0,0 +1,64 @@
+use redis::AsyncCommands;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use app_core::{
+    config::RedisConfig,
+    error::{ApiError, Result},
+};
+use monitoring::{DependencyHealthTracker, GuardedRedis};
+
+/// Name Redis is reported under in dependency health
+pub const REDIS_DEPENDENCY: &str = "redis";
+
+/// Prefix of the Redis keys holding revoked token ids
+const KEY_PREFIX: &str = "auth:revoked:";
+
+/// Revoked token ids, kept in Redis until the token would have expired anyway.
+/// Calls fail with 503 while Redis is unreachable.
+#[derive(Clone)]
+pub struct TokenBlacklist {
+    redis: GuardedRedis,
+}
+
+impl TokenBlacklist {
+    /// `timeout` is the longest a blacklist call may hold up a request
+    pub fn new(
+        config: &RedisConfig,
+        timeout: Duration,
+        dependency_health: Arc<DependencyHealthTracker>,
+    ) -> Result<Self> {
+        Ok(Self {
+            redis: GuardedRedis::new(&config.url, REDIS_DEPENDENCY, timeout, dependency_health)?,
+        })
+    }
+
+    /// Revoke `jti` until `exp` (Unix seconds); already expired tokens need no entry
+    pub async fn revoke(&self, jti: &str, exp: i64) -> Result<()> {
+        let ttl = exp - OffsetDateTime::now_utc().unix_timestamp();
+        if ttl <= 0 {
+            return Ok(());
+        }
+
+        self.redis
+            .call(|mut connection| async move { connection.set_ex::<_, _, ()>(key(jti), 1, ttl as u64).await })
+            .await
+            .ok_or_else(unavailable)
+    }
+
+    pub async fn is_revoked(&self, jti: &str) -> Result<bool> {
+        self.redis
+            .call(|mut connection| async move { connection.exists(key(jti)).await })
+            .await
+            .ok_or_else(unavailable)
+    }
+}
+
+fn key(jti: &str) -> String {
+    format!("{}{}", KEY_PREFIX, jti)
+}
+
+fn unavailable() -> ApiError {
+    ApiError::ServiceUnavailable("Token revocation store is unavailable".to_string())
+}
@@ -0,0 +1,273 @@
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use app_core::config::{AuthConfig, JwtAlgorithm, JwtKeyConfig};
+use app_core::error::{ApiError, Result};
+
+/// Role of a key in the signing key ring
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    /// Signs new tokens
+    Primary,
+    /// Verifies only, ready to be promoted
+    Standby,
+    /// Verifies only until its grace period ends
+    Retiring,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SigningKeyInfo {
+    pub id: String,
+    pub status: KeyStatus,
+    #[serde(skip_serializing_if = "Option::is_none", with = "app_core::timestamp::option")]
+    pub retires_at: Option<OffsetDateTime>,
+}
+
+/// Outcome of promoting a new primary key
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyRotation {
+    pub primary_key_id: String,
+    pub retiring_key_id: String,
+    #[serde(with = "app_core::timestamp")]
+    pub retires_at: OffsetDateTime,
+}
+
+pub(crate) struct SigningKey {
+    id: String,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl SigningKey {
+    pub(crate) fn from_secret(id: &str, secret: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            algorithm: Algorithm::HS256,
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    /// The configured primary key: `jwt_secret` for HS256, or the PEM key pair for RS256.
+    /// An RSA pair is checked by signing and verifying a probe token, so unreadable,
+    /// malformed or mismatched key files are reported here rather than on first use.
+    pub(crate) fn primary(config: &AuthConfig) -> Result<Self> {
+        match config.algorithm {
+            JwtAlgorithm::Hs256 => Ok(Self::from_secret(&config.jwt_key_id, &config.jwt_secret)),
+            JwtAlgorithm::Rs256 => Self::from_rsa_pem_files(
+                &config.jwt_key_id,
+                "auth",
+                config.private_key_path.as_deref(),
+                config.public_key_path.as_deref(),
+            ),
+        }
+    }
+
+    /// The configured standby key, using the primary's algorithm so promoting it never
+    /// changes how tokens are signed: its `secret` for HS256, its PEM key pair for RS256
+    pub(crate) fn standby(config: &AuthConfig, key: &JwtKeyConfig) -> Result<Self> {
+        match config.algorithm {
+            JwtAlgorithm::Hs256 => {
+                if key.secret.is_empty() {
+                    return Err(anyhow::anyhow!("auth.jwt_standby_key.secret is required for HS256").into());
+                }
+                Ok(Self::from_secret(&key.id, &key.secret))
+            }
+            JwtAlgorithm::Rs256 => Self::from_rsa_pem_files(
+                &key.id,
+                "auth.jwt_standby_key",
+                key.private_key_path.as_deref(),
+                key.public_key_path.as_deref(),
+            ),
+        }
+    }
+
+    /// RS256 key from the PEM files named by `<section>.private_key_path` and
+    /// `<section>.public_key_path`, checked to form a pair
+    fn from_rsa_pem_files(
+        id: &str,
+        section: &str,
+        private_path: Option<&str>,
+        public_path: Option<&str>,
+    ) -> Result<Self> {
+        let private_pem = read_key_file(section, "private_key_path", private_path)?;
+        let public_pem = read_key_file(section, "public_key_path", public_path)?;
+
+        let key = Self {
+            id: id.to_string(),
+            algorithm: Algorithm::RS256,
+            encoding: EncodingKey::from_rsa_pem(&private_pem).map_err(|e| {
+                anyhow::anyhow!("{}.private_key_path is not a PEM RSA private key: {}", section, e)
+            })?,
+            decoding: DecodingKey::from_rsa_pem(&public_pem).map_err(|e| {
+                anyhow::anyhow!("{}.public_key_path is not a PEM RSA public key: {}", section, e)
+            })?,
+        };
+        key.check_pair(section)?;
+        Ok(key)
+    }
+
+    fn check_pair(&self, section: &str) -> Result<()> {
+        #[derive(Serialize, serde::Deserialize)]
+        struct Probe {
+            exp: i64,
+        }
+
+        let probe = Probe { exp: OffsetDateTime::now_utc().unix_timestamp() + 60 };
+        let token = encode(&Header::new(self.algorithm), &probe, &self.encoding)
+            .map_err(|e| anyhow::anyhow!("Failed to sign with {}.private_key_path: {}", section, e))?;
+        decode::<Probe>(&token, &self.decoding, &Validation::new(self.algorithm)).map_err(|_| {
+            anyhow::anyhow!("{0}.public_key_path does not match {0}.private_key_path", section)
+        })?;
+        Ok(())
+    }
+}
+
+fn read_key_file(section: &str, setting: &str, path: Option<&str>) -> Result<Vec<u8>> {
+    let path = path.ok_or_else(|| anyhow::anyhow!("{}.{} is required for RS256", section, setting))?;
+    std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}.{} ({}): {}", section, setting, path, e).into())
+}
+
+/// Keys used for JWTs, addressed by the token's `kid` header.
+/// Only the primary signs; standby and retiring keys are accepted for verification.
+pub(crate) struct KeyRing {
+    primary: SigningKey,
+    standby: Option<SigningKey>,
+    retiring: Vec<(SigningKey, OffsetDateTime)>,
+    /// `jwt_key_id` of the config last applied, which may no longer be the primary once
+    /// the standby has been promoted
+    configured_id: String,
+}
+
+impl KeyRing {
+    pub(crate) fn new(config: &AuthConfig) -> Result<Self> {
+        let mut ring = Self {
+            primary: SigningKey::primary(config)?,
+            standby: None,
+            retiring: Vec::new(),
+            configured_id: config.jwt_key_id.clone(),
+        };
+        ring.set_standby(config)?;
+        Ok(ring)
+    }
+
+    pub(crate) fn primary_id(&self) -> &str {
+        &self.primary.id
+    }
+
+    pub(crate) fn standby_id(&self) -> Option<&str> {
+        self.standby.as_ref().map(|key| key.id.as_str())
+    }
+
+    pub(crate) fn configured_id(&self) -> &str {
+        &self.configured_id
+    }
+
+    pub(crate) fn primary_encoding_key(&self) -> (&EncodingKey, Algorithm) {
+        (&self.primary.encoding, self.primary.algorithm)
+    }
+
+    /// Key that verifies tokens carrying `kid`, with the algorithm it must have been used
+    /// with; tokens without a `kid` predate key ids and can only have been signed by the primary
+    pub(crate) fn decoding_key(&self, kid: Option<&str>) -> Option<(&DecodingKey, Algorithm)> {
+        let Some(kid) = kid else {
+            return Some((&self.primary.decoding, self.primary.algorithm));
+        };
+
+        std::iter::once(&self.primary)
+            .chain(self.standby.as_ref())
+            .chain(self.retiring.iter().map(|(key, _)| key))
+            .find(|key| key.id == kid)
+            .map(|key| (&key.decoding, key.algorithm))
+    }
+
+    /// Replace the standby key with the configured one; ignored if it names the current
+    /// primary. The current standby is kept when the configured one can't be loaded.
+    pub(crate) fn set_standby(&mut self, config: &AuthConfig) -> Result<()> {
+        self.standby = match config.jwt_standby_key.as_ref().filter(|key| key.id != self.primary.id) {
+            Some(key) => Some(SigningKey::standby(config, key)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Make `key` the primary, demoting the current primary to retiring until `retires_at`
+    pub(crate) fn promote(&mut self, key: SigningKey, retires_at: OffsetDateTime) -> KeyRotation {
+        self.retiring.retain(|(retiring, _)| retiring.id != key.id);
+        if self.standby.as_ref().is_some_and(|standby| standby.id == key.id) {
+            self.standby = None;
+        }
+
+        let previous = std::mem::replace(&mut self.primary, key);
+        let rotation = KeyRotation {
+            primary_key_id: self.primary.id.clone(),
+            retiring_key_id: previous.id.clone(),
+            retires_at,
+        };
+        self.retiring.push((previous, retires_at));
+        rotation
+    }
+
+    /// Promote the standby key. Refused when it signs with a different algorithm than the
+    /// primary, since verifiers expecting the current algorithm would reject its tokens.
+    pub(crate) fn promote_standby(&mut self, retires_at: OffsetDateTime) -> Result<KeyRotation> {
+        self.check_standby_promotion()?;
+        let standby = self.standby.take().expect("checked to exist");
+        Ok(self.promote(standby, retires_at))
+    }
+
+    /// Id of the standby key, if promoting it would be allowed
+    pub(crate) fn check_standby_promotion(&self) -> Result<&str> {
+        let Some(standby) = &self.standby else {
+            return Err(ApiError::Conflict("No standby signing key configured".to_string()));
+        };
+        if standby.algorithm != self.primary.algorithm {
+            return Err(ApiError::Conflict(format!(
+                "Standby key '{}' uses {:?} but the primary uses {:?}",
+                standby.id, standby.algorithm, self.primary.algorithm
+            )));
+        }
+        Ok(&standby.id)
+    }
+
+    /// Make the primary key from newly loaded config primary, unless it already is, e.g.
+    /// because it was promoted from standby before being written into config
+    pub(crate) fn promote_configured(&mut self, key: SigningKey, retires_at: OffsetDateTime) -> Option<KeyRotation> {
+        self.configured_id = key.id.clone();
+        if key.id == self.primary.id {
+            return None;
+        }
+        Some(self.promote(key, retires_at))
+    }
+
+    /// Drop retiring keys whose grace period has ended, returning their ids
+    pub(crate) fn retire_expired(&mut self, now: OffsetDateTime) -> Vec<String> {
+        let (expired, remaining) = std::mem::take(&mut self.retiring)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(_, retires_at)| *retires_at <= now);
+        self.retiring = remaining;
+
+        expired.into_iter().map(|(key, _)| key.id).collect()
+    }
+
+    pub(crate) fn describe(&self) -> Vec<SigningKeyInfo> {
+        let info = |key: &SigningKey, status, retires_at| SigningKeyInfo {
+            id: key.id.clone(),
+            status,
+            retires_at,
+        };
+
+        std::iter::once(info(&self.primary, KeyStatus::Primary, None))
+            .chain(self.standby.iter().map(|key| info(key, KeyStatus::Standby, None)))
+            .chain(
+                self.retiring
+                    .iter()
+                    .map(|(key, retires_at)| info(key, KeyStatus::Retiring, Some(*retires_at))),
+            )
+            .collect()
+    }
+}
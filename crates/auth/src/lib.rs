@@ -1,5 +1,14 @@
 pub mod service;
+pub mod blacklist;
+pub mod keys;
+pub mod mfa;
 pub mod models;
+pub mod password;
+pub mod permissions;
 
 pub use service::AuthService;
+pub use blacklist::{TokenBlacklist, REDIS_DEPENDENCY};
+pub use keys::{KeyRotation, KeyStatus, SigningKeyInfo};
 pub use models::*;
+pub use password::{LoginVerification, PasswordHasher};
+pub use permissions::{Permission, PermissionResolver, ProductsDelete, ProductsWrite};
@@ -0,0 +1,138 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+use time::OffsetDateTime;
+
+use app_core::error::Result;
+
+/// Seconds each code is valid for (RFC 6238 default)
+const STEP_SECONDS: i64 = 30;
+
+/// Digits per code
+const DIGITS: usize = 6;
+
+/// Steps either side of the current one whose codes are still accepted, for clock drift
+const SKEW_STEPS: i64 = 1;
+
+/// Secret length recommended by RFC 4226 for HMAC-SHA1
+const SECRET_BYTES: usize = 20;
+
+/// New random TOTP secret, base32-encoded as authenticator apps expect
+pub fn generate_totp_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+/// `otpauth://` URI for enrolling `secret` in an authenticator app, usually shown as a QR code
+pub fn provisioning_uri(secret: &str, account: &str, issuer: &str) -> String {
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer,
+        utf8_percent_encode(account, NON_ALPHANUMERIC),
+        secret,
+        issuer,
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+/// Check `code` against the current 30-second step and one step either side. Returns the
+/// step the code belongs to, so callers can refuse a code that was already used.
+pub fn verify_totp(secret: &str, code: &str) -> Result<Option<i64>> {
+    verify_totp_at(secret, code, OffsetDateTime::now_utc())
+}
+
+fn verify_totp_at(secret: &str, code: &str, now: OffsetDateTime) -> Result<Option<i64>> {
+    let key = BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {}", e))?;
+
+    if code.len() != DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let current = now.unix_timestamp() / STEP_SECONDS;
+    let matched = (current - SKEW_STEPS..=current + SKEW_STEPS)
+        .filter(|step| *step >= 0)
+        .find(|step| constant_time_eq(code_at(&key, *step).as_bytes(), code.as_bytes()));
+
+    Ok(matched)
+}
+
+/// RFC 4226 HOTP value for `step`, zero-padded to `DIGITS`
+fn code_at(key: &[u8], step: i64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]])
+        & 0x7fff_ffff;
+
+    format!("{:0width$}", binary % 10u32.pow(DIGITS as u32), width = DIGITS)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 6238 Appendix B SHA-1 key, "12345678901234567890"
+    fn rfc_secret() -> String {
+        BASE32_NOPAD.encode(b"12345678901234567890")
+    }
+
+    fn at(unix_seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(unix_seconds).unwrap()
+    }
+
+    #[test]
+    fn rfc6238_sha1_test_vectors_verify() {
+        // Appendix B codes are 8 digits; 6-digit codes are their last six
+        let vectors = [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_111_111_111, "050471"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+            (20_000_000_000, "353130"),
+        ];
+
+        for (time, code) in vectors {
+            let step = verify_totp_at(&rfc_secret(), code, at(time)).unwrap();
+            assert_eq!(step, Some(time / STEP_SECONDS), "T = {}", time);
+        }
+    }
+
+    #[test]
+    fn codes_one_step_either_side_are_accepted_and_two_steps_are_not() {
+        let secret = rfc_secret();
+        let key = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+        let now = at(1_234_567_890);
+        let current = now.unix_timestamp() / STEP_SECONDS;
+
+        for offset in [-1, 1] {
+            let code = code_at(&key, current + offset);
+            assert_eq!(verify_totp_at(&secret, &code, now).unwrap(), Some(current + offset));
+        }
+        for offset in [-2, 2] {
+            let code = code_at(&key, current + offset);
+            assert_eq!(verify_totp_at(&secret, &code, now).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn malformed_codes_and_secrets_are_refused() {
+        let now = at(59);
+        assert_eq!(verify_totp_at(&rfc_secret(), "28708", now).unwrap(), None);
+        assert_eq!(verify_totp_at(&rfc_secret(), "28708a", now).unwrap(), None);
+        assert!(verify_totp_at("not base32!", "287082", now).is_err());
+    }
+}
@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
+use app_core::error::{ApiError, Result};
+use app_core::models::{validate_email, Email, TenantScope};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid,           // Subject (user id)
@@ -11,35 +14,161 @@ pub struct Claims {
     pub roles: Vec<String>,
     pub exp: i64,           // Expiration time
     pub iat: i64,           // Issued at
+    pub iss: String,        // Issuer
+    pub aud: String,        // Audience
+    /// Admin acting as `sub`; present only on impersonation tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonated_by: Option<Uuid>,
+    /// Tenant the user belongs to; absent in single-tenant deployments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<Uuid>,
+    /// Unique token id, used to revoke the token before it expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Login session the token was issued for, revoked along with the token at logout;
+    /// absent on impersonation tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
 }
 
 impl Claims {
     pub fn is_admin(&self) -> bool {
-        self.roles.contains(&"admin".to_string())
+        self.has_role("admin")
     }
 
+    /// Impersonation tokens never hold the admin role, whatever the token claims
     pub fn has_role(&self, role: &str) -> bool {
+        if role == "admin" && self.is_impersonated() {
+            return false;
+        }
         self.roles.contains(&role.to_string())
     }
+
+    pub fn is_impersonated(&self) -> bool {
+        self.impersonated_by.is_some()
+    }
+
+    /// Super-admins may operate across tenants; never granted to impersonation tokens
+    pub fn is_super_admin(&self) -> bool {
+        !self.is_impersonated() && self.roles.iter().any(|role| role == "super_admin")
+    }
+
+    /// Confined to their own tenant: a tenant's user who isn't a super-admin
+    pub fn is_tenant_bound(&self) -> bool {
+        self.tenant_id.is_some() && !self.is_super_admin()
+    }
+
+    /// Fails with 401 unless the caller is an admin
+    pub fn require_admin(&self) -> Result<()> {
+        if !self.is_admin() {
+            return Err(ApiError::Unauthorized("Admin access required".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Fails with 401 unless the caller is an admin who may act on data shared by every
+    /// tenant; tenant admins also need super-admin
+    pub fn require_cross_tenant_admin(&self) -> Result<()> {
+        self.require_admin()?;
+        if self.is_tenant_bound() {
+            return Err(ApiError::Unauthorized("Cross-tenant access requires super-admin".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Data visible to this caller. Cross-tenant access is only granted when requested
+    /// by a super-admin; everyone else is confined to their own tenant.
+    pub fn tenant_scope(&self, all_tenants: bool) -> TenantScope {
+        if all_tenants && self.is_super_admin() {
+            TenantScope::all_tenants(self.tenant_id)
+        } else {
+            TenantScope::tenant(self.tenant_id)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct LoginRequest {
-    #[validate(email)]
-    pub email: String,
+    #[validate(custom(function = "validate_email"))]
+    pub email: Email,
 
     #[validate(length(min = 1))]
     pub password: String,
 }
 
+#[derive(Clone, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1))]
+    pub current_password: String,
+
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct PasswordResetRequest {
+    #[validate(custom(function = "validate_email"))]
+    pub email: Email,
+}
+
+#[derive(Clone, Deserialize, Validate)]
+pub struct PasswordResetConfirmRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginResponse {
     pub access_token: String,
+    /// Identifies the login session; revoked when the session is evicted
+    pub refresh_token: String,
     pub token_type: String,
     pub expires_in: u64,
     pub user: UserInfo,
 }
 
+/// Issued by `login` instead of tokens when the user has MFA enabled; exchange the
+/// challenge token and a TOTP code at `/auth/mfa/verify` for the tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub challenge_token: String,
+    pub expires_in: u64,
+}
+
+/// Outcome of a successful password check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Authenticated(LoginResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
+#[derive(Clone, Deserialize, Validate)]
+pub struct MfaVerifyRequest {
+    #[validate(length(min = 1))]
+    pub challenge_token: String,
+
+    #[validate(length(equal = 6))]
+    pub code: String,
+}
+
+#[derive(Clone, Deserialize, Validate)]
+pub struct MfaConfirmRequest {
+    #[validate(length(equal = 6))]
+    pub code: String,
+}
+
+/// A new, not yet active, TOTP secret; MFA is enabled once a code from it is confirmed
+#[derive(Clone, Serialize)]
+pub struct MfaEnrollmentResponse {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserInfo {
     pub id: Uuid,
@@ -60,3 +189,71 @@ pub struct TokenResponse {
     pub token_type: String,
     pub expires_in: u64,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct IntrospectRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+/// OAuth 2.0 (RFC 7662) style introspection result; only `active` is set for
+/// tokens that are invalid, expired or revoked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(flatten)]
+    pub claims: Option<Claims>,
+}
+
+impl IntrospectionResponse {
+    pub fn active(claims: Claims) -> Self {
+        Self { active: true, claims: Some(claims) }
+    }
+
+    pub fn inactive() -> Self {
+        Self { active: false, claims: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claims(roles: &[&str], tenant_id: Option<Uuid>) -> Claims {
+        Claims {
+            sub: Uuid::new_v4(),
+            username: "someone".to_string(),
+            email: "someone@example.com".to_string(),
+            roles: roles.iter().map(|role| role.to_string()).collect(),
+            exp: 0,
+            iat: 0,
+            iss: "issuer".to_string(),
+            aud: "audience".to_string(),
+            impersonated_by: None,
+            tenant_id,
+            jti: None,
+            sid: None,
+        }
+    }
+
+    #[test]
+    fn admin_checks_refuse_non_admins_and_impersonation_tokens() {
+        assert!(claims(&["admin"], None).require_admin().is_ok());
+        assert!(claims(&["user"], None).require_admin().is_err());
+
+        let mut impersonated = claims(&["admin"], None);
+        impersonated.impersonated_by = Some(Uuid::new_v4());
+        assert!(impersonated.require_admin().is_err());
+    }
+
+    #[test]
+    fn cross_tenant_admin_checks_refuse_tenant_admins_without_super_admin() {
+        let tenant = Some(Uuid::new_v4());
+
+        assert!(claims(&["admin"], None).require_cross_tenant_admin().is_ok());
+        assert!(claims(&["admin", "super_admin"], tenant).require_cross_tenant_admin().is_ok());
+        assert!(claims(&["admin"], tenant).require_cross_tenant_admin().is_err());
+        // Super-admin alone doesn't make the caller an admin
+        assert!(claims(&["super_admin"], None).require_cross_tenant_admin().is_err());
+    }
+}
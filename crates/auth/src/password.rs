@@ -0,0 +1,230 @@
+use argon2::{Argon2, PasswordHash, PasswordHasher as _, PasswordVerifier};
+use argon2::password_hash::{rand_core::OsRng, SaltString};
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+use app_core::error::Result;
+use app_core::models::User;
+
+/// Password hashing algorithm that can produce and verify hashes in its own format
+pub trait PasswordHasher: Send + Sync {
+    /// Whether `hash` was produced by this algorithm
+    fn can_verify(&self, hash: &str) -> bool;
+
+    fn hash(&self, password: &str) -> Result<String>;
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool>;
+}
+
+/// Default algorithm for all newly stored passwords
+#[derive(Clone, Default)]
+pub struct Argon2Hasher {
+    argon2: Argon2<'static>,
+}
+
+impl PasswordHasher for Argon2Hasher {
+    fn can_verify(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2")
+    }
+
+    fn hash(&self, password: &str) -> Result<String> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        let password_hash = self.argon2
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| {
+                error!("Failed to hash password: {}", e);
+                anyhow::anyhow!("Password hashing failed")
+            })?;
+
+        Ok(password_hash.to_string())
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool> {
+        let parsed_hash = PasswordHash::new(hash)
+            .map_err(|e| {
+                error!("Failed to parse password hash: {}", e);
+                anyhow::anyhow!("Invalid password hash")
+            })?;
+
+        match self.argon2.verify_password(password.as_bytes(), &parsed_hash) {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => {
+                error!("Password verification error: {}", e);
+                Err(anyhow::anyhow!("Password verification failed").into())
+            }
+        }
+    }
+}
+
+/// Bcrypt support for verifying hashes imported from legacy systems
+#[derive(Clone)]
+pub struct BcryptHasher {
+    cost: u32,
+}
+
+impl BcryptHasher {
+    pub fn new(cost: u32) -> Self {
+        Self { cost }
+    }
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn can_verify(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"]
+            .iter()
+            .any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn hash(&self, password: &str) -> Result<String> {
+        bcrypt::hash(password, self.cost).map_err(|e| {
+            error!("Failed to hash password: {}", e);
+            anyhow::anyhow!("Password hashing failed").into()
+        })
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool> {
+        bcrypt::verify(password, hash).map_err(|e| {
+            error!("Password verification error: {}", e);
+            anyhow::anyhow!("Password verification failed").into()
+        })
+    }
+}
+
+/// Outcome of checking a login's password, from `Passwords::verify_login`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginVerification {
+    /// No account has the login's email
+    UnknownAccount,
+    /// The password doesn't match the account's
+    InvalidPassword,
+    /// The password matches, but the account is deactivated
+    Inactive,
+    Verified,
+}
+
+/// The algorithm new hashes use, legacy algorithms accepted only for verifying existing
+/// hashes, and a dummy hash for logins that name no account
+#[derive(Clone)]
+pub struct Passwords {
+    current: Arc<dyn PasswordHasher>,
+    legacy: Vec<Arc<dyn PasswordHasher>>,
+    /// Hash of a random password, verified against when a login names no account so
+    /// that it takes as long as one that does
+    dummy_hash: Arc<str>,
+}
+
+impl Passwords {
+    pub fn new(current: Arc<dyn PasswordHasher>, legacy: Vec<Arc<dyn PasswordHasher>>) -> Result<Self> {
+        let dummy_hash = current.hash(&Uuid::new_v4().to_string())?.into();
+        Ok(Self { current, legacy, dummy_hash })
+    }
+
+    pub fn hash(&self, password: &str) -> Result<String> {
+        self.current.hash(password)
+    }
+
+    /// Verify against whichever supported algorithm produced `hash`
+    pub fn verify(&self, password: &str, hash: &str) -> Result<bool> {
+        let hasher = std::iter::once(&self.current)
+            .chain(self.legacy.iter())
+            .find(|hasher| hasher.can_verify(hash))
+            .ok_or_else(|| {
+                error!("Unsupported password hash format");
+                anyhow::anyhow!("Unsupported password hash format")
+            })?;
+
+        hasher.verify(password, hash)
+    }
+
+    /// Whether `hash` uses a legacy algorithm and should be replaced after a successful login
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        !self.current.can_verify(hash)
+    }
+
+    /// Check a login's password against `user`, or against the dummy hash when the login
+    /// named no account. Exactly one verification runs whatever the outcome, and the
+    /// password is checked before the account's status, so response time reveals neither
+    /// whether the account exists nor whether it is active.
+    pub fn verify_login(&self, password: &str, user: Option<&User>) -> Result<LoginVerification> {
+        let Some(user) = user else {
+            self.current.verify(password, &self.dummy_hash)?;
+            return Ok(LoginVerification::UnknownAccount);
+        };
+
+        if !self.verify(password, &user.password_hash)? {
+            return Ok(LoginVerification::InvalidPassword);
+        }
+        if !user.is_active {
+            return Ok(LoginVerification::Inactive);
+        }
+        Ok(LoginVerification::Verified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use time::OffsetDateTime;
+
+    /// Argon2, counting how many verifications it runs
+    #[derive(Default)]
+    struct CountingArgon2 {
+        argon2: Argon2Hasher,
+        verifications: AtomicUsize,
+    }
+
+    impl PasswordHasher for CountingArgon2 {
+        fn can_verify(&self, hash: &str) -> bool {
+            self.argon2.can_verify(hash)
+        }
+
+        fn hash(&self, password: &str) -> Result<String> {
+            self.argon2.hash(password)
+        }
+
+        fn verify(&self, password: &str, hash: &str) -> Result<bool> {
+            self.verifications.fetch_add(1, Ordering::SeqCst);
+            self.argon2.verify(password, hash)
+        }
+    }
+
+    fn user(password_hash: String, is_active: bool) -> User {
+        let now = OffsetDateTime::now_utc();
+        User {
+            id: Uuid::new_v4(),
+            tenant_id: None,
+            username: "alice".parse().unwrap(),
+            email: "alice@example.com".parse().unwrap(),
+            password_hash,
+            is_active,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn verify_login_runs_argon2_exactly_once_on_every_branch() {
+        let hasher = Arc::new(CountingArgon2::default());
+        let passwords = Passwords::new(hasher.clone(), Vec::new()).unwrap();
+        let hash = passwords.hash("correct horse").unwrap();
+        let active = user(hash.clone(), true);
+        let inactive = user(hash, false);
+
+        let cases = [
+            ("correct horse", None, LoginVerification::UnknownAccount),
+            ("wrong", Some(&active), LoginVerification::InvalidPassword),
+            ("wrong", Some(&inactive), LoginVerification::InvalidPassword),
+            ("correct horse", Some(&inactive), LoginVerification::Inactive),
+            ("correct horse", Some(&active), LoginVerification::Verified),
+        ];
+        for (password, user, expected) in cases {
+            hasher.verifications.store(0, Ordering::SeqCst);
+            assert_eq!(passwords.verify_login(password, user).unwrap(), expected);
+            assert_eq!(hasher.verifications.load(Ordering::SeqCst), 1, "{:?}", expected);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+use crate::models::Claims;
+use app_core::config::AuthConfig;
+
+/// Permission granting every other permission
+pub const WILDCARD_PERMISSION: &str = "*";
+
+/// Permission an operation requires, as a type so handlers can declare it in their signature.
+/// Which roles hold each permission is configured in `auth.role_permissions`.
+pub trait Permission: Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+/// Create and modify products, including bulk price changes
+pub struct ProductsWrite;
+
+impl Permission for ProductsWrite {
+    const NAME: &'static str = "products:write";
+}
+
+/// Delete and restore products
+pub struct ProductsDelete;
+
+impl Permission for ProductsDelete {
+    const NAME: &'static str = "products:delete";
+}
+
+/// Resolves role→permission grants for authorization checks.
+///
+/// Each role's permission set is expanded once and cached, so the hot path is a
+/// set lookup per role. The cache is dropped whenever the mapping is reloaded.
+pub struct PermissionResolver {
+    role_permissions: RwLock<HashMap<String, Vec<String>>>,
+    cache: RwLock<HashMap<String, Arc<HashSet<String>>>>,
+    max_roles_per_check: usize,
+}
+
+impl PermissionResolver {
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            role_permissions: RwLock::new(config.role_permissions.clone()),
+            cache: RwLock::new(HashMap::new()),
+            max_roles_per_check: config.max_roles_per_check,
+        }
+    }
+
+    /// Whether any of the token's roles grants `permission`.
+    /// Only the first `max_roles_per_check` roles are evaluated.
+    pub fn has_permission(&self, claims: &Claims, permission: &str) -> bool {
+        if claims.roles.len() > self.max_roles_per_check {
+            warn!(
+                user_id = %claims.sub,
+                roles = claims.roles.len(),
+                limit = self.max_roles_per_check,
+                "Token carries more roles than evaluated for permission checks"
+            );
+        }
+
+        claims
+            .roles
+            .iter()
+            .take(self.max_roles_per_check)
+            .any(|role| {
+                let granted = self.permissions_for_role(role);
+                granted.contains(permission) || granted.contains(WILDCARD_PERMISSION)
+            })
+    }
+
+    /// Expanded permission set for `role`, computed on first use
+    pub fn permissions_for_role(&self, role: &str) -> Arc<HashSet<String>> {
+        if let Some(cached) = self.cache.read().unwrap().get(role) {
+            return cached.clone();
+        }
+
+        // Hold the mapping lock while caching so a concurrent reload can't be
+        // overwritten with an expansion of the old mapping
+        let role_permissions = self.role_permissions.read().unwrap();
+        let expanded: Arc<HashSet<String>> = Arc::new(
+            role_permissions
+                .get(role)
+                .map(|permissions| permissions.iter().cloned().collect())
+                .unwrap_or_default(),
+        );
+
+        self.cache
+            .write()
+            .unwrap()
+            .insert(role.to_string(), expanded.clone());
+        expanded
+    }
+
+    /// Replace the role→permission mapping and invalidate every cached expansion
+    pub fn reload(&self, config: &AuthConfig) {
+        let mut role_permissions = self.role_permissions.write().unwrap();
+        let mut cache = self.cache.write().unwrap();
+
+        *role_permissions = config.role_permissions.clone();
+        cache.clear();
+    }
+}
@@ -1,66 +1,107 @@
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use argon2::password_hash::{rand_core::OsRng, SaltString};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use time::OffsetDateTime;
-use tracing::{error, instrument};
+use tracing::{error, instrument, warn};
 use uuid::Uuid;
 
+use crate::blacklist::TokenBlacklist;
+use crate::keys::{KeyRing, KeyRotation, SigningKey, SigningKeyInfo};
 use crate::models::Claims;
-use app_core::{config::AuthConfig, error::Result};
+use crate::password::{Argon2Hasher, BcryptHasher, LoginVerification, Passwords};
+use app_core::{
+    config::{is_explicit_development, AuthConfig, JwtAlgorithm, INSECURE_DEFAULT_JWT_SECRET},
+    error::{ApiError, Result},
+    models::{ActiveSigningKey, User},
+    traits::SigningKeyStore,
+};
 
 #[derive(Clone)]
 pub struct AuthService {
-    encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    keys: Arc<RwLock<KeyRing>>,
+    key_retirement_grace_period: u64,
     jwt_expiration: u64,
-    argon2: Argon2<'static>,
+    impersonation_token_ttl: u64,
+    jwt_issuer: String,
+    jwt_audience: String,
+    accepted_audiences: Vec<String>,
+    login_permits: Arc<Semaphore>,
+    passwords: Passwords,
+    /// Tokens revoked before their expiry, e.g. by logout
+    blacklist: TokenBlacklist,
+    /// Accept tokens when the blacklist can't be consulted, instead of rejecting them
+    blacklist_fail_open: bool,
+    /// Which key is primary across all instances
+    key_store: Arc<dyn SigningKeyStore>,
 }
 
 impl AuthService {
-    pub fn new(config: &AuthConfig) -> Result<Self> {
-        let encoding_key = EncodingKey::from_secret(config.jwt_secret.as_bytes());
-        let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_bytes());
+    /// Fails when the well-known default JWT secret is configured outside an explicit
+    /// development environment, regardless of any other config validation, when a token
+    /// lifetime exceeds its configured ceiling, or when a demoted key would be retired
+    /// while tokens it signed are still live
+    pub fn new(config: &AuthConfig, blacklist: TokenBlacklist, key_store: Arc<dyn SigningKeyStore>) -> Result<Self> {
+        if uses_insecure_default_secret(config) && !is_explicit_development() {
+            error!("Refusing to start with the default JWT secret outside development");
+            return Err(anyhow::anyhow!(
+                "jwt_secret is the insecure default; configure a secret or set APP_ENVIRONMENT=development"
+            )
+            .into());
+        }
+        check_token_lifetimes(config)?;
+        check_key_retirement_grace_period(config)?;
 
-        let argon2 = Argon2::default();
+        let passwords = Passwords::new(
+            Arc::new(Argon2Hasher::default()),
+            vec![Arc::new(BcryptHasher::new(config.bcrypt_cost))],
+        )?;
 
         Ok(Self {
-            encoding_key,
-            decoding_key,
+            keys: Arc::new(RwLock::new(KeyRing::new(config)?)),
+            key_retirement_grace_period: config.jwt_key_retirement_grace_period,
             jwt_expiration: config.jwt_expiration,
-            argon2,
+            impersonation_token_ttl: config.impersonation_token_ttl,
+            jwt_issuer: config.jwt_issuer.clone(),
+            jwt_audience: config.jwt_audience.clone(),
+            accepted_audiences: config.accepted_audiences(),
+            login_permits: Arc::new(Semaphore::new(config.max_concurrent_logins)),
+            passwords,
+            blacklist,
+            blacklist_fail_open: config.token_blacklist_fail_open,
+            key_store,
         })
     }
 
     #[instrument(skip(self, password))]
     pub fn hash_password(&self, password: &str) -> Result<String> {
-        let salt = SaltString::generate(&mut OsRng);
-
-        let password_hash = self.argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|e| {
-                error!("Failed to hash password: {}", e);
-                anyhow::anyhow!("Password hashing failed")
-            })?;
+        self.passwords.hash(password)
+    }
 
-        Ok(password_hash.to_string())
+    /// Reserve a slot for an expensive login attempt, shedding with 429 when the
+    /// auth subsystem is saturated. Hold the permit until verification completes.
+    pub fn try_acquire_login_permit(&self) -> Result<OwnedSemaphorePermit> {
+        self.login_permits.clone().try_acquire_owned().map_err(|_| {
+            warn!("Login concurrency limit reached, shedding login attempt");
+            ApiError::RateLimitExceeded("Too many login attempts, please retry shortly".to_string())
+        })
     }
 
+    /// Verify against whichever supported algorithm produced `hash`
     #[instrument(skip(self, password, hash))]
     pub fn verify_password(&self, password: &str, hash: &str) -> Result<bool> {
-        let parsed_hash = PasswordHash::new(hash)
-            .map_err(|e| {
-                error!("Failed to parse password hash: {}", e);
-                anyhow::anyhow!("Invalid password hash")
-            })?;
+        self.passwords.verify(password, hash)
+    }
 
-        match self.argon2.verify_password(password.as_bytes(), &parsed_hash) {
-            Ok(()) => Ok(true),
-            Err(argon2::password_hash::Error::Password) => Ok(false),
-            Err(e) => {
-                error!("Password verification error: {}", e);
-                Err(anyhow::anyhow!("Password verification failed").into())
-            }
-        }
+    /// Check a login's password against the account it named, if any; see
+    /// `Passwords::verify_login`
+    #[instrument(skip(self, password, user))]
+    pub fn verify_login(&self, password: &str, user: Option<&User>) -> Result<LoginVerification> {
+        self.passwords.verify_login(password, user)
+    }
+
+    /// Whether `hash` uses a legacy algorithm and should be replaced after a successful login
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        self.passwords.needs_rehash(hash)
     }
 
     #[instrument(skip(self))]
@@ -70,6 +111,8 @@ impl AuthService {
         username: String,
         email: String,
         roles: Vec<String>,
+        tenant_id: Option<Uuid>,
+        session_id: Uuid,
     ) -> Result<String> {
         let now = OffsetDateTime::now_utc().unix_timestamp();
         let expiration = now + self.jwt_expiration as i64;
@@ -81,34 +124,134 @@ impl AuthService {
             roles,
             exp: expiration,
             iat: now,
+            iss: self.jwt_issuer.clone(),
+            aud: self.jwt_audience.clone(),
+            impersonated_by: None,
+            tenant_id,
+            jti: Some(Uuid::new_v4().to_string()),
+            sid: Some(session_id),
+        };
+
+        self.encode_claims(&claims)
+    }
+
+    /// Short-lived token acting as `user_id` on behalf of `admin_id`.
+    /// The admin role is never carried over, so the token can't perform admin actions.
+    #[instrument(skip(self))]
+    pub fn generate_impersonation_token(
+        &self,
+        user_id: Uuid,
+        username: String,
+        email: String,
+        roles: Vec<String>,
+        tenant_id: Option<Uuid>,
+        admin_id: Uuid,
+    ) -> Result<String> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let expiration = now + self.impersonation_token_ttl as i64;
+
+        let claims = Claims {
+            sub: user_id,
+            username,
+            email,
+            roles: roles.into_iter().filter(|role| role != "admin").collect(),
+            exp: expiration,
+            iat: now,
+            iss: self.jwt_issuer.clone(),
+            aud: self.jwt_audience.clone(),
+            impersonated_by: Some(admin_id),
+            tenant_id,
+            jti: Some(Uuid::new_v4().to_string()),
+            sid: None,
         };
 
-        encode(&Header::default(), &claims, &self.encoding_key)
+        self.encode_claims(&claims)
+    }
+
+    fn encode_claims(&self, claims: &Claims) -> Result<String> {
+        let keys = self.keys.read().unwrap();
+        let (encoding_key, algorithm) = keys.primary_encoding_key();
+        let header = Header {
+            kid: Some(keys.primary_id().to_string()),
+            ..Header::new(algorithm)
+        };
+
+        encode(&header, claims, encoding_key)
             .map_err(|e| {
                 error!("Failed to encode JWT: {}", e);
                 anyhow::anyhow!("Token generation failed").into()
             })
     }
 
+    /// Verify `token` against the cached signing keys, then check it hasn't been revoked.
+    ///
+    /// Only the signature check is needed to trust a token, and it never leaves the process.
+    /// Anything remote (today the revocation lookup) goes through its own circuit breaker and
+    /// time budget, so a slow or failing dependency yields `ServiceUnavailable` quickly
+    /// instead of stalling every authenticated request. A remote key source added later
+    /// must follow the same rule and only refresh the cached `KeyRing`, never gate reads on it.
     #[instrument(skip(self, token))]
     pub async fn validate_token(&self, token: &str) -> Result<Claims> {
-        let validation = Validation::default();
+        let header = decode_header(token).map_err(|e| {
+            error!("Failed to decode JWT header: {}", e);
+            ApiError::Unauthorized("malformed token".to_string())
+        })?;
 
-        let token_data = decode::<Claims>(token, &self.decoding_key, &validation)
-            .map_err(|e| {
-                error!("Failed to decode JWT: {}", e);
-                anyhow::anyhow!("Invalid token")
+        let token_data = {
+            let keys = self.keys.read().unwrap();
+            let (decoding_key, algorithm) = keys.decoding_key(header.kid.as_deref()).ok_or_else(|| {
+                warn!("JWT signed with unknown key id: {:?}", header.kid);
+                ApiError::Unauthorized("unknown signing key".to_string())
             })?;
 
+            // The algorithm comes from our key, never the token's header, so an RS256
+            // public key can't be passed off as an HMAC secret. Require our issuer and one
+            // of the accepted audiences so tokens minted for another service are rejected.
+            let mut validation = Validation::new(algorithm);
+            validation.set_issuer(&[&self.jwt_issuer]);
+            validation.set_audience(&self.accepted_audiences);
+            validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+            decode::<Claims>(token, decoding_key, &validation)
+                .map_err(|e| {
+                    error!("Failed to decode JWT: {}", e);
+                    ApiError::Unauthorized(jwt_failure_reason(&e).to_string())
+                })?
+        };
+
         // Check if token is expired
         let now = OffsetDateTime::now_utc().unix_timestamp();
         if token_data.claims.exp < now {
-            return Err(anyhow::anyhow!("Token expired").into());
+            return Err(ApiError::Unauthorized("token expired".to_string()));
+        }
+
+        if let Some(jti) = &token_data.claims.jti {
+            match self.blacklist.is_revoked(jti).await {
+                Ok(false) => {}
+                Ok(true) => return Err(ApiError::Unauthorized("token revoked".to_string())),
+                Err(e) if self.blacklist_fail_open => {
+                    warn!("Accepting token without revocation check: {}", e);
+                }
+                Err(e) => return Err(e),
+            }
         }
 
         Ok(token_data.claims)
     }
 
+    /// Reject the token carrying these claims from now until it expires.
+    /// Tokens issued before `jti` was introduced can't be revoked individually.
+    #[instrument(skip(self, claims), fields(user_id = %claims.sub))]
+    pub async fn revoke_token(&self, claims: &Claims) -> Result<bool> {
+        let Some(jti) = &claims.jti else {
+            warn!("Token has no jti and can't be revoked");
+            return Ok(false);
+        };
+
+        self.blacklist.revoke(jti, claims.exp).await?;
+        Ok(true)
+    }
+
     #[instrument(skip(self))]
     pub fn extract_token_from_header<'a>(&self, auth_header: &'a str) -> Result<&'a str> {
         auth_header
@@ -119,4 +262,227 @@ impl AuthService {
     pub fn jwt_expiration(&self) -> u64 {
         self.jwt_expiration
     }
+
+    pub fn impersonation_token_ttl(&self) -> u64 {
+        self.impersonation_token_ttl
+    }
+
+    /// Current signing keys and their roles
+    pub fn signing_keys(&self) -> Vec<SigningKeyInfo> {
+        self.keys.read().unwrap().describe()
+    }
+
+    /// Atomically make the standby key primary; the old primary keeps verifying
+    /// tokens until the retirement grace period ends. The promotion is recorded in the
+    /// shared key store first, and the other instances follow it on their next sync.
+    pub async fn promote_standby_key(&self) -> Result<KeyRotation> {
+        let retires_at = self.retirement_deadline();
+        let active = {
+            let keys = self.keys.read().unwrap();
+            ActiveSigningKey {
+                primary_key_id: keys.check_standby_promotion()?.to_string(),
+                retiring_key_id: keys.primary_id().to_string(),
+                retires_at,
+            }
+        };
+        self.key_store.set_active_key(&active).await?;
+
+        let rotation = self.keys.write().unwrap().promote_standby(retires_at)?;
+        warn!(
+            "Promoted signing key '{}'; key '{}' retires at {}",
+            rotation.primary_key_id, rotation.retiring_key_id, rotation.retires_at
+        );
+        Ok(rotation)
+    }
+
+    /// Apply key changes from reloaded config, then follow the shared key store. A new
+    /// `jwt_key_id` promotes the configured key to primary and records it in the store for
+    /// the other instances; the standby key is replaced. Config is compared with the config
+    /// applied before it, not with the primary, so a key promoted through the API stays.
+    pub async fn reload_keys(&self, config: &AuthConfig) -> Option<KeyRotation> {
+        if uses_insecure_default_secret(config) && !is_explicit_development() {
+            error!("Ignoring reloaded signing keys: the default JWT secret is not allowed outside development");
+            return None;
+        }
+
+        let rotation = {
+            let mut keys = self.keys.write().unwrap();
+
+            let rotation = if keys.configured_id() != config.jwt_key_id {
+                match SigningKey::primary(config) {
+                    Ok(key) => keys.promote_configured(key, self.retirement_deadline()),
+                    Err(e) => {
+                        error!("Keeping the current signing key: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if let Err(e) = keys.set_standby(config) {
+                error!("Keeping the current standby key: {}", e);
+            }
+            rotation
+        };
+
+        if let Some(rotation) = &rotation {
+            let active = ActiveSigningKey {
+                primary_key_id: rotation.primary_key_id.clone(),
+                retiring_key_id: rotation.retiring_key_id.clone(),
+                retires_at: rotation.retires_at,
+            };
+            if let Err(e) = self.key_store.set_active_key(&active).await {
+                // Syncing now would follow the stale record and could undo this rotation
+                error!("Failed to record signing key '{}' for other instances: {}", active.primary_key_id, e);
+                return Some(rotation.clone());
+            }
+        }
+
+        if let Err(e) = self.sync_signing_keys().await {
+            error!("Failed to sync signing keys after reload: {}", e);
+        }
+        rotation
+    }
+
+    /// Promote the standby key when the shared key store names it as primary, so this
+    /// instance signs with the same key as the others. Run at startup and periodically.
+    pub async fn sync_signing_keys(&self) -> Result<Option<KeyRotation>> {
+        let Some(active) = self.key_store.active_key().await? else {
+            return Ok(None);
+        };
+
+        let mut keys = self.keys.write().unwrap();
+        if keys.primary_id() == active.primary_key_id {
+            return Ok(None);
+        }
+        if keys.standby_id() != Some(active.primary_key_id.as_str()) {
+            warn!(
+                "Signing key '{}' is primary on other instances but is not this instance's standby",
+                active.primary_key_id
+            );
+            return Ok(None);
+        }
+
+        let rotation = keys.promote_standby(active.retires_at)?;
+        warn!(
+            "Promoted signing key '{}' to match other instances; key '{}' retires at {}",
+            rotation.primary_key_id, rotation.retiring_key_id, rotation.retires_at
+        );
+        Ok(Some(rotation))
+    }
+
+    /// Drop demoted keys whose grace period has ended, returning their ids
+    pub fn retire_expired_keys(&self) -> Vec<String> {
+        self.keys.write().unwrap().retire_expired(OffsetDateTime::now_utc())
+    }
+
+    fn retirement_deadline(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc() + time::Duration::seconds(self.key_retirement_grace_period as i64)
+    }
+}
+
+fn uses_insecure_default_secret(config: &AuthConfig) -> bool {
+    (config.algorithm == JwtAlgorithm::Hs256 && config.jwt_secret == INSECURE_DEFAULT_JWT_SECRET)
+        || config
+            .jwt_standby_key
+            .as_ref()
+            .is_some_and(|key| config.algorithm == JwtAlgorithm::Hs256 && key.secret == INSECURE_DEFAULT_JWT_SECRET)
+}
+
+/// Refuse lifetimes above `max_access_token_lifetime` (access and impersonation tokens)
+/// or `max_refresh_token_lifetime` (sessions), so a typo can't issue near-immortal tokens
+fn check_token_lifetimes(config: &AuthConfig) -> Result<()> {
+    let lifetimes = [
+        ("jwt_expiration", config.jwt_expiration, "max_access_token_lifetime", config.max_access_token_lifetime),
+        (
+            "impersonation_token_ttl",
+            config.impersonation_token_ttl,
+            "max_access_token_lifetime",
+            config.max_access_token_lifetime,
+        ),
+        ("session_ttl", config.session_ttl, "max_refresh_token_lifetime", config.max_refresh_token_lifetime),
+    ];
+
+    for (setting, lifetime, cap_setting, cap) in lifetimes {
+        if lifetime > cap {
+            error!("auth.{} of {}s exceeds auth.{} of {}s", setting, lifetime, cap_setting, cap);
+            return Err(anyhow::anyhow!(
+                "auth.{} ({}s) exceeds auth.{} ({}s); shorten it or raise the cap deliberately",
+                setting,
+                lifetime,
+                cap_setting,
+                cap
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// A demoted key must keep verifying for as long as any token it signed can live, or
+/// promoting the standby key logs users out early
+fn check_key_retirement_grace_period(config: &AuthConfig) -> Result<()> {
+    let lifetimes = [
+        ("jwt_expiration", config.jwt_expiration),
+        ("impersonation_token_ttl", config.impersonation_token_ttl),
+    ];
+
+    for (setting, lifetime) in lifetimes {
+        if config.jwt_key_retirement_grace_period < lifetime {
+            error!(
+                "auth.jwt_key_retirement_grace_period of {}s is shorter than auth.{} of {}s",
+                config.jwt_key_retirement_grace_period, setting, lifetime
+            );
+            return Err(anyhow::anyhow!(
+                "auth.jwt_key_retirement_grace_period ({}s) must be at least auth.{} ({}s)",
+                config.jwt_key_retirement_grace_period,
+                setting,
+                lifetime
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Precise reason a token was rejected. Only ever exposed to clients in debug mode.
+fn jwt_failure_reason(error: &jsonwebtoken::errors::Error) -> &'static str {
+    use jsonwebtoken::errors::ErrorKind;
+
+    match error.kind() {
+        ErrorKind::ExpiredSignature => "token expired",
+        ErrorKind::ImmatureSignature => "token not yet valid",
+        ErrorKind::InvalidSignature => "invalid signature",
+        ErrorKind::InvalidAudience => "audience mismatch",
+        ErrorKind::InvalidIssuer => "issuer mismatch",
+        ErrorKind::InvalidAlgorithm => "unexpected signing algorithm",
+        ErrorKind::MissingRequiredClaim(_) => "missing required claim",
+        _ => "malformed token",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_core::config::Config;
+
+    #[test]
+    fn demoted_keys_must_outlive_the_tokens_they_signed() {
+        let mut config = Config::default().auth;
+        config.jwt_expiration = 3600;
+        config.impersonation_token_ttl = 900;
+
+        config.jwt_key_retirement_grace_period = 3599;
+        let error = check_key_retirement_grace_period(&config).unwrap_err().to_string();
+        assert!(error.contains("jwt_expiration"), "{}", error);
+
+        config.jwt_key_retirement_grace_period = 3600;
+        assert!(check_key_retirement_grace_period(&config).is_ok());
+
+        config.impersonation_token_ttl = 7200;
+        let error = check_key_retirement_grace_period(&config).unwrap_err().to_string();
+        assert!(error.contains("impersonation_token_ttl"), "{}", error);
+    }
 }
@@ -1,6 +1,17 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 
+use crate::enterprise::FlagVariant;
+
+/// JWT secret used when none is configured. Only acceptable in development.
+pub const INSECURE_DEFAULT_JWT_SECRET: &str = "your-super-secret-jwt-key";
+
+/// Whether `APP_ENVIRONMENT` is explicitly set to `development`; unset counts as not
+pub fn is_explicit_development() -> bool {
+    env::var("APP_ENVIRONMENT").is_ok_and(|environment| environment == "development")
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
@@ -8,6 +19,8 @@ pub struct Config {
     pub auth: AuthConfig,
     pub redis: RedisConfig,
     pub monitoring: MonitoringConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +28,266 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub workers: Option<usize>,
+    /// In-flight requests allowed per authenticated (non-admin) user; at least 1
+    #[serde(default = "default_max_concurrent_requests_per_user")]
+    pub max_concurrent_requests_per_user: usize,
+    /// Seconds a client may go without sending request body data before being dropped
+    #[serde(default = "default_body_read_timeout")]
+    pub body_read_timeout: u64,
+    /// Largest body, in bytes, accepted by endpoints that read it as a stream, such as
+    /// bulk imports; other endpoints keep axum's 2 MiB limit
+    #[serde(default = "default_max_streamed_body_bytes")]
+    pub max_streamed_body_bytes: usize,
+    /// Per-user quota for the enhanced profile endpoint
+    #[serde(default = "default_enhanced_profile_requests_per_minute")]
+    pub enhanced_profile_requests_per_minute: u32,
+    /// Per-user cost units per minute for expensive operations such as imports, exports
+    /// and bulk writes; costs are only measured, not limited, when unset
+    #[serde(default)]
+    pub request_cost_budget_per_minute: Option<u32>,
+    /// Format of generated request and correlation ids
+    #[serde(default)]
+    pub request_id_format: RequestIdFormat,
+    /// Shared secret for the `X-Body-Signature` HMAC; response signing is off when unset
+    #[serde(default)]
+    pub response_signing_secret: Option<String>,
+    /// Path prefixes whose responses are signed when a signing secret is configured
+    #[serde(default)]
+    pub signed_response_paths: Vec<String>,
+    /// Path prefixes whose request bodies are verified against a `Content-MD5` or
+    /// `X-Content-SHA256` header when the client sends one
+    #[serde(default)]
+    pub checksum_verified_paths: Vec<String>,
+    /// Requests per minute sharing one client-supplied correlation id before it is
+    /// flagged as reuse (the id is still honored)
+    #[serde(default = "default_correlation_id_reuse_threshold")]
+    pub correlation_id_reuse_threshold: u64,
+    /// Most correlation ids counted at once; the id with the oldest window is dropped
+    /// to make room for a new one
+    #[serde(default = "default_correlation_id_tracking_capacity")]
+    pub correlation_id_tracking_capacity: usize,
+    /// Minimum TLS version reported by the terminating proxy; no check when unset
+    #[serde(default)]
+    pub tls_policy: Option<TlsPolicyConfig>,
+    /// Per-client request quota applied to every route except health and metrics;
+    /// no limit when unset
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Per-client quotas on individual resources, e.g. fewer creates than reads of
+    /// products; applied in addition to `rate_limit`, first matching entry only
+    #[serde(default)]
+    pub resource_rate_limits: Vec<ResourceRateLimitConfig>,
+    /// Largest response body, in bytes, sent in one piece; larger ones are replaced by an
+    /// error asking the client to page or stream. Streamed bodies aren't limited. `null` disables.
+    #[serde(default = "default_max_response_bytes")]
+    pub max_response_bytes: Option<usize>,
+    /// Whether `null` fields are kept in JSON responses or stripped for compact payloads
+    #[serde(default)]
+    pub null_fields: NullFieldPolicy,
+    /// Response to API requests whose `Accept` header rules out JSON
+    #[serde(default)]
+    pub unsupported_accept: UnsupportedAcceptPolicy,
+    /// Treatment of request body fields the endpoint doesn't declare
+    #[serde(default)]
+    pub unknown_json_fields: UnknownFieldPolicy,
+    /// Timestamp format of JSON responses when the request doesn't choose one with a
+    /// `timestamps` parameter in `Accept`
+    #[serde(default)]
+    pub timestamp_format: TimestampFormat,
+    /// Send a `Server-Timing` header breaking each response's time down into auth and
+    /// database phases. It reveals internal timings, so keep it off in production.
+    #[serde(default)]
+    pub server_timing: bool,
+    /// Externally reachable base URL, used to build links sent to users
+    #[serde(default = "default_public_url")]
+    pub public_url: String,
+    /// Where background exports are written; a directory under the system temp dir when unset.
+    /// With more than one instance this must be storage they all share.
+    #[serde(default)]
+    pub export_dir: Option<String>,
+    /// Secret export download links are signed with. Required, and the same on every
+    /// instance so a link works whichever instance serves it.
+    #[serde(default)]
+    pub export_url_secret: Option<String>,
+    /// Seconds an export download link stays valid
+    #[serde(default = "default_export_url_ttl")]
+    pub export_url_ttl: u64,
+    /// Seconds a finished export is kept before its file is deleted
+    #[serde(default = "default_export_retention")]
+    pub export_retention: u64,
+    /// Seconds between runs of the job that generates queued exports
+    #[serde(default = "default_export_poll_interval")]
+    pub export_poll_interval: u64,
+}
+
+/// Treatment of `null`-valued object fields in JSON responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NullFieldPolicy {
+    /// Every field is present, `null` when unset
+    #[default]
+    Include,
+    /// Fields whose value is `null` are left out
+    Omit,
+}
+
+/// Handling of API requests whose `Accept` header allows none of the types we serve
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedAcceptPolicy {
+    /// 406 Not Acceptable listing the supported types
+    #[default]
+    Reject,
+    /// Serve JSON regardless
+    Ignore,
+}
+
+/// How timestamps are written in JSON responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// Strings such as `2024-01-01T00:00:00Z`
+    #[default]
+    Rfc3339,
+    /// Integer milliseconds since the Unix epoch
+    UnixMillis,
+}
+
+/// Handling of JSON request body fields the target type doesn't declare
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownFieldPolicy {
+    /// Dropped silently
+    #[default]
+    Ignore,
+    /// 400 Bad Request naming each unknown field
+    Reject,
+}
+
+/// Advisory TLS floor for deployments where TLS terminates at a proxy that reports
+/// the negotiated protocol version in a request header
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsPolicyConfig {
+    /// Header carrying the negotiated version, e.g. `TLSv1.2`
+    #[serde(default = "default_tls_version_header")]
+    pub version_header: String,
+    /// Lowest acceptable version, e.g. `1.2`
+    pub min_version: String,
+    /// Peers allowed to set the version header; it is ignored from anyone else
+    #[serde(default)]
+    pub trusted_proxies: Vec<std::net::IpAddr>,
+    /// Reject outdated connections with 403 instead of only logging and counting them
+    #[serde(default)]
+    pub enforce: bool,
+}
+
+fn default_tls_version_header() -> String {
+    "x-forwarded-tls-version".to_string()
+}
+
+/// Requests allowed per client within a window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests: u32,
+    pub window: RateLimitWindow,
+}
+
+/// Quota on the routes of one resource, optionally only for some methods
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceRateLimitConfig {
+    /// Route pattern, e.g. `/api/v1/products`; also covers routes below it such as
+    /// `/api/v1/products/:id`
+    pub path: String,
+    /// Methods limited, e.g. `["POST", "PUT", "PATCH", "DELETE"]`; every method when empty
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(flatten)]
+    pub limit: RateLimitConfig,
+}
+
+/// How a rate-limit quota is replenished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RateLimitWindow {
+    /// Quota refills gradually, `requests` per `period` seconds
+    Sliding { period: u64 },
+    /// Quota resets all at once at each `boundary` in `timezone`, e.g. midnight in
+    /// `America/New_York` for a calendar-day quota
+    Fixed {
+        boundary: WindowBoundary,
+        /// IANA time zone name
+        #[serde(default = "default_rate_limit_timezone")]
+        timezone: String,
+    },
+}
+
+/// Calendar boundary at which a fixed rate-limit window resets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowBoundary {
+    Hourly,
+    Daily,
+}
+
+fn default_rate_limit_timezone() -> String {
+    "UTC".to_string()
+}
+
+/// Strategy for generating request and correlation ids
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestIdFormat {
+    /// Random UUIDv4 (36 chars)
+    #[default]
+    UuidV4,
+    /// Time-sortable ULID (26 chars)
+    Ulid,
+    /// Random 128-bit id in base62 (22 chars)
+    Base62,
+}
+
+fn default_max_concurrent_requests_per_user() -> usize {
+    10
+}
+
+fn default_max_response_bytes() -> Option<usize> {
+    Some(10 * 1024 * 1024)
+}
+
+fn default_max_streamed_body_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_body_read_timeout() -> u64 {
+    10
+}
+
+fn default_enhanced_profile_requests_per_minute() -> u32 {
+    30
+}
+
+fn default_correlation_id_reuse_threshold() -> u64 {
+    1000
+}
+
+fn default_correlation_id_tracking_capacity() -> usize {
+    10_000
+}
+
+fn default_public_url() -> String {
+    "http://localhost:8080".to_string()
+}
+
+fn default_export_url_ttl() -> u64 {
+    900
+}
+
+fn default_export_retention() -> u64 {
+    86400
+}
+
+fn default_export_poll_interval() -> u64 {
+    5
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,25 +297,538 @@ pub struct DatabaseConfig {
     pub min_connections: u32,
     pub acquire_timeout: u64,
     pub idle_timeout: u64,
+    /// Log `EXPLAIN (ANALYZE, BUFFERS)` for slow read queries; debugging only
+    #[serde(default)]
+    pub explain_slow_queries: bool,
+    #[serde(default = "default_slow_query_threshold_ms")]
+    pub slow_query_threshold_ms: u64,
+    /// Shed requests with 503 once this many are queued behind an exhausted pool;
+    /// disabled when unset, in which case callers wait up to `acquire_timeout`
+    #[serde(default)]
+    pub pool_shed_threshold: Option<usize>,
+    /// Service name reported as `application_name` (with the build version) in `pg_stat_activity`
+    #[serde(default = "default_application_name")]
+    pub application_name: String,
+    /// Connections reserved for health checks, in addition to `max_connections`
+    #[serde(default = "default_health_check_connections")]
+    pub health_check_connections: u32,
+    /// Seconds allowed at startup to open `min_connections` and run a query on each, so
+    /// the first requests don't pay for connecting; no warm-up when unset
+    #[serde(default)]
+    pub warm_up_timeout: Option<u64>,
+    /// Warn at startup, with the `CREATE INDEX` to run, when an index the hot queries
+    /// rely on is missing; nothing is created automatically
+    #[serde(default = "default_index_advisories")]
+    pub index_advisories: bool,
+    /// Where product data is read from and written to
+    #[serde(default)]
+    pub product_backend: ProductBackendConfig,
+}
+
+/// Storage backend for products; users and everything else always live in Postgres
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProductBackendConfig {
+    #[default]
+    Postgres,
+    /// External catalog service exposing a REST `products` resource
+    Http(HttpCatalogConfig),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpCatalogConfig {
+    /// Base URL the `/products` paths are appended to
+    pub base_url: String,
+    /// Sent as a bearer token when set
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Seconds before an outbound catalog request is abandoned
+    #[serde(default = "default_catalog_timeout")]
+    pub timeout: u64,
+    /// Seconds a catalog read is served from the local cache; 0 disables caching
+    #[serde(default = "default_catalog_cache_ttl")]
+    pub cache_ttl: u64,
+}
+
+fn default_catalog_timeout() -> u64 {
+    5
+}
+
+fn default_catalog_cache_ttl() -> u64 {
+    30
+}
+
+fn default_application_name() -> String {
+    "scalable-rust-api".to_string()
+}
+
+fn default_health_check_connections() -> u32 {
+    1
+}
+
+fn default_index_advisories() -> bool {
+    true
+}
+
+fn default_slow_query_threshold_ms() -> u64 {
+    500
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthConfig {
+    /// HMAC secret of the primary key; only used with HS256
     pub jwt_secret: String,
+    /// Algorithm new tokens are signed with
+    #[serde(default)]
+    pub algorithm: JwtAlgorithm,
+    /// PEM RSA private key of the primary key; required for RS256
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    /// PEM RSA public key matching `private_key_path`; required for RS256
+    #[serde(default)]
+    pub public_key_path: Option<String>,
     pub jwt_expiration: u64,
+    /// Ceiling in seconds on `jwt_expiration` and `impersonation_token_ttl`; startup fails
+    /// when either exceeds it. Raise it only deliberately.
+    #[serde(default = "default_max_access_token_lifetime")]
+    pub max_access_token_lifetime: u64,
+    /// Ceiling in seconds on `session_ttl`, which bounds how long a refresh token works
+    #[serde(default = "default_max_refresh_token_lifetime")]
+    pub max_refresh_token_lifetime: u64,
     pub bcrypt_cost: u32,
+    /// Value stamped into the `iss` claim and required on validation
+    #[serde(default = "default_jwt_issuer")]
+    pub jwt_issuer: String,
+    /// Value stamped into the `aud` claim of issued tokens
+    #[serde(default = "default_jwt_audience")]
+    pub jwt_audience: String,
+    /// Audiences accepted on validation; falls back to `jwt_audience` when empty
+    #[serde(default)]
+    pub jwt_accepted_audiences: Vec<String>,
+    /// Login attempts allowed to verify passwords concurrently before shedding with 429
+    #[serde(default = "default_max_concurrent_logins")]
+    pub max_concurrent_logins: usize,
+    /// Deactivate accounts with no login for this many days; disabled when unset
+    #[serde(default)]
+    pub inactivity_deactivation_days: Option<u64>,
+    /// Usernames (service accounts, admins) never auto-deactivated for inactivity
+    #[serde(default)]
+    pub inactivity_exempt_usernames: Vec<String>,
+    /// Accounts deactivated per batch by the inactivity job
+    #[serde(default = "default_inactivity_batch_size")]
+    pub inactivity_batch_size: i64,
+    /// Permissions granted by each role; `"*"` grants everything. Reloaded on SIGHUP.
+    #[serde(default)]
+    pub role_permissions: HashMap<String, Vec<String>>,
+    /// Roles on a token considered by a single permission check
+    #[serde(default = "default_max_roles_per_check")]
+    pub max_roles_per_check: usize,
+    /// Lifetime in seconds of tokens issued for admin impersonation
+    #[serde(default = "default_impersonation_token_ttl")]
+    pub impersonation_token_ttl: u64,
+    /// Keys accepted in `X-API-Key` from trusted services (e.g. the gateway)
+    /// calling service-only endpoints such as token introspection
+    #[serde(default)]
+    pub service_api_keys: Vec<String>,
+    /// `kid` stamped on tokens signed with `jwt_secret`. Changing it (with a new secret)
+    /// and sending SIGHUP promotes the new key and retires the previous one.
+    #[serde(default = "default_jwt_key_id")]
+    pub jwt_key_id: String,
+    /// Warm standby key: accepted for verification now, promotable to primary via the admin API
+    #[serde(default)]
+    pub jwt_standby_key: Option<JwtKeyConfig>,
+    /// Seconds a demoted key keeps verifying tokens before it is retired
+    #[serde(default = "default_jwt_key_retirement_grace_period")]
+    pub jwt_key_retirement_grace_period: u64,
+    /// Explain rejected credentials in an `X-Auth-Debug` response header.
+    /// For development only: the reasons help attackers probe tokens.
+    #[serde(default)]
+    pub expose_auth_debug: bool,
+    /// Previous passwords per user that a new password may not match; 0 disables the check
+    #[serde(default = "default_password_history_size")]
+    pub password_history_size: usize,
+    /// Seconds an email change verification link stays valid
+    #[serde(default = "default_email_change_token_ttl")]
+    pub email_change_token_ttl: u64,
+    /// Seconds a password reset link stays valid
+    #[serde(default = "default_password_reset_token_ttl")]
+    pub password_reset_token_ttl: u64,
+    /// Password reset and verification emails sent to one address per hour; further
+    /// requests get the usual response but no email
+    #[serde(default = "default_email_sends_per_address_per_hour")]
+    pub email_sends_per_address_per_hour: u32,
+    /// Password reset and verification emails one client address may trigger per hour
+    #[serde(default = "default_email_sends_per_client_per_hour")]
+    pub email_sends_per_client_per_hour: u32,
+    /// Seconds a login has to complete its MFA step once the password is accepted
+    #[serde(default = "default_mfa_challenge_ttl")]
+    pub mfa_challenge_ttl: u64,
+    /// Wrong codes accepted per MFA challenge before the login must start over
+    #[serde(default = "default_mfa_max_attempts")]
+    pub mfa_max_attempts: i32,
+    /// Active sessions a non-admin user may hold at once, at least 1; unlimited when unset
+    #[serde(default)]
+    pub max_sessions_per_user: Option<usize>,
+    /// What a login does when the user already holds `max_sessions_per_user` sessions
+    #[serde(default)]
+    pub session_limit_policy: SessionLimitPolicy,
+    /// Seconds a login session, and the refresh token identifying it, stays valid
+    #[serde(default = "default_session_ttl")]
+    pub session_ttl: u64,
+    /// Seconds past its expiry a refresh token is still accepted, absorbing client clock
+    /// skew at the refresh boundary. Independent of access token validation. Every second
+    /// of grace also extends how long a stolen refresh token can be used, so keep it short.
+    #[serde(default = "default_refresh_token_leeway")]
+    pub refresh_token_leeway: u64,
+    /// Accept tokens when the revocation blacklist (Redis) is unreachable; by default
+    /// they are refused with 503 until it recovers
+    #[serde(default)]
+    pub token_blacklist_fail_open: bool,
+    /// Milliseconds token validation may spend on remote checks before failing with 503
+    #[serde(default = "default_token_validation_timeout_ms")]
+    pub token_validation_timeout_ms: u64,
+}
+
+/// Signing algorithm of the primary JWT key. Standby keys are always HMAC secrets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JwtAlgorithm {
+    /// Shared secret; every verifier can also mint tokens
+    #[default]
+    Hs256,
+    /// RSA key pair; other services can verify with the public key alone
+    Rs256,
+}
+
+/// Handling of a login that would exceed the per-user session limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionLimitPolicy {
+    /// Revoke the user's oldest sessions to make room
+    #[default]
+    EvictOldest,
+    /// Refuse the new login
+    Reject,
+}
+
+/// Named key used to sign and verify JWTs, with the same algorithm as the primary key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtKeyConfig {
+    pub id: String,
+    /// HMAC secret; required for HS256
+    #[serde(default)]
+    pub secret: String,
+    /// PEM RSA private key; required for RS256
+    #[serde(default)]
+    pub private_key_path: Option<String>,
+    /// PEM RSA public key matching `private_key_path`; required for RS256
+    #[serde(default)]
+    pub public_key_path: Option<String>,
+}
+
+impl AuthConfig {
+    /// Audiences a presented token may carry to be accepted by this service
+    pub fn accepted_audiences(&self) -> Vec<String> {
+        if self.jwt_accepted_audiences.is_empty() {
+            vec![self.jwt_audience.clone()]
+        } else {
+            self.jwt_accepted_audiences.clone()
+        }
+    }
+}
+
+fn default_jwt_issuer() -> String {
+    "scalable-rust-api".to_string()
+}
+
+fn default_jwt_audience() -> String {
+    "api".to_string()
+}
+
+fn default_jwt_key_id() -> String {
+    "default".to_string()
+}
+
+fn default_jwt_key_retirement_grace_period() -> u64 {
+    3600
+}
+
+fn default_max_concurrent_logins() -> usize {
+    16
+}
+
+fn default_inactivity_batch_size() -> i64 {
+    100
+}
+
+fn default_max_roles_per_check() -> usize {
+    32
+}
+
+fn default_impersonation_token_ttl() -> u64 {
+    900
+}
+
+fn default_password_history_size() -> usize {
+    5
+}
+
+fn default_email_change_token_ttl() -> u64 {
+    86400
+}
+
+fn default_password_reset_token_ttl() -> u64 {
+    3600
+}
+
+fn default_email_sends_per_address_per_hour() -> u32 {
+    3
+}
+
+fn default_email_sends_per_client_per_hour() -> u32 {
+    10
+}
+
+fn default_mfa_challenge_ttl() -> u64 {
+    300
+}
+
+fn default_mfa_max_attempts() -> i32 {
+    5
+}
+
+fn default_max_access_token_lifetime() -> u64 {
+    86_400
+}
+
+fn default_max_refresh_token_lifetime() -> u64 {
+    7_776_000
+}
+
+fn default_session_ttl() -> u64 {
+    2_592_000
+}
+
+fn default_refresh_token_leeway() -> u64 {
+    30
+}
+
+fn default_token_validation_timeout_ms() -> u64 {
+    500
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedisConfig {
     pub url: String,
     pub pool_size: u32,
+    /// Seconds products read from Postgres are cached in Redis; 0 disables the cache
+    #[serde(default = "default_product_cache_ttl")]
+    pub product_cache_ttl: u64,
+    /// Milliseconds a cache call may take before it counts as a failure
+    #[serde(default = "default_cache_timeout_ms")]
+    pub cache_timeout_ms: u64,
+    /// While Redis is unavailable, treat cache reads as misses and skip cache writes so
+    /// requests go to the database; when false they fail with 503 instead
+    #[serde(default = "default_cache_fail_open")]
+    pub cache_fail_open: bool,
+}
+
+fn default_product_cache_ttl() -> u64 {
+    60
+}
+
+fn default_cache_timeout_ms() -> u64 {
+    100
+}
+
+fn default_cache_fail_open() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitoringConfig {
     pub prometheus_port: u16,
     pub jaeger_endpoint: Option<String>,
+    /// Seconds between refreshes of the cached aggregate stats
+    #[serde(default = "default_stats_refresh_interval")]
+    pub stats_refresh_interval: u64,
+    /// Seconds between reloads of the feature flag cache from its backing store
+    #[serde(default = "default_feature_flag_refresh_interval")]
+    pub feature_flag_refresh_interval: u64,
+    /// Seconds last-known flags keep being served while the flag store is unavailable
+    #[serde(default = "default_feature_flag_max_staleness")]
+    pub feature_flag_max_staleness: u64,
+    /// Record sampled flag evaluations per user, queryable by admins for debugging
+    /// experiment exposure. Off by default because of the write volume.
+    #[serde(default)]
+    pub flag_evaluation_log: bool,
+    /// Fraction of evaluations (0.0-1.0) recorded while `flag_evaluation_log` is on
+    #[serde(default = "default_flag_evaluation_sample_rate")]
+    pub flag_evaluation_sample_rate: f64,
+    /// Seconds recorded evaluations are kept
+    #[serde(default = "default_flag_evaluation_retention")]
+    pub flag_evaluation_retention: u64,
+    /// Seconds between outbox relay passes
+    #[serde(default = "default_outbox_relay_interval")]
+    pub outbox_relay_interval: u64,
+    /// Outbox events dispatched per relay pass
+    #[serde(default = "default_outbox_batch_size")]
+    pub outbox_batch_size: i64,
+    /// Seconds to wait before each retry of a failed outbox event; once every retry has
+    /// failed the event is dead-lettered and operators are notified
+    #[serde(default = "default_outbox_retry_schedule")]
+    pub outbox_retry_schedule: Vec<u64>,
+    /// Where feature flags are stored
+    #[serde(default)]
+    pub feature_flag_backend: FeatureFlagBackend,
+    /// Flags seeded into the flag store at startup, so each environment's config file can
+    /// start flags differently. Flags already in the store are left as they are.
+    #[serde(default = "default_feature_flags")]
+    pub default_feature_flags: Vec<FeatureFlagDefault>,
+    /// Initial audit switch per action name, seeded as `audit.<action>` feature flags so
+    /// they can be toggled at runtime; unlisted actions are always audited
+    #[serde(default)]
+    pub audited_actions: HashMap<String, bool>,
+    /// Seconds between dependency probes and degradation checks
+    #[serde(default = "default_dependency_check_interval")]
+    pub dependency_check_interval: u64,
+    /// p99 latency above which a dependency is reported as degraded
+    #[serde(default = "default_dependency_latency_threshold_ms")]
+    pub dependency_latency_threshold_ms: u64,
+    /// Fraction of failed calls (0.0-1.0) above which a dependency is reported as degraded
+    #[serde(default = "default_dependency_error_rate_threshold")]
+    pub dependency_error_rate_threshold: f64,
+}
+
+/// Storage for feature flags; evaluations are always served from an in-memory cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureFlagBackend {
+    /// Per-process; admin changes are lost on restart
+    #[default]
+    Memory,
+    /// The `feature_flags` table, shared between instances and kept across restarts
+    Database,
+}
+
+/// Initial state of a feature flag, as seeded from config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureFlagDefault {
+    pub name: String,
+    pub enabled: bool,
+    #[serde(default = "default_rollout_percentage")]
+    pub rollout_percentage: f32,
+    #[serde(default)]
+    pub conditions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub variants: Vec<FlagVariant>,
+}
+
+fn default_rollout_percentage() -> f32 {
+    100.0
+}
+
+/// Flags seeded when the config names none
+fn default_feature_flags() -> Vec<FeatureFlagDefault> {
+    vec![
+        FeatureFlagDefault {
+            name: "user_registration".to_string(),
+            enabled: true,
+            rollout_percentage: 100.0,
+            conditions: None,
+            depends_on: Vec::new(),
+            variants: Vec::new(),
+        },
+        FeatureFlagDefault {
+            name: "beta_features".to_string(),
+            enabled: false,
+            rollout_percentage: 10.0,
+            conditions: Some(serde_json::json!({
+                "user_tier": ["premium", "enterprise"]
+            })),
+            depends_on: Vec::new(),
+            variants: Vec::new(),
+        },
+        FeatureFlagDefault {
+            name: "advanced_analytics".to_string(),
+            enabled: true,
+            rollout_percentage: 50.0,
+            conditions: None,
+            depends_on: vec!["beta_features".to_string()],
+            variants: Vec::new(),
+        },
+    ]
+}
+
+fn default_stats_refresh_interval() -> u64 {
+    300
+}
+
+fn default_feature_flag_refresh_interval() -> u64 {
+    30
+}
+
+fn default_feature_flag_max_staleness() -> u64 {
+    600
+}
+
+fn default_flag_evaluation_sample_rate() -> f64 {
+    0.1
+}
+
+fn default_flag_evaluation_retention() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_outbox_relay_interval() -> u64 {
+    5
+}
+
+fn default_outbox_batch_size() -> i64 {
+    100
+}
+
+fn default_outbox_retry_schedule() -> Vec<u64> {
+    vec![60, 300, 1800, 7200]
+}
+
+fn default_dependency_check_interval() -> u64 {
+    30
+}
+
+fn default_dependency_latency_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_dependency_error_rate_threshold() -> f64 {
+    0.1
+}
+
+/// Transport used for notifications meant for humans (lockouts, incidents, ...)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+pub enum NotificationConfig {
+    /// Write notifications to the application log
+    #[default]
+    Log,
+    Email {
+        smtp_host: String,
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        smtp_username: Option<String>,
+        smtp_password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+    Slack {
+        webhook_url: String,
+    },
+}
+
+fn default_smtp_port() -> u16 {
+    587
 }
 
 impl Config {
@@ -57,8 +843,39 @@ impl Config {
             .build()?;
 
         let config: Config = config.try_deserialize()?;
+        config.validate()?;
         Ok(config)
     }
+
+    /// Reject settings that deserialize but can't work
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.server.max_concurrent_requests_per_user == 0 {
+            return Err(anyhow::anyhow!("server.max_concurrent_requests_per_user must be at least 1").into());
+        }
+        if self.auth.max_sessions_per_user == Some(0) {
+            return Err(anyhow::anyhow!("auth.max_sessions_per_user must be at least 1 when set").into());
+        }
+        // Otherwise cached flags would expire before the next refresh could renew them
+        if self.monitoring.feature_flag_refresh_interval > self.monitoring.feature_flag_max_staleness {
+            return Err(anyhow::anyhow!(
+                "monitoring.feature_flag_refresh_interval ({}s) must not exceed \
+                 monitoring.feature_flag_max_staleness ({}s)",
+                self.monitoring.feature_flag_refresh_interval,
+                self.monitoring.feature_flag_max_staleness
+            )
+            .into());
+        }
+        // Half a login would otherwise be dropped and email sent unauthenticated
+        if let NotificationConfig::Email { smtp_username, smtp_password, .. } = &self.notifications {
+            if smtp_username.is_some() != smtp_password.is_some() {
+                return Err(anyhow::anyhow!(
+                    "notifications.smtp_username and notifications.smtp_password must be set together"
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for Config {
@@ -68,6 +885,32 @@ impl Default for Config {
                 host: "0.0.0.0".to_string(),
                 port: 8080,
                 workers: None,
+                max_concurrent_requests_per_user: default_max_concurrent_requests_per_user(),
+                body_read_timeout: default_body_read_timeout(),
+                max_streamed_body_bytes: default_max_streamed_body_bytes(),
+                enhanced_profile_requests_per_minute: default_enhanced_profile_requests_per_minute(),
+                request_cost_budget_per_minute: None,
+                request_id_format: RequestIdFormat::default(),
+                response_signing_secret: None,
+                signed_response_paths: Vec::new(),
+                checksum_verified_paths: Vec::new(),
+                correlation_id_reuse_threshold: default_correlation_id_reuse_threshold(),
+                correlation_id_tracking_capacity: default_correlation_id_tracking_capacity(),
+                tls_policy: None,
+                rate_limit: None,
+                resource_rate_limits: Vec::new(),
+                max_response_bytes: default_max_response_bytes(),
+                null_fields: NullFieldPolicy::default(),
+                unsupported_accept: UnsupportedAcceptPolicy::default(),
+                unknown_json_fields: UnknownFieldPolicy::default(),
+                timestamp_format: TimestampFormat::default(),
+                server_timing: false,
+                public_url: default_public_url(),
+                export_dir: None,
+                export_url_secret: env::var("EXPORT_URL_SECRET").ok(),
+                export_url_ttl: default_export_url_ttl(),
+                export_retention: default_export_retention(),
+                export_poll_interval: default_export_poll_interval(),
             },
             database: DatabaseConfig {
                 url: env::var("DATABASE_URL")
@@ -76,22 +919,169 @@ impl Default for Config {
                 min_connections: 1,
                 acquire_timeout: 30,
                 idle_timeout: 600,
+                explain_slow_queries: false,
+                slow_query_threshold_ms: default_slow_query_threshold_ms(),
+                pool_shed_threshold: None,
+                application_name: default_application_name(),
+                health_check_connections: default_health_check_connections(),
+                warm_up_timeout: None,
+                index_advisories: default_index_advisories(),
+                product_backend: ProductBackendConfig::default(),
             },
             auth: AuthConfig {
                 jwt_secret: env::var("JWT_SECRET")
-                    .unwrap_or_else(|_| "your-super-secret-jwt-key".to_string()),
+                    .unwrap_or_else(|_| INSECURE_DEFAULT_JWT_SECRET.to_string()),
+                algorithm: JwtAlgorithm::default(),
+                private_key_path: None,
+                public_key_path: None,
                 jwt_expiration: 3600, // 1 hour
+                max_access_token_lifetime: default_max_access_token_lifetime(),
+                max_refresh_token_lifetime: default_max_refresh_token_lifetime(),
                 bcrypt_cost: 12,
+                jwt_issuer: default_jwt_issuer(),
+                jwt_audience: default_jwt_audience(),
+                jwt_accepted_audiences: Vec::new(),
+                max_concurrent_logins: default_max_concurrent_logins(),
+                inactivity_deactivation_days: None,
+                inactivity_exempt_usernames: Vec::new(),
+                inactivity_batch_size: default_inactivity_batch_size(),
+                role_permissions: HashMap::new(),
+                max_roles_per_check: default_max_roles_per_check(),
+                impersonation_token_ttl: default_impersonation_token_ttl(),
+                service_api_keys: Vec::new(),
+                jwt_key_id: default_jwt_key_id(),
+                jwt_standby_key: None,
+                jwt_key_retirement_grace_period: default_jwt_key_retirement_grace_period(),
+                expose_auth_debug: false,
+                password_history_size: default_password_history_size(),
+                email_change_token_ttl: default_email_change_token_ttl(),
+                password_reset_token_ttl: default_password_reset_token_ttl(),
+                email_sends_per_address_per_hour: default_email_sends_per_address_per_hour(),
+                email_sends_per_client_per_hour: default_email_sends_per_client_per_hour(),
+                mfa_challenge_ttl: default_mfa_challenge_ttl(),
+                mfa_max_attempts: default_mfa_max_attempts(),
+                max_sessions_per_user: None,
+                session_limit_policy: SessionLimitPolicy::default(),
+                session_ttl: default_session_ttl(),
+                refresh_token_leeway: default_refresh_token_leeway(),
+                token_blacklist_fail_open: false,
+                token_validation_timeout_ms: default_token_validation_timeout_ms(),
             },
             redis: RedisConfig {
                 url: env::var("REDIS_URL")
                     .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
                 pool_size: 10,
+                product_cache_ttl: default_product_cache_ttl(),
+                cache_timeout_ms: default_cache_timeout_ms(),
+                cache_fail_open: default_cache_fail_open(),
             },
             monitoring: MonitoringConfig {
                 prometheus_port: 9090,
                 jaeger_endpoint: env::var("JAEGER_ENDPOINT").ok(),
+                stats_refresh_interval: default_stats_refresh_interval(),
+                feature_flag_refresh_interval: default_feature_flag_refresh_interval(),
+                feature_flag_max_staleness: default_feature_flag_max_staleness(),
+                flag_evaluation_log: false,
+                flag_evaluation_sample_rate: default_flag_evaluation_sample_rate(),
+                flag_evaluation_retention: default_flag_evaluation_retention(),
+                outbox_relay_interval: default_outbox_relay_interval(),
+                outbox_batch_size: default_outbox_batch_size(),
+                outbox_retry_schedule: default_outbox_retry_schedule(),
+                feature_flag_backend: FeatureFlagBackend::default(),
+                default_feature_flags: default_feature_flags(),
+                audited_actions: HashMap::new(),
+                dependency_check_interval: default_dependency_check_interval(),
+                dependency_latency_threshold_ms: default_dependency_latency_threshold_ms(),
+                dependency_error_rate_threshold: default_dependency_error_rate_threshold(),
+            },
+            notifications: NotificationConfig::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection(config: &Config) -> String {
+        config.validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn a_zero_concurrency_cap_is_rejected() {
+        let mut config = Config::default();
+        config.server.max_concurrent_requests_per_user = 0;
+        assert!(rejection(&config).contains("max_concurrent_requests_per_user"));
+    }
+
+    #[test]
+    fn a_zero_session_limit_is_rejected() {
+        let mut config = Config::default();
+        config.auth.max_sessions_per_user = Some(0);
+        assert!(rejection(&config).contains("max_sessions_per_user"));
+
+        config.auth.max_sessions_per_user = Some(1);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn smtp_credentials_must_be_set_together() {
+        let email = |smtp_username: Option<&str>, smtp_password: Option<&str>| Config {
+            notifications: NotificationConfig::Email {
+                smtp_host: "smtp.example.com".to_string(),
+                smtp_port: default_smtp_port(),
+                smtp_username: smtp_username.map(str::to_string),
+                smtp_password: smtp_password.map(str::to_string),
+                from: "api@example.com".to_string(),
+                to: vec!["ops@example.com".to_string()],
             },
+            ..Config::default()
+        };
+
+        assert!(rejection(&email(Some("api"), None)).contains("smtp_password"));
+        assert!(rejection(&email(None, Some("secret"))).contains("smtp_username"));
+        assert!(email(Some("api"), Some("secret")).validate().is_ok());
+        assert!(email(None, None).validate().is_ok());
+    }
+
+    #[test]
+    fn flags_must_refresh_within_their_max_staleness() {
+        let mut config = Config::default();
+        config.monitoring.feature_flag_max_staleness = 60;
+
+        config.monitoring.feature_flag_refresh_interval = 61;
+        assert!(rejection(&config).contains("feature_flag_refresh_interval"));
+
+        config.monitoring.feature_flag_refresh_interval = 60;
+        assert!(config.validate().is_ok());
+    }
+
+    fn environment_config(environment: &str) -> Config {
+        let path = format!("{}/../../config/{}.yaml", env!("CARGO_MANIFEST_DIR"), environment);
+        config::Config::builder()
+            .add_source(config::File::with_name(&path))
+            .build()
+            .and_then(config::Config::try_deserialize)
+            .unwrap_or_else(|e| panic!("config/{}.yaml: {}", environment, e))
+    }
+
+    #[test]
+    fn environment_config_files_load_and_validate() {
+        for environment in ["development", "staging", "production"] {
+            assert!(environment_config(environment).validate().is_ok(), "config/{}.yaml", environment);
         }
     }
+
+    #[test]
+    fn staging_rolls_beta_features_out_to_everyone() {
+        let config = environment_config("staging");
+        let beta = config.monitoring.default_feature_flags.iter().find(|flag| flag.name == "beta_features").unwrap();
+        assert!(beta.enabled);
+        assert_eq!(beta.rollout_percentage, 100.0);
+    }
 }
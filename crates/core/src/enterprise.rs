@@ -1,14 +1,20 @@
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use uuid::Uuid;
+use validator::Validate;
 use ipnetwork::IpNetwork;
 
-/// Circuit breaker configuration for fault tolerance
+use crate::error::{ApiError, Result};
+
+/// Circuit breaker configuration for fault tolerance.
+/// Built through `CircuitBreakerConfig::builder()` so every instance is valid; deserializing
+/// goes through the builder too.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "CircuitBreakerSettings")]
 pub struct CircuitBreakerConfig {
-    pub failure_threshold: u32,
-    pub recovery_timeout: Duration,
-    pub half_open_max_calls: u32,
+    failure_threshold: u32,
+    recovery_timeout: Duration,
+    half_open_max_calls: u32,
 }
 
 impl Default for CircuitBreakerConfig {
@@ -21,17 +27,205 @@ impl Default for CircuitBreakerConfig {
     }
 }
 
+impl CircuitBreakerConfig {
+    /// Builder starting from the defaults
+    pub fn builder() -> CircuitBreakerConfigBuilder {
+        CircuitBreakerConfigBuilder { config: Self::default() }
+    }
+
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    pub fn recovery_timeout(&self) -> Duration {
+        self.recovery_timeout
+    }
+
+    pub fn half_open_max_calls(&self) -> u32 {
+        self.half_open_max_calls
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfigBuilder {
+    config: CircuitBreakerConfig,
+}
+
+impl CircuitBreakerConfigBuilder {
+    /// Consecutive failures that open the circuit
+    pub fn failure_threshold(mut self, failures: u32) -> Self {
+        self.config.failure_threshold = failures;
+        self
+    }
+
+    /// How long an open circuit rejects calls before letting trial calls through
+    pub fn recovery_timeout(mut self, timeout: Duration) -> Self {
+        self.config.recovery_timeout = timeout;
+        self
+    }
+
+    /// Trial calls allowed while half-open
+    pub fn half_open_max_calls(mut self, calls: u32) -> Self {
+        self.config.half_open_max_calls = calls;
+        self
+    }
+
+    /// Reject settings that would trip on the first call or never recover
+    pub fn build(self) -> Result<CircuitBreakerConfig> {
+        let config = self.config;
+        if config.failure_threshold == 0 {
+            return Err(anyhow::anyhow!("Circuit breaker failure_threshold must be at least 1").into());
+        }
+        if config.recovery_timeout.is_zero() {
+            return Err(anyhow::anyhow!("Circuit breaker recovery_timeout must be non-zero").into());
+        }
+        if config.half_open_max_calls == 0 {
+            return Err(anyhow::anyhow!("Circuit breaker half_open_max_calls must be at least 1").into());
+        }
+        Ok(config)
+    }
+}
+
+/// Unchecked `CircuitBreakerConfig` fields as deserialized, before `build` validates them
+#[derive(Deserialize)]
+struct CircuitBreakerSettings {
+    failure_threshold: u32,
+    recovery_timeout: Duration,
+    half_open_max_calls: u32,
+}
+
+impl TryFrom<CircuitBreakerSettings> for CircuitBreakerConfig {
+    type Error = ApiError;
+
+    fn try_from(settings: CircuitBreakerSettings) -> Result<Self> {
+        CircuitBreakerConfig::builder()
+            .failure_threshold(settings.failure_threshold)
+            .recovery_timeout(settings.recovery_timeout)
+            .half_open_max_calls(settings.half_open_max_calls)
+            .build()
+    }
+}
+
+/// Broad class of an audited action, used to separate security events from routine traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "audit_category", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    /// Authentication, authorization and account lifecycle
+    Security,
+    /// Administrative configuration changes
+    Admin,
+    /// Creation or modification of business data
+    DataChange,
+    /// Routine reads
+    Access,
+    /// Actions taken by background jobs
+    System,
+}
+
+/// Importance of an audited action; ordered so filters can ask for "at least" a level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "audit_severity", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Optional filters applied by audit trail queries
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditFilter {
+    pub category: Option<AuditCategory>,
+    /// Only entries at or above this severity
+    pub min_severity: Option<AuditSeverity>,
+}
+
+/// Range and filters of an exported audit report; `from` is inclusive, `to` exclusive,
+/// both RFC 3339 (e.g. `2024-01-01T00:00:00Z`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReportQuery {
+    #[serde(with = "crate::timestamp")]
+    pub from: time::OffsetDateTime,
+    #[serde(with = "crate::timestamp")]
+    pub to: time::OffsetDateTime,
+    #[serde(default)]
+    pub format: AuditReportFormat,
+    pub user_id: Option<Uuid>,
+    pub resource_type: Option<String>,
+    pub category: Option<AuditCategory>,
+}
+
+/// Representation of an exported audit report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditReportFormat {
+    /// Every matching entry, one row each
+    #[default]
+    Csv,
+    /// Entry counts by category, severity and action
+    Pdf,
+}
+
+/// Entry counts over an audit report's range, largest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditReportSummary {
+    pub total: i64,
+    pub by_category: Vec<(AuditCategory, i64)>,
+    pub by_severity: Vec<(AuditSeverity, i64)>,
+    pub top_actions: Vec<(String, i64)>,
+}
+
+/// What an export job produces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExportRequest {
+    AuditReport(AuditReportQuery),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "export_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Ready,
+    Failed,
+}
+
+/// A background export and, once ready, the file it produced
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportJob {
+    pub id: Uuid,
+    pub requested_by: Uuid,
+    pub request: ExportRequest,
+    pub status: ExportStatus,
+    #[serde(with = "crate::timestamp")]
+    pub created_at: time::OffsetDateTime,
+    #[serde(with = "crate::timestamp::option")]
+    pub completed_at: Option<time::OffsetDateTime>,
+    /// When the file is deleted; download links never outlive it
+    #[serde(with = "crate::timestamp::option")]
+    pub expires_at: Option<time::OffsetDateTime>,
+    pub error: Option<String>,
+    pub filename: String,
+    pub content_type: String,
+}
+
 /// Audit log entry for tracking user actions
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct AuditLog {
     pub id: Uuid,
     pub user_id: Option<Uuid>,
     pub action: String,
+    pub category: AuditCategory,
+    pub severity: AuditSeverity,
     pub resource_type: String,
     pub resource_id: Option<Uuid>,
     pub ip_address: IpNetwork,
     pub user_agent: Option<String>,
     pub details: serde_json::Value,
+    #[serde(with = "crate::timestamp")]
     pub created_at: time::OffsetDateTime,
 }
 
@@ -44,6 +238,7 @@ pub struct ApiResponse<T> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponseMetadata {
+    #[serde(with = "crate::timestamp")]
     pub timestamp: time::OffsetDateTime,
     pub request_id: String,
     pub version: String,
@@ -54,6 +249,7 @@ pub struct ResponseMetadata {
 pub struct RateLimitInfo {
     pub limit: u32,
     pub remaining: u32,
+    #[serde(with = "crate::timestamp")]
     pub reset_at: time::OffsetDateTime,
 }
 
@@ -82,6 +278,7 @@ pub struct PerformanceMetrics {
     pub memory_usage_mb: f64,
     pub db_query_time_ms: Option<f64>,
     pub cache_hit: bool,
+    #[serde(with = "crate::timestamp")]
     pub timestamp: time::OffsetDateTime,
 }
 
@@ -92,6 +289,173 @@ pub struct FeatureFlag {
     pub enabled: bool,
     pub rollout_percentage: f32,
     pub conditions: Option<serde_json::Value>,
+    /// Flags that must also be enabled for this one to be
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Buckets of a multivariate flag, e.g. for an A/B/C test; empty for on/off flags
+    #[serde(default)]
+    pub variants: Vec<FlagVariant>,
+    #[serde(with = "crate::timestamp")]
     pub created_at: time::OffsetDateTime,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: time::OffsetDateTime,
 }
+
+/// One bucket of a multivariate flag. Users are split between variants in proportion to
+/// their weights, which needn't add up to 100.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlagVariant {
+    pub name: String,
+    pub weight: f32,
+}
+
+/// A new feature flag; names are unique
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateFeatureFlagRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[serde(default)]
+    pub enabled: bool,
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub rollout_percentage: f32,
+    pub conditions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub variants: Vec<FlagVariant>,
+}
+
+/// Replaces a flag's rollout, conditions, dependencies and variants; whether it is
+/// enabled is changed through the toggle endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateFeatureFlagRequest {
+    #[validate(range(min = 0.0, max = 100.0))]
+    pub rollout_percentage: f32,
+    pub conditions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub variants: Vec<FlagVariant>,
+}
+
+/// Flags to evaluate together for the current user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkFlagEvaluationRequest {
+    pub flags: Vec<String>,
+    /// Extra attributes for conditions; must be an object. Attributes derived from the
+    /// caller's token, such as `user_tier`, take precedence.
+    pub context: Option<serde_json::Value>,
+}
+
+/// A proposed rollout percentage to preview against the live flag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloutSimulationRequest {
+    pub rollout_percentage: f32,
+    /// Most recently active users to sample; defaults to, and is capped at, the server maximum
+    pub sample_size: Option<i64>,
+}
+
+/// How a sample of users would be bucketed under the live and proposed rollouts. Only the
+/// percentage rollout is simulated; conditions, dependencies and the enabled switch aren't.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloutSimulation {
+    pub flag_name: String,
+    pub current_percentage: f32,
+    pub proposed_percentage: f32,
+    pub sampled_users: usize,
+    pub in_rollout_now: usize,
+    pub in_rollout_after: usize,
+    /// Sampled users outside the live rollout who would be inside the proposed one
+    pub newly_enabled: usize,
+    pub newly_disabled: usize,
+}
+
+/// One recorded feature flag evaluation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagEvaluation {
+    pub flag_name: String,
+    pub user_id: String,
+    pub enabled: bool,
+    #[serde(with = "crate::timestamp")]
+    pub evaluated_at: time::OffsetDateTime,
+}
+
+/// Range of a flag evaluation history query; `from` is inclusive, `to` exclusive,
+/// both RFC 3339
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagEvaluationQuery {
+    #[serde(with = "crate::timestamp")]
+    pub from: time::OffsetDateTime,
+    #[serde(with = "crate::timestamp")]
+    pub to: time::OffsetDateTime,
+    /// Only evaluations of this flag
+    pub flag_name: Option<String>,
+}
+
+/// A flag's place in the dependency graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlagDependencies {
+    pub name: String,
+    pub enabled: bool,
+    pub depends_on: Vec<String>,
+    /// Flags that list this one in `depends_on`
+    pub dependents: Vec<String>,
+}
+
+/// Precomputed aggregate counts served by the admin stats endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateStats {
+    pub total_users: i64,
+    pub active_users: i64,
+    pub total_products: i64,
+    pub active_products: i64,
+    #[serde(with = "crate::timestamp")]
+    pub computed_at: time::OffsetDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rejection(builder: CircuitBreakerConfigBuilder) -> String {
+        builder.build().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn builders_reject_settings_that_trip_at_once_or_never_recover() {
+        let builder = CircuitBreakerConfig::builder;
+        assert!(rejection(builder().failure_threshold(0)).contains("failure_threshold"));
+        assert!(rejection(builder().recovery_timeout(Duration::ZERO)).contains("recovery_timeout"));
+        assert!(rejection(builder().half_open_max_calls(0)).contains("half_open_max_calls"));
+    }
+
+    #[test]
+    fn builders_keep_valid_settings() {
+        let config = CircuitBreakerConfig::builder()
+            .failure_threshold(1)
+            .recovery_timeout(Duration::from_millis(1))
+            .half_open_max_calls(1)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.failure_threshold(), 1);
+        assert_eq!(config.recovery_timeout(), Duration::from_millis(1));
+        assert_eq!(config.half_open_max_calls(), 1);
+    }
+
+    #[test]
+    fn deserializing_validates_through_the_builder() {
+        let config = CircuitBreakerConfig::builder().failure_threshold(2).build().unwrap();
+        let round_trip: CircuitBreakerConfig = serde_json::from_value(serde_json::to_value(&config).unwrap()).unwrap();
+        assert_eq!(round_trip.failure_threshold(), 2);
+
+        let zero_threshold = json!({
+            "failure_threshold": 0,
+            "recovery_timeout": {"secs": 60, "nanos": 0},
+            "half_open_max_calls": 3,
+        });
+        let error = serde_json::from_value::<CircuitBreakerConfig>(zero_threshold).unwrap_err();
+        assert!(error.to_string().contains("failure_threshold"), "{}", error);
+    }
+}
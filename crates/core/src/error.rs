@@ -1,13 +1,21 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
 use thiserror::Error;
+use validator::{ValidationErrors, ValidationErrorsKind};
 
 pub type Result<T> = std::result::Result<T, ApiError>;
 
+/// RFC 7807 media type clients may request instead of the default error envelope
+pub const PROBLEM_JSON_MEDIA_TYPE: &str = "application/problem+json";
+
+/// Base of the stable `type` URIs used in problem+json responses
+const PROBLEM_TYPE_BASE: &str = "/problems";
+
 #[derive(Error, Debug)]
 pub enum ApiError {
     #[error("Database error: {0}")]
@@ -16,9 +24,16 @@ pub enum ApiError {
     #[error("Authentication error: {0}")]
     Unauthorized(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Every failed rule of a validated request, so clients can flag all fields at once
+    #[error("Validation error: {} invalid field(s)", .0.len())]
+    InvalidFields(Vec<FieldError>),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
@@ -31,47 +46,214 @@ pub enum ApiError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    /// None of the client's `Accept` types can be produced; carries the ones that can
+    #[error("Not acceptable: supported types are {}", .0.join(", "))]
+    NotAcceptable(Vec<&'static str>),
+
     #[error("Conflict: {0}")]
     Conflict(String),
 
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    /// The serialized response would exceed `server.max_response_bytes`; carries the limit
+    #[error("Response too large: exceeds {0} bytes")]
+    ResponseTooLarge(usize),
+
+    /// The request body, or one element of a streamed body, exceeds the limit it carries
+    #[error("Payload too large: exceeds {0} bytes")]
+    PayloadTooLarge(usize),
+
     #[error("Configuration error: {0}")]
     Config(#[from] config::ConfigError),
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let (status, error_message, error_code) = match self {
+impl ApiError {
+    /// HTTP status, client-facing message and stable error code for this error
+    pub fn parts(&self) -> (StatusCode, String, &'static str) {
+        match self {
+            ApiError::Database(sqlx::Error::PoolTimedOut) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Database is overloaded, please retry shortly".to_string(),
+                "DATABASE_UNAVAILABLE",
+            ),
             ApiError::Database(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Database error occurred".to_string(),
                 "DATABASE_ERROR",
             ),
-            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg, "UNAUTHORIZED"),
-            ApiError::Validation(msg) => (StatusCode::BAD_REQUEST, msg, "VALIDATION_ERROR"),
-            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg, "NOT_FOUND"),
-            ApiError::RateLimitExceeded(msg) => (StatusCode::TOO_MANY_REQUESTS, msg, "RATE_LIMIT_EXCEEDED"),
+            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg.clone(), "UNAUTHORIZED"),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone(), "FORBIDDEN"),
+            ApiError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone(), "VALIDATION_ERROR"),
+            ApiError::InvalidFields(fields) => {
+                let mut names: Vec<&str> = fields.iter().map(|f| f.field.as_str()).collect();
+                names.dedup();
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Validation failed for: {}", names.join(", ")),
+                    "VALIDATION_ERROR",
+                )
+            }
+            ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone(), "NOT_FOUND"),
+            ApiError::RateLimitExceeded(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone(), "RATE_LIMIT_EXCEEDED"),
             ApiError::Internal(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error".to_string(),
                 "INTERNAL_ERROR",
             ),
-            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg, "BAD_REQUEST"),
-            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg, "CONFLICT"),
+            ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone(), "BAD_REQUEST"),
+            ApiError::NotAcceptable(supported) => (
+                StatusCode::NOT_ACCEPTABLE,
+                format!("None of the requested media types can be served; supported: {}", supported.join(", ")),
+                "NOT_ACCEPTABLE",
+            ),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone(), "CONFLICT"),
+            ApiError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone(), "SERVICE_UNAVAILABLE"),
+            ApiError::ResponseTooLarge(limit) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!(
+                    "Response would exceed the maximum of {} bytes; request a smaller page or use a streaming export",
+                    limit
+                ),
+                "RESPONSE_TOO_LARGE",
+            ),
+            ApiError::PayloadTooLarge(limit) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Request body exceeds the maximum of {} bytes", limit),
+                "PAYLOAD_TOO_LARGE",
+            ),
             ApiError::Config(_) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Configuration error".to_string(), 
                 "CONFIG_ERROR"
             ),
+        }
+    }
+
+    /// Per-field failures carried by this error, if any
+    pub fn field_errors(&self) -> &[FieldError] {
+        match self {
+            ApiError::InvalidFields(fields) => fields,
+            _ => &[],
+        }
+    }
+}
+
+/// A single failed validation rule; `field` is a dotted path for nested values
+/// (e.g. `items[2].price`)
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        collect_field_errors(&errors, "", &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        ApiError::InvalidFields(fields)
+    }
+}
+
+fn collect_field_errors(errors: &ValidationErrors, prefix: &str, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
         };
 
-        let body = Json(json!({
+        match kind {
+            ValidationErrorsKind::Field(failures) => {
+                out.extend(failures.iter().map(|failure| FieldError {
+                    field: path.clone(),
+                    code: failure.code.to_string(),
+                    message: failure
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| format!("{} is invalid ({})", path, failure.code)),
+                }))
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(nested, &path, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(nested, &format!("{}[{}]", path, index), out);
+                }
+            }
+        }
+    }
+}
+
+/// Rendered error attached to every error response's extensions so middleware can
+/// re-encode it in another representation
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    pub status: StatusCode,
+    pub message: String,
+    pub code: &'static str,
+    pub fields: Vec<FieldError>,
+}
+
+impl ErrorDetails {
+    /// Stable problem `type` URI derived from the error code, e.g. `/problems/not-found`
+    pub fn problem_type(&self) -> String {
+        format!("{}/{}", PROBLEM_TYPE_BASE, self.code.to_lowercase().replace('_', "-"))
+    }
+
+    /// Render as an RFC 7807 `application/problem+json` response
+    pub fn to_problem_response(&self, instance: Option<&str>) -> Response {
+        let mut body = json!({
+            "type": self.problem_type(),
+            "title": self.status.canonical_reason().unwrap_or("Error"),
+            "status": self.status.as_u16(),
+            "detail": self.message,
+            "code": self.code,
+        });
+        if let Some(instance) = instance {
+            body["instance"] = json!(instance);
+        }
+        if !self.fields.is_empty() {
+            body["errors"] = json!(self.fields);
+        }
+
+        let mut response = (self.status, Json(body)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON_MEDIA_TYPE),
+        );
+        response
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error_message, error_code) = self.parts();
+        let fields = self.field_errors().to_vec();
+
+        let mut body = json!({
             "error": {
                 "code": error_code,
                 "message": error_message,
                 "timestamp": time::OffsetDateTime::now_utc(),
             }
-        }));
+        });
+        if !fields.is_empty() {
+            body["error"]["fields"] = json!(fields);
+        }
+        if let ApiError::NotAcceptable(supported) = &self {
+            body["error"]["supported_types"] = json!(supported);
+        }
 
-        (status, body).into_response()
+        let mut response = (status, Json(body)).into_response();
+        response.extensions_mut().insert(ErrorDetails {
+            status,
+            message: error_message,
+            code: error_code,
+            fields,
+        });
+        response
     }
 }
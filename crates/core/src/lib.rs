@@ -1,6 +1,8 @@
 pub mod config;
 pub mod error;
 pub mod models;
+pub mod server_timing;
+pub mod timestamp;
 pub mod traits;
 pub mod enterprise;
 
@@ -1,26 +1,218 @@
-use serde::{Deserialize, Serialize};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use time::OffsetDateTime;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidateEmail, ValidateLength, ValidationError};
+
+use crate::error::ApiError;
+use crate::timestamp::Json;
+
+/// Normalized (trimmed and lowercased) email address.
+///
+/// Parsing validates it. Deserializing only normalizes, so that request types can report
+/// an invalid address from `validate()` alongside their other fields, through
+/// `#[validate(custom(function = "validate_email"))]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(from = "String", into = "String")]
+#[sqlx(transparent)]
+pub struct Email(String);
+
+impl Email {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Validation rule for `Email` fields of requests
+pub fn validate_email(email: &Email) -> Result<(), ValidationError> {
+    if email.0.validate_email() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("email").with_message("Invalid email address".into()))
+    }
+}
+
+impl FromStr for Email {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let email = Self::from(s.to_string());
+        validate_email(&email).map_err(into_api_error)?;
+        Ok(email)
+    }
+}
+
+impl From<String> for Email {
+    fn from(value: String) -> Self {
+        Self(value.trim().to_lowercase())
+    }
+}
+
+impl From<Email> for String {
+    fn from(email: Email) -> Self {
+        email.0
+    }
+}
+
+impl AsRef<str> for Email {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Names that can't be registered because they could be mistaken for the system or staff
+const RESERVED_USERNAMES: &[&str] = &[
+    "admin", "administrator", "root", "system", "support", "api", "me", "null", "undefined",
+];
+
+/// Username of 3-50 ASCII letters, digits, `.`, `_` or `-`, starting with a letter or
+/// digit, and not a reserved name (compared case-insensitively).
+///
+/// Parsing validates it; deserializing doesn't, as for `Email`. Requests check it with
+/// `#[validate(custom(function = "validate_username"))]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(from = "String", into = "String")]
+#[sqlx(transparent)]
+pub struct Username(String);
+
+impl Username {
+    pub const MIN_LENGTH: usize = 3;
+    pub const MAX_LENGTH: usize = 50;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Validation rule for `Username` fields of requests
+pub fn validate_username(username: &Username) -> Result<(), ValidationError> {
+    let name = username.as_str();
+    if !(Username::MIN_LENGTH..=Username::MAX_LENGTH).contains(&name.len()) {
+        return Err(ValidationError::new("length").with_message(
+            format!(
+                "Username must be between {} and {} characters",
+                Username::MIN_LENGTH,
+                Username::MAX_LENGTH
+            )
+            .into(),
+        ));
+    }
+
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        return Err(ValidationError::new("username").with_message(
+            "Username may only contain letters, digits, '.', '_' and '-', and must start with a letter or digit".into(),
+        ));
+    }
+
+    if RESERVED_USERNAMES.iter().any(|reserved| name.eq_ignore_ascii_case(reserved)) {
+        return Err(ValidationError::new("reserved").with_message("Username is reserved".into()));
+    }
+
+    Ok(())
+}
+
+impl FromStr for Username {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let username = Self::from(s.to_string());
+        validate_username(&username).map_err(into_api_error)?;
+        Ok(username)
+    }
+}
+
+impl From<String> for Username {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Username> for String {
+    fn from(username: Username) -> Self {
+        username.0
+    }
+}
+
+impl AsRef<str> for Username {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A failed rule reported on its own, outside a request's `validate()`
+fn into_api_error(error: ValidationError) -> ApiError {
+    ApiError::Validation(error.message.map_or_else(|| error.code.to_string(), |message| message.to_string()))
+}
+
+/// Tenant visibility applied to repository queries.
+/// A `None` tenant matches only rows without a tenant (single-tenant deployments).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TenantScope {
+    tenant_id: Option<Uuid>,
+    all_tenants: bool,
+}
+
+impl TenantScope {
+    /// Rows owned by `tenant_id` only
+    pub fn tenant(tenant_id: Option<Uuid>) -> Self {
+        Self { tenant_id, all_tenants: false }
+    }
+
+    /// Rows of every tenant; new rows are created under `tenant_id`.
+    /// Reserved for super-admins and internal jobs.
+    pub fn all_tenants(tenant_id: Option<Uuid>) -> Self {
+        Self { tenant_id, all_tenants: true }
+    }
+
+    pub fn tenant_id(&self) -> Option<Uuid> {
+        self.tenant_id
+    }
+
+    pub fn is_all_tenants(&self) -> bool {
+        self.all_tenants
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
-    pub username: String,
-    pub email: String,
+    pub tenant_id: Option<Uuid>,
+    pub username: Username,
+    pub email: Email,
     pub password_hash: String,
     pub is_active: bool,
+    #[serde(with = "crate::timestamp")]
     pub created_at: OffsetDateTime,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: OffsetDateTime,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateUserRequest {
-    #[validate(length(min = 3, max = 50))]
-    pub username: String,
+    #[validate(custom(function = "validate_username"))]
+    pub username: Username,
 
-    #[validate(email)]
-    pub email: String,
+    #[validate(custom(function = "validate_email"))]
+    pub email: Email,
 
     #[validate(length(min = 8))]
     pub password: String,
@@ -28,11 +220,23 @@ pub struct CreateUserRequest {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct UpdateUserRequest {
-    #[validate(length(min = 3, max = 50))]
-    pub username: Option<String>,
+    #[validate(custom(function = "validate_username"))]
+    pub username: Option<Username>,
 
-    #[validate(email)]
-    pub email: Option<String>,
+    #[validate(custom(function = "validate_email"))]
+    pub email: Option<Email>,
+}
+
+/// Start a verified email change; the new address only takes effect once confirmed
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(custom(function = "validate_email"))]
+    pub new_email: Email,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConfirmEmailChangeQuery {
+    pub token: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,7 +245,9 @@ pub struct UserResponse {
     pub username: String,
     pub email: String,
     pub is_active: bool,
+    #[serde(with = "crate::timestamp")]
     pub created_at: OffsetDateTime,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: OffsetDateTime,
 }
 
@@ -49,8 +255,8 @@ impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
             id: user.id,
-            username: user.username,
-            email: user.email,
+            username: user.username.into(),
+            email: user.email.into(),
             is_active: user.is_active,
             created_at: user.created_at,
             updated_at: user.updated_at,
@@ -58,16 +264,40 @@ impl From<User> for UserResponse {
     }
 }
 
+/// Response type whose top-level fields can be picked with a `fields` query parameter
+pub trait Fieldset: Serialize {
+    /// Selectable field names, as serialized
+    const FIELDS: &'static [&'static str];
+}
+
+impl Fieldset for UserResponse {
+    const FIELDS: &'static [&'static str] =
+        &["id", "username", "email", "is_active", "created_at", "updated_at"];
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Product {
     pub id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<Uuid>,
     pub name: String,
     pub description: Option<String>,
     pub price: i64, // Price in cents to avoid floating point issues
     pub category_id: Uuid,
     pub is_active: bool,
+    #[serde(with = "crate::timestamp")]
     pub created_at: OffsetDateTime,
+    #[serde(with = "crate::timestamp")]
     pub updated_at: OffsetDateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::timestamp::option")]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+impl Fieldset for Product {
+    const FIELDS: &'static [&'static str] = &[
+        "id", "tenant_id", "name", "description", "price", "category_id",
+        "is_active", "created_at", "updated_at", "deleted_at",
+    ];
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -84,12 +314,252 @@ pub struct CreateProductRequest {
     pub category_id: Uuid,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateProductRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+
+    #[serde(default, skip_serializing_if = "PatchField::is_undefined")]
+    #[validate(length(max = 1000))]
+    pub description: PatchField<String>,
+
+    #[validate(range(min = 0))]
+    pub price: Option<i64>,
+
+    pub category_id: Option<Uuid>,
+}
+
+/// Three-state field for PATCH requests that distinguishes an omitted field
+/// (leave unchanged) from an explicit `null` (clear the value).
+/// Use with `#[serde(default)]` so omitted fields deserialize as `Undefined`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PatchField<T> {
+    #[default]
+    Undefined,
+    Null,
+    Value(T),
+}
+
+impl<T> PatchField<T> {
+    pub fn is_undefined(&self) -> bool {
+        matches!(self, PatchField::Undefined)
+    }
+
+    /// The new value to store, or `None` when the field should be cleared or is unchanged
+    pub fn as_option(&self) -> Option<&T> {
+        match self {
+            PatchField::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for PatchField<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Only reached when the field is present, so `None` means an explicit null
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => PatchField::Value(value),
+            None => PatchField::Null,
+        })
+    }
+}
+
+impl<T> ValidateLength<u64> for PatchField<T>
+where
+    T: ValidateLength<u64>,
+{
+    fn length(&self) -> Option<u64> {
+        self.as_option().and_then(|value| value.length())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListResponse<T> {
     pub data: Vec<T>,
     pub pagination: PaginationMetadata,
 }
 
+/// Upper bound for any product price, in cents
+pub const MAX_PRODUCT_PRICE: i64 = 100_000_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct PriceUpdate {
+    pub id: Uuid,
+
+    #[validate(range(min = 0, max = MAX_PRODUCT_PRICE))]
+    pub new_price: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkPriceUpdateRequest {
+    pub updates: Vec<PriceUpdate>,
+
+    /// Roll the whole batch back if any item fails; otherwise apply what succeeds
+    #[serde(default = "default_bulk_atomic")]
+    pub atomic: bool,
+}
+
+fn default_bulk_atomic() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub ids: Vec<Uuid>,
+
+    /// Must be `true`; guards against accidental mass deletion
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Newly recorded login session
+#[derive(Debug, Clone)]
+pub struct SessionStart {
+    pub session_id: Uuid,
+    /// Older sessions revoked to keep the user within the session limit
+    pub evicted: Vec<Uuid>,
+}
+
+/// Signing key every instance should sign with, and the key it replaced with the end of
+/// that key's grace period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveSigningKey {
+    pub primary_key_id: String,
+    pub retiring_key_id: String,
+    pub retires_at: OffsetDateTime,
+}
+
+/// Unrevoked, unexpired login session found by its refresh token
+#[derive(Debug, Clone, Copy)]
+pub struct ActiveSession {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// A user's TOTP enrollment
+#[derive(Debug, Clone)]
+pub struct MfaSettings {
+    /// Base32 secret, set on enrollment
+    pub secret: Option<String>,
+    /// Whether logins require a code; only once one has confirmed the secret
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProductImportParams {
+    /// Import nothing if any row is invalid, instead of skipping bad rows
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// Import row that was not created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowIssue {
+    /// 1-based line in the uploaded file
+    pub line: u64,
+    pub reason: String,
+}
+
+/// Result of a CSV product import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProductImportSummary {
+    pub created: usize,
+    /// Rows intentionally left out: blank lines and repeats of an earlier row
+    pub skipped: Vec<ImportRowIssue>,
+    pub errors: Vec<ImportRowIssue>,
+}
+
+/// Outcome of a single item within a bulk operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemStatus {
+    pub index: usize,
+    pub status: u16,
+    pub id: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+/// Per-item results of a bulk operation, returned as `207 Multi-Status`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultiStatus {
+    pub results: Vec<ItemStatus>,
+}
+
+impl MultiStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_success(&mut self, index: usize, status: StatusCode, id: Uuid) {
+        self.results.push(ItemStatus {
+            index,
+            status: status.as_u16(),
+            id: Some(id),
+            error: None,
+        });
+    }
+
+    pub fn push_error(&mut self, index: usize, error: &ApiError) {
+        let (status, message, _) = error.parts();
+        self.results.push(ItemStatus {
+            index,
+            status: status.as_u16(),
+            id: None,
+            error: Some(message),
+        });
+    }
+
+    /// Item that was valid on its own but not applied because its batch was rolled back
+    pub fn push_failed_dependency(&mut self, index: usize, id: Uuid) {
+        self.results.push(ItemStatus {
+            index,
+            status: StatusCode::FAILED_DEPENDENCY.as_u16(),
+            id: Some(id),
+            error: Some("Not applied: batch was rolled back".to_string()),
+        });
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.results.iter().filter(|item| item.error.is_some()).count()
+    }
+}
+
+impl IntoResponse for MultiStatus {
+    fn into_response(self) -> Response {
+        (StatusCode::MULTI_STATUS, Json(self)).into_response()
+    }
+}
+
+/// Newly created resource, returned as `201 Created` with a `Location` header
+#[derive(Debug, Clone)]
+pub struct Created<T> {
+    pub location: String,
+    pub body: T,
+}
+
+impl<T> Created<T> {
+    pub fn new(location: impl Into<String>, body: T) -> Self {
+        Self { location: location.into(), body }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Created<T> {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::CREATED,
+            [(axum::http::header::LOCATION, self.location)],
+            Json(self.body),
+        )
+            .into_response()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginationMetadata {
     pub page: u32,
@@ -112,3 +582,71 @@ impl Default for PaginationParams {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_identities_are_reported_with_the_other_fields() {
+        let request: CreateUserRequest = serde_json::from_value(serde_json::json!({
+            "username": "admin",
+            "email": "not-an-email",
+            "password": "short",
+        }))
+        .unwrap();
+
+        let ApiError::InvalidFields(fields) = ApiError::from(request.validate().unwrap_err()) else {
+            panic!("expected a field report");
+        };
+        let codes: Vec<_> = fields.iter().map(|field| (field.field.as_str(), field.code.as_str())).collect();
+        assert_eq!(codes, vec![("email", "email"), ("password", "length"), ("username", "reserved")]);
+    }
+
+    #[test]
+    fn emails_are_normalized_when_deserialized_and_checked_when_parsed() {
+        let email: Email = serde_json::from_value(serde_json::json!("  Alice@Example.COM ")).unwrap();
+        assert_eq!(email.as_str(), "alice@example.com");
+        assert!(validate_email(&email).is_ok());
+
+        assert!(matches!("alice".parse::<Email>(), Err(ApiError::Validation(_))));
+        assert!(matches!("-alice".parse::<Username>(), Err(ApiError::Validation(_))));
+    }
+
+    /// `T::FIELDS` must name exactly the keys of `value`, which has every optional field set
+    fn assert_fields_match_serialized_keys<T: Fieldset>(value: &T) {
+        let serde_json::Value::Object(object) = serde_json::to_value(value).unwrap() else {
+            panic!("expected a JSON object");
+        };
+        let keys: std::collections::BTreeSet<_> = object.keys().map(String::as_str).collect();
+        let fields: std::collections::BTreeSet<_> = T::FIELDS.iter().copied().collect();
+        assert_eq!(fields, keys);
+        assert_eq!(fields.len(), T::FIELDS.len(), "FIELDS lists a field twice");
+    }
+
+    #[test]
+    fn fieldsets_list_exactly_the_serialized_fields() {
+        let now = OffsetDateTime::now_utc();
+
+        assert_fields_match_serialized_keys(&UserResponse {
+            id: Uuid::new_v4(),
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+        });
+        assert_fields_match_serialized_keys(&Product {
+            id: Uuid::new_v4(),
+            tenant_id: Some(Uuid::new_v4()),
+            name: "Widget".to_string(),
+            description: Some("A widget".to_string()),
+            price: 1_000,
+            category_id: Uuid::new_v4(),
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+            deleted_at: Some(now),
+        });
+    }
+}
@@ -0,0 +1,110 @@
+//! Per-request phase durations for the `Server-Timing` response header. Code running on
+//! the request's task adds to a phase with `record` or a `PhaseTimer`; outside `collect`
+//! both do nothing, so background jobs and spawned tasks aren't measured.
+
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    static TIMINGS: Arc<Mutex<ServerTimings>>;
+}
+
+/// Time spent in each phase of one request, in the order phases were first recorded
+#[derive(Debug, Clone, Default)]
+pub struct ServerTimings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl ServerTimings {
+    fn add(&mut self, phase: &'static str, duration: Duration) {
+        match self.phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += duration,
+            None => self.phases.push((phase, duration)),
+        }
+    }
+
+    /// `Server-Timing` header value, e.g. `auth;dur=0.8, db;dur=12.4, total;dur=15.1`,
+    /// with durations in milliseconds
+    pub fn header_value(&self, total: Duration) -> String {
+        let mut value = String::new();
+        for (phase, duration) in self.phases.iter().chain([("total", total)].iter()) {
+            if !value.is_empty() {
+                value.push_str(", ");
+            }
+            let _ = write!(value, "{};dur={:.1}", phase, duration.as_secs_f64() * 1000.0);
+        }
+        value
+    }
+}
+
+/// Run `future`, returning its output with the phase durations recorded while it ran
+pub async fn collect<F: Future>(future: F) -> (F::Output, ServerTimings) {
+    let timings = Arc::new(Mutex::new(ServerTimings::default()));
+    let output = TIMINGS.scope(timings.clone(), future).await;
+    let timings = timings.lock().unwrap().clone();
+    (output, timings)
+}
+
+/// Add `duration` to `phase` of the current request
+pub fn record(phase: &'static str, duration: Duration) {
+    let _ = TIMINGS.try_with(|timings| timings.lock().unwrap().add(phase, duration));
+}
+
+/// Adds the time from `start` until it is dropped to a phase of the current request
+#[derive(Debug)]
+pub struct PhaseTimer {
+    phase: &'static str,
+    started: Instant,
+}
+
+impl PhaseTimer {
+    pub fn start(phase: &'static str) -> Self {
+        Self { phase, started: Instant::now() }
+    }
+}
+
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        record(self.phase, self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_phases_add_up_and_keep_their_first_position() {
+        let mut timings = ServerTimings::default();
+        timings.add("auth", Duration::from_micros(800));
+        timings.add("db", Duration::from_millis(10));
+        timings.add("auth", Duration::from_micros(200));
+        timings.add("db", Duration::from_micros(2400));
+
+        assert_eq!(timings.header_value(Duration::from_millis(15)), "auth;dur=1.0, db;dur=12.4, total;dur=15.0");
+    }
+
+    #[test]
+    fn the_header_always_ends_with_the_total() {
+        assert_eq!(ServerTimings::default().header_value(Duration::from_micros(1250)), "total;dur=1.2");
+        assert_eq!(ServerTimings::default().header_value(Duration::ZERO), "total;dur=0.0");
+    }
+
+    #[tokio::test]
+    async fn phases_are_only_recorded_inside_collect() {
+        record("db", Duration::from_millis(5));
+
+        let ((), timings) = collect(async {
+            record("db", Duration::from_millis(2));
+            drop(PhaseTimer::start("serialize"));
+        })
+        .await;
+
+        let phases: Vec<(&str, Duration)> = timings.phases;
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0], ("db", Duration::from_millis(2)));
+        assert_eq!(phases[1].0, "serialize");
+    }
+}
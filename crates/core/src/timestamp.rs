@@ -0,0 +1,191 @@
+//! Serde format for `OffsetDateTime` fields of API payloads, used as
+//! `#[serde(with = "app_core::timestamp")]`. Timestamps are written as RFC 3339 strings,
+//! or as integer Unix milliseconds in response bodies written through [`Json`] while
+//! handling a request run under `with_format(UnixMillis, ..)`. Anything else serialized
+//! meanwhile, such as audit details or cache entries, keeps RFC 3339.
+//! Either form is accepted when reading, including millis given as a string (query parameters).
+
+use axum::response::{IntoResponse, Response};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::future::Future;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::config::TimestampFormat;
+use crate::server_timing::PhaseTimer;
+
+/// `Server-Timing` phase for writing JSON response bodies
+const SERIALIZE_PHASE: &str = "serialize";
+
+tokio::task_local! {
+    /// Format the client asked for, applied only by `Json`
+    static REQUESTED: TimestampFormat;
+    /// Format used by `serialize`, set while `Json` writes a response body
+    static FORMAT: TimestampFormat;
+}
+
+/// Run `future` with response bodies written by [`Json`] using `format` for timestamps
+pub async fn with_format<F: Future>(format: TimestampFormat, future: F) -> F::Output {
+    REQUESTED.scope(format, future).await
+}
+
+fn current_format() -> TimestampFormat {
+    FORMAT.try_with(|format| *format).unwrap_or_default()
+}
+
+/// JSON response body, with timestamps in the format requested for the current request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        let format = REQUESTED.try_with(|format| *format).unwrap_or_default();
+        let _timer = PhaseTimer::start(SERIALIZE_PHASE);
+        // axum's `Json` serializes eagerly, so the scope and timer cover the whole body
+        FORMAT.sync_scope(format, || axum::Json(self.0).into_response())
+    }
+}
+
+pub fn serialize<S: Serializer>(value: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    match current_format() {
+        TimestampFormat::Rfc3339 => time::serde::rfc3339::serialize(value, serializer),
+        TimestampFormat::UnixMillis => {
+            let millis = value.unix_timestamp_nanos() / 1_000_000;
+            serializer.serialize_i64(millis as i64)
+        }
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OffsetDateTime, D::Error> {
+    deserializer.deserialize_any(TimestampVisitor)
+}
+
+/// The same format for optional fields: `#[serde(with = "app_core::timestamp::option")]`
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Option<OffsetDateTime>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.map(Timestamp).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<OffsetDateTime>, D::Error> {
+        Ok(Option::<Timestamp>::deserialize(deserializer)?.map(|timestamp| timestamp.0))
+    }
+}
+
+struct Timestamp(OffsetDateTime);
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Timestamp)
+    }
+}
+
+struct TimestampVisitor;
+
+impl TimestampVisitor {
+    fn from_millis<E: de::Error>(millis: i128) -> Result<OffsetDateTime, E> {
+        OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000)
+            .map_err(|_| E::custom(format!("timestamp {} ms is out of range", millis)))
+    }
+}
+
+impl<'de> de::Visitor<'de> for TimestampVisitor {
+    type Value = OffsetDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an RFC 3339 timestamp or integer Unix milliseconds")
+    }
+
+    fn visit_i64<E: de::Error>(self, millis: i64) -> Result<Self::Value, E> {
+        Self::from_millis(millis.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, millis: u64) -> Result<Self::Value, E> {
+        Self::from_millis(millis.into())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        if let Ok(millis) = value.parse::<i64>() {
+            return Self::from_millis(millis.into());
+        }
+        OffsetDateTime::parse(value, &Rfc3339).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Event {
+        #[serde(with = "crate::timestamp")]
+        at: OffsetDateTime,
+        #[serde(default, with = "crate::timestamp::option")]
+        ended_at: Option<OffsetDateTime>,
+    }
+
+    fn event_at(at: serde_json::Value) -> serde_json::Result<Event> {
+        serde_json::from_value(json!({ "at": at }))
+    }
+
+    fn expected() -> OffsetDateTime {
+        OffsetDateTime::parse("2023-11-14T22:13:20.123Z", &Rfc3339).unwrap()
+    }
+
+    #[test]
+    fn millis_are_read_from_numbers_and_strings() {
+        assert_eq!(event_at(json!(1_700_000_000_123_i64)).unwrap().at, expected());
+        assert_eq!(event_at(json!("1700000000123")).unwrap().at, expected());
+        assert_eq!(event_at(json!(-1000)).unwrap().at, OffsetDateTime::UNIX_EPOCH - time::Duration::SECOND);
+    }
+
+    #[test]
+    fn rfc3339_strings_are_read() {
+        assert_eq!(event_at(json!("2023-11-14T22:13:20.123Z")).unwrap().at, expected());
+        assert_eq!(event_at(json!("2023-11-15T00:13:20.123+02:00")).unwrap().at, expected());
+        assert!(event_at(json!("14/11/2023")).is_err());
+        assert!(event_at(json!(true)).is_err());
+    }
+
+    #[test]
+    fn out_of_range_millis_are_rejected() {
+        let error = event_at(json!(i64::MAX)).unwrap_err();
+        assert!(error.to_string().contains("out of range"), "{}", error);
+        assert!(event_at(json!(u64::MAX)).is_err());
+        assert!(event_at(json!(i64::MIN.to_string())).is_err());
+    }
+
+    #[test]
+    fn optional_timestamps_read_either_form_or_null() {
+        let event: Event = serde_json::from_value(json!({ "at": 0, "ended_at": "1700000000123" })).unwrap();
+        assert_eq!(event.ended_at, Some(expected()));
+
+        let event: Event = serde_json::from_value(json!({ "at": 0, "ended_at": null })).unwrap();
+        assert_eq!(event.ended_at, None);
+        assert_eq!(serde_json::to_value(&event).unwrap()["ended_at"], json!(null));
+    }
+
+    #[tokio::test]
+    async fn the_requested_format_applies_only_to_response_bodies() {
+        let event = Event { at: expected(), ended_at: Some(expected()) };
+
+        let (stored, body) = with_format(TimestampFormat::UnixMillis, async {
+            let stored = serde_json::to_value(&event).unwrap();
+            let response = Json(&event).into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (stored, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+        })
+        .await;
+
+        assert_eq!(stored, json!({ "at": "2023-11-14T22:13:20.123Z", "ended_at": "2023-11-14T22:13:20.123Z" }));
+        assert_eq!(body, json!({ "at": 1_700_000_000_123_i64, "ended_at": 1_700_000_000_123_i64 }));
+    }
+}
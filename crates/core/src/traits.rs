@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use uuid::Uuid;
 
 use crate::error::Result;
+use crate::models::{ActiveSigningKey, ListResponse, PaginationParams};
 
 /// Generic repository trait for CRUD operations
 #[async_trait]
@@ -12,6 +13,33 @@ pub trait Repository<T, ID> {
     async fn delete(&self, id: ID) -> Result<bool>;
 }
 
+/// Soft-delete operations for repositories whose records are hidden rather than removed.
+///
+/// Contract for implementors:
+/// - `soft_delete` stamps `deleted_at` and returns `false` when the record is missing or
+///   already deleted. Deleted records drop out of every other lookup and list.
+/// - `restore` clears `deleted_at` and returns the record, or `None` when it is missing or
+///   not deleted, so callers can use `find_by_id_including_deleted` to tell the two apart.
+/// - `find_by_id_including_deleted` and `list_deleted` are the only reads that see
+///   deleted records; `list_deleted` orders the most recently deleted first.
+/// - All four respect the repository's tenant scope.
+#[async_trait]
+pub trait SoftDeleteRepository<T, ID>: Send + Sync {
+    async fn soft_delete(&self, id: ID) -> Result<bool>;
+    async fn restore(&self, id: ID) -> Result<Option<T>>;
+    async fn find_by_id_including_deleted(&self, id: ID) -> Result<Option<T>>;
+    async fn list_deleted(&self, pagination: PaginationParams) -> Result<ListResponse<T>>;
+}
+
+/// Storage shared by every instance recording which JWT signing key is primary, so a
+/// rotation made on one instance reaches all of them
+#[async_trait]
+pub trait SigningKeyStore: Send + Sync {
+    /// The last recorded rotation; `None` before the first
+    async fn active_key(&self) -> Result<Option<ActiveSigningKey>>;
+    async fn set_active_key(&self, key: &ActiveSigningKey) -> Result<()>;
+}
+
 /// Service trait for business logic layer
 #[async_trait]
 pub trait Service<T, CreateRequest, UpdateRequest> {
@@ -28,16 +56,16 @@ pub trait HealthCheck {
     async fn health_check(&self) -> Result<()>;
 }
 
-/// Cache trait for caching layer
+/// Cache trait for caching layer; `ttl` is in seconds
 #[async_trait]
-pub trait Cache {
+pub trait Cache: Send + Sync {
     async fn get<T>(&self, key: &str) -> Result<Option<T>>
     where
-        T: serde::de::DeserializeOwned;
+        T: serde::de::DeserializeOwned + Send;
 
     async fn set<T>(&self, key: &str, value: &T, ttl: Option<u64>) -> Result<()>
     where
-        T: serde::Serialize;
+        T: serde::Serialize + Sync;
 
     async fn delete(&self, key: &str) -> Result<()>;
     async fn exists(&self, key: &str) -> Result<bool>;
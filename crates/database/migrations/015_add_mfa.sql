@@ -0,0 +1,20 @@
+-- TOTP multi-factor authentication; the secret is set on enrollment and only
+-- required at login once a code has confirmed it
+ALTER TABLE users ADD COLUMN IF NOT EXISTS mfa_secret VARCHAR(64);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS mfa_enabled BOOLEAN NOT NULL DEFAULT false;
+-- Time step of the last accepted code, so a code can't be used twice
+ALTER TABLE users ADD COLUMN IF NOT EXISTS mfa_last_used_step BIGINT;
+
+-- Logins that passed the password check and await a TOTP code
+CREATE TABLE IF NOT EXISTS mfa_challenges (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- SHA-256 of the challenge token; the token itself is never stored
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- At most one outstanding challenge per user; a new login replaces it
+CREATE UNIQUE INDEX IF NOT EXISTS idx_mfa_challenges_user_id ON mfa_challenges(user_id);
@@ -0,0 +1,2 @@
+-- Flags that must also be enabled for a flag to be, persisted by the database flag backend
+ALTER TABLE feature_flags ADD COLUMN IF NOT EXISTS depends_on TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,21 @@
+-- Background exports, shared by every instance: any of them can generate a queued
+-- export or serve a download link another one issued
+CREATE TYPE export_status AS ENUM ('pending', 'running', 'ready', 'failed');
+
+CREATE TABLE IF NOT EXISTS export_jobs (
+    id UUID PRIMARY KEY,
+    requested_by UUID NOT NULL,
+    request JSONB NOT NULL,
+    status export_status NOT NULL DEFAULT 'pending',
+    filename TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- When the current instance claimed the job, to spot ones abandoned by a dead instance
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_export_jobs_pending ON export_jobs(created_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_export_jobs_expires_at ON export_jobs(expires_at);
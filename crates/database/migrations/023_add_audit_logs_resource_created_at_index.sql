@@ -0,0 +1,4 @@
+-- A resource's audit trail is filtered by type and id and read newest first. This index
+-- serves both the filter and the order; it also covers lookups the two-column index did.
+CREATE INDEX IF NOT EXISTS idx_audit_logs_resource_created_at ON audit_logs(resource_type, resource_id, created_at);
+DROP INDEX IF EXISTS idx_audit_logs_resource;
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use sqlx::{pool::PoolConnection, PgPool, Postgres};
+use tracing::{instrument, warn};
+
+use app_core::error::Result;
+use monitoring::{JobLock, JobLockGuard};
+
+/// Session-level Postgres advisory locks keyed by name, so work such as a singleton
+/// job runs on one instance at a time across the fleet
+#[derive(Clone)]
+pub struct AdvisoryLocks {
+    pool: PgPool,
+}
+
+/// A held advisory lock. The lock belongs to the connection it was taken on, so it is
+/// released when that connection closes, including when the instance dies; dropping
+/// the lock without `release` closes the connection rather than returning it to the pool.
+pub struct AdvisoryLock {
+    connection: Option<PoolConnection<Postgres>>,
+    name: String,
+}
+
+impl AdvisoryLocks {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Take the lock called `name` without waiting; `None` while another session holds it
+    #[instrument(skip(self))]
+    pub async fn try_lock(&self, name: &str) -> Result<Option<AdvisoryLock>> {
+        let mut connection = self.pool.acquire().await?;
+        let acquired = sqlx::query_scalar!(
+            r#"SELECT pg_try_advisory_lock(hashtextextended($1, 0)) AS "acquired!""#,
+            name
+        )
+        .fetch_one(&mut *connection)
+        .await?;
+
+        Ok(acquired.then(|| AdvisoryLock { connection: Some(connection), name: name.to_string() }))
+    }
+}
+
+impl AdvisoryLock {
+    /// Release the lock and return its connection to the pool
+    pub async fn release(mut self) -> Result<()> {
+        let Some(mut connection) = self.connection.take() else {
+            return Ok(());
+        };
+
+        let released = sqlx::query_scalar!(
+            r#"SELECT pg_advisory_unlock(hashtextextended($1, 0)) AS "released!""#,
+            self.name
+        )
+        .fetch_one(&mut *connection)
+        .await;
+
+        match released {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                // The session lost the lock somehow; don't hand a suspect connection back
+                drop(connection.detach());
+                Err(anyhow::anyhow!("Advisory lock '{}' was not held at release", self.name).into())
+            }
+            Err(e) => {
+                drop(connection.detach());
+                Err(e.into())
+            }
+        }
+    }
+}
+
+impl Drop for AdvisoryLock {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            warn!(lock = %self.name, "Advisory lock dropped without release, closing its connection");
+            drop(connection.detach());
+        }
+    }
+}
+
+#[async_trait]
+impl JobLock for AdvisoryLocks {
+    async fn try_lock(&self, job: &str) -> Result<Option<Box<dyn JobLockGuard>>> {
+        let lock = AdvisoryLocks::try_lock(self, &format!("scheduler:{}", job)).await?;
+        Ok(lock.map(|lock| Box::new(lock) as Box<dyn JobLockGuard>))
+    }
+}
+
+#[async_trait]
+impl JobLockGuard for AdvisoryLock {
+    async fn release(self: Box<Self>) -> Result<()> {
+        AdvisoryLock::release(*self).await
+    }
+}
@@ -0,0 +1,279 @@
+use sqlx::PgPool;
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use app_core::enterprise::{ExportJob, ExportRequest, ExportStatus};
+use app_core::error::Result;
+
+/// Export jobs kept in the `export_jobs` table, so whichever instance a client reaches
+/// can report on, generate or serve any of them
+#[derive(Clone)]
+pub struct ExportJobs {
+    pool: PgPool,
+}
+
+struct ExportJobRow {
+    id: Uuid,
+    requested_by: Uuid,
+    request: serde_json::Value,
+    status: ExportStatus,
+    created_at: OffsetDateTime,
+    completed_at: Option<OffsetDateTime>,
+    expires_at: Option<OffsetDateTime>,
+    error: Option<String>,
+    filename: String,
+    content_type: String,
+}
+
+impl TryFrom<ExportJobRow> for ExportJob {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ExportJobRow) -> std::result::Result<Self, Self::Error> {
+        let request: ExportRequest = serde_json::from_value(row.request)
+            .map_err(|e| anyhow::anyhow!("Export {} has an unreadable request: {}", row.id, e))?;
+
+        Ok(ExportJob {
+            id: row.id,
+            requested_by: row.requested_by,
+            request,
+            status: row.status,
+            created_at: row.created_at,
+            completed_at: row.completed_at,
+            expires_at: row.expires_at,
+            error: row.error,
+            filename: row.filename,
+            content_type: row.content_type,
+        })
+    }
+}
+
+fn into_jobs(rows: Vec<ExportJobRow>) -> Result<Vec<ExportJob>> {
+    Ok(rows.into_iter().map(ExportJob::try_from).collect::<std::result::Result<_, _>>()?)
+}
+
+impl ExportJobs {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(skip(self, job), fields(export_id = %job.id))]
+    pub async fn insert(&self, job: &ExportJob) -> Result<()> {
+        let request = serde_json::to_value(&job.request).map_err(anyhow::Error::from)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO export_jobs (id, requested_by, request, status, filename, content_type, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            job.id,
+            job.requested_by,
+            request,
+            job.status as ExportStatus,
+            job.filename,
+            job.content_type,
+            job.created_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get(&self, id: Uuid) -> Result<Option<ExportJob>> {
+        let row = sqlx::query_as!(
+            ExportJobRow,
+            r#"
+            SELECT id, requested_by, request, status AS "status: ExportStatus", created_at,
+                   completed_at, expires_at, error, filename, content_type
+            FROM export_jobs
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(ExportJob::try_from).transpose()?)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn exists(&self, id: Uuid) -> Result<bool> {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM export_jobs WHERE id = $1) AS "exists!""#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(exists)
+    }
+
+    /// Mark every pending job as running on this instance and return them, oldest first.
+    /// Jobs another instance is claiming at the same time are skipped, not shared.
+    #[instrument(skip(self))]
+    pub async fn claim_pending(&self) -> Result<Vec<ExportJob>> {
+        let rows = sqlx::query_as!(
+            ExportJobRow,
+            r#"
+            UPDATE export_jobs
+            SET status = 'running', started_at = NOW()
+            WHERE id IN (
+                SELECT id FROM export_jobs
+                WHERE status = 'pending'
+                ORDER BY created_at
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, requested_by, request, status AS "status: ExportStatus", created_at,
+                      completed_at, expires_at, error, filename, content_type
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut jobs = into_jobs(rows)?;
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    /// Record how a running job ended; both outcomes are kept until `expires_at`
+    #[instrument(skip(self))]
+    pub async fn finish(&self, id: Uuid, error: Option<&str>, expires_at: OffsetDateTime) -> Result<()> {
+        let status = if error.is_some() { ExportStatus::Failed } else { ExportStatus::Ready };
+
+        sqlx::query!(
+            r#"
+            UPDATE export_jobs
+            SET status = $2, error = $3, completed_at = NOW(), expires_at = $4
+            WHERE id = $1
+            "#,
+            id,
+            status as ExportStatus,
+            error,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fail running jobs claimed before `started_before`, whose instance presumably died
+    /// while generating them, returning how many
+    #[instrument(skip(self))]
+    pub async fn fail_abandoned(&self, started_before: OffsetDateTime, expires_at: OffsetDateTime) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE export_jobs
+            SET status = 'failed', error = 'Export was interrupted', completed_at = NOW(), expires_at = $2
+            WHERE status = 'running' AND started_at < $1
+            "#,
+            started_before,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Delete jobs past their expiry and return them, so their files can be removed
+    #[instrument(skip(self))]
+    pub async fn delete_expired(&self, now: OffsetDateTime) -> Result<Vec<ExportJob>> {
+        let rows = sqlx::query_as!(
+            ExportJobRow,
+            r#"
+            DELETE FROM export_jobs
+            WHERE expires_at <= $1
+            RETURNING id, requested_by, request, status AS "status: ExportStatus", created_at,
+                      completed_at, expires_at, error, filename, content_type
+            "#,
+            now
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        into_jobs(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_core::enterprise::{AuditReportFormat, AuditReportQuery};
+    use time::Duration;
+
+    fn job(created_at: OffsetDateTime) -> ExportJob {
+        ExportJob {
+            id: Uuid::new_v4(),
+            requested_by: Uuid::new_v4(),
+            request: ExportRequest::AuditReport(AuditReportQuery {
+                from: created_at - Duration::days(1),
+                to: created_at,
+                format: AuditReportFormat::Csv,
+                user_id: None,
+                resource_type: None,
+                category: None,
+            }),
+            status: ExportStatus::Pending,
+            created_at,
+            completed_at: None,
+            expires_at: None,
+            error: None,
+            filename: "audit-report.csv".to_string(),
+            content_type: "text/csv; charset=utf-8".to_string(),
+        }
+    }
+
+    // These run the workspace's `migrations/`, since this crate's copy of 003 doesn't
+    // apply to a fresh database
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn pending_jobs_are_claimed_once_in_order(pool: PgPool) {
+        let jobs = ExportJobs::new(pool);
+        let now = OffsetDateTime::now_utc();
+        let (older, newer) = (job(now - Duration::minutes(1)), job(now));
+        jobs.insert(&newer).await.unwrap();
+        jobs.insert(&older).await.unwrap();
+
+        let claimed: Vec<Uuid> = jobs.claim_pending().await.unwrap().iter().map(|job| job.id).collect();
+        assert_eq!(claimed, vec![older.id, newer.id]);
+        assert_eq!(jobs.get(older.id).await.unwrap().unwrap().status, ExportStatus::Running);
+
+        // Another instance polling now finds nothing left to do
+        assert!(jobs.claim_pending().await.unwrap().is_empty());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn finished_jobs_are_kept_until_they_expire(pool: PgPool) {
+        let jobs = ExportJobs::new(pool);
+        let now = OffsetDateTime::now_utc();
+        let (ready, failed) = (job(now), job(now));
+        jobs.insert(&ready).await.unwrap();
+        jobs.insert(&failed).await.unwrap();
+        jobs.claim_pending().await.unwrap();
+
+        jobs.finish(ready.id, None, now + Duration::hours(1)).await.unwrap();
+        jobs.finish(failed.id, Some("boom"), now - Duration::seconds(1)).await.unwrap();
+        let finished = jobs.get(failed.id).await.unwrap().unwrap();
+        assert_eq!((finished.status, finished.error.as_deref()), (ExportStatus::Failed, Some("boom")));
+
+        let deleted: Vec<Uuid> = jobs.delete_expired(now).await.unwrap().iter().map(|job| job.id).collect();
+        assert_eq!(deleted, vec![failed.id]);
+        assert_eq!(jobs.get(ready.id).await.unwrap().unwrap().status, ExportStatus::Ready);
+        assert!(!jobs.exists(failed.id).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn jobs_running_too_long_are_failed_as_abandoned(pool: PgPool) {
+        let jobs = ExportJobs::new(pool);
+        let now = OffsetDateTime::now_utc();
+        let running = job(now);
+        jobs.insert(&running).await.unwrap();
+        jobs.claim_pending().await.unwrap();
+
+        assert_eq!(jobs.fail_abandoned(now - Duration::hours(1), now).await.unwrap(), 0);
+        assert_eq!(jobs.fail_abandoned(now + Duration::seconds(5), now).await.unwrap(), 1);
+        assert_eq!(jobs.get(running.id).await.unwrap().unwrap().status, ExportStatus::Failed);
+    }
+}
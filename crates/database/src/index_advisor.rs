@@ -0,0 +1,170 @@
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+/// An index the application's frequent queries rely on
+struct RecommendedIndex {
+    name: &'static str,
+    table: &'static str,
+    columns: &'static [&'static str],
+}
+
+impl RecommendedIndex {
+    /// Builds the index without blocking writes. `CONCURRENTLY` can't run in a transaction,
+    /// so the migration holding it must start with `-- no-transaction`.
+    fn create_statement(&self) -> String {
+        format!(
+            "CREATE INDEX CONCURRENTLY IF NOT EXISTS {} ON {} ({});",
+            self.name,
+            self.table,
+            self.columns.join(", ")
+        )
+    }
+}
+
+/// Hot lookups: login and registration by email/username, a user's audit history, and
+/// a resource's audit trail (filtered by type and id, newest first)
+const RECOMMENDED_INDEXES: &[RecommendedIndex] = &[
+    RecommendedIndex { name: "idx_users_email", table: "users", columns: &["email"] },
+    RecommendedIndex { name: "idx_users_username", table: "users", columns: &["username"] },
+    RecommendedIndex { name: "idx_audit_logs_user_id", table: "audit_logs", columns: &["user_id"] },
+    RecommendedIndex {
+        name: "idx_audit_logs_resource_created_at",
+        table: "audit_logs",
+        columns: &["resource_type", "resource_id", "created_at"],
+    },
+];
+
+/// Log a WARN with the `CREATE INDEX` statement for every recommended index that no
+/// existing index covers. Indexes are never created here; that is left to migrations.
+/// Failures are logged rather than returned so diagnostics can't block startup.
+pub async fn warn_missing_indexes(pool: &PgPool) {
+    let tables: Vec<String> = RECOMMENDED_INDEXES.iter().map(|index| index.table.to_string()).collect();
+
+    let existing = match sqlx::query!(
+        r#"
+        SELECT tablename::text AS "table!", indexdef AS "definition!"
+        FROM pg_indexes
+        WHERE schemaname = current_schema() AND tablename = ANY($1)
+        "#,
+        &tables
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            warn!("Failed to check for missing indexes: {}", e);
+            return;
+        }
+    };
+
+    let mut missing = 0;
+    for recommended in RECOMMENDED_INDEXES {
+        let covered = existing
+            .iter()
+            .filter(|row| row.table == recommended.table)
+            .any(|row| leads_with(&indexed_columns(&row.definition), recommended.columns));
+
+        if !covered {
+            missing += 1;
+            warn!(
+                table = recommended.table,
+                columns = %recommended.columns.join(","),
+                "Missing recommended index, queries on these columns will scan the table. \
+                 Add a migration starting with `-- no-transaction` and containing: {}",
+                recommended.create_statement()
+            );
+        }
+    }
+
+    if missing == 0 {
+        info!("All recommended indexes are present");
+    }
+}
+
+/// Whether an index on `indexed` can serve lookups on `wanted`, i.e. `wanted` is a prefix
+fn leads_with(indexed: &[String], wanted: &[&str]) -> bool {
+    indexed.len() >= wanted.len() && indexed.iter().zip(wanted).all(|(column, wanted)| column == wanted)
+}
+
+/// Key columns of a `pg_indexes.indexdef`, e.g. `CREATE INDEX i ON public.t USING btree (a, b DESC)`.
+/// Expression keys are kept verbatim, so they never match a plain column name.
+fn indexed_columns(definition: &str) -> Vec<String> {
+    let Some(using) = definition.find(" USING ") else {
+        return Vec::new();
+    };
+    let Some(open) = definition[using..].find('(').map(|offset| using + offset) else {
+        return Vec::new();
+    };
+
+    let mut columns = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in definition[open + 1..].chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                columns.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    columns.push(current);
+
+    columns
+        .iter()
+        .map(|key| {
+            // Drop ordering and operator class, e.g. `created_at DESC` or `name text_pattern_ops`
+            let key = key.trim();
+            if key.starts_with('(') {
+                key.to_string()
+            } else {
+                key.split_whitespace().next().unwrap_or_default().trim_matches('"').to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_columns_are_read_from_index_definitions() {
+        let definition = "CREATE INDEX idx ON public.audit_logs USING btree (resource_type, resource_id, created_at)";
+        assert_eq!(indexed_columns(definition), vec!["resource_type", "resource_id", "created_at"]);
+        assert_eq!(
+            indexed_columns("CREATE UNIQUE INDEX users_email_key ON public.users USING btree (email)"),
+            vec!["email"]
+        );
+    }
+
+    #[test]
+    fn ordering_operator_classes_and_quotes_are_dropped() {
+        let definition = r#"CREATE INDEX i ON t USING btree ("Name" text_pattern_ops, created_at DESC NULLS LAST)"#;
+        assert_eq!(indexed_columns(definition), vec!["Name", "created_at"]);
+    }
+
+    #[test]
+    fn expression_keys_are_kept_whole_and_predicates_ignored() {
+        let definition = "CREATE INDEX i ON users USING btree (lower((email)::text), id) WHERE (deleted_at IS NULL)";
+        assert_eq!(indexed_columns(definition), vec!["lower((email)::text)", "id"]);
+        assert_eq!(
+            indexed_columns("CREATE INDEX i ON public.users USING btree ((lower(email))) WHERE (deleted_at IS NULL)"),
+            vec!["(lower(email))"]
+        );
+        assert!(indexed_columns("not an index").is_empty());
+    }
+
+    #[test]
+    fn indexes_cover_lookups_on_a_prefix_of_their_columns() {
+        let indexed = vec!["resource_type".to_string(), "resource_id".to_string(), "created_at".to_string()];
+        assert!(leads_with(&indexed, &["resource_type", "resource_id"]));
+        assert!(!leads_with(&indexed, &["resource_id"]));
+        assert!(!leads_with(&indexed[..2], &["resource_type", "resource_id", "created_at"]));
+    }
+}
@@ -1,6 +1,23 @@
+pub mod advisory_lock;
+pub mod export_jobs;
+pub mod index_advisor;
+pub mod outbox;
 pub mod pool;
+pub mod product_store;
+pub mod query_plan;
+pub mod redis_cache;
 pub mod repositories;
+pub mod signing_keys;
+pub mod timed_pool;
 //pub mod migrations;
 
-pub use pool::DatabasePool;
+pub use advisory_lock::{AdvisoryLock, AdvisoryLocks};
+pub use export_jobs::ExportJobs;
+pub use outbox::{LogDispatcher, OutboxDispatcher, OutboxEvent, OutboxRelay, RelayOutcome};
+pub use pool::{AdmissionGuard, DatabasePool};
+pub use product_store::ProductStore;
+pub use query_plan::QueryPlanLogger;
+pub use redis_cache::{RedisCache, REDIS_CACHE_DEPENDENCY};
 pub use repositories::*;
+pub use signing_keys::DatabaseSigningKeyStore;
+pub use timed_pool::{TimedPool, TimedTransaction};
@@ -0,0 +1,194 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::{error, info, instrument, warn};
+use uuid::Uuid;
+
+use app_core::error::Result;
+
+/// Domain event recorded in the outbox alongside the change that produced it
+#[derive(Debug, Clone, Serialize)]
+pub struct OutboxEvent {
+    pub id: Uuid,
+    pub aggregate_type: String,
+    pub aggregate_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub created_at: OffsetDateTime,
+    /// Failed dispatches so far
+    pub attempts: i32,
+}
+
+/// Destination the relay delivers outbox events to.
+/// Delivery is at-least-once, so implementations must tolerate duplicates (`event.id` is stable).
+#[async_trait]
+pub trait OutboxDispatcher: Send + Sync {
+    async fn dispatch(&self, event: &OutboxEvent) -> Result<()>;
+}
+
+/// Default destination: events only go to the application log
+#[derive(Clone, Default)]
+pub struct LogDispatcher;
+
+#[async_trait]
+impl OutboxDispatcher for LogDispatcher {
+    async fn dispatch(&self, event: &OutboxEvent) -> Result<()> {
+        info!(
+            event_id = %event.id,
+            event_type = %event.event_type,
+            aggregate_id = %event.aggregate_id,
+            "Outbox event: {}",
+            event.payload
+        );
+        Ok(())
+    }
+}
+
+/// Record an event on the caller's transaction so it commits or rolls back with the change
+pub(crate) async fn enqueue(
+    conn: &mut PgConnection,
+    aggregate_type: &str,
+    aggregate_id: Uuid,
+    event_type: &str,
+    payload: &impl Serialize,
+) -> Result<()> {
+    let payload = serde_json::to_value(payload).map_err(anyhow::Error::from)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO outbox (id, aggregate_type, aggregate_id, event_type, payload, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        Uuid::new_v4(),
+        aggregate_type,
+        aggregate_id,
+        event_type,
+        payload,
+        OffsetDateTime::now_utc()
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Events handled by one relay pass
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct RelayOutcome {
+    pub dispatched: usize,
+    /// Failures that will be retried later
+    pub failed: usize,
+    /// Failures with no retries left
+    pub dead_lettered: usize,
+}
+
+/// Moves committed outbox events to their dispatcher
+#[derive(Clone)]
+pub struct OutboxRelay {
+    pool: PgPool,
+    batch_size: i64,
+    retry_schedule: Vec<Duration>,
+}
+
+impl OutboxRelay {
+    pub fn new(pool: PgPool, batch_size: i64, retry_schedule: Vec<Duration>) -> Self {
+        Self {
+            pool,
+            batch_size: batch_size.max(1),
+            retry_schedule,
+        }
+    }
+
+    /// Dispatch up to one batch of due events, oldest first.
+    ///
+    /// Claimed rows stay locked (`SKIP LOCKED`) until the pass commits, so relays on other
+    /// instances never deliver the same event concurrently. A failed event becomes due again
+    /// after the next delay in the retry schedule and is dead-lettered once the schedule is
+    /// used up; a crash before commit redelivers the whole batch.
+    #[instrument(skip(self, dispatcher))]
+    pub async fn relay(&self, dispatcher: &dyn OutboxDispatcher) -> Result<RelayOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let events = sqlx::query_as!(
+            OutboxEvent,
+            r#"
+            SELECT id, aggregate_type, aggregate_id, event_type, payload, created_at, attempts
+            FROM outbox
+            WHERE processed_at IS NULL AND dead_lettered_at IS NULL AND next_attempt_at <= $2
+            ORDER BY created_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            self.batch_size,
+            OffsetDateTime::now_utc()
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut outcome = RelayOutcome::default();
+        for event in &events {
+            match dispatcher.dispatch(event).await {
+                Ok(()) => {
+                    sqlx::query!(
+                        "UPDATE outbox SET processed_at = $2 WHERE id = $1",
+                        event.id,
+                        OffsetDateTime::now_utc()
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                    outcome.dispatched += 1;
+                }
+                Err(e) => {
+                    let now = OffsetDateTime::now_utc();
+                    let attempts = event.attempts + 1;
+                    // `attempts` failures so far, so the next retry waits for the `attempts`-th delay
+                    let retry_after = self.retry_schedule.get(attempts as usize - 1);
+
+                    match retry_after {
+                        Some(delay) => warn!(
+                            event_id = %event.id,
+                            event_type = %event.event_type,
+                            attempts,
+                            retry_in_secs = delay.as_secs(),
+                            "Failed to dispatch outbox event: {}",
+                            e
+                        ),
+                        None => error!(
+                            event_id = %event.id,
+                            event_type = %event.event_type,
+                            attempts,
+                            "Dead-lettering outbox event after final failed attempt: {}",
+                            e
+                        ),
+                    }
+
+                    sqlx::query!(
+                        r#"
+                        UPDATE outbox
+                        SET attempts = $2, last_error = $3, next_attempt_at = $4, dead_lettered_at = $5
+                        WHERE id = $1
+                        "#,
+                        event.id,
+                        attempts,
+                        e.to_string(),
+                        retry_after.map_or(now, |delay| now + *delay),
+                        retry_after.is_none().then_some(now)
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+
+                    if retry_after.is_some() {
+                        outcome.failed += 1;
+                    } else {
+                        outcome.dead_lettered += 1;
+                    }
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(outcome)
+    }
+}
@@ -1,13 +1,45 @@
-use sqlx::{postgres::PgPoolOptions, PgPool, Row};
-use std::time::Duration;
-use tracing::{info, instrument};
+use sqlx::{postgres::{PgConnectOptions, PgPoolOptions}, PgPool, Row};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, instrument, warn};
 
-use app_core::{config::DatabaseConfig, error::Result};
-use crate::repositories::{UserRepository};
+use app_core::{
+    config::DatabaseConfig,
+    enterprise::AggregateStats,
+    error::{ApiError, Result},
+    models::TenantScope,
+};
+use crate::advisory_lock::AdvisoryLocks;
+use crate::export_jobs::ExportJobs;
+use crate::index_advisor::warn_missing_indexes;
+use crate::outbox::OutboxRelay;
+use crate::query_plan::QueryPlanLogger;
+use crate::repositories::{ProductRepository, UserRepository};
+use crate::signing_keys::DatabaseSigningKeyStore;
 
 #[derive(Clone)]
 pub struct DatabasePool {
     pool: PgPool,
+    /// Small dedicated pool so health probes never queue behind application traffic
+    health_pool: PgPool,
+    query_plans: QueryPlanLogger,
+    /// Requests currently admitted to use the pool
+    in_flight: Arc<AtomicUsize>,
+    max_connections: u32,
+    shed_threshold: Option<usize>,
+}
+
+/// Marks an admitted request as in flight until dropped
+pub struct AdmissionGuard {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for AdmissionGuard {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 impl DatabasePool {
@@ -15,34 +47,113 @@ impl DatabasePool {
     pub async fn new(config: &DatabaseConfig) -> Result<Self> {
         info!("Initializing database connection pool");
 
+        // Identify this service and build to DBAs in pg_stat_activity
+        let application_name = format!("{}/{}", config.application_name, env!("CARGO_PKG_VERSION"));
+        let connect_options = PgConnectOptions::from_str(&config.url)?
+            .application_name(&application_name);
+
         let pool = PgPoolOptions::new()
             .max_connections(config.max_connections)
             .min_connections(config.min_connections)
             .acquire_timeout(Duration::from_secs(config.acquire_timeout))
             .idle_timeout(Duration::from_secs(config.idle_timeout))
-            .connect(&config.url)
+            .connect_with(connect_options.clone())
+            .await?;
+
+        let health_pool = PgPoolOptions::new()
+            .max_connections(config.health_check_connections)
+            .min_connections(config.health_check_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout))
+            .connect_with(connect_options)
             .await?;
 
         // Run migrations
         sqlx::migrate!("./migrations").run(&pool).await
             .map_err(|e| anyhow::anyhow!("Migration failed: {}", e))?;
 
+        if config.index_advisories {
+            warn_missing_indexes(&pool).await;
+        }
+
+        if let Some(timeout) = config.warm_up_timeout {
+            warm_up(&pool, config.min_connections, Duration::from_secs(timeout)).await;
+        }
+
+        let query_plans = QueryPlanLogger::new(pool.clone(), config);
+        if config.explain_slow_queries {
+            warn!("Slow query EXPLAIN ANALYZE logging is enabled; do not use in production");
+        }
+
         info!("Database connection pool initialized successfully");
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            health_pool,
+            query_plans,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            max_connections: config.max_connections,
+            shed_threshold: config.pool_shed_threshold,
+        })
     }
 
     pub fn pool(&self) -> &PgPool {
         &self.pool
     }
 
-    pub fn user_repository(&self) -> UserRepository {
-        UserRepository::new(self.pool.clone())
+    /// Admit a request that will use the pool, or fail fast with 503 when every
+    /// connection the pool may open is open and busy and more than the shed threshold
+    /// are already queued. Hold the guard for the lifetime of the request.
+    pub fn try_admit(&self) -> Result<AdmissionGuard> {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::AcqRel) + 1;
+        let guard = AdmissionGuard { in_flight: self.in_flight.clone() };
+
+        if let Some(threshold) = self.shed_threshold {
+            let saturated = self.pool.size() == self.max_connections && self.pool.num_idle() == 0;
+            if let Some(queued) = queued_over_threshold(in_flight, self.max_connections, saturated, threshold) {
+                warn!(queued, threshold, "Database pool exhausted, shedding request");
+                return Err(ApiError::ServiceUnavailable(
+                    "Service is overloaded, please retry shortly".to_string(),
+                ));
+            }
+        }
+
+        Ok(guard)
+    }
+
+    /// Users repository limited to the rows visible to `scope`
+    pub fn user_repository(&self, scope: TenantScope) -> UserRepository {
+        UserRepository::new(self.pool.clone(), self.query_plans.clone(), scope)
+    }
+
+    /// Products repository limited to the rows visible to `scope`
+    pub fn product_repository(&self, scope: TenantScope) -> ProductRepository {
+        ProductRepository::new(self.pool.clone(), self.query_plans.clone(), scope)
+    }
+
+    /// Relay delivering up to `batch_size` outbox events per pass, retrying failures
+    /// after each delay in `retry_schedule`
+    pub fn outbox_relay(&self, batch_size: i64, retry_schedule: Vec<Duration>) -> OutboxRelay {
+        OutboxRelay::new(self.pool.clone(), batch_size, retry_schedule)
+    }
+
+    /// Named advisory locks held on connections from the main pool
+    pub fn advisory_locks(&self) -> AdvisoryLocks {
+        AdvisoryLocks::new(self.pool.clone())
+    }
+
+    /// Background export jobs, shared by every instance
+    pub fn export_jobs(&self) -> ExportJobs {
+        ExportJobs::new(self.pool.clone())
+    }
+
+    /// Shared record of the primary JWT signing key
+    pub fn signing_key_store(&self) -> DatabaseSigningKeyStore {
+        DatabaseSigningKeyStore::new(self.pool.clone())
     }
 
     #[instrument(skip(self))]
     pub async fn health_check(&self) -> Result<()> {
         let row = sqlx::query("SELECT 1 as health_check")
-            .fetch_one(&self.pool)
+            .fetch_one(&self.health_pool)
             .await?;
 
         let health_check: i32 = row.get("health_check");
@@ -53,7 +164,79 @@ impl DatabasePool {
         }
     }
 
+    #[instrument(skip(self))]
+    pub async fn aggregate_stats(&self) -> Result<AggregateStats> {
+        let stats = sqlx::query!(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM users WHERE deleted_at IS NULL) AS "total_users!",
+                (SELECT COUNT(*) FROM users WHERE is_active = true AND deleted_at IS NULL) AS "active_users!",
+                (SELECT COUNT(*) FROM products WHERE deleted_at IS NULL) AS "total_products!",
+                (SELECT COUNT(*) FROM products WHERE is_active = true AND deleted_at IS NULL) AS "active_products!"
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(AggregateStats {
+            total_users: stats.total_users,
+            active_users: stats.active_users,
+            total_products: stats.total_products,
+            active_products: stats.active_products,
+            computed_at: time::OffsetDateTime::now_utc(),
+        })
+    }
+
     pub async fn close(&self) {
         self.pool.close().await;
+        self.health_pool.close().await;
+    }
+}
+
+/// Open `connections` connections and run a trivial query on each, giving up after
+/// `timeout`. Failures are logged rather than returned; the pool still fills lazily.
+async fn warm_up(pool: &PgPool, connections: u32, timeout: Duration) {
+    let started = Instant::now();
+    let warm = async {
+        // Held until every one is checked so each query runs on a distinct connection
+        let mut held = Vec::with_capacity(connections as usize);
+        for _ in 0..connections {
+            let mut connection = pool.acquire().await?;
+            sqlx::query("SELECT 1").execute(&mut *connection).await?;
+            held.push(connection);
+        }
+        Ok::<usize, sqlx::Error>(held.len())
+    };
+
+    match tokio::time::timeout(timeout, warm).await {
+        Ok(Ok(warmed)) => info!(connections = warmed, elapsed_ms = started.elapsed().as_millis() as u64, "Database pool warmed up"),
+        Ok(Err(e)) => warn!("Database pool warm-up failed: {}", e),
+        Err(_) => warn!(timeout_secs = timeout.as_secs(), "Database pool warm-up timed out, continuing with a cold pool"),
+    }
+}
+
+/// How many of `in_flight` requests are waiting for a connection, when that is more than
+/// `threshold`. Requests only queue once the pool is `saturated`: it has opened all
+/// `max_connections` and none are idle.
+fn queued_over_threshold(in_flight: usize, max_connections: u32, saturated: bool, threshold: usize) -> Option<usize> {
+    let queued = in_flight.saturating_sub(max_connections as usize);
+    (saturated && queued > threshold).then_some(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_shed_once_the_queue_passes_the_threshold() {
+        // 10 connections busy, 5 waiting; a threshold of 5 still admits the next one
+        assert_eq!(queued_over_threshold(15, 10, true, 5), None);
+        assert_eq!(queued_over_threshold(16, 10, true, 5), Some(6));
+        assert_eq!(queued_over_threshold(3, 10, true, 0), None);
+    }
+
+    #[test]
+    fn nothing_is_shed_while_the_pool_can_still_open_or_lend_connections() {
+        assert_eq!(queued_over_threshold(50, 10, false, 5), None);
     }
 }
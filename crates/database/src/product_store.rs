@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use app_core::{
+    config::{ProductBackendConfig, RedisConfig},
+    error::Result,
+    models::TenantScope,
+};
+use monitoring::DependencyHealthTracker;
+
+use crate::pool::DatabasePool;
+use crate::redis_cache::RedisCache;
+use crate::repositories::{
+    CachedProductRepository, HttpProductCatalog, HttpProductRepository, ProductRepositoryTrait,
+};
+
+/// Product storage selected by `database.product_backend`.
+/// Handlers go through `repository` and work the same against either backend.
+#[derive(Clone)]
+pub enum ProductStore {
+    /// Reads are cached in Redis unless `redis.product_cache_ttl` is 0; the cache is
+    /// boxed so it doesn't set the size of every store
+    Postgres(DatabasePool, Option<Box<(RedisCache, u64)>>),
+    /// The catalog client keeps its own in-process cache
+    Http(Arc<HttpProductCatalog>),
+}
+
+impl ProductStore {
+    pub fn from_config(
+        config: &ProductBackendConfig,
+        redis: &RedisConfig,
+        db_pool: &DatabasePool,
+        dependency_health: Arc<DependencyHealthTracker>,
+    ) -> Result<Self> {
+        Ok(match config {
+            ProductBackendConfig::Postgres => {
+                let cache = if redis.product_cache_ttl > 0 {
+                    Some(Box::new((RedisCache::new(redis, dependency_health)?, redis.product_cache_ttl)))
+                } else {
+                    None
+                };
+                Self::Postgres(db_pool.clone(), cache)
+            }
+            ProductBackendConfig::Http(catalog) => {
+                Self::Http(Arc::new(HttpProductCatalog::new(catalog, dependency_health)?))
+            }
+        })
+    }
+
+    /// The Redis cache in front of Postgres, if one is configured
+    pub fn cache(&self) -> Option<&RedisCache> {
+        match self {
+            Self::Postgres(_, Some(cache)) => Some(&cache.0),
+            Self::Postgres(_, None) | Self::Http(_) => None,
+        }
+    }
+
+    /// Products repository limited to the rows visible to `scope`
+    pub fn repository(&self, scope: TenantScope) -> Arc<dyn ProductRepositoryTrait> {
+        match self {
+            Self::Postgres(db_pool, None) => Arc::new(db_pool.product_repository(scope)),
+            Self::Postgres(db_pool, Some(cache)) => {
+                let (cache, ttl) = cache.as_ref();
+                Arc::new(CachedProductRepository::new(
+                    Arc::new(db_pool.product_repository(scope)),
+                    cache.clone(),
+                    scope,
+                    *ttl,
+                ))
+            }
+            Self::Http(catalog) => Arc::new(HttpProductRepository::new(catalog.clone(), scope)),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use sqlx::{postgres::PgArguments, query::Query, PgPool, Postgres, Row};
+use std::time::Duration;
+use tracing::warn;
+
+use app_core::config::DatabaseConfig;
+
+/// Opt-in diagnostics that log `EXPLAIN (ANALYZE, BUFFERS)` output for slow queries.
+/// `EXPLAIN ANALYZE` re-executes the statement, so only read-only queries may be explained.
+#[derive(Clone)]
+pub struct QueryPlanLogger {
+    pool: PgPool,
+    enabled: bool,
+    threshold: Duration,
+}
+
+impl QueryPlanLogger {
+    pub fn new(pool: PgPool, config: &DatabaseConfig) -> Self {
+        Self {
+            pool,
+            enabled: config.explain_slow_queries,
+            threshold: Duration::from_millis(config.slow_query_threshold_ms),
+        }
+    }
+
+    /// Whether a query that took `elapsed` should have its plan logged
+    pub fn should_explain(&self, elapsed: Duration) -> bool {
+        self.enabled && elapsed >= self.threshold
+    }
+
+    /// Run an `EXPLAIN (ANALYZE, BUFFERS) ...` query and log the plan at WARN
+    pub async fn log_plan(
+        &self,
+        operation: &str,
+        elapsed: Duration,
+        explain: Query<'_, Postgres, PgArguments>,
+    ) {
+        match explain.fetch_all(&self.pool).await {
+            Ok(rows) => {
+                let plan: Vec<String> = rows
+                    .iter()
+                    .filter_map(|row| row.try_get::<String, _>(0).ok())
+                    .collect();
+
+                warn!(
+                    operation = operation,
+                    elapsed_ms = elapsed.as_millis() as u64,
+                    "Slow query plan:\n{}",
+                    plan.join("\n")
+                );
+            }
+            Err(e) => warn!(operation = operation, "Failed to explain slow query: {}", e),
+        }
+    }
+}
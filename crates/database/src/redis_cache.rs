@@ -0,0 +1,116 @@
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use app_core::{
+    config::RedisConfig,
+    error::{ApiError, Result},
+    traits::Cache,
+};
+use monitoring::{DependencyHealthTracker, GuardedRedis};
+
+/// Name the cache's Redis calls are reported under in dependency health
+pub const REDIS_CACHE_DEPENDENCY: &str = "redis_cache";
+
+/// Cache shared by every instance, kept in Redis.
+///
+/// Calls time out after `redis.cache_timeout_ms`. While Redis is unavailable the cache
+/// fails open by default: reads miss, so callers go to the database, and writes are
+/// skipped. With `redis.cache_fail_open: false` those calls fail with 503 instead.
+#[derive(Clone)]
+pub struct RedisCache {
+    redis: GuardedRedis,
+    fail_open: bool,
+}
+
+impl RedisCache {
+    pub fn new(config: &RedisConfig, dependency_health: Arc<DependencyHealthTracker>) -> Result<Self> {
+        let timeout = Duration::from_millis(config.cache_timeout_ms);
+        Ok(Self {
+            redis: GuardedRedis::new(&config.url, REDIS_CACHE_DEPENDENCY, timeout, dependency_health)?,
+            fail_open: config.cache_fail_open,
+        })
+    }
+
+    /// Whether calls degrade to misses and skipped writes while Redis is unavailable
+    pub fn fails_open(&self) -> bool {
+        self.fail_open
+    }
+
+    /// Ping Redis through the cache's breaker and timeout. An open circuit counts as
+    /// unhealthy without a ping.
+    pub async fn health_check(&self) -> Result<()> {
+        self.redis
+            .call(|mut connection| async move { redis::cmd("PING").query_async::<_, ()>(&mut connection).await })
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Redis cache is unavailable").into())
+    }
+
+    /// Run `operation` against Redis; `None` when Redis is unavailable and the cache
+    /// fails open
+    async fn guarded<T, F, Fut>(&self, operation: F) -> Result<Option<T>>
+    where
+        F: FnOnce(ConnectionManager) -> Fut,
+        Fut: Future<Output = redis::RedisResult<T>>,
+    {
+        match self.redis.call(operation).await {
+            Some(value) => Ok(Some(value)),
+            None if self.fail_open => Ok(None),
+            None => Err(ApiError::ServiceUnavailable("Cache is unavailable".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    /// A value that no longer deserializes, e.g. one written before its type changed,
+    /// counts as a miss
+    async fn get<T>(&self, key: &str) -> Result<Option<T>>
+    where
+        T: DeserializeOwned + Send,
+    {
+        let cached: Option<Option<String>> = self
+            .guarded(|mut connection| async move { connection.get(key).await })
+            .await?;
+
+        Ok(cached.flatten().and_then(|value| match serde_json::from_str(&value) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!(key, "Ignoring unreadable cache entry: {}", e);
+                None
+            }
+        }))
+    }
+
+    async fn set<T>(&self, key: &str, value: &T, ttl: Option<u64>) -> Result<()>
+    where
+        T: Serialize + Sync,
+    {
+        let value = serde_json::to_string(value).map_err(anyhow::Error::from)?;
+        self.guarded(|mut connection| async move {
+            match ttl {
+                Some(ttl) => connection.set_ex::<_, _, ()>(key, value, ttl).await,
+                None => connection.set::<_, _, ()>(key, value).await,
+            }
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.guarded(|mut connection| async move { connection.del::<_, ()>(key).await })
+            .await?;
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self
+            .guarded(|mut connection| async move { connection.exists(key).await })
+            .await?
+            .unwrap_or(false))
+    }
+}
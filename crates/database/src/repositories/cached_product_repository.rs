@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+use crate::redis_cache::RedisCache;
+use crate::repositories::ProductRepositoryTrait;
+use app_core::{
+    error::Result,
+    models::{
+        CreateProductRequest, ListResponse, PaginationParams, PriceUpdate, Product, TenantScope, UpdateProductRequest,
+    },
+    traits::{Cache, SoftDeleteRepository},
+};
+
+/// Serves `find_by_id` from Redis and drops a product's entry whenever it is written.
+///
+/// Entries are keyed by product alone and checked against the scope on read, so one
+/// invalidation covers every scope. An invalidation skipped while Redis was unavailable
+/// leaves the entry stale for at most the cache TTL.
+pub struct CachedProductRepository {
+    inner: Arc<dyn ProductRepositoryTrait>,
+    cache: RedisCache,
+    scope: TenantScope,
+    ttl: u64,
+}
+
+impl CachedProductRepository {
+    pub fn new(inner: Arc<dyn ProductRepositoryTrait>, cache: RedisCache, scope: TenantScope, ttl: u64) -> Self {
+        Self { inner, cache, scope, ttl }
+    }
+
+    fn visible(&self, product: &Product) -> bool {
+        self.scope.is_all_tenants() || product.tenant_id == self.scope.tenant_id()
+    }
+
+    async fn invalidate(&self, ids: &[Uuid]) -> Result<()> {
+        for id in ids {
+            self.cache.delete(&key(*id)).await?;
+        }
+        Ok(())
+    }
+}
+
+fn key(id: Uuid) -> String {
+    format!("product:{}", id)
+}
+
+#[async_trait]
+impl ProductRepositoryTrait for CachedProductRepository {
+    async fn create(&self, request: CreateProductRequest) -> Result<Product> {
+        self.inner.create(request).await
+    }
+
+    async fn create_many(&self, requests: &[CreateProductRequest], atomic: bool) -> Result<Vec<Option<Product>>> {
+        self.inner.create_many(requests, atomic).await
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Product>> {
+        if let Some(product) = self.cache.get::<Product>(&key(id)).await? {
+            return Ok(Some(product).filter(|product| self.visible(product)));
+        }
+
+        let product = self.inner.find_by_id(id).await?;
+        if let Some(product) = &product {
+            if let Err(e) = self.cache.set(&key(id), product, Some(self.ttl)).await {
+                warn!("Failed to cache product {}: {}", id, e);
+            }
+        }
+        Ok(product)
+    }
+
+    async fn list(&self, pagination: PaginationParams) -> Result<ListResponse<Product>> {
+        self.inner.list(pagination).await
+    }
+
+    async fn update(&self, id: Uuid, request: UpdateProductRequest) -> Result<Option<Product>> {
+        let updated = self.inner.update(id, request).await?;
+        self.invalidate(&[id]).await?;
+        Ok(updated)
+    }
+
+    async fn update_prices(&self, updates: &[PriceUpdate], atomic: bool) -> Result<Vec<bool>> {
+        let updated = self.inner.update_prices(updates, atomic).await?;
+        let ids: Vec<Uuid> = updates.iter().map(|update| update.id).collect();
+        self.invalidate(&ids).await?;
+        Ok(updated)
+    }
+
+    async fn delete_many(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let deleted = self.inner.delete_many(ids).await?;
+        self.invalidate(ids).await?;
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl SoftDeleteRepository<Product, Uuid> for CachedProductRepository {
+    async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let deleted = self.inner.soft_delete(id).await?;
+        self.invalidate(&[id]).await?;
+        Ok(deleted)
+    }
+
+    async fn restore(&self, id: Uuid) -> Result<Option<Product>> {
+        let restored = self.inner.restore(id).await?;
+        self.invalidate(&[id]).await?;
+        Ok(restored)
+    }
+
+    async fn find_by_id_including_deleted(&self, id: Uuid) -> Result<Option<Product>> {
+        self.inner.find_by_id_including_deleted(id).await
+    }
+
+    async fn list_deleted(&self, pagination: PaginationParams) -> Result<ListResponse<Product>> {
+        self.inner.list_deleted(pagination).await
+    }
+}
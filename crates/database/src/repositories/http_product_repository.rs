@@ -0,0 +1,317 @@
+use async_trait::async_trait;
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+use app_core::{
+    config::HttpCatalogConfig,
+    enterprise::CircuitBreakerConfig,
+    error::{ApiError, Result},
+    traits::SoftDeleteRepository,
+    models::{Product, TenantScope, CreateProductRequest, UpdateProductRequest, PriceUpdate, PaginationParams, ListResponse},
+};
+use monitoring::{CircuitBreaker, DependencyHealthTracker};
+
+use super::ProductRepositoryTrait;
+
+/// Name the catalog is reported under in dependency health
+pub const PRODUCT_CATALOG_DEPENDENCY: &str = "product_catalog";
+
+/// Cached reads kept before the cache is swept
+const MAX_CACHE_ENTRIES: usize = 1000;
+
+/// Connection to an external product catalog, shared by every request.
+///
+/// Successful reads are cached for `cache_ttl` per tenant; any write through this instance
+/// clears the cache. Not-found answers aren't cached, so a product created elsewhere shows
+/// up straight away. Calls go through a circuit breaker, so an unreachable catalog fails fast with 503
+/// instead of holding requests for the full timeout.
+pub struct HttpProductCatalog {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, Value)>>,
+    breaker: Arc<CircuitBreaker>,
+    dependency_health: Arc<DependencyHealthTracker>,
+}
+
+impl HttpProductCatalog {
+    pub fn new(config: &HttpCatalogConfig, dependency_health: Arc<DependencyHealthTracker>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .build()
+            .map_err(anyhow::Error::from)?;
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::builder().build()?));
+        dependency_health.attach_breaker(PRODUCT_CATALOG_DEPENDENCY, breaker.clone());
+
+        Ok(Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            cache_ttl: Duration::from_secs(config.cache_ttl),
+            cache: Mutex::new(HashMap::new()),
+            breaker,
+            dependency_health,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str, scope: &TenantScope) -> RequestBuilder {
+        let mut request = self.client.request(method, format!("{}{}", self.base_url, path));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        if !scope.is_all_tenants() {
+            if let Some(tenant_id) = scope.tenant_id() {
+                request = request.header("x-tenant-id", tenant_id.to_string());
+            }
+        }
+        request
+    }
+
+    /// Send `request`, classifying the catalog's answer
+    async fn send(&self, request: RequestBuilder) -> Result<Reply> {
+        if !self.breaker.allow_request().await {
+            return Err(ApiError::ServiceUnavailable("Product catalog is unavailable".to_string()));
+        }
+
+        let started = Instant::now();
+        let result = request.send().await;
+        // Only transport errors and 5xx count against the catalog; 4xx are our problem
+        let healthy = matches!(&result, Ok(response) if !response.status().is_server_error());
+        self.breaker.record_outcome(healthy).await;
+        self.dependency_health.record(PRODUCT_CATALOG_DEPENDENCY, started.elapsed(), healthy);
+
+        let response = result.map_err(|e| {
+            warn!("Product catalog request failed: {}", e);
+            ApiError::ServiceUnavailable("Product catalog is unavailable".to_string())
+        })?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(Reply::NotFound),
+            StatusCode::NO_CONTENT => Ok(Reply::NoContent),
+            StatusCode::CONFLICT => Err(ApiError::Conflict("Product conflicts with an existing product".to_string())),
+            status if status.is_server_error() => {
+                warn!(status = status.as_u16(), "Product catalog returned a server error");
+                Err(ApiError::ServiceUnavailable("Product catalog is unavailable".to_string()))
+            }
+            status if !status.is_success() => {
+                Err(anyhow::anyhow!("Product catalog rejected request with status {}", status).into())
+            }
+            _ => Ok(Reply::Body(response.json().await.map_err(anyhow::Error::from)?)),
+        }
+    }
+
+    /// GET `path`, served from the cache while the last answer is fresh
+    async fn get_cached<T: DeserializeOwned>(&self, path: &str, scope: &TenantScope) -> Result<Option<T>> {
+        let key = format!("{}:{}:{}", scope.is_all_tenants(), scope.tenant_id().unwrap_or_default(), path);
+
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.cache_ttl)
+            .map(|(_, value)| value.clone());
+
+        let value = match cached {
+            Some(value) => value,
+            None => {
+                let Some(value) = self.send(self.request(Method::GET, path, scope)).await?.into_body()? else {
+                    return Ok(None);
+                };
+                if !self.cache_ttl.is_zero() {
+                    let mut cache = self.cache.lock().unwrap();
+                    if cache.len() >= MAX_CACHE_ENTRIES {
+                        cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.cache_ttl);
+                        if cache.len() >= MAX_CACHE_ENTRIES {
+                            cache.clear();
+                        }
+                    }
+                    cache.insert(key, (Instant::now(), value.clone()));
+                }
+                value
+            }
+        };
+
+        Ok(Some(serde_json::from_value(value).map_err(anyhow::Error::from)?))
+    }
+
+    /// Send a write and drop every cached read, since any of them may now be stale
+    async fn write(&self, request: RequestBuilder) -> Result<Reply> {
+        let result = self.send(request).await;
+        self.cache.lock().unwrap().clear();
+        result
+    }
+
+    /// Send a write answered with the resulting resource; `None` on 404
+    async fn write_returning<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<Option<T>> {
+        self.write(request)
+            .await?
+            .into_body()?
+            .map(|value| serde_json::from_value(value).map_err(|e| anyhow::Error::from(e).into()))
+            .transpose()
+    }
+}
+
+/// How the catalog answered a request that didn't fail
+enum Reply {
+    NotFound,
+    /// 204, e.g. for a delete
+    NoContent,
+    Body(Value),
+}
+
+impl Reply {
+    /// Whether the resource the request named exists
+    fn found(&self) -> bool {
+        !matches!(self, Reply::NotFound)
+    }
+
+    /// The body, for requests that are answered with one; `None` on 404
+    fn into_body(self) -> Result<Option<Value>> {
+        match self {
+            Reply::NotFound => Ok(None),
+            Reply::NoContent => Err(anyhow::anyhow!("Product catalog answered without a body").into()),
+            Reply::Body(value) => Ok(Some(value)),
+        }
+    }
+}
+
+/// Products repository backed by an external catalog, limited to what `scope` may see
+#[derive(Clone)]
+pub struct HttpProductRepository {
+    catalog: Arc<HttpProductCatalog>,
+    scope: TenantScope,
+}
+
+impl HttpProductRepository {
+    pub fn new(catalog: Arc<HttpProductCatalog>, scope: TenantScope) -> Self {
+        Self { catalog, scope }
+    }
+}
+
+#[async_trait]
+impl ProductRepositoryTrait for HttpProductRepository {
+    #[instrument(skip(self))]
+    async fn create(&self, request: CreateProductRequest) -> Result<Product> {
+        let request = self.catalog.request(Method::POST, "/products", &self.scope).json(&request);
+        self.catalog
+            .write_returning(request)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Product catalog has no products resource").into())
+    }
+
+    /// The catalog has no batch endpoint, so products are created one request at a time;
+    /// a 404 from the catalog (unknown category) yields `None`
+    #[instrument(skip(self, requests))]
+    async fn create_many(&self, requests: &[CreateProductRequest], atomic: bool) -> Result<Vec<Option<Product>>> {
+        if atomic {
+            return Err(ApiError::BadRequest(
+                "Atomic product imports are not supported by the product catalog".to_string(),
+            ));
+        }
+
+        let mut created = Vec::with_capacity(requests.len());
+        for request in requests {
+            let request = self.catalog.request(Method::POST, "/products", &self.scope).json(request);
+            created.push(self.catalog.write_returning(request).await?);
+        }
+        Ok(created)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Product>> {
+        self.catalog.get_cached(&format!("/products/{}", id), &self.scope).await
+    }
+
+    #[instrument(skip(self))]
+    async fn list(&self, pagination: PaginationParams) -> Result<ListResponse<Product>> {
+        let page = pagination.page.unwrap_or(1).max(1);
+        let per_page = pagination.per_page.unwrap_or(20).clamp(1, 100);
+        let path = format!("/products?page={}&per_page={}", page, per_page);
+
+        self.catalog
+            .get_cached(&path, &self.scope)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Product catalog has no products resource").into())
+    }
+
+    #[instrument(skip(self))]
+    async fn update(&self, id: Uuid, request: UpdateProductRequest) -> Result<Option<Product>> {
+        let request = self
+            .catalog
+            .request(Method::PATCH, &format!("/products/{}", id), &self.scope)
+            .json(&request);
+        self.catalog.write_returning(request).await
+    }
+
+    #[instrument(skip(self, updates))]
+    async fn update_prices(&self, updates: &[PriceUpdate], atomic: bool) -> Result<Vec<bool>> {
+        if atomic {
+            return Err(ApiError::BadRequest(
+                "Atomic price updates are not supported by the product catalog".to_string(),
+            ));
+        }
+
+        let mut applied = Vec::with_capacity(updates.len());
+        for update in updates {
+            let request = self
+                .catalog
+                .request(Method::PATCH, &format!("/products/{}", update.id), &self.scope)
+                .json(&serde_json::json!({ "price": update.new_price }));
+            applied.push(self.catalog.write(request).await?.found());
+        }
+        Ok(applied)
+    }
+
+    #[instrument(skip(self, ids))]
+    async fn delete_many(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let mut deleted = Vec::with_capacity(ids.len());
+        for id in ids {
+            deleted.push(self.soft_delete(*id).await?);
+        }
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl SoftDeleteRepository<Product, Uuid> for HttpProductRepository {
+    #[instrument(skip(self))]
+    async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let request = self.catalog.request(Method::DELETE, &format!("/products/{}", id), &self.scope);
+        Ok(self.catalog.write(request).await?.found())
+    }
+
+    #[instrument(skip(self))]
+    async fn restore(&self, id: Uuid) -> Result<Option<Product>> {
+        let request = self
+            .catalog
+            .request(Method::POST, &format!("/products/{}/restore", id), &self.scope);
+        self.catalog.write_returning(request).await
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id_including_deleted(&self, id: Uuid) -> Result<Option<Product>> {
+        self.catalog
+            .get_cached(&format!("/products/{}?include_deleted=true", id), &self.scope)
+            .await
+    }
+
+    #[instrument(skip(self))]
+    async fn list_deleted(&self, pagination: PaginationParams) -> Result<ListResponse<Product>> {
+        let page = pagination.page.unwrap_or(1).max(1);
+        let per_page = pagination.per_page.unwrap_or(20).clamp(1, 100);
+        let path = format!("/products/deleted?page={}&per_page={}", page, per_page);
+
+        self.catalog
+            .get_cached(&path, &self.scope)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Product catalog has no deleted products resource").into())
+    }
+}
@@ -1,5 +1,9 @@
 pub mod user_repository;
 pub mod product_repository;
+pub mod http_product_repository;
+pub mod cached_product_repository;
 
 pub use user_repository::*;
 pub use product_repository::*;
+pub use http_product_repository::*;
+pub use cached_product_repository::*;
@@ -3,30 +3,41 @@ use sqlx::PgPool;
 use time::OffsetDateTime;
 use tracing::instrument;
 use uuid::Uuid;
+use std::collections::HashSet;
 use std::option::Option;
+use std::time::Instant;
 
+use crate::outbox;
+use crate::query_plan::QueryPlanLogger;
+use crate::timed_pool::TimedPool;
 use app_core::{
     error::Result,
-    models::{Product, CreateProductRequest, PaginationParams, ListResponse, PaginationMetadata},
+    traits::SoftDeleteRepository,
+    models::{Product, TenantScope, CreateProductRequest, UpdateProductRequest, PriceUpdate, PaginationParams, ListResponse, PaginationMetadata},
 };
 
 #[async_trait]
-pub trait ProductRepositoryTrait: Send + Sync {
+pub trait ProductRepositoryTrait: SoftDeleteRepository<Product, Uuid> {
     async fn create(&self, request: CreateProductRequest) -> Result<Product>;
+    async fn create_many(&self, requests: &[CreateProductRequest], atomic: bool) -> Result<Vec<Option<Product>>>;
     async fn find_by_id(&self, id: Uuid) -> Result<Option<Product>>;
     async fn list(&self, pagination: PaginationParams) -> Result<ListResponse<Product>>;
-    async fn update(&self, id: Uuid, request: CreateProductRequest) -> Result<Option<Product>>;
-    async fn delete(&self, id: Uuid) -> Result<bool>;
+    async fn update(&self, id: Uuid, request: UpdateProductRequest) -> Result<Option<Product>>;
+    async fn update_prices(&self, updates: &[PriceUpdate], atomic: bool) -> Result<Vec<bool>>;
+    async fn delete_many(&self, ids: &[Uuid]) -> Result<Vec<bool>>;
 }
 
 #[derive(Clone)]
 pub struct ProductRepository {
-    pool: PgPool,
+    pool: TimedPool,
+    query_plans: QueryPlanLogger,
+    scope: TenantScope,
 }
 
 impl ProductRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    /// Repository whose queries only see products visible to `scope`
+    pub fn new(pool: PgPool, query_plans: QueryPlanLogger, scope: TenantScope) -> Self {
+        Self { pool: TimedPool::new(pool), query_plans, scope }
     }
 }
 
@@ -36,12 +47,13 @@ impl ProductRepositoryTrait for ProductRepository {
     async fn create(&self, request: CreateProductRequest) -> Result<Product> {
         let id = Uuid::new_v4();
         let now = OffsetDateTime::now_utc();
+        let mut tx = self.pool.begin().await?;
 
         let product = sqlx::query_as!(
             Product,
             r#"
-            INSERT INTO products (id, name, description, price, category_id, is_active, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO products (id, tenant_id, name, description, price, category_id, is_active, created_at, updated_at)
+            VALUES ($1, $9, $2, $3, $4, $5, $6, $7, $8)
             RETURNING *
             "#,
             id,
@@ -51,20 +63,83 @@ impl ProductRepositoryTrait for ProductRepository {
             request.category_id,
             true,
             now,
-            now
+            now,
+            self.scope.tenant_id()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+        outbox::enqueue(&mut tx, "product", product.id, "product.created", &product).await?;
+        tx.commit().await?;
+
         Ok(product)
     }
 
+    /// Insert products in a single transaction, returning each created product, or `None`
+    /// where its category doesn't exist. With `atomic`, any miss rolls the whole batch back.
+    #[instrument(skip(self, requests))]
+    async fn create_many(&self, requests: &[CreateProductRequest], atomic: bool) -> Result<Vec<Option<Product>>> {
+        let now = OffsetDateTime::now_utc();
+        let category_ids: Vec<Uuid> = requests.iter().map(|request| request.category_id).collect();
+        let mut tx = self.pool.begin().await?;
+
+        // Checked up front: a foreign key violation would abort the whole transaction
+        let categories: HashSet<Uuid> = sqlx::query_scalar!(
+            "SELECT id FROM categories WHERE id = ANY($1)",
+            &category_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let mut created = Vec::with_capacity(requests.len());
+        for request in requests {
+            if !categories.contains(&request.category_id) {
+                created.push(None);
+                continue;
+            }
+
+            let product = sqlx::query_as!(
+                Product,
+                r#"
+                INSERT INTO products (id, tenant_id, name, description, price, category_id, is_active, created_at, updated_at)
+                VALUES ($1, $8, $2, $3, $4, $5, true, $6, $7)
+                RETURNING *
+                "#,
+                Uuid::new_v4(),
+                request.name,
+                request.description,
+                request.price,
+                request.category_id,
+                now,
+                now,
+                self.scope.tenant_id()
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            outbox::enqueue(&mut tx, "product", product.id, "product.created", &product).await?;
+            created.push(Some(product));
+        }
+
+        if atomic && created.iter().any(Option::is_none) {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok(created)
+    }
+
     #[instrument(skip(self))]
     async fn find_by_id(&self, id: Uuid) -> Result<Option<Product>> {
         let product = sqlx::query_as!(
             Product,
-            "SELECT * FROM products WHERE id = $1",
-            id
+            "SELECT * FROM products WHERE id = $1 AND deleted_at IS NULL AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)",
+            id,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -79,21 +154,41 @@ impl ProductRepositoryTrait for ProductRepository {
         let offset = (page - 1) * per_page;
 
         let total_count = sqlx::query_scalar!(
-            "SELECT COUNT(*) FROM products WHERE is_active = true"
+            "SELECT COUNT(*) FROM products WHERE is_active = true AND deleted_at IS NULL AND ($1 OR tenant_id IS NOT DISTINCT FROM $2)",
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_one(&self.pool)
         .await?
         .unwrap_or(0) as u64;
 
+        let query_start = Instant::now();
         let products = sqlx::query_as!(
             Product,
-            "SELECT * FROM products WHERE is_active = true ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+            "SELECT * FROM products WHERE is_active = true AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4) ORDER BY created_at DESC LIMIT $1 OFFSET $2",
             per_page as i64,
-            offset as i64
+            offset as i64,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_all(&self.pool)
         .await?;
 
+        let elapsed = query_start.elapsed();
+        if self.query_plans.should_explain(elapsed) {
+            self.query_plans.log_plan(
+                "products.list",
+                elapsed,
+                sqlx::query(
+                    "EXPLAIN (ANALYZE, BUFFERS) SELECT * FROM products WHERE is_active = true AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4) ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+                )
+                .bind(per_page as i64)
+                .bind(offset as i64)
+                .bind(self.scope.is_all_tenants())
+                .bind(self.scope.tenant_id()),
+            ).await;
+        }
+
         let total_pages = ((total_count as f64) / (per_page as f64)).ceil() as u32;
 
         Ok(ListResponse {
@@ -108,44 +203,215 @@ impl ProductRepositoryTrait for ProductRepository {
     }
 
     #[instrument(skip(self))]
-    async fn update(&self, id: Uuid, request: CreateProductRequest) -> Result<Option<Product>> {
+    async fn update(&self, id: Uuid, request: UpdateProductRequest) -> Result<Option<Product>> {
         let now = OffsetDateTime::now_utc();
+        let mut tx = self.pool.begin().await?;
 
+        // Omitted fields keep their current value; an explicit null clears the description
         let product = sqlx::query_as!(
             Product,
             r#"
             UPDATE products
-            SET name = $2,
-                description = $3,
-                price = $4,
-                category_id = $5,
-                updated_at = $6
-            WHERE id = $1
+            SET name = COALESCE($2, name),
+                description = CASE WHEN $3 THEN $4 ELSE description END,
+                price = COALESCE($5, price),
+                category_id = COALESCE($6, category_id),
+                updated_at = $7
+            WHERE id = $1 AND deleted_at IS NULL
+              AND ($8 OR tenant_id IS NOT DISTINCT FROM $9)
             RETURNING *
             "#,
             id,
             request.name,
-            request.description,
+            !request.description.is_undefined(),
+            request.description.as_option(),
             request.price,
             request.category_id,
-            now
+            now,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *tx)
         .await?;
 
+        if let Some(product) = &product {
+            outbox::enqueue(&mut tx, "product", product.id, "product.updated", product).await?;
+        }
+        tx.commit().await?;
+
         Ok(product)
     }
 
+    /// Apply price changes in a single transaction, returning whether each id matched
+    /// an active product. With `atomic`, any miss rolls the whole batch back.
+    #[instrument(skip(self, updates))]
+    async fn update_prices(&self, updates: &[PriceUpdate], atomic: bool) -> Result<Vec<bool>> {
+        let now = OffsetDateTime::now_utc();
+        let mut tx = self.pool.begin().await?;
+        let mut applied = Vec::with_capacity(updates.len());
+
+        for update in updates {
+            let result = sqlx::query!(
+                "UPDATE products SET price = $2, updated_at = $3 WHERE id = $1 AND is_active = true AND deleted_at IS NULL AND ($4 OR tenant_id IS NOT DISTINCT FROM $5)",
+                update.id,
+                update.new_price,
+                now,
+                self.scope.is_all_tenants(),
+                self.scope.tenant_id()
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let matched = result.rows_affected() > 0;
+            if matched {
+                outbox::enqueue(&mut tx, "product", update.id, "product.price_updated", update).await?;
+            }
+            applied.push(matched);
+        }
+
+        if atomic && applied.contains(&false) {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok(applied)
+    }
+
+    /// Soft delete every id in one statement, returning whether each was deleted
+    #[instrument(skip(self, ids))]
+    async fn delete_many(&self, ids: &[Uuid]) -> Result<Vec<bool>> {
+        let mut tx = self.pool.begin().await?;
+        let deleted: HashSet<Uuid> = sqlx::query_scalar!(
+            r#"
+            UPDATE products
+            SET is_active = false, deleted_at = $2, updated_at = $2
+            WHERE id = ANY($1) AND deleted_at IS NULL
+              AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)
+            RETURNING id
+            "#,
+            ids,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        for id in &deleted {
+            outbox::enqueue(&mut tx, "product", *id, "product.deleted", &serde_json::json!({ "id": id })).await?;
+        }
+        tx.commit().await?;
+
+        Ok(ids.iter().map(|id| deleted.contains(id)).collect())
+    }
+}
+
+#[async_trait]
+impl SoftDeleteRepository<Product, Uuid> for ProductRepository {
     #[instrument(skip(self))]
-    async fn delete(&self, id: Uuid) -> Result<bool> {
+    async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
         let result = sqlx::query!(
-            "UPDATE products SET is_active = false, updated_at = $2 WHERE id = $1",
+            "UPDATE products SET is_active = false, deleted_at = $2, updated_at = $2 WHERE id = $1 AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)",
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let deleted = result.rows_affected() > 0;
+        if deleted {
+            outbox::enqueue(&mut tx, "product", id, "product.deleted", &serde_json::json!({ "id": id })).await?;
+        }
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    #[instrument(skip(self))]
+    async fn restore(&self, id: Uuid) -> Result<Option<Product>> {
+        let mut tx = self.pool.begin().await?;
+        let product = sqlx::query_as!(
+            Product,
+            r#"
+            UPDATE products
+            SET is_active = true, deleted_at = NULL, updated_at = $2
+            WHERE id = $1 AND deleted_at IS NOT NULL
+              AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)
+            RETURNING *
+            "#,
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(product) = &product {
+            outbox::enqueue(&mut tx, "product", product.id, "product.restored", product).await?;
+        }
+        tx.commit().await?;
+
+        Ok(product)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id_including_deleted(&self, id: Uuid) -> Result<Option<Product>> {
+        let product = sqlx::query_as!(
+            Product,
+            "SELECT * FROM products WHERE id = $1 AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)",
             id,
-            OffsetDateTime::now_utc()
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(product)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_deleted(&self, pagination: PaginationParams) -> Result<ListResponse<Product>> {
+        let page = pagination.page.unwrap_or(1);
+        let per_page = pagination.per_page.unwrap_or(20).min(100);
+        let offset = (page - 1) * per_page;
+
+        let total_count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM products WHERE deleted_at IS NOT NULL AND ($1 OR tenant_id IS NOT DISTINCT FROM $2)",
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .unwrap_or(0) as u64;
+
+        let products = sqlx::query_as!(
+            Product,
+            "SELECT * FROM products WHERE deleted_at IS NOT NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4) ORDER BY deleted_at DESC LIMIT $1 OFFSET $2",
+            per_page as i64,
+            offset as i64,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        let total_pages = ((total_count as f64) / (per_page as f64)).ceil() as u32;
+
+        Ok(ListResponse {
+            data: products,
+            pagination: PaginationMetadata {
+                page,
+                per_page,
+                total: total_count,
+                total_pages,
+            },
+        })
     }
 }
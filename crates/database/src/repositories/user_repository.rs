@@ -1,38 +1,171 @@
 use async_trait::async_trait;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use time::OffsetDateTime;
 use tracing::instrument;
 use uuid::Uuid;
 use std::pin;
 use std::future;
 use std::option::Option;
+use std::time::Instant;
 
+use crate::query_plan::QueryPlanLogger;
+use crate::timed_pool::TimedPool;
 use app_core::{
-    error::Result,
-    models::{User, CreateUserRequest, UpdateUserRequest, PaginationParams, ListResponse, PaginationMetadata},
+    config::SessionLimitPolicy,
+    error::{ApiError, Result},
+    traits::SoftDeleteRepository,
+    models::{ActiveSession, Email, MfaSettings, SessionStart, TenantScope, User, Username, CreateUserRequest, UpdateUserRequest, PaginationParams, ListResponse, PaginationMetadata},
 };
 
 #[async_trait]
-pub trait UserRepositoryTrait: Send + Sync {
+pub trait UserRepositoryTrait: SoftDeleteRepository<User, Uuid> {
     async fn create(&self, request: CreateUserRequest, password_hash: String) -> Result<User>;
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>>;
-    async fn find_by_email(&self, email: &str) -> Result<Option<User>>;
-    async fn find_by_username(&self, username: &str) -> Result<Option<User>>;
+    async fn find_by_email(&self, email: &Email) -> Result<Option<User>>;
+    async fn find_by_username(&self, username: &Username) -> Result<Option<User>>;
     async fn list(&self, pagination: PaginationParams) -> Result<ListResponse<User>>;
     async fn update(&self, id: Uuid, request: UpdateUserRequest) -> Result<Option<User>>;
-    async fn delete(&self, id: Uuid) -> Result<bool>;
     async fn activate(&self, id: Uuid) -> Result<bool>;
     async fn deactivate(&self, id: Uuid) -> Result<bool>;
+    async fn record_login(&self, id: Uuid) -> Result<()>;
+    /// Roles to issue the user's tokens with: `user`, plus any granted in `user_roles`
+    async fn roles(&self, id: Uuid) -> Result<Vec<String>>;
+    async fn update_password_hash(&self, id: Uuid, password_hash: String) -> Result<bool>;
+    /// Hashes of the user's most recent previous passwords, newest first
+    async fn password_history(&self, id: Uuid, limit: i64) -> Result<Vec<String>>;
+    /// Set a new password, moving the current hash into the history and keeping at most
+    /// `history_size` previous hashes
+    async fn change_password(&self, id: Uuid, password_hash: String, history_size: i64) -> Result<bool>;
+    /// Store a reset token for the user, replacing any outstanding one
+    async fn request_password_reset(&self, id: Uuid, token_hash: &str, expires_at: OffsetDateTime) -> Result<()>;
+    /// User an unexpired reset token belongs to, without consuming the token
+    async fn find_password_reset(&self, token_hash: &str) -> Result<Option<Uuid>>;
+    /// Consume the reset token `token_hash` and set its user's password as `change_password`
+    /// does, revoking every session of the user. `None` if the token was used or expired.
+    async fn update_password(&self, token_hash: &str, password_hash: String, history_size: i64) -> Result<Option<Uuid>>;
+    /// The user's TOTP enrollment; `None` if there is no such user
+    async fn mfa_settings(&self, id: Uuid) -> Result<Option<MfaSettings>>;
+    /// Store a new TOTP secret for the user, not yet required at login. `false` if the
+    /// user already has MFA enabled.
+    async fn set_pending_mfa_secret(&self, id: Uuid, secret: &str) -> Result<bool>;
+    /// Require the enrolled secret at login from now on
+    async fn enable_mfa(&self, id: Uuid) -> Result<bool>;
+    /// Mark the TOTP time step `step` as used. `false` if it, or a later one, already was,
+    /// meaning the code is being replayed.
+    async fn record_mfa_step(&self, id: Uuid, step: i64) -> Result<bool>;
+    /// Store a login challenge for the user, replacing any outstanding one
+    async fn create_mfa_challenge(&self, id: Uuid, token_hash: &str, expires_at: OffsetDateTime) -> Result<()>;
+    /// User an unexpired challenge belongs to, without consuming it
+    async fn find_mfa_challenge(&self, token_hash: &str) -> Result<Option<Uuid>>;
+    /// Count a wrong code against the challenge, discarding it after `max_attempts`
+    async fn fail_mfa_challenge(&self, token_hash: &str, max_attempts: i32) -> Result<()>;
+    /// Consume the challenge, returning its user if it was still unexpired
+    async fn consume_mfa_challenge(&self, token_hash: &str) -> Result<Option<Uuid>>;
+    /// Store `new_email` as the user's pending email, replacing any earlier pending change
+    async fn request_email_change(&self, id: Uuid, new_email: &Email, token_hash: &str, expires_at: OffsetDateTime) -> Result<()>;
+    /// Apply the pending change identified by `token_hash`, returning the updated user and
+    /// the previous email; `None` if the token is unknown or expired
+    async fn confirm_email_change(&self, token_hash: &str) -> Result<Option<(User, Email)>>;
+    /// Record a login session identified by `refresh_token_hash`. With a `limit`, a user
+    /// already holding that many active sessions is refused (`None`) or has the oldest
+    /// revoked, depending on the policy.
+    async fn create_session(
+        &self,
+        id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: OffsetDateTime,
+        limit: Option<(usize, SessionLimitPolicy)>,
+    ) -> Result<Option<SessionStart>>;
+    /// Unrevoked session identified by `refresh_token_hash`, if it expires after `valid_at`
+    async fn find_session(&self, refresh_token_hash: &str, valid_at: OffsetDateTime) -> Result<Option<ActiveSession>>;
+    /// Revoke one of the user's sessions, e.g. at logout, so its refresh token stops
+    /// working. `false` if the session isn't theirs or was already revoked.
+    async fn revoke_session(&self, id: Uuid, session_id: Uuid) -> Result<bool>;
+    /// Sessions of the user that are neither revoked nor expired
+    async fn count_active_sessions(&self, id: Uuid) -> Result<i64>;
+    async fn deactivate_inactive(
+        &self,
+        inactive_since: OffsetDateTime,
+        exempt_usernames: &[String],
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>>;
+    /// Ids of up to `limit` active users, most recently logged in (or created) first
+    async fn recently_active_ids(&self, limit: i64) -> Result<Vec<Uuid>>;
 }
 
 #[derive(Clone)]
 pub struct UserRepository {
-    pool: PgPool,
+    pool: TimedPool,
+    query_plans: QueryPlanLogger,
+    scope: TenantScope,
 }
 
 impl UserRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    /// Repository whose queries only see users visible to `scope`
+    pub fn new(pool: PgPool, query_plans: QueryPlanLogger, scope: TenantScope) -> Self {
+        Self { pool: TimedPool::new(pool), query_plans, scope }
+    }
+
+    /// Replace the password of a user visible to this scope inside `tx`, moving the old
+    /// hash into the history and trimming it to `history_size`. `false` if no such user.
+    async fn set_password(
+        &self,
+        tx: &mut PgConnection,
+        id: Uuid,
+        password_hash: String,
+        history_size: i64,
+    ) -> Result<bool> {
+        let now = OffsetDateTime::now_utc();
+
+        let previous = sqlx::query_scalar!(
+            "SELECT password_hash FROM users WHERE id = $1 AND deleted_at IS NULL AND ($2 OR tenant_id IS NOT DISTINCT FROM $3) FOR UPDATE",
+            id,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(previous) = previous else {
+            return Ok(false);
+        };
+
+        sqlx::query!(
+            "UPDATE users SET password_hash = $2, updated_at = $3 WHERE id = $1",
+            id,
+            password_hash,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if history_size > 0 {
+            sqlx::query!(
+                "INSERT INTO password_history (id, user_id, password_hash, created_at) VALUES ($1, $2, $3, $4)",
+                Uuid::new_v4(),
+                id,
+                previous,
+                now
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Drop entries beyond the configured history
+        sqlx::query!(
+            r#"
+            DELETE FROM password_history
+            WHERE user_id = $1 AND id NOT IN (
+                SELECT id FROM password_history WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2
+            )
+            "#,
+            id,
+            history_size
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        Ok(true)
     }
 }
 
@@ -46,17 +179,18 @@ impl UserRepositoryTrait for UserRepository {
         let user = sqlx::query_as!(
             User,
             r#"
-            INSERT INTO users (id, username, email, password_hash, is_active, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
-            RETURNING *
+            INSERT INTO users (id, tenant_id, username, email, password_hash, is_active, created_at, updated_at)
+            VALUES ($1, $8, $2, $3, $4, $5, $6, $7)
+            RETURNING id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
             "#,
             id,
-            request.username,
-            request.email,
+            request.username.as_str(),
+            request.email.as_str(),
             password_hash,
             true,
             now,
-            now
+            now,
+            self.scope.tenant_id()
         )
         .fetch_one(&self.pool)
         .await?;
@@ -68,8 +202,15 @@ impl UserRepositoryTrait for UserRepository {
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>> {
         let user = sqlx::query_as!(
             User,
-            "SELECT * FROM users WHERE id = $1",
-            id
+            r#"
+            SELECT id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL
+              AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)
+            "#,
+            id,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -78,11 +219,18 @@ impl UserRepositoryTrait for UserRepository {
     }
 
     #[instrument(skip(self))]
-    async fn find_by_email(&self, email: &str) -> Result<Option<User>> {
+    async fn find_by_email(&self, email: &Email) -> Result<Option<User>> {
         let user = sqlx::query_as!(
             User,
-            "SELECT * FROM users WHERE email = $1",
-            email
+            r#"
+            SELECT id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            FROM users
+            WHERE email = $1 AND deleted_at IS NULL
+              AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)
+            "#,
+            email.as_str(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -91,11 +239,18 @@ impl UserRepositoryTrait for UserRepository {
     }
 
     #[instrument(skip(self))]
-    async fn find_by_username(&self, username: &str) -> Result<Option<User>> {
+    async fn find_by_username(&self, username: &Username) -> Result<Option<User>> {
         let user = sqlx::query_as!(
             User,
-            "SELECT * FROM users WHERE username = $1",
-            username
+            r#"
+            SELECT id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            FROM users
+            WHERE username = $1 AND deleted_at IS NULL
+              AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)
+            "#,
+            username.as_str(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -111,22 +266,47 @@ impl UserRepositoryTrait for UserRepository {
 
         // Get total count
         let total_count = sqlx::query_scalar!(
-            "SELECT COUNT(*) FROM users"
+            "SELECT COUNT(*) FROM users WHERE deleted_at IS NULL AND ($1 OR tenant_id IS NOT DISTINCT FROM $2)",
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_one(&self.pool)
         .await?
         .unwrap_or(0) as u64;
 
         // Get users
+        let query_start = Instant::now();
         let users = sqlx::query_as!(
             User,
-            "SELECT * FROM users ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+            r#"
+            SELECT id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            FROM users
+            WHERE deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)
+            ORDER BY created_at DESC LIMIT $1 OFFSET $2
+            "#,
             per_page as i64,
-            offset as i64
+            offset as i64,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_all(&self.pool)
         .await?;
 
+        let elapsed = query_start.elapsed();
+        if self.query_plans.should_explain(elapsed) {
+            self.query_plans.log_plan(
+                "users.list",
+                elapsed,
+                sqlx::query(
+                    "EXPLAIN (ANALYZE, BUFFERS) SELECT * FROM users WHERE deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4) ORDER BY created_at DESC LIMIT $1 OFFSET $2"
+                )
+                .bind(per_page as i64)
+                .bind(offset as i64)
+                .bind(self.scope.is_all_tenants())
+                .bind(self.scope.tenant_id()),
+            ).await;
+        }
+
         let total_pages = ((total_count as f64) / (per_page as f64)).ceil() as u32;
 
         Ok(ListResponse {
@@ -151,13 +331,16 @@ impl UserRepositoryTrait for UserRepository {
             SET username = COALESCE($2, username),
                 email = COALESCE($3, email),
                 updated_at = $4
-            WHERE id = $1
-            RETURNING *
+            WHERE id = $1 AND deleted_at IS NULL
+              AND ($5 OR tenant_id IS NOT DISTINCT FROM $6)
+            RETURNING id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
             "#,
             id,
-            request.username,
-            request.email,
-            now
+            request.username.as_ref().map(Username::as_str),
+            request.email.as_ref().map(Email::as_str),
+            now,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .fetch_optional(&self.pool)
         .await?;
@@ -166,10 +349,13 @@ impl UserRepositoryTrait for UserRepository {
     }
 
     #[instrument(skip(self))]
-    async fn delete(&self, id: Uuid) -> Result<bool> {
+    async fn activate(&self, id: Uuid) -> Result<bool> {
         let result = sqlx::query!(
-            "DELETE FROM users WHERE id = $1",
-            id
+            "UPDATE users SET is_active = true, updated_at = $2 WHERE id = $1 AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)",
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
         )
         .execute(&self.pool)
         .await?;
@@ -178,23 +364,477 @@ impl UserRepositoryTrait for UserRepository {
     }
 
     #[instrument(skip(self))]
-    async fn activate(&self, id: Uuid) -> Result<bool> {
+    async fn deactivate(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE users SET is_active = false, updated_at = $2 WHERE id = $1 AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)",
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_login(&self, id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "UPDATE users SET last_login_at = $2 WHERE id = $1 AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)",
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn roles(&self, id: Uuid) -> Result<Vec<String>> {
+        let granted = sqlx::query_scalar!(
+            "SELECT role FROM user_roles WHERE user_id = $1 AND role <> 'user' ORDER BY role",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(std::iter::once("user".to_string()).chain(granted).collect())
+    }
+
+    #[instrument(skip(self, password_hash))]
+    async fn update_password_hash(&self, id: Uuid, password_hash: String) -> Result<bool> {
         let result = sqlx::query!(
-            "UPDATE users SET is_active = true, updated_at = $2 WHERE id = $1",
+            "UPDATE users SET password_hash = $2, updated_at = $3 WHERE id = $1 AND ($4 OR tenant_id IS NOT DISTINCT FROM $5)",
+            id,
+            password_hash,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn password_history(&self, id: Uuid, limit: i64) -> Result<Vec<String>> {
+        let hashes = sqlx::query_scalar!(
+            "SELECT password_hash FROM password_history WHERE user_id = $1 ORDER BY created_at DESC LIMIT $2",
+            id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(hashes)
+    }
+
+    #[instrument(skip(self, password_hash))]
+    async fn change_password(&self, id: Uuid, password_hash: String, history_size: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        if !self.set_password(&mut tx, id, password_hash, history_size).await? {
+            return Ok(false);
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn request_password_reset(&self, id: Uuid, token_hash: &str, expires_at: OffsetDateTime) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (id, user_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE
+            SET token_hash = EXCLUDED.token_hash,
+                expires_at = EXCLUDED.expires_at,
+                created_at = EXCLUDED.created_at
+            "#,
+            Uuid::new_v4(),
             id,
+            token_hash,
+            expires_at,
             OffsetDateTime::now_utc()
         )
         .execute(&self.pool)
         .await?;
 
+        Ok(())
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn find_password_reset(&self, token_hash: &str) -> Result<Option<Uuid>> {
+        let user_id = sqlx::query_scalar!(
+            "SELECT user_id FROM password_reset_tokens WHERE token_hash = $1 AND expires_at > $2",
+            token_hash,
+            OffsetDateTime::now_utc()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    #[instrument(skip(self, token_hash, password_hash))]
+    async fn update_password(&self, token_hash: &str, password_hash: String, history_size: i64) -> Result<Option<Uuid>> {
+        let now = OffsetDateTime::now_utc();
+        let mut tx = self.pool.begin().await?;
+
+        // Tokens are single use: consumed whether or not they are still valid
+        let pending = sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE token_hash = $1 RETURNING user_id, expires_at",
+            token_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(pending) = pending.filter(|pending| pending.expires_at > now) else {
+            tx.commit().await?;
+            return Ok(None);
+        };
+
+        if !self.set_password(&mut tx, pending.user_id, password_hash, history_size).await? {
+            tx.commit().await?;
+            return Ok(None);
+        }
+
+        // Whoever knew the old password may hold a session; make them sign in again
+        sqlx::query!(
+            "UPDATE sessions SET revoked_at = $2 WHERE user_id = $1 AND revoked_at IS NULL",
+            pending.user_id,
+            now
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(pending.user_id))
+    }
+
+    #[instrument(skip(self))]
+    async fn mfa_settings(&self, id: Uuid) -> Result<Option<MfaSettings>> {
+        let settings = sqlx::query_as!(
+            MfaSettings,
+            r#"
+            SELECT mfa_secret as secret, mfa_enabled as enabled
+            FROM users
+            WHERE id = $1 AND deleted_at IS NULL AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)
+            "#,
+            id,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(settings)
+    }
+
+    #[instrument(skip(self, secret))]
+    async fn set_pending_mfa_secret(&self, id: Uuid, secret: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET mfa_secret = $2, mfa_last_used_step = NULL, updated_at = $3
+            WHERE id = $1 AND NOT mfa_enabled AND deleted_at IS NULL AND ($4 OR tenant_id IS NOT DISTINCT FROM $5)
+            "#,
+            id,
+            secret,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&self.pool)
+        .await?;
+
         Ok(result.rows_affected() > 0)
     }
 
     #[instrument(skip(self))]
-    async fn deactivate(&self, id: Uuid) -> Result<bool> {
+    async fn enable_mfa(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users SET mfa_enabled = true, updated_at = $2
+            WHERE id = $1 AND mfa_secret IS NOT NULL AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)
+            "#,
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn record_mfa_step(&self, id: Uuid, step: i64) -> Result<bool> {
+        // Conditional update so two requests racing with the same code can't both pass
+        let result = sqlx::query!(
+            "UPDATE users SET mfa_last_used_step = $2 WHERE id = $1 AND (mfa_last_used_step IS NULL OR mfa_last_used_step < $2)",
+            id,
+            step
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn create_mfa_challenge(&self, id: Uuid, token_hash: &str, expires_at: OffsetDateTime) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO mfa_challenges (id, user_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE
+            SET token_hash = EXCLUDED.token_hash,
+                failed_attempts = 0,
+                expires_at = EXCLUDED.expires_at,
+                created_at = EXCLUDED.created_at
+            "#,
+            Uuid::new_v4(),
+            id,
+            token_hash,
+            expires_at,
+            OffsetDateTime::now_utc()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn find_mfa_challenge(&self, token_hash: &str) -> Result<Option<Uuid>> {
+        let user_id = sqlx::query_scalar!(
+            "SELECT user_id FROM mfa_challenges WHERE token_hash = $1 AND expires_at > $2",
+            token_hash,
+            OffsetDateTime::now_utc()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user_id)
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn fail_mfa_challenge(&self, token_hash: &str, max_attempts: i32) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let failed_attempts = sqlx::query_scalar!(
+            "UPDATE mfa_challenges SET failed_attempts = failed_attempts + 1 WHERE token_hash = $1 RETURNING failed_attempts",
+            token_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if failed_attempts.is_some_and(|failed_attempts| failed_attempts >= max_attempts) {
+            sqlx::query!("DELETE FROM mfa_challenges WHERE token_hash = $1", token_hash)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn consume_mfa_challenge(&self, token_hash: &str) -> Result<Option<Uuid>> {
+        let challenge = sqlx::query!(
+            "DELETE FROM mfa_challenges WHERE token_hash = $1 RETURNING user_id, expires_at",
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(challenge
+            .filter(|challenge| challenge.expires_at > OffsetDateTime::now_utc())
+            .map(|challenge| challenge.user_id))
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn request_email_change(&self, id: Uuid, new_email: &Email, token_hash: &str, expires_at: OffsetDateTime) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_change_requests (id, user_id, new_email, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id) DO UPDATE
+            SET new_email = EXCLUDED.new_email,
+                token_hash = EXCLUDED.token_hash,
+                expires_at = EXCLUDED.expires_at,
+                created_at = EXCLUDED.created_at
+            "#,
+            Uuid::new_v4(),
+            id,
+            new_email.as_str(),
+            token_hash,
+            expires_at,
+            OffsetDateTime::now_utc()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, token_hash))]
+    async fn confirm_email_change(&self, token_hash: &str) -> Result<Option<(User, Email)>> {
+        let now = OffsetDateTime::now_utc();
+        let mut tx = self.pool.begin().await?;
+
+        // Tokens are single use: consumed whether or not they are still valid
+        let pending = sqlx::query!(
+            "DELETE FROM email_change_requests WHERE token_hash = $1 RETURNING user_id, new_email, expires_at",
+            token_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(pending) = pending.filter(|pending| pending.expires_at > now) else {
+            tx.commit().await?;
+            return Ok(None);
+        };
+
+        let previous = sqlx::query_scalar!(
+            r#"SELECT email as "email: Email" FROM users WHERE id = $1 AND deleted_at IS NULL AND ($2 OR tenant_id IS NOT DISTINCT FROM $3) FOR UPDATE"#,
+            pending.user_id,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(previous) = previous else {
+            tx.commit().await?;
+            return Ok(None);
+        };
+
+        // The address may have been claimed by another account since the change was requested
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE email = $1 AND id <> $2) as "exists!""#,
+            pending.new_email,
+            pending.user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if taken {
+            tx.commit().await?;
+            return Err(ApiError::Conflict("Email already in use".to_string()));
+        }
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users SET email = $2, updated_at = $3
+            WHERE id = $1
+            RETURNING id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            "#,
+            pending.user_id,
+            pending.new_email,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some((user, previous)))
+    }
+
+    #[instrument(skip(self, refresh_token_hash))]
+    async fn create_session(
+        &self,
+        id: Uuid,
+        refresh_token_hash: &str,
+        expires_at: OffsetDateTime,
+        limit: Option<(usize, SessionLimitPolicy)>,
+    ) -> Result<Option<SessionStart>> {
+        let now = OffsetDateTime::now_utc();
+        let mut tx = self.pool.begin().await?;
+
+        // Serializes concurrent logins by the same user so they can't overshoot the limit
+        sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let mut evicted = Vec::new();
+        if let Some((max_active, policy)) = limit {
+            let active = sqlx::query_scalar!(
+                "SELECT id FROM sessions WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > $2 ORDER BY created_at",
+                id,
+                now
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+
+            if active.len() >= max_active {
+                if policy == SessionLimitPolicy::Reject {
+                    tx.commit().await?;
+                    return Ok(None);
+                }
+
+                let excess = (active.len() + 1).saturating_sub(max_active).min(active.len());
+                evicted = active[..excess].to_vec();
+                sqlx::query!(
+                    "UPDATE sessions SET revoked_at = $2 WHERE id = ANY($1)",
+                    &evicted,
+                    now
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        let session_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, user_id, refresh_token_hash, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            session_id,
+            id,
+            refresh_token_hash,
+            now,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(SessionStart { session_id, evicted }))
+    }
+
+    #[instrument(skip(self, refresh_token_hash))]
+    async fn find_session(&self, refresh_token_hash: &str, valid_at: OffsetDateTime) -> Result<Option<ActiveSession>> {
+        let session = sqlx::query_as!(
+            ActiveSession,
+            r#"
+            SELECT id AS session_id, user_id
+            FROM sessions
+            WHERE refresh_token_hash = $1 AND revoked_at IS NULL AND expires_at > $2
+            "#,
+            refresh_token_hash,
+            valid_at
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    #[instrument(skip(self))]
+    async fn revoke_session(&self, id: Uuid, session_id: Uuid) -> Result<bool> {
         let result = sqlx::query!(
-            "UPDATE users SET is_active = false, updated_at = $2 WHERE id = $1",
+            "UPDATE sessions SET revoked_at = $3 WHERE id = $2 AND user_id = $1 AND revoked_at IS NULL",
             id,
+            session_id,
             OffsetDateTime::now_utc()
         )
         .execute(&self.pool)
@@ -202,4 +842,256 @@ impl UserRepositoryTrait for UserRepository {
 
         Ok(result.rows_affected() > 0)
     }
+
+    #[instrument(skip(self))]
+    async fn count_active_sessions(&self, id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM sessions WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > $2",
+            id,
+            OffsetDateTime::now_utc()
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .unwrap_or(0);
+
+        Ok(count)
+    }
+
+    #[instrument(skip(self))]
+    async fn deactivate_inactive(
+        &self,
+        inactive_since: OffsetDateTime,
+        exempt_usernames: &[String],
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>> {
+        // Accounts that never logged in are measured from their creation time
+        let rows = sqlx::query!(
+            r#"
+            UPDATE users
+            SET is_active = false, updated_at = $1
+            WHERE id IN (
+                SELECT id FROM users
+                WHERE is_active = true
+                  AND deleted_at IS NULL
+                  AND COALESCE(last_login_at, created_at) < $2
+                  AND NOT (username = ANY($3))
+                  AND ($5 OR tenant_id IS NOT DISTINCT FROM $6)
+                ORDER BY id
+                LIMIT $4
+            )
+            RETURNING id, username
+            "#,
+            OffsetDateTime::now_utc(),
+            inactive_since,
+            exempt_usernames,
+            limit,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.id, row.username)).collect())
+    }
+
+    #[instrument(skip(self))]
+    async fn recently_active_ids(&self, limit: i64) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id FROM users
+            WHERE is_active = true AND deleted_at IS NULL AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)
+            ORDER BY COALESCE(last_login_at, created_at) DESC
+            LIMIT $1
+            "#,
+            limit,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl SoftDeleteRepository<User, Uuid> for UserRepository {
+    #[instrument(skip(self))]
+    async fn soft_delete(&self, id: Uuid) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE users SET deleted_at = $2, updated_at = $2 WHERE id = $1 AND deleted_at IS NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)",
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self))]
+    async fn restore(&self, id: Uuid) -> Result<Option<User>> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET deleted_at = NULL, updated_at = $2
+            WHERE id = $1 AND deleted_at IS NOT NULL
+              AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)
+            RETURNING id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            "#,
+            id,
+            OffsetDateTime::now_utc(),
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| match &e {
+            // Someone else signed up with the email or username while this user was deleted
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                ApiError::Conflict("Email or username is now used by another user".to_string())
+            }
+            _ => e.into(),
+        })?;
+
+        Ok(user)
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id_including_deleted(&self, id: Uuid) -> Result<Option<User>> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            FROM users
+            WHERE id = $1 AND ($2 OR tenant_id IS NOT DISTINCT FROM $3)
+            "#,
+            id,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    #[instrument(skip(self))]
+    async fn list_deleted(&self, pagination: PaginationParams) -> Result<ListResponse<User>> {
+        let page = pagination.page.unwrap_or(1);
+        let per_page = pagination.per_page.unwrap_or(20).min(100);
+        let offset = (page - 1) * per_page;
+
+        let total_count = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM users WHERE deleted_at IS NOT NULL AND ($1 OR tenant_id IS NOT DISTINCT FROM $2)",
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .unwrap_or(0) as u64;
+
+        let users = sqlx::query_as!(
+            User,
+            r#"
+            SELECT id, tenant_id, username as "username: Username", email as "email: Email", password_hash, is_active, created_at, updated_at
+            FROM users
+            WHERE deleted_at IS NOT NULL AND ($3 OR tenant_id IS NOT DISTINCT FROM $4)
+            ORDER BY deleted_at DESC LIMIT $1 OFFSET $2
+            "#,
+            per_page as i64,
+            offset as i64,
+            self.scope.is_all_tenants(),
+            self.scope.tenant_id()
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total_pages = ((total_count as f64) / (per_page as f64)).ceil() as u32;
+
+        Ok(ListResponse {
+            data: users,
+            pagination: PaginationMetadata {
+                page,
+                per_page,
+                total: total_count,
+                total_pages,
+            },
+        })
+    }
+}
+
+// These run the workspace's `migrations/`, since this crate's copy of 003 doesn't apply
+// to a fresh database
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use app_core::config::Config;
+
+    fn repository(pool: PgPool) -> UserRepository {
+        let query_plans = QueryPlanLogger::new(pool.clone(), &Config::default().database);
+        UserRepository::new(pool, query_plans, TenantScope::all_tenants(None))
+    }
+
+    async fn create_user(repo: &UserRepository) -> User {
+        let request = CreateUserRequest {
+            username: "alice".parse().unwrap(),
+            email: "alice@example.com".parse().unwrap(),
+            password: "correct horse".to_string(),
+        };
+        repo.create(request, "$argon2id$unused".to_string()).await.unwrap()
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn every_user_has_the_user_role_plus_those_granted(pool: PgPool) {
+        let repo = repository(pool.clone());
+        let user = create_user(&repo).await;
+        assert_eq!(repo.roles(user.id).await.unwrap(), vec!["user"]);
+
+        sqlx::query("INSERT INTO user_roles (user_id, role) VALUES ($1, 'user'), ($1, 'admin')")
+            .bind(user.id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(repo.roles(user.id).await.unwrap(), vec!["user", "admin"]);
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn refresh_token_stops_working_once_its_session_is_revoked(pool: PgPool) {
+        let repo = repository(pool);
+        let user = create_user(&repo).await;
+        let now = OffsetDateTime::now_utc();
+        let session = repo
+            .create_session(user.id, "refresh-hash", now + time::Duration::hours(1), None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let found = repo.find_session("refresh-hash", now).await.unwrap().unwrap();
+        assert_eq!((found.session_id, found.user_id), (session.session_id, user.id));
+
+        // What logout does with the access token's `sid`
+        assert!(repo.revoke_session(user.id, session.session_id).await.unwrap());
+
+        assert!(repo.find_session("refresh-hash", now).await.unwrap().is_none());
+        assert!(!repo.revoke_session(user.id, session.session_id).await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn sessions_of_other_users_are_not_revoked(pool: PgPool) {
+        let repo = repository(pool);
+        let user = create_user(&repo).await;
+        let now = OffsetDateTime::now_utc();
+        let session = repo
+            .create_session(user.id, "refresh-hash", now + time::Duration::hours(1), None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!repo.revoke_session(Uuid::new_v4(), session.session_id).await.unwrap());
+        assert!(repo.find_session("refresh-hash", now).await.unwrap().is_some());
+    }
 }
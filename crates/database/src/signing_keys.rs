@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tracing::instrument;
+
+use app_core::{error::Result, models::ActiveSigningKey, traits::SigningKeyStore};
+
+/// Active signing key kept in the single-row `signing_key_state` table
+#[derive(Clone)]
+pub struct DatabaseSigningKeyStore {
+    pool: PgPool,
+}
+
+impl DatabaseSigningKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SigningKeyStore for DatabaseSigningKeyStore {
+    #[instrument(skip(self))]
+    async fn active_key(&self) -> Result<Option<ActiveSigningKey>> {
+        let key = sqlx::query_as!(
+            ActiveSigningKey,
+            "SELECT primary_key_id, retiring_key_id, retires_at FROM signing_key_state"
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(key)
+    }
+
+    #[instrument(skip(self))]
+    async fn set_active_key(&self, key: &ActiveSigningKey) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO signing_key_state (singleton, primary_key_id, retiring_key_id, retires_at, updated_at)
+            VALUES (TRUE, $1, $2, $3, NOW())
+            ON CONFLICT (singleton) DO UPDATE
+            SET primary_key_id = EXCLUDED.primary_key_id,
+                retiring_key_id = EXCLUDED.retiring_key_id,
+                retires_at = EXCLUDED.retires_at,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            key.primary_key_id,
+            key.retiring_key_id,
+            key.retires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use futures_util::{future::BoxFuture, stream::BoxStream, FutureExt, StreamExt};
+use sqlx::postgres::{PgQueryResult, PgRow, PgStatement, PgTypeInfo};
+use sqlx::{Describe, Either, Execute, Executor, PgConnection, PgPool, Postgres, Transaction};
+use std::ops::{Deref, DerefMut};
+
+use app_core::server_timing::PhaseTimer;
+
+/// `Server-Timing` phase that database work is reported under
+pub const DB_PHASE: &str = "db";
+
+/// Pool whose queries and transactions count towards the current request's `db` time
+#[derive(Debug, Clone)]
+pub struct TimedPool {
+    pool: PgPool,
+}
+
+/// Transaction timed from `begin` until it is committed, rolled back or dropped.
+/// Dereferences to its connection, so queries run on `&mut *tx`.
+pub struct TimedTransaction {
+    tx: Transaction<'static, Postgres>,
+    timer: PhaseTimer,
+}
+
+impl TimedPool {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn begin(&self) -> sqlx::Result<TimedTransaction> {
+        let timer = PhaseTimer::start(DB_PHASE);
+        let tx = self.pool.begin().await?;
+        Ok(TimedTransaction { tx, timer })
+    }
+}
+
+impl TimedTransaction {
+    pub async fn commit(self) -> sqlx::Result<()> {
+        let Self { tx, timer } = self;
+        let result = tx.commit().await;
+        drop(timer);
+        result
+    }
+
+    pub async fn rollback(self) -> sqlx::Result<()> {
+        let Self { tx, timer } = self;
+        let result = tx.rollback().await;
+        drop(timer);
+        result
+    }
+}
+
+impl Deref for TimedTransaction {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        &self.tx
+    }
+}
+
+impl DerefMut for TimedTransaction {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        &mut self.tx
+    }
+}
+
+impl<'p> Executor<'p> for &'p TimedPool {
+    type Database = Postgres;
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<PgQueryResult, PgRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, Postgres>,
+    {
+        // Stops when the stream is dropped, i.e. once the caller has read what it needs
+        let timer = PhaseTimer::start(DB_PHASE);
+        self.pool
+            .fetch_many(query)
+            .map(move |step| {
+                let _ = &timer;
+                step
+            })
+            .boxed()
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(self, query: E) -> BoxFuture<'e, Result<Option<PgRow>, sqlx::Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, Postgres>,
+    {
+        let timer = PhaseTimer::start(DB_PHASE);
+        async move {
+            let row = self.pool.fetch_optional(query).await;
+            drop(timer);
+            row
+        }
+        .boxed()
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [PgTypeInfo],
+    ) -> BoxFuture<'e, Result<PgStatement<'q>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        self.pool.prepare_with(sql, parameters)
+    }
+
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<Postgres>, sqlx::Error>>
+    where
+        'p: 'e,
+    {
+        self.pool.describe(sql)
+    }
+}
@@ -1,10 +1,57 @@
 use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
 use sqlx::PgPool;
-use time::OffsetDateTime;
-use tracing::{instrument, warn};
+use std::sync::Arc;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::{debug, instrument, warn};
 use uuid::Uuid;
 
-use app_core::{enterprise::AuditLog, error::Result};
+use app_core::{
+    enterprise::{AuditCategory, AuditFilter, AuditLog, AuditReportQuery, AuditReportSummary, AuditSeverity},
+    error::Result,
+};
+use crate::feature_flags::FeatureFlagService;
+use crate::sanitize::{sanitize_json, sanitize_str};
+
+/// Prefix of the feature flags that switch auditing of individual actions on or off
+pub const AUDIT_FLAG_PREFIX: &str = "audit.";
+
+/// Name of the flag controlling whether `action` is audited
+pub fn audit_flag_name(action: &str) -> String {
+    format!("{}{}", AUDIT_FLAG_PREFIX, action)
+}
+
+/// Whether `flag_name` is one of the flags that gate auditing
+pub fn is_audit_flag(flag_name: &str) -> bool {
+    flag_name.starts_with(AUDIT_FLAG_PREFIX)
+}
+
+/// Fields left out of audit diffs: secrets, and bookkeeping that changes on every update
+const UNAUDITED_FIELDS: &[&str] = &["password_hash", "updated_at"];
+
+/// Top-level fields that differ between two versions of a resource, as
+/// `{ "email": { "from": "a@x", "to": "b@x" } }`, for the `details` of update actions
+pub fn audit_diff(before: &impl Serialize, after: &impl Serialize) -> Value {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) = (serde_json::to_value(before), serde_json::to_value(after)) else {
+        return Value::Object(Map::new());
+    };
+
+    let mut changes = Map::new();
+    for field in after.keys().chain(before.keys().filter(|field| !after.contains_key(*field))) {
+        if UNAUDITED_FIELDS.contains(&field.as_str()) {
+            continue;
+        }
+
+        let from = before.get(field).unwrap_or(&Value::Null);
+        let to = after.get(field).unwrap_or(&Value::Null);
+        if from != to {
+            changes.insert(field.clone(), json!({ "from": from, "to": to }));
+        }
+    }
+
+    Value::Object(changes)
+}
 
 #[async_trait]
 pub trait AuditService: Send + Sync {
@@ -12,6 +59,8 @@ pub trait AuditService: Send + Sync {
         &self,
         user_id: Option<Uuid>,
         action: &str,
+        category: AuditCategory,
+        severity: AuditSeverity,
         resource_type: &str,
         resource_id: Option<Uuid>,
         ip_address: &str,
@@ -19,10 +68,24 @@ pub trait AuditService: Send + Sync {
         details: serde_json::Value,
     ) -> Result<()>;
 
-    async fn get_user_audit_trail(&self, user_id: Uuid, limit: i64) -> Result<Vec<AuditLog>>;
-    async fn get_resource_audit_trail(&self, resource_type: &str, resource_id: Uuid, limit: i64) -> Result<Vec<AuditLog>>;
+    async fn get_user_audit_trail(&self, user_id: Uuid, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>>;
+    async fn get_resource_audit_trail(&self, resource_type: &str, resource_id: Uuid, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>>;
+    /// Most recent entries across all users matching `filter`
+    async fn get_audit_events(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>>;
+    /// Up to `limit` entries matching `query`, oldest first, starting after the
+    /// `(created_at, id)` of the last entry of the previous page
+    async fn get_audit_report_page(
+        &self,
+        query: &AuditReportQuery,
+        after: Option<(OffsetDateTime, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>>;
+    async fn get_audit_report_summary(&self, query: &AuditReportQuery) -> Result<AuditReportSummary>;
 }
 
+/// Actions listed individually in an audit report summary
+const REPORT_TOP_ACTIONS: i64 = 20;
+
 #[derive(Clone)]
 pub struct DatabaseAuditService {
     pool: PgPool,
@@ -32,6 +95,113 @@ impl DatabaseAuditService {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    /// Load historical entries with a single `COPY`, an order of magnitude faster than
+    /// row-by-row inserts, for backfills and migrations. Live logging stays on `log_action`.
+    ///
+    /// Entries keep their own ids and timestamps and are sanitized like live ones. The load
+    /// is all-or-nothing: one bad row, such as a duplicate id, rejects the whole batch.
+    #[instrument(skip(self, entries), fields(entries = entries.len()))]
+    pub async fn bulk_load(&self, entries: &[AuditLog]) -> Result<u64> {
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        self.ensure_partitions(entries).await?;
+
+        let mut connection = self.pool.acquire().await?;
+        let mut copy = connection
+            .copy_in_raw(
+                "COPY audit_logs (id, user_id, action, category, severity, resource_type, resource_id, ip_address, user_agent, details, created_at) \
+                 FROM STDIN WITH (FORMAT csv)",
+            )
+            .await?;
+
+        let mut buffer = String::new();
+        for (index, entry) in entries.iter().enumerate() {
+            copy_row(&mut buffer, entry);
+            if buffer.len() >= COPY_CHUNK_BYTES || index + 1 == entries.len() {
+                if let Err(e) = copy.send(buffer.as_bytes()).await {
+                    let _ = copy.abort("audit backfill failed").await;
+                    return Err(e.into());
+                }
+                buffer.clear();
+            }
+        }
+
+        Ok(copy.finish().await?)
+    }
+
+    /// Where `audit_logs` is partitioned by month, only the current month's partition is
+    /// created up front; historical entries need theirs before they can be loaded
+    async fn ensure_partitions(&self, entries: &[AuditLog]) -> Result<()> {
+        let partitioned = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM pg_class WHERE relname = 'audit_logs' AND relkind = 'p') as "partitioned!""#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        if !partitioned {
+            return Ok(());
+        }
+
+        let months: std::collections::BTreeSet<time::Date> = entries
+            .iter()
+            .map(|entry| entry.created_at.to_offset(time::UtcOffset::UTC).date().replace_day(1).expect("day 1 exists"))
+            .collect();
+        for month in months {
+            sqlx::query("SELECT create_monthly_partition('audit_logs', $1::date)")
+                .bind(month)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Bytes buffered before being sent to `COPY`
+const COPY_CHUNK_BYTES: usize = 1 << 20;
+
+/// Append `entry` as a CSV record in `COPY` column order. Every present value is quoted,
+/// which is how `COPY` tells an empty string from an unquoted empty NULL.
+fn copy_row(buffer: &mut String, entry: &AuditLog) {
+    let category = match entry.category {
+        AuditCategory::Security => "security",
+        AuditCategory::Admin => "admin",
+        AuditCategory::DataChange => "data_change",
+        AuditCategory::Access => "access",
+        AuditCategory::System => "system",
+    };
+    let severity = match entry.severity {
+        AuditSeverity::Info => "info",
+        AuditSeverity::Warning => "warning",
+        AuditSeverity::Critical => "critical",
+    };
+
+    let fields = [
+        Some(entry.id.to_string()),
+        entry.user_id.map(|id| id.to_string()),
+        Some(sanitize_str(&entry.action)),
+        Some(category.to_string()),
+        Some(severity.to_string()),
+        Some(sanitize_str(&entry.resource_type)),
+        entry.resource_id.map(|id| id.to_string()),
+        Some(sanitize_str(&entry.ip_address.ip().to_string())),
+        entry.user_agent.as_deref().map(sanitize_str),
+        Some(sanitize_json(entry.details.clone()).to_string()),
+        Some(entry.created_at.format(&Rfc3339).unwrap_or_default()),
+    ];
+
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            buffer.push(',');
+        }
+        if let Some(value) = field {
+            buffer.push('"');
+            buffer.push_str(&value.replace('"', "\"\""));
+            buffer.push('"');
+        }
+    }
+    buffer.push('\n');
 }
 
 #[async_trait]
@@ -41,6 +211,8 @@ impl AuditService for DatabaseAuditService {
         &self,
         user_id: Option<Uuid>,
         action: &str,
+        category: AuditCategory,
+        severity: AuditSeverity,
         resource_type: &str,
         resource_id: Option<Uuid>,
         ip_address: &str,
@@ -50,14 +222,23 @@ impl AuditService for DatabaseAuditService {
         let audit_id = Uuid::new_v4();
         let now = OffsetDateTime::now_utc();
 
+        // Escape user-controlled content before it reaches the audit table
+        let action = sanitize_str(action);
+        let resource_type = sanitize_str(resource_type);
+        let ip_address = sanitize_str(ip_address);
+        let user_agent = user_agent.map(sanitize_str);
+        let details = sanitize_json(details);
+
         let result = sqlx::query!(
             r#"
-            INSERT INTO audit_logs (id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            INSERT INTO audit_logs (id, user_id, action, category, severity, resource_type, resource_id, ip_address, user_agent, details, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
             audit_id,
             user_id,
             action,
+            category as AuditCategory,
+            severity as AuditSeverity,
             resource_type,
             resource_id,
             ip_address,
@@ -79,17 +260,22 @@ impl AuditService for DatabaseAuditService {
     }
 
     #[instrument(skip(self))]
-    async fn get_user_audit_trail(&self, user_id: Uuid, limit: i64) -> Result<Vec<AuditLog>> {
+    async fn get_user_audit_trail(&self, user_id: Uuid, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>> {
         let logs = sqlx::query_as!(
             AuditLog,
             r#"
-            SELECT id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at
+            SELECT id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at,
+                   category as "category: AuditCategory", severity as "severity: AuditSeverity"
             FROM audit_logs
             WHERE user_id = $1
+              AND ($2::audit_category IS NULL OR category = $2)
+              AND ($3::audit_severity IS NULL OR severity >= $3)
             ORDER BY created_at DESC
-            LIMIT $2
+            LIMIT $4
             "#,
             user_id,
+            filter.category as Option<AuditCategory>,
+            filter.min_severity as Option<AuditSeverity>,
             limit
         )
         .fetch_all(&self.pool)
@@ -99,18 +285,46 @@ impl AuditService for DatabaseAuditService {
     }
 
     #[instrument(skip(self))]
-    async fn get_resource_audit_trail(&self, resource_type: &str, resource_id: Uuid, limit: i64) -> Result<Vec<AuditLog>> {
+    async fn get_resource_audit_trail(&self, resource_type: &str, resource_id: Uuid, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>> {
         let logs = sqlx::query_as!(
             AuditLog,
             r#"
-            SELECT id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at
+            SELECT id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at,
+                   category as "category: AuditCategory", severity as "severity: AuditSeverity"
             FROM audit_logs
             WHERE resource_type = $1 AND resource_id = $2
+              AND ($3::audit_category IS NULL OR category = $3)
+              AND ($4::audit_severity IS NULL OR severity >= $4)
             ORDER BY created_at DESC
-            LIMIT $3
+            LIMIT $5
             "#,
             resource_type,
             resource_id,
+            filter.category as Option<AuditCategory>,
+            filter.min_severity as Option<AuditSeverity>,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(logs)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_audit_events(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>> {
+        let logs = sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at,
+                   category as "category: AuditCategory", severity as "severity: AuditSeverity"
+            FROM audit_logs
+            WHERE ($1::audit_category IS NULL OR category = $1)
+              AND ($2::audit_severity IS NULL OR severity >= $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            filter.category as Option<AuditCategory>,
+            filter.min_severity as Option<AuditSeverity>,
             limit
         )
         .fetch_all(&self.pool)
@@ -118,15 +332,200 @@ impl AuditService for DatabaseAuditService {
 
         Ok(logs)
     }
+
+    #[instrument(skip(self))]
+    async fn get_audit_report_page(
+        &self,
+        query: &AuditReportQuery,
+        after: Option<(OffsetDateTime, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>> {
+        let logs = sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT id, user_id, action, resource_type, resource_id, ip_address, user_agent, details, created_at,
+                   category as "category: AuditCategory", severity as "severity: AuditSeverity"
+            FROM audit_logs
+            WHERE created_at >= $1 AND created_at < $2
+              AND ($3::uuid IS NULL OR user_id = $3)
+              AND ($4::text IS NULL OR resource_type = $4)
+              AND ($5::audit_category IS NULL OR category = $5)
+              AND ($6::timestamptz IS NULL OR (created_at, id) > ($6, $7))
+            ORDER BY created_at, id
+            LIMIT $8
+            "#,
+            query.from,
+            query.to,
+            query.user_id,
+            query.resource_type,
+            query.category as Option<AuditCategory>,
+            after.map(|(created_at, _)| created_at),
+            after.map(|(_, id)| id),
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(logs)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_audit_report_summary(&self, query: &AuditReportQuery) -> Result<AuditReportSummary> {
+        let by_category = sqlx::query!(
+            r#"
+            SELECT category as "category!: AuditCategory", COUNT(*) as "count!"
+            FROM audit_logs
+            WHERE created_at >= $1 AND created_at < $2
+              AND ($3::uuid IS NULL OR user_id = $3)
+              AND ($4::text IS NULL OR resource_type = $4)
+              AND ($5::audit_category IS NULL OR category = $5)
+            GROUP BY category
+            ORDER BY 2 DESC
+            "#,
+            query.from,
+            query.to,
+            query.user_id,
+            query.resource_type,
+            query.category as Option<AuditCategory>
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let by_severity = sqlx::query!(
+            r#"
+            SELECT severity as "severity!: AuditSeverity", COUNT(*) as "count!"
+            FROM audit_logs
+            WHERE created_at >= $1 AND created_at < $2
+              AND ($3::uuid IS NULL OR user_id = $3)
+              AND ($4::text IS NULL OR resource_type = $4)
+              AND ($5::audit_category IS NULL OR category = $5)
+            GROUP BY severity
+            ORDER BY 2 DESC
+            "#,
+            query.from,
+            query.to,
+            query.user_id,
+            query.resource_type,
+            query.category as Option<AuditCategory>
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let top_actions = sqlx::query!(
+            r#"
+            SELECT action, COUNT(*) as "count!"
+            FROM audit_logs
+            WHERE created_at >= $1 AND created_at < $2
+              AND ($3::uuid IS NULL OR user_id = $3)
+              AND ($4::text IS NULL OR resource_type = $4)
+              AND ($5::audit_category IS NULL OR category = $5)
+            GROUP BY action
+            ORDER BY 2 DESC, action
+            LIMIT $6
+            "#,
+            query.from,
+            query.to,
+            query.user_id,
+            query.resource_type,
+            query.category as Option<AuditCategory>,
+            REPORT_TOP_ACTIONS
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(AuditReportSummary {
+            total: by_category.iter().map(|row| row.count).sum(),
+            by_category: by_category.into_iter().map(|row| (row.category, row.count)).collect(),
+            by_severity: by_severity.into_iter().map(|row| (row.severity, row.count)).collect(),
+            top_actions: top_actions.into_iter().map(|row| (row.action, row.count)).collect(),
+        })
+    }
+}
+
+/// Skips actions whose `audit.<action>` flag is disabled so operators can cut audit volume
+/// at runtime. Actions without a flag are always recorded, as are critical events, which
+/// include every change to an `audit.*` flag so switching auditing off is never itself lost.
+pub struct GatedAuditService {
+    inner: Arc<dyn AuditService>,
+    flags: Arc<dyn FeatureFlagService>,
+}
+
+impl GatedAuditService {
+    pub fn new(inner: Arc<dyn AuditService>, flags: Arc<dyn FeatureFlagService>) -> Self {
+        Self { inner, flags }
+    }
+
+    async fn is_audited(&self, action: &str, severity: AuditSeverity) -> bool {
+        if severity == AuditSeverity::Critical {
+            return true;
+        }
+
+        match self.flags.get_flag(&audit_flag_name(action)).await {
+            Ok(Some(flag)) => flag.enabled,
+            _ => true,
+        }
+    }
+}
+
+#[async_trait]
+impl AuditService for GatedAuditService {
+    async fn log_action(
+        &self,
+        user_id: Option<Uuid>,
+        action: &str,
+        category: AuditCategory,
+        severity: AuditSeverity,
+        resource_type: &str,
+        resource_id: Option<Uuid>,
+        ip_address: &str,
+        user_agent: Option<&str>,
+        details: serde_json::Value,
+    ) -> Result<()> {
+        if !self.is_audited(action, severity).await {
+            debug!(action = %sanitize_str(action), "Auditing disabled for action");
+            return Ok(());
+        }
+
+        self.inner
+            .log_action(user_id, action, category, severity, resource_type, resource_id, ip_address, user_agent, details)
+            .await
+    }
+
+    async fn get_user_audit_trail(&self, user_id: Uuid, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>> {
+        self.inner.get_user_audit_trail(user_id, filter, limit).await
+    }
+
+    async fn get_resource_audit_trail(&self, resource_type: &str, resource_id: Uuid, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>> {
+        self.inner.get_resource_audit_trail(resource_type, resource_id, filter, limit).await
+    }
+
+    async fn get_audit_events(&self, filter: &AuditFilter, limit: i64) -> Result<Vec<AuditLog>> {
+        self.inner.get_audit_events(filter, limit).await
+    }
+
+    async fn get_audit_report_page(
+        &self,
+        query: &AuditReportQuery,
+        after: Option<(OffsetDateTime, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<AuditLog>> {
+        self.inner.get_audit_report_page(query, after, limit).await
+    }
+
+    async fn get_audit_report_summary(&self, query: &AuditReportQuery) -> Result<AuditReportSummary> {
+        self.inner.get_audit_report_summary(query).await
+    }
 }
 
 /// Audit logging macros for easy usage
 #[macro_export]
 macro_rules! audit_action {
-    ($audit_service:expr, $user_id:expr, $action:expr, $resource_type:expr, $resource_id:expr, $ip:expr, $user_agent:expr) => {
+    ($audit_service:expr, $user_id:expr, $action:expr, $category:expr, $severity:expr, $resource_type:expr, $resource_id:expr, $ip:expr, $user_agent:expr) => {
         $audit_service.log_action(
             $user_id,
             $action,
+            $category,
+            $severity,
             $resource_type,
             $resource_id,
             $ip,
@@ -135,10 +534,12 @@ macro_rules! audit_action {
         ).await
     };
 
-    ($audit_service:expr, $user_id:expr, $action:expr, $resource_type:expr, $resource_id:expr, $ip:expr, $user_agent:expr, $details:expr) => {
+    ($audit_service:expr, $user_id:expr, $action:expr, $category:expr, $severity:expr, $resource_type:expr, $resource_id:expr, $ip:expr, $user_agent:expr, $details:expr) => {
         $audit_service.log_action(
             $user_id,
             $action,
+            $category,
+            $severity,
             $resource_type,
             $resource_id,
             $ip,
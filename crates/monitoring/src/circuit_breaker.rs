@@ -1,5 +1,8 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock as StdRwLock};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
@@ -7,7 +10,8 @@ use tracing::{error, info, warn};
 use app_core::enterprise::CircuitBreakerConfig;
 use app_core::error::Result;
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CircuitState {
     Closed,    // Normal operation
     Open,      // Failing, reject all requests
@@ -21,6 +25,7 @@ pub struct CircuitBreaker {
     failure_count: Arc<AtomicU32>,
     last_failure_time: Arc<RwLock<Option<Instant>>>,
     half_open_calls: Arc<AtomicU32>,
+    half_open_since: Arc<RwLock<Option<Instant>>>,
 }
 
 impl CircuitBreaker {
@@ -31,6 +36,7 @@ impl CircuitBreaker {
             failure_count: Arc::new(AtomicU32::new(0)),
             last_failure_time: Arc::new(RwLock::new(None)),
             half_open_calls: Arc::new(AtomicU32::new(0)),
+            half_open_since: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -39,8 +45,7 @@ impl CircuitBreaker {
         F: FnOnce() -> std::result::Result<T, E>,
         E: std::error::Error + Send + Sync + 'static,
     {
-        // Check if circuit is open
-        if self.is_open().await {
+        if !self.try_acquire().await {
             return Err(anyhow::anyhow!("Circuit breaker is open").into());
         }
 
@@ -57,38 +62,117 @@ impl CircuitBreaker {
         }
     }
 
-    async fn is_open(&self) -> bool {
-        let state = self.state.read().await;
-        match *state {
-            CircuitState::Open => {
-                // Check if we should transition to half-open
-                if let Some(last_failure) = *self.last_failure_time.read().await {
-                    if last_failure.elapsed() >= self.config.recovery_timeout {
-                        drop(state);
-                        self.transition_to_half_open().await;
-                        false
-                    } else {
-                        true
-                    }
-                } else {
-                    true
-                }
+    /// Like `call`, for operations that return a future, such as database queries and
+    /// HTTP requests
+    pub async fn call_async<F, Fut, T, E>(&self, operation: F) -> Result<T>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if !self.try_acquire().await {
+            return Err(anyhow::anyhow!("Circuit breaker is open").into());
+        }
+
+        match operation().await {
+            Ok(result) => {
+                self.on_success().await;
+                Ok(result)
+            }
+            Err(e) => {
+                self.on_failure().await;
+                Err(anyhow::anyhow!("Operation failed: {}", e).into())
             }
+        }
+    }
+
+    /// Like `call_async`, but returns `fallback()` instead of an error when the circuit
+    /// rejects the call or the operation fails, e.g. to serve stale data during an outage.
+    ///
+    /// Only the operation's own outcome is recorded: a failure counts towards opening the
+    /// circuit (and reopens a half-open one) exactly as in `call_async`, while a fallback
+    /// served because the call was rejected records nothing. A fallback therefore never
+    /// counts as a success and can't close a half-open circuit.
+    pub async fn call_with_fallback<F, Fut, T, E, G>(&self, operation: F, fallback: G) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+        G: FnOnce() -> T,
+    {
+        if !self.try_acquire().await {
+            return fallback();
+        }
+
+        match operation().await {
+            Ok(result) => {
+                self.on_success().await;
+                result
+            }
+            Err(e) => {
+                self.on_failure().await;
+                warn!("Circuit breaker call failed, serving fallback: {}", e);
+                fallback()
+            }
+        }
+    }
+
+    /// Admit a call, counting it against the half-open allowance. The whole decision is
+    /// made under the state write lock, so concurrent callers can't each move an open
+    /// circuit to half-open or together exceed `half_open_max_calls`.
+    async fn try_acquire(&self) -> bool {
+        let mut state = self.state.write().await;
+
+        if *state == CircuitState::Open {
+            let recovered = self
+                .last_failure_time
+                .read()
+                .await
+                .is_none_or(|last_failure| last_failure.elapsed() >= self.config.recovery_timeout());
+            if !recovered {
+                return false;
+            }
+            self.enter_half_open(&mut state).await;
+        }
+
+        match *state {
+            CircuitState::Closed => true,
             CircuitState::HalfOpen => {
-                // Allow limited calls in half-open state
-                self.half_open_calls.load(Ordering::Relaxed) >= self.config.half_open_max_calls
+                let admitted = self.take_half_open_call();
+                // Trial calls that never report back, e.g. because the caller was cancelled,
+                // would otherwise hold the circuit half-open for good
+                let stalled = self
+                    .half_open_since
+                    .read()
+                    .await
+                    .is_some_and(|since| since.elapsed() >= self.config.recovery_timeout());
+                if !admitted && stalled {
+                    warn!("Circuit breaker trial calls did not report back, restarting HALF_OPEN");
+                    self.enter_half_open(&mut state).await;
+                    return self.take_half_open_call();
+                }
+                admitted
             }
-            CircuitState::Closed => false,
+            CircuitState::Open => false,
         }
     }
 
+    fn take_half_open_call(&self) -> bool {
+        let max_calls = self.config.half_open_max_calls();
+        self.half_open_calls
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |calls| (calls < max_calls).then_some(calls + 1))
+            .is_ok()
+    }
+
+    /// Outcomes hold the state write lock from reading the state until the transition is
+    /// made, so a concurrent call can't act on a state that has since changed
     async fn on_success(&self) {
-        let current_state = *self.state.read().await;
+        let mut state = self.state.write().await;
 
-        match current_state {
+        match *state {
             CircuitState::HalfOpen => {
                 // Success in half-open state, transition to closed
-                self.transition_to_closed().await;
+                self.transition_to_closed(&mut state).await;
                 info!("Circuit breaker transitioned to CLOSED after successful recovery");
             }
             CircuitState::Closed => {
@@ -96,30 +180,27 @@ impl CircuitBreaker {
                 self.failure_count.store(0, Ordering::Relaxed);
             }
             CircuitState::Open => {
-                // Should not happen, but reset if it does
-                warn!("Unexpected success in OPEN state");
+                // A trial call finishing after another one reopened the circuit
             }
         }
     }
 
     async fn on_failure(&self) {
-        let current_state = *self.state.read().await;
+        let mut state = self.state.write().await;
         let failures = self.failure_count.fetch_add(1, Ordering::Relaxed) + 1;
 
         error!("Circuit breaker recorded failure #{}", failures);
 
-        match current_state {
+        match *state {
             CircuitState::Closed => {
-                if failures >= self.config.failure_threshold {
-                    drop(current_state);
-                    self.transition_to_open().await;
+                if failures >= self.config.failure_threshold() {
+                    self.transition_to_open(&mut state).await;
                     error!("Circuit breaker transitioned to OPEN after {} failures", failures);
                 }
             }
             CircuitState::HalfOpen => {
                 // Failure in half-open state, transition back to open
-                drop(current_state);
-                self.transition_to_open().await;
+                self.transition_to_open(&mut state).await;
                 warn!("Circuit breaker transitioned back to OPEN from HALF_OPEN");
             }
             CircuitState::Open => {
@@ -129,20 +210,21 @@ impl CircuitBreaker {
         }
     }
 
-    async fn transition_to_open(&self) {
-        *self.state.write().await = CircuitState::Open;
+    async fn transition_to_open(&self, state: &mut CircuitState) {
+        *state = CircuitState::Open;
         *self.last_failure_time.write().await = Some(Instant::now());
         self.half_open_calls.store(0, Ordering::Relaxed);
     }
 
-    async fn transition_to_half_open(&self) {
-        *self.state.write().await = CircuitState::HalfOpen;
-        self.half_open_calls.store(0, Ordering::Relaxed);
+    async fn enter_half_open(&self, state: &mut CircuitState) {
+        *state = CircuitState::HalfOpen;
+        *self.half_open_since.write().await = Some(Instant::now());
+        self.half_open_calls.store(0, Ordering::Release);
         info!("Circuit breaker transitioned to HALF_OPEN for recovery testing");
     }
 
-    async fn transition_to_closed(&self) {
-        *self.state.write().await = CircuitState::Closed;
+    async fn transition_to_closed(&self, state: &mut CircuitState) {
+        *state = CircuitState::Closed;
         self.failure_count.store(0, Ordering::Relaxed);
         *self.last_failure_time.write().await = None;
         self.half_open_calls.store(0, Ordering::Relaxed);
@@ -155,4 +237,154 @@ impl CircuitBreaker {
     pub fn get_failure_count(&self) -> u32 {
         self.failure_count.load(Ordering::Relaxed)
     }
+
+    /// Whether a call may go ahead now, for callers that judge the outcome themselves
+    /// rather than by the `Err` of `call_async`. Report how every admitted call went with
+    /// `record_outcome`.
+    pub async fn allow_request(&self) -> bool {
+        self.try_acquire().await
+    }
+
+    /// Record the result of a call admitted by `allow_request`
+    pub async fn record_outcome(&self, success: bool) {
+        if success {
+            self.on_success().await;
+        } else {
+            self.on_failure().await;
+        }
+    }
+}
+
+/// State of one registered breaker
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitBreakerSnapshot {
+    pub name: String,
+    pub state: CircuitState,
+    pub failure_count: u32,
+}
+
+/// Circuit breakers keyed by downstream service, so one failing dependency doesn't trip
+/// calls to the others. Breakers are created on first use from a shared config.
+#[derive(Debug)]
+pub struct CircuitBreakerRegistry {
+    config: CircuitBreakerConfig,
+    breakers: StdRwLock<HashMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl CircuitBreakerRegistry {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: StdRwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The breaker guarding `name`, created on first use
+    pub fn get(&self, name: &str) -> Arc<CircuitBreaker> {
+        if let Some(breaker) = self.breakers.read().unwrap().get(name) {
+            return breaker.clone();
+        }
+
+        self.breakers
+            .write()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(self.config.clone())))
+            .clone()
+    }
+
+    /// Register a breaker built elsewhere, e.g. with its own config, under `name`
+    pub fn insert(&self, name: &str, breaker: Arc<CircuitBreaker>) {
+        self.breakers.write().unwrap().insert(name.to_string(), breaker);
+    }
+
+    /// State of every registered breaker, ordered by name
+    pub async fn snapshot(&self) -> Vec<CircuitBreakerSnapshot> {
+        let breakers: Vec<(String, Arc<CircuitBreaker>)> = self
+            .breakers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, breaker)| (name.clone(), breaker.clone()))
+            .collect();
+
+        let mut snapshots = Vec::with_capacity(breakers.len());
+        for (name, breaker) in breakers {
+            snapshots.push(CircuitBreakerSnapshot {
+                name,
+                state: breaker.get_state().await,
+                failure_count: breaker.get_failure_count(),
+            });
+        }
+        snapshots.sort_by(|a, b| a.name.cmp(&b.name));
+        snapshots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::{mpsc, Semaphore};
+
+    #[derive(Debug)]
+    struct Unavailable;
+
+    impl std::fmt::Display for Unavailable {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("unavailable")
+        }
+    }
+
+    impl std::error::Error for Unavailable {}
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_calls_on_a_half_open_circuit_admit_at_most_the_trial_allowance() {
+        const CALLERS: usize = 16;
+        let recovery = Duration::from_millis(200);
+        let config = CircuitBreakerConfig::builder()
+            .failure_threshold(1)
+            .recovery_timeout(recovery)
+            .half_open_max_calls(2)
+            .build()
+            .unwrap();
+        let breaker = Arc::new(CircuitBreaker::new(config));
+
+        let _ = breaker.call_async(|| async { Err::<(), _>(Unavailable) }).await;
+        assert_eq!(breaker.get_state().await, CircuitState::Open);
+        tokio::time::sleep(recovery).await;
+
+        // Admitted calls hold until every caller has been admitted or rejected
+        let gate = Arc::new(Semaphore::new(0));
+        let (entered, mut outcomes) = mpsc::unbounded_channel();
+        let calls: Vec<_> = (0..CALLERS)
+            .map(|_| {
+                let (breaker, gate, entered) = (breaker.clone(), gate.clone(), entered.clone());
+                tokio::spawn(async move {
+                    let admitted = entered.clone();
+                    let result = breaker
+                        .call_async(|| async move {
+                            admitted.send(true).unwrap();
+                            let _permit = gate.acquire().await.unwrap();
+                            Ok::<(), Unavailable>(())
+                        })
+                        .await;
+                    if result.is_err() {
+                        entered.send(false).unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        let mut admitted = 0;
+        for _ in 0..CALLERS {
+            admitted += outcomes.recv().await.unwrap() as usize;
+        }
+        assert_eq!(admitted, 2);
+
+        gate.add_permits(CALLERS);
+        for call in calls {
+            call.await.unwrap();
+        }
+        assert_eq!(breaker.get_state().await, CircuitState::Closed);
+    }
 }
@@ -0,0 +1,194 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info};
+
+use app_core::config::MonitoringConfig;
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerRegistry, CircuitState};
+use crate::notifications::{Notification, NotificationService};
+use crate::service::MetricsService;
+
+/// Most recent calls kept per dependency for success rate and latency percentiles
+const WINDOW_SAMPLES: usize = 500;
+
+/// Calls needed in the window before a dependency can be judged degraded
+const MIN_SAMPLES: usize = 20;
+
+/// Current health of one downstream dependency
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyReport {
+    pub name: String,
+    pub samples: usize,
+    pub success_rate: f64,
+    pub p99_latency_ms: u64,
+    /// `None` when no circuit breaker guards the dependency
+    pub circuit_open: Option<bool>,
+    pub degraded: bool,
+}
+
+#[derive(Default)]
+struct DependencyWindow {
+    samples: VecDeque<(Duration, bool)>,
+    degraded: bool,
+}
+
+impl DependencyWindow {
+    fn success_rate(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 1.0;
+        }
+        let successes = self.samples.iter().filter(|(_, ok)| *ok).count();
+        successes as f64 / self.samples.len() as f64
+    }
+
+    fn p99_latency(&self) -> Duration {
+        let mut latencies: Vec<Duration> = self.samples.iter().map(|(latency, _)| *latency).collect();
+        if latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        latencies.sort_unstable();
+        let index = ((latencies.len() as f64) * 0.99).ceil() as usize;
+        latencies[index.saturating_sub(1).min(latencies.len() - 1)]
+    }
+}
+
+/// Rolling per-dependency success rate and latency, fed by the DB layer and outbound
+/// clients. Dependencies crossing the configured thresholds are reported once per
+/// degradation with a high-severity log and an operator notification.
+pub struct DependencyHealthTracker {
+    metrics: MetricsService,
+    notifications: NotificationService,
+    latency_threshold: Duration,
+    error_rate_threshold: f64,
+    windows: Mutex<BTreeMap<String, DependencyWindow>>,
+    breakers: Arc<CircuitBreakerRegistry>,
+}
+
+impl DependencyHealthTracker {
+    /// Circuit state is reported for every breaker in `breakers`
+    pub fn new(
+        metrics: MetricsService,
+        notifications: NotificationService,
+        config: &MonitoringConfig,
+        breakers: Arc<CircuitBreakerRegistry>,
+    ) -> Self {
+        Self {
+            metrics,
+            notifications,
+            latency_threshold: Duration::from_millis(config.dependency_latency_threshold_ms),
+            error_rate_threshold: config.dependency_error_rate_threshold,
+            windows: Mutex::new(BTreeMap::new()),
+            breakers,
+        }
+    }
+
+    /// Report `breaker`'s state alongside `dependency`
+    pub fn attach_breaker(&self, dependency: &str, breaker: Arc<CircuitBreaker>) {
+        self.breakers.insert(dependency, breaker);
+    }
+
+    /// Record one call to `dependency`
+    pub fn record(&self, dependency: &str, latency: Duration, success: bool) {
+        let outcome = if success { "success" } else { "failure" };
+        self.metrics.increment_counter(
+            "dependency_calls_total",
+            &[("dependency", dependency), ("outcome", outcome)],
+        );
+        self.metrics.record_histogram(
+            "dependency_latency_milliseconds",
+            latency.as_millis() as f64,
+            &[("dependency", dependency)],
+        );
+
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(dependency.to_string()).or_default();
+        if window.samples.len() == WINDOW_SAMPLES {
+            window.samples.pop_front();
+        }
+        window.samples.push_back((latency, success));
+    }
+
+    /// Health of every dependency seen so far, ordered by name
+    pub async fn report(&self) -> Vec<DependencyReport> {
+        let circuit_states: BTreeMap<String, bool> = self
+            .breakers
+            .snapshot()
+            .await
+            .into_iter()
+            .map(|breaker| (breaker.name, breaker.state == CircuitState::Open))
+            .collect();
+
+        let windows = self.windows.lock().unwrap();
+        let mut names: Vec<&String> = windows.keys().chain(circuit_states.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let window = windows.get(name);
+                DependencyReport {
+                    name: name.clone(),
+                    samples: window.map_or(0, |w| w.samples.len()),
+                    success_rate: window.map_or(1.0, DependencyWindow::success_rate),
+                    p99_latency_ms: window.map_or(0, |w| w.p99_latency().as_millis() as u64),
+                    circuit_open: circuit_states.get(name).copied(),
+                    degraded: window.is_some_and(|w| w.degraded),
+                }
+            })
+            .collect()
+    }
+
+    /// Compare each dependency against the thresholds, alerting when one starts degrading
+    /// and logging when it recovers
+    pub async fn evaluate(&self) {
+        let circuit_open: BTreeMap<String, bool> = self
+            .report()
+            .await
+            .into_iter()
+            .filter_map(|report| report.circuit_open.map(|open| (report.name, open)))
+            .collect();
+
+        let mut newly_degraded = Vec::new();
+        {
+            let mut windows = self.windows.lock().unwrap();
+            for (name, window) in windows.iter_mut() {
+                let latency = window.p99_latency();
+                let error_rate = 1.0 - window.success_rate();
+                let breaker_open = circuit_open.get(name).copied().unwrap_or(false);
+
+                let degraded = breaker_open
+                    || (window.samples.len() >= MIN_SAMPLES
+                        && (latency > self.latency_threshold || error_rate > self.error_rate_threshold));
+
+                if degraded && !window.degraded {
+                    newly_degraded.push((name.clone(), latency, error_rate, breaker_open));
+                } else if !degraded && window.degraded {
+                    info!(dependency = %name, "Dependency recovered");
+                }
+                window.degraded = degraded;
+            }
+        }
+
+        for (name, latency, error_rate, breaker_open) in newly_degraded {
+            error!(
+                dependency = %name,
+                p99_latency_ms = latency.as_millis() as u64,
+                error_rate,
+                circuit_open = breaker_open,
+                "Dependency is degraded"
+            );
+            self.metrics.increment_counter("dependency_degraded_total", &[("dependency", &name)]);
+            self.notifications.notify(Notification::new(
+                format!("Dependency degraded: {}", name),
+                format!(
+                    "p99 latency {}ms, error rate {:.1}%, circuit breaker {}",
+                    latency.as_millis(),
+                    error_rate * 100.0,
+                    if breaker_open { "open" } else { "closed" }
+                ),
+            ));
+        }
+    }
+}
@@ -1,85 +1,491 @@
 use async_trait::async_trait;
+use regex::Regex;
 use serde_json::Value;
-use std::collections::HashMap;
+use sqlx::{types::Json, PgPool, Postgres, Transaction};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
-use tracing::{info, instrument};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::{info, instrument, warn};
 
-use app_core::{enterprise::FeatureFlag, error::Result};
+use app_core::{
+    config::FeatureFlagDefault,
+    enterprise::{FeatureFlag, FlagDependencies, FlagVariant},
+    error::{ApiError, Result},
+};
+
+/// Deepest nesting of objects/arrays accepted in `FeatureFlag.conditions`
+pub const MAX_CONDITION_DEPTH: usize = 4;
+
+/// Most JSON values (objects, arrays and scalars) accepted in `FeatureFlag.conditions`
+pub const MAX_CONDITION_RULES: usize = 64;
+
+/// Condition keys holding the flag's schedule rather than a rule on the context
+const SCHEDULE_KEYS: [&str; 2] = ["start_at", "end_at"];
+
+/// Operators accepted in a condition object, e.g. `{"signup_age_days": {"greater_than": 30}}`
+const CONDITION_OPERATORS: [&str; 5] = ["in", "equals", "greater_than", "less_than", "matches"];
+
+/// A flag's conditions with each rule parsed and every `matches` pattern compiled, built
+/// once when the flag is stored or loaded so evaluations don't recompile them
+#[derive(Debug, Clone, Default)]
+pub struct CompiledConditions {
+    rules: Vec<(String, Vec<Operator>)>,
+}
+
+#[derive(Debug, Clone)]
+enum Operator {
+    In(Value),
+    Equals(Value),
+    GreaterThan(Value),
+    LessThan(Value),
+    Matches(Regex),
+}
+
+/// Reject condition documents that would make per-request evaluation expensive or
+/// that use unknown operators or invalid `matches` patterns, and compile the rest
+pub fn validate_conditions(conditions: &Value) -> Result<CompiledConditions> {
+    let mut rules = 0;
+    // Walk iteratively so hostile input can't exhaust the stack either
+    let mut pending = vec![(conditions, 1)];
+
+    while let Some((value, depth)) = pending.pop() {
+        if depth > MAX_CONDITION_DEPTH {
+            return Err(ApiError::Validation(format!(
+                "Flag conditions exceed the maximum nesting depth of {}",
+                MAX_CONDITION_DEPTH
+            )));
+        }
+
+        rules += 1;
+        if rules > MAX_CONDITION_RULES {
+            return Err(ApiError::Validation(format!(
+                "Flag conditions exceed the maximum of {} rules",
+                MAX_CONDITION_RULES
+            )));
+        }
+
+        match value {
+            Value::Object(map) => pending.extend(map.values().map(|child| (child, depth + 1))),
+            Value::Array(items) => pending.extend(items.iter().map(|child| (child, depth + 1))),
+            _ => {}
+        }
+    }
+
+    let rules = conditions
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| !SCHEDULE_KEYS.contains(&key.as_str()));
+    let mut compiled = CompiledConditions::default();
+    for (key, rule) in rules {
+        let operators = match rule {
+            Value::Array(_) => vec![Operator::In(rule.clone())],
+            Value::Object(operators) => operators
+                .iter()
+                .map(|(operator, expected)| compile_operator(key, operator, expected))
+                .collect::<Result<_>>()?,
+            _ => vec![Operator::Equals(rule.clone())],
+        };
+        compiled.rules.push((key.clone(), operators));
+    }
+
+    Ok(compiled)
+}
+
+fn compile_operator(key: &str, operator: &str, expected: &Value) -> Result<Operator> {
+    match operator {
+        "in" => Ok(Operator::In(expected.clone())),
+        "equals" => Ok(Operator::Equals(expected.clone())),
+        "greater_than" => Ok(Operator::GreaterThan(expected.clone())),
+        "less_than" => Ok(Operator::LessThan(expected.clone())),
+        "matches" => {
+            let pattern = expected.as_str().ok_or_else(|| {
+                ApiError::Validation(format!("The {} condition's matches pattern must be a string", key))
+            })?;
+            let regex = Regex::new(pattern).map_err(|e| {
+                ApiError::Validation(format!("Invalid matches pattern in the {} condition: {}", key, e))
+            })?;
+            Ok(Operator::Matches(regex))
+        }
+        _ => Err(ApiError::Validation(format!(
+            "Unknown operator {} in the {} condition; expected one of {}",
+            operator,
+            key,
+            CONDITION_OPERATORS.join(", ")
+        ))),
+    }
+}
+
+/// Reject variants with blank or repeated names or negative weights, and variant lists
+/// whose weights are all zero. Weights are otherwise normalized, not required to sum to 100.
+pub fn validate_variants(variants: &[FlagVariant]) -> Result<()> {
+    let mut names = HashSet::new();
+    for variant in variants {
+        if variant.name.trim().is_empty() {
+            return Err(ApiError::Validation("Flag variant names must not be empty".to_string()));
+        }
+        if !names.insert(variant.name.as_str()) {
+            return Err(ApiError::Validation(format!("Flag variant {} is listed twice", variant.name)));
+        }
+        if !(variant.weight >= 0.0 && variant.weight.is_finite()) {
+            return Err(ApiError::Validation(format!(
+                "Flag variant {} must have a non-negative weight",
+                variant.name
+            )));
+        }
+    }
+
+    if !variants.is_empty() && variants.iter().all(|variant| variant.weight == 0.0) {
+        return Err(ApiError::Validation("At least one flag variant needs a positive weight".to_string()));
+    }
+    Ok(())
+}
+
+impl CompiledConditions {
+    /// Whether `context` satisfies every rule. Each key other than the schedule's is read
+    /// from the context and checked against its rule:
+    /// - an array: the value must be one of its elements (`{"user_tier": ["premium"]}`)
+    /// - an object of operators, all of which must hold:
+    ///   `in`, `equals`, `greater_than`, `less_than` (numbers) and `matches` (a regex on strings)
+    /// - any other value: the value must equal it
+    ///
+    /// A missing field or a value of the wrong type fails the rule.
+    pub fn matches(&self, context: &Value) -> bool {
+        self.rules.iter().all(|(key, operators)| {
+            let Some(actual) = context.get(key) else {
+                return false;
+            };
+            operators.iter().all(|operator| operator.holds(actual))
+        })
+    }
+}
+
+impl Operator {
+    fn holds(&self, actual: &Value) -> bool {
+        match self {
+            Operator::In(expected) => expected
+                .as_array()
+                .is_some_and(|candidates| candidates.iter().any(|candidate| values_equal(candidate, actual))),
+            Operator::Equals(expected) => values_equal(expected, actual),
+            Operator::GreaterThan(expected) => {
+                matches!((actual.as_f64(), expected.as_f64()), (Some(a), Some(e)) if a > e)
+            }
+            Operator::LessThan(expected) => {
+                matches!((actual.as_f64(), expected.as_f64()), (Some(a), Some(e)) if a < e)
+            }
+            Operator::Matches(regex) => actual.as_str().is_some_and(|actual| regex.is_match(actual)),
+        }
+    }
+}
+
+/// JSON equality, except that numbers compare by value so `30` equals `30.0`
+fn values_equal(expected: &Value, actual: &Value) -> bool {
+    match (expected.as_f64(), actual.as_f64()) {
+        (Some(e), Some(a)) => e == a,
+        _ => expected == actual,
+    }
+}
+
+/// Reject dependencies on unknown flags and any that would form a cycle once `flag`
+/// replaces its current version in `flags`
+pub fn validate_dependencies(flag: &FeatureFlag, flags: &HashMap<String, FeatureFlag>) -> Result<()> {
+    if let Some(missing) = flag.depends_on.iter().find(|name| **name != flag.name && !flags.contains_key(*name)) {
+        return Err(ApiError::Validation(format!("Flag {} depends on unknown flag {}", flag.name, missing)));
+    }
+
+    let depends_on = |name: &str| -> &[String] {
+        if name == flag.name {
+            &flag.depends_on
+        } else {
+            flags.get(name).map_or(&[], |f| f.depends_on.as_slice())
+        }
+    };
+
+    // Depth-first from the new flag; reaching it again means a cycle through it
+    let mut pending: Vec<Vec<&str>> = flag.depends_on.iter().map(|d| vec![flag.name.as_str(), d.as_str()]).collect();
+    let mut visited = HashSet::new();
+    while let Some(path) = pending.pop() {
+        let current = *path.last().unwrap();
+        if current == flag.name {
+            return Err(ApiError::Validation(format!("Flag dependency cycle: {}", path.join(" -> "))));
+        }
+        if !visited.insert(current) {
+            continue;
+        }
+        for next in depends_on(current) {
+            let mut next_path = path.clone();
+            next_path.push(next);
+            pending.push(next_path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Conflict naming the flags in `flags` that depend on `flag_name`, if any do
+fn check_no_dependents<'a>(flag_name: &str, flags: impl IntoIterator<Item = &'a FeatureFlag>) -> Result<()> {
+    let mut dependents: Vec<&str> = flags
+        .into_iter()
+        .filter(|flag| flag.depends_on.iter().any(|dependency| dependency == flag_name))
+        .map(|flag| flag.name.as_str())
+        .collect();
+    if dependents.is_empty() {
+        return Ok(());
+    }
+
+    dependents.sort_unstable();
+    Err(ApiError::Conflict(format!("Feature flag {} is required by: {}", flag_name, dependents.join(", "))))
+}
+
+/// Each flag with its prerequisites and the flags that require it, ordered by name
+pub fn dependency_graph(flags: &[FeatureFlag]) -> Vec<FlagDependencies> {
+    let mut graph: BTreeMap<&str, FlagDependencies> = flags
+        .iter()
+        .map(|flag| {
+            (flag.name.as_str(), FlagDependencies {
+                name: flag.name.clone(),
+                enabled: flag.enabled,
+                depends_on: flag.depends_on.clone(),
+                dependents: Vec::new(),
+            })
+        })
+        .collect();
+
+    for flag in flags {
+        for dependency in &flag.depends_on {
+            if let Some(node) = graph.get_mut(dependency.as_str()) {
+                node.dependents.push(flag.name.clone());
+            }
+        }
+    }
+
+    graph
+        .into_values()
+        .map(|mut node| {
+            node.dependents.sort();
+            node
+        })
+        .collect()
+}
+
+/// Add each of `defaults` missing from `flags`, returning the added flags. Dependencies
+/// are checked once all are in, so defaults may depend on flags listed after them.
+fn seed_defaults(flags: &mut HashMap<String, FeatureFlag>, defaults: &[FeatureFlagDefault]) -> Result<Vec<FeatureFlag>> {
+    let now = OffsetDateTime::now_utc();
+
+    let mut seeded = Vec::new();
+    for default in defaults {
+        if flags.contains_key(&default.name) {
+            continue;
+        }
+        if let Some(conditions) = &default.conditions {
+            validate_conditions(conditions)?;
+        }
+        validate_variants(&default.variants)?;
+        let flag = FeatureFlag {
+            name: default.name.clone(),
+            enabled: default.enabled,
+            rollout_percentage: default.rollout_percentage,
+            conditions: default.conditions.clone(),
+            depends_on: default.depends_on.clone(),
+            variants: default.variants.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        flags.insert(flag.name.clone(), flag.clone());
+        seeded.push(flag);
+    }
+
+    for flag in &seeded {
+        validate_dependencies(flag, flags)?;
+    }
+
+    Ok(seeded)
+}
 
 #[async_trait]
 pub trait FeatureFlagService: Send + Sync {
     async fn is_enabled(&self, flag_name: &str, user_id: Option<&str>, context: Option<&Value>) -> bool;
     async fn get_flag(&self, flag_name: &str) -> Result<Option<FeatureFlag>>;
     async fn set_flag(&self, flag: FeatureFlag) -> Result<()>;
+    /// Insert `flag` unless one with its name already exists; false when it does
+    async fn create_flag(&self, flag: FeatureFlag) -> Result<bool>;
+    /// Remove a flag, refusing with a conflict while other flags depend on it; false when
+    /// there is no such flag
     async fn delete_flag(&self, flag_name: &str) -> Result<bool>;
     async fn list_flags(&self) -> Result<Vec<FeatureFlag>>;
+
+    /// The variant of a multivariate flag assigned to `user_id`, evaluated like
+    /// `is_enabled`; `None` when the flag is off for the caller or has no variants
+    async fn get_variant(&self, flag_name: &str, user_id: Option<&str>, context: Option<&Value>) -> Option<String> {
+        if !self.is_enabled(flag_name, user_id, context).await {
+            return None;
+        }
+        let flag = self.get_flag(flag_name).await.ok().flatten()?;
+        assign_variant(&flag.name, &flag.variants, user_id).map(|variant| variant.name.clone())
+    }
 }
 
 #[derive(Clone)]
 pub struct InMemoryFeatureFlagService {
-    flags: Arc<RwLock<HashMap<String, FeatureFlag>>>,
+    flags: Arc<RwLock<FlagSet>>,
+}
+
+/// Flags by name, alongside the compiled form of each one's conditions
+#[derive(Default)]
+struct FlagSet {
+    flags: HashMap<String, FeatureFlag>,
+    conditions: HashMap<String, CompiledConditions>,
+}
+
+impl FlagSet {
+    fn insert(&mut self, flag: FeatureFlag, conditions: Option<CompiledConditions>) {
+        match conditions {
+            Some(conditions) => self.conditions.insert(flag.name.clone(), conditions),
+            None => self.conditions.remove(&flag.name),
+        };
+        self.flags.insert(flag.name.clone(), flag);
+    }
+
+    /// Add a flag that hasn't been validated, e.g. one read from a backing store. If its
+    /// conditions don't compile it keeps no compiled form, so they never match.
+    fn load(&mut self, flag: FeatureFlag) {
+        let conditions = match flag.conditions.as_ref().map(validate_conditions).transpose() {
+            Ok(conditions) => conditions,
+            Err(e) => {
+                warn!("Feature flag {} has invalid conditions, they will never match: {}", flag.name, e);
+                None
+            }
+        };
+        self.insert(flag, conditions);
+    }
+
+    fn remove(&mut self, flag_name: &str) -> bool {
+        self.conditions.remove(flag_name);
+        self.flags.remove(flag_name).is_some()
+    }
 }
 
 impl InMemoryFeatureFlagService {
     pub fn new() -> Self {
         Self {
-            flags: Arc::new(RwLock::new(HashMap::new())),
-        }
-    }
-
-    pub async fn initialize_default_flags(&self) -> Result<()> {
-        let default_flags = vec![
-            FeatureFlag {
-                name: "user_registration".to_string(),
-                enabled: true,
-                rollout_percentage: 100.0,
-                conditions: None,
-                created_at: time::OffsetDateTime::now_utc(),
-                updated_at: time::OffsetDateTime::now_utc(),
-            },
-            FeatureFlag {
-                name: "beta_features".to_string(),
-                enabled: false,
-                rollout_percentage: 10.0,
-                conditions: Some(serde_json::json!({
-                    "user_tier": ["premium", "enterprise"]
-                })),
-                created_at: time::OffsetDateTime::now_utc(),
-                updated_at: time::OffsetDateTime::now_utc(),
-            },
-            FeatureFlag {
-                name: "advanced_analytics".to_string(),
-                enabled: true,
-                rollout_percentage: 50.0,
-                conditions: None,
-                created_at: time::OffsetDateTime::now_utc(),
-                updated_at: time::OffsetDateTime::now_utc(),
-            },
-        ];
+            flags: Arc::new(RwLock::new(FlagSet::default())),
+        }
+    }
 
+    /// Seed `defaults` for every flag not already in the store; existing flags keep
+    /// their current state
+    pub async fn initialize_default_flags(&self, defaults: &[FeatureFlagDefault]) -> Result<()> {
         let mut flags = self.flags.write().await;
-        for flag in default_flags {
-            flags.insert(flag.name.clone(), flag);
+        let seeded = seed_defaults(&mut flags.flags, defaults)?;
+        for flag in &seeded {
+            flags.load(flag.clone());
         }
 
-        info!("Initialized {} default feature flags", flags.len());
+        info!("Initialized {} default feature flags", seeded.len());
         Ok(())
     }
 
-    fn evaluate_conditions(&self, flag: &FeatureFlag, context: Option<&Value>) -> bool {
-        if let (Some(conditions), Some(ctx)) = (&flag.conditions, context) {
-            // Simple condition evaluation - in production, use a more sophisticated rules engine
-            if let Some(user_tier_conditions) = conditions.get("user_tier") {
-                if let Some(user_tier) = ctx.get("user_tier") {
-                    if let Some(tier_array) = user_tier_conditions.as_array() {
-                        return tier_array.contains(user_tier);
-                    }
-                }
-                return false;
+    /// Replace every flag at once, e.g. with a snapshot loaded from a backing store
+    pub async fn replace_all(&self, snapshot: Vec<FeatureFlag>) {
+        let mut flags = FlagSet::default();
+        for flag in snapshot {
+            flags.load(flag);
+        }
+        *self.flags.write().await = flags;
+    }
+
+    /// Flags are only targeted when the caller supplies a context; without one the
+    /// conditions are not applied
+    fn evaluate_conditions(&self, flags: &FlagSet, flag: &FeatureFlag, context: Option<&Value>) -> bool {
+        match (&flag.conditions, context) {
+            (Some(_), Some(ctx)) => flags.conditions.get(&flag.name).is_some_and(|conditions| conditions.matches(ctx)),
+            _ => true,
+        }
+    }
+
+    /// Whether `now` falls inside the flag's optional `start_at`/`end_at` window
+    /// (RFC 3339 timestamps in `conditions`). Malformed bounds keep the flag off.
+    fn within_schedule(&self, flag: &FeatureFlag, now: OffsetDateTime) -> bool {
+        let Some(conditions) = &flag.conditions else {
+            return true;
+        };
+
+        let bound = |key: &str| -> std::result::Result<Option<OffsetDateTime>, ()> {
+            match conditions.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(value) => value
+                    .as_str()
+                    .and_then(|raw| OffsetDateTime::parse(raw, &Rfc3339).ok())
+                    .map(Some)
+                    .ok_or_else(|| {
+                        warn!("Feature flag {} has an invalid {}: {}", flag.name, key, value);
+                    }),
             }
+        };
+
+        let (Ok(start_at), Ok(end_at)) = (bound("start_at"), bound("end_at")) else {
+            return false;
+        };
+
+        let started = match start_at {
+            Some(start) => now >= start,
+            None => true,
+        };
+        let expired = match end_at {
+            Some(end) => now >= end,
+            None => false,
+        };
+
+        started && !expired
+    }
+
+    /// Evaluate `flag_name` and, with the same user and context, every flag it depends on.
+    /// `depth` stops a cycle loaded from a backing store from recursing forever.
+    fn evaluate(
+        &self,
+        flags: &FlagSet,
+        flag_name: &str,
+        user_id: Option<&str>,
+        context: Option<&Value>,
+        depth: usize,
+    ) -> bool {
+        let Some(flag) = flags.flags.get(flag_name) else {
+            return false; // Flag doesn't exist, default to disabled
+        };
+
+        if !flag.enabled {
+            return false;
+        }
+
+        if depth > flags.flags.len() {
+            warn!("Feature flag {} is part of a dependency cycle, treating as disabled", flag_name);
+            return false;
         }
-        true
+
+        // A flag is only on when everything it depends on is on for the same caller
+        if !flag
+            .depends_on
+            .iter()
+            .all(|dependency| self.evaluate(flags, dependency, user_id, context, depth + 1))
+        {
+            return false;
+        }
+
+        // Scheduled window is evaluated against server time, before any ramp
+        if !self.within_schedule(flag, OffsetDateTime::now_utc()) {
+            return false;
+        }
+
+        // Check conditions first
+        if !self.evaluate_conditions(flags, flag, context) {
+            return false;
+        }
+
+        // Check rollout percentage
+        self.check_rollout(flag, user_id)
     }
 
     fn check_rollout(&self, flag: &FeatureFlag, user_id: Option<&str>) -> bool {
@@ -89,23 +495,71 @@ impl InMemoryFeatureFlagService {
 
         if let Some(uid) = user_id {
             // Consistent hashing based on user ID for stable rollout
-            let hash = self.hash_user_id(uid);
-            let percentage = (hash % 100) as f32;
-            percentage < flag.rollout_percentage
+            in_rollout(&flag.name, flag.rollout_percentage, uid)
         } else {
             // Random rollout for anonymous users
             rand::random::<f32>() * 100.0 < flag.rollout_percentage
         }
     }
+}
 
-    fn hash_user_id(&self, user_id: &str) -> u32 {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
+/// Whether `user_id` falls inside `flag_name`'s rollout of `rollout_percentage`. Users
+/// keep their bucket as the percentage changes, so raising it only ever adds users.
+pub fn in_rollout(flag_name: &str, rollout_percentage: f32, user_id: &str) -> bool {
+    if rollout_percentage >= 100.0 {
+        return true;
+    }
+    (rollout_bucket(flag_name, user_id) as f32) < rollout_percentage
+}
+
+/// The user's position, 0-99, in the flag's percentage rollout
+fn rollout_bucket(flag_name: &str, user_id: &str) -> u32 {
+    (stable_hash(flag_name, user_id) % 100) as u32
+}
+
+/// Resolution of the split between variants, finer than the rollout's whole percentages
+const VARIANT_BUCKETS: u64 = 10_000;
 
-        let mut hasher = DefaultHasher::new();
-        user_id.hash(&mut hasher);
-        hasher.finish() as u32
+/// Pick a variant for `user_id` in proportion to the variants' weights, normalized by
+/// their total. Users keep their variant across restarts, like their rollout bucket, but
+/// the split is hashed separately so it doesn't correlate with rollout membership.
+/// Anonymous callers get a random variant.
+pub fn assign_variant<'a>(flag_name: &str, variants: &'a [FlagVariant], user_id: Option<&str>) -> Option<&'a FlagVariant> {
+    let weight = |variant: &FlagVariant| f64::from(variant.weight.max(0.0));
+    let total: f64 = variants.iter().map(weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let position = match user_id {
+        Some(uid) => {
+            let salt = format!("{}:variant", flag_name);
+            (stable_hash(&salt, uid) % VARIANT_BUCKETS) as f64 / VARIANT_BUCKETS as f64
+        }
+        None => rand::random::<f64>(),
+    } * total;
+
+    let mut cumulative = 0.0;
+    for variant in variants {
+        cumulative += weight(variant);
+        if position < cumulative {
+            return Some(variant);
+        }
     }
+    // Only reachable through float rounding at the very top of the range
+    variants.iter().rev().find(|variant| variant.weight > 0.0)
+}
+
+/// FNV-1a of `salt` and `user_id`. FNV-1a is fixed by its spec, unlike `DefaultHasher`,
+/// so results stay put across restarts, builds and Rust versions. Salting with the flag
+/// name buckets each flag independently.
+fn stable_hash(salt: &str, user_id: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    // 0xff never occurs in UTF-8, so no salt/user pair can collide with another's input
+    let input = salt.bytes().chain(std::iter::once(0xff)).chain(user_id.bytes());
+    input.fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
 }
 
 #[async_trait]
@@ -113,47 +567,381 @@ impl FeatureFlagService for InMemoryFeatureFlagService {
     #[instrument(skip(self, context))]
     async fn is_enabled(&self, flag_name: &str, user_id: Option<&str>, context: Option<&Value>) -> bool {
         let flags = self.flags.read().await;
+        self.evaluate(&flags, flag_name, user_id, context, 0)
+    }
 
-        if let Some(flag) = flags.get(flag_name) {
-            if !flag.enabled {
-                return false;
-            }
+    #[instrument(skip(self))]
+    async fn get_flag(&self, flag_name: &str) -> Result<Option<FeatureFlag>> {
+        let flags = self.flags.read().await;
+        Ok(flags.flags.get(flag_name).cloned())
+    }
+
+    #[instrument(skip(self))]
+    async fn set_flag(&self, flag: FeatureFlag) -> Result<()> {
+        let conditions = flag.conditions.as_ref().map(validate_conditions).transpose()?;
+        validate_variants(&flag.variants)?;
+
+        let mut flags = self.flags.write().await;
+        validate_dependencies(&flag, &flags.flags)?;
+        flags.insert(flag, conditions);
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn create_flag(&self, flag: FeatureFlag) -> Result<bool> {
+        let conditions = flag.conditions.as_ref().map(validate_conditions).transpose()?;
+        validate_variants(&flag.variants)?;
+
+        let mut flags = self.flags.write().await;
+        if flags.flags.contains_key(&flag.name) {
+            return Ok(false);
+        }
+        validate_dependencies(&flag, &flags.flags)?;
+        flags.insert(flag, conditions);
+        Ok(true)
+    }
 
-            // Check conditions first
-            if !self.evaluate_conditions(flag, context) {
+    #[instrument(skip(self))]
+    async fn delete_flag(&self, flag_name: &str) -> Result<bool> {
+        let mut flags = self.flags.write().await;
+        check_no_dependents(flag_name, flags.flags.values())?;
+        Ok(flags.remove(flag_name))
+    }
+
+    #[instrument(skip(self))]
+    async fn list_flags(&self) -> Result<Vec<FeatureFlag>> {
+        let flags = self.flags.read().await;
+        Ok(flags.flags.values().cloned().collect())
+    }
+}
+
+/// Flags persisted in the `feature_flags` table, so admin changes survive restarts and
+/// are shared between instances. Meant to sit behind `CachedFeatureFlagService`; called
+/// directly, every evaluation reads all flags from the database.
+#[derive(Clone)]
+pub struct DatabaseFeatureFlagService {
+    pool: PgPool,
+}
+
+/// `feature_flags` row; variants are stored as JSONB
+struct FeatureFlagRow {
+    name: String,
+    enabled: bool,
+    rollout_percentage: f32,
+    conditions: Option<Value>,
+    depends_on: Vec<String>,
+    variants: Json<Vec<FlagVariant>>,
+    created_at: OffsetDateTime,
+    updated_at: OffsetDateTime,
+}
+
+impl From<FeatureFlagRow> for FeatureFlag {
+    fn from(row: FeatureFlagRow) -> Self {
+        Self {
+            name: row.name,
+            enabled: row.enabled,
+            rollout_percentage: row.rollout_percentage,
+            conditions: row.conditions,
+            depends_on: row.depends_on,
+            variants: row.variants.0,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+impl DatabaseFeatureFlagService {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Start a transaction for changing flags, holding a lock that only other flag writers
+    /// wait on, so dependency checks stay true until the change commits. Returns the
+    /// flags as of the lock.
+    async fn begin_write(&self) -> Result<(Transaction<'static, Postgres>, HashMap<String, FeatureFlag>)> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("LOCK TABLE feature_flags IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+
+        let flags = sqlx::query_as!(
+            FeatureFlagRow,
+            r#"
+            SELECT name, enabled, rollout_percentage, conditions, depends_on,
+                   variants AS "variants: Json<Vec<FlagVariant>>", created_at, updated_at
+            FROM feature_flags
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let flags = flags.into_iter().map(|row| (row.name.clone(), FeatureFlag::from(row))).collect();
+        Ok((tx, flags))
+    }
+
+    /// Insert `defaults` for every flag not already stored. Flags changed by admins keep
+    /// their values, and a concurrent insert by another instance wins.
+    #[instrument(skip(self, defaults))]
+    pub async fn initialize_default_flags(&self, defaults: &[FeatureFlagDefault]) -> Result<()> {
+        let mut flags: HashMap<String, FeatureFlag> =
+            self.list_flags().await?.into_iter().map(|flag| (flag.name.clone(), flag)).collect();
+        let seeded = seed_defaults(&mut flags, defaults)?;
+
+        let mut inserted = 0;
+        for flag in &seeded {
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO feature_flags (name, enabled, rollout_percentage, conditions, depends_on, variants, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (name) DO NOTHING
+                "#,
+                flag.name,
+                flag.enabled,
+                flag.rollout_percentage,
+                flag.conditions,
+                &flag.depends_on,
+                Json(&flag.variants) as _,
+                flag.created_at,
+                flag.updated_at
+            )
+            .execute(&self.pool)
+            .await?;
+            inserted += result.rows_affected();
+        }
+
+        info!("Initialized {} default feature flags", inserted);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl FeatureFlagService for DatabaseFeatureFlagService {
+    #[instrument(skip(self, context))]
+    async fn is_enabled(&self, flag_name: &str, user_id: Option<&str>, context: Option<&Value>) -> bool {
+        let flags = match self.list_flags().await {
+            Ok(flags) => flags,
+            Err(e) => {
+                warn!("Failed to load feature flags, treating {} as disabled: {}", flag_name, e);
                 return false;
             }
+        };
 
-            // Check rollout percentage
-            self.check_rollout(flag, user_id)
-        } else {
-            false // Flag doesn't exist, default to disabled
-        }
+        let snapshot = InMemoryFeatureFlagService::new();
+        snapshot.replace_all(flags).await;
+        snapshot.is_enabled(flag_name, user_id, context).await
     }
 
     #[instrument(skip(self))]
     async fn get_flag(&self, flag_name: &str) -> Result<Option<FeatureFlag>> {
-        let flags = self.flags.read().await;
-        Ok(flags.get(flag_name).cloned())
+        let flag = sqlx::query_as!(
+            FeatureFlagRow,
+            r#"
+            SELECT name, enabled, rollout_percentage, conditions, depends_on,
+                   variants AS "variants: Json<Vec<FlagVariant>>", created_at, updated_at
+            FROM feature_flags
+            WHERE name = $1
+            "#,
+            flag_name
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(flag.map(FeatureFlag::from))
     }
 
     #[instrument(skip(self))]
     async fn set_flag(&self, flag: FeatureFlag) -> Result<()> {
-        let mut flags = self.flags.write().await;
-        flags.insert(flag.name.clone(), flag);
+        if let Some(conditions) = &flag.conditions {
+            validate_conditions(conditions)?;
+        }
+        validate_variants(&flag.variants)?;
+
+        let (mut tx, flags) = self.begin_write().await?;
+        validate_dependencies(&flag, &flags)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO feature_flags (name, enabled, rollout_percentage, conditions, depends_on, variants, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (name) DO UPDATE
+            SET enabled = EXCLUDED.enabled,
+                rollout_percentage = EXCLUDED.rollout_percentage,
+                conditions = EXCLUDED.conditions,
+                depends_on = EXCLUDED.depends_on,
+                variants = EXCLUDED.variants,
+                updated_at = EXCLUDED.updated_at
+            "#,
+            flag.name,
+            flag.enabled,
+            flag.rollout_percentage,
+            flag.conditions,
+            &flag.depends_on,
+            Json(&flag.variants) as _,
+            flag.created_at,
+            flag.updated_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
         Ok(())
     }
 
+    #[instrument(skip(self))]
+    async fn create_flag(&self, flag: FeatureFlag) -> Result<bool> {
+        if let Some(conditions) = &flag.conditions {
+            validate_conditions(conditions)?;
+        }
+        validate_variants(&flag.variants)?;
+
+        let (mut tx, flags) = self.begin_write().await?;
+        if flags.contains_key(&flag.name) {
+            return Ok(false);
+        }
+        validate_dependencies(&flag, &flags)?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO feature_flags (name, enabled, rollout_percentage, conditions, depends_on, variants, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (name) DO NOTHING
+            "#,
+            flag.name,
+            flag.enabled,
+            flag.rollout_percentage,
+            flag.conditions,
+            &flag.depends_on,
+            Json(&flag.variants) as _,
+            flag.created_at,
+            flag.updated_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     #[instrument(skip(self))]
     async fn delete_flag(&self, flag_name: &str) -> Result<bool> {
-        let mut flags = self.flags.write().await;
-        Ok(flags.remove(flag_name).is_some())
+        let (mut tx, flags) = self.begin_write().await?;
+        check_no_dependents(flag_name, flags.values())?;
+
+        let result = sqlx::query!("DELETE FROM feature_flags WHERE name = $1", flag_name)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
     }
 
     #[instrument(skip(self))]
     async fn list_flags(&self) -> Result<Vec<FeatureFlag>> {
-        let flags = self.flags.read().await;
-        Ok(flags.values().cloned().collect())
+        let flags = sqlx::query_as!(
+            FeatureFlagRow,
+            r#"
+            SELECT name, enabled, rollout_percentage, conditions, depends_on,
+                   variants AS "variants: Json<Vec<FlagVariant>>", created_at, updated_at
+            FROM feature_flags
+            ORDER BY name
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(flags.into_iter().map(FeatureFlag::from).collect())
+    }
+}
+
+/// Fail-static cache in front of a backing flag store.
+///
+/// Evaluations only read the in-memory snapshot, which `refresh` (run from the
+/// scheduler) replaces with the store's contents. When the store is unavailable the
+/// last-known values keep being served, up to `max_staleness`; past that every flag
+/// evaluates as disabled until a refresh succeeds.
+pub struct CachedFeatureFlagService {
+    store: Arc<dyn FeatureFlagService>,
+    cache: InMemoryFeatureFlagService,
+    last_refreshed: RwLock<Option<Instant>>,
+    max_staleness: Duration,
+}
+
+impl CachedFeatureFlagService {
+    pub fn new(store: Arc<dyn FeatureFlagService>, max_staleness: Duration) -> Self {
+        Self {
+            store,
+            cache: InMemoryFeatureFlagService::new(),
+            last_refreshed: RwLock::new(None),
+            max_staleness,
+        }
+    }
+
+    /// Reload the snapshot from the backing store, keeping the current one on failure
+    #[instrument(skip(self))]
+    pub async fn refresh(&self) -> Result<()> {
+        match self.store.list_flags().await {
+            Ok(flags) => {
+                self.cache.replace_all(flags).await;
+                *self.last_refreshed.write().await = Some(Instant::now());
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Feature flag store unavailable, serving last-known flags: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn is_stale(&self) -> bool {
+        match *self.last_refreshed.read().await {
+            Some(refreshed) => refreshed.elapsed() > self.max_staleness,
+            None => true,
+        }
+    }
+}
+
+#[async_trait]
+impl FeatureFlagService for CachedFeatureFlagService {
+    #[instrument(skip(self, context))]
+    async fn is_enabled(&self, flag_name: &str, user_id: Option<&str>, context: Option<&Value>) -> bool {
+        if self.is_stale().await {
+            warn!("Feature flag cache exceeded max staleness, treating {} as disabled", flag_name);
+            return false;
+        }
+
+        self.cache.is_enabled(flag_name, user_id, context).await
+    }
+
+    async fn get_flag(&self, flag_name: &str) -> Result<Option<FeatureFlag>> {
+        self.cache.get_flag(flag_name).await
+    }
+
+    /// Written through to the store first so the cache never holds unsaved changes
+    #[instrument(skip(self))]
+    async fn set_flag(&self, flag: FeatureFlag) -> Result<()> {
+        self.store.set_flag(flag.clone()).await?;
+        self.cache.set_flag(flag).await
+    }
+
+    #[instrument(skip(self))]
+    async fn create_flag(&self, flag: FeatureFlag) -> Result<bool> {
+        if !self.store.create_flag(flag.clone()).await? {
+            return Ok(false);
+        }
+        self.cache.set_flag(flag).await?;
+        Ok(true)
+    }
+
+    /// The store decides whether the flag may go; the cache follows without rechecking
+    #[instrument(skip(self))]
+    async fn delete_flag(&self, flag_name: &str) -> Result<bool> {
+        let deleted = self.store.delete_flag(flag_name).await?;
+        self.cache.flags.write().await.remove(flag_name);
+        Ok(deleted)
+    }
+
+    async fn list_flags(&self) -> Result<Vec<FeatureFlag>> {
+        self.cache.list_flags().await
     }
 }
 
@@ -172,3 +960,366 @@ macro_rules! feature_enabled {
         $service.is_enabled($flag, Some($user_id), Some($context)).await
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn variant(name: &str, weight: f32) -> FlagVariant {
+        FlagVariant { name: name.to_string(), weight }
+    }
+
+    // Pinned values: a change here reshuffles every user's rollout bucket and variant
+    #[test]
+    fn stable_hash_is_fnv1a_of_salt_and_user() {
+        assert_eq!(stable_hash("", ""), 0xaf64_724c_8602_eb6e);
+        assert_eq!(stable_hash("new_checkout", "user-1"), 0x7287_0c79_6b8e_9a32);
+        assert_eq!(stable_hash("new_checkout", "user-2"), 0x7287_0b79_6b8e_987f);
+        assert_eq!(stable_hash("dark_mode", "user-1"), 0xc00c_6f7e_d3ba_41df);
+    }
+
+    #[test]
+    fn rollout_buckets_are_pinned() {
+        assert_eq!(rollout_bucket("new_checkout", "user-1"), 90);
+        assert_eq!(rollout_bucket("new_checkout", "user-2"), 79);
+        assert_eq!(rollout_bucket("new_checkout", "alice"), 13);
+        assert_eq!(rollout_bucket("dark_mode", "user-1"), 51);
+    }
+
+    #[test]
+    fn in_rollout_admits_users_below_the_percentage() {
+        assert!(!in_rollout("checkout_redesign", 35.0, "user-1"));
+        assert!(in_rollout("checkout_redesign", 36.0, "user-1"));
+        assert!(!in_rollout("checkout_redesign", 0.0, "bob"));
+        assert!(in_rollout("checkout_redesign", 100.0, "bob"));
+    }
+
+    #[test]
+    fn assigned_variants_are_pinned() {
+        let variants = [variant("a", 1.0), variant("b", 1.0), variant("c", 2.0)];
+        let assigned = |user_id| assign_variant("checkout_redesign", &variants, Some(user_id)).map(|v| v.name.as_str());
+
+        assert_eq!(assigned("user-1"), Some("a"));
+        assert_eq!(assigned("user-2"), Some("a"));
+        assert_eq!(assigned("user-3"), Some("b"));
+        assert_eq!(assigned("user-4"), Some("c"));
+        assert_eq!(assigned("alice"), Some("a"));
+    }
+
+    #[test]
+    fn assign_variant_skips_zero_weights_and_needs_a_positive_total() {
+        let variants = [variant("off", 0.0), variant("on", 1.0)];
+        assert_eq!(assign_variant("checkout_redesign", &variants, Some("user-1")).map(|v| v.name.as_str()), Some("on"));
+        assert!(assign_variant("checkout_redesign", &[variant("off", 0.0)], Some("user-1")).is_none());
+        assert!(assign_variant("checkout_redesign", &[], Some("user-1")).is_none());
+    }
+
+    fn compile(conditions: Value) -> CompiledConditions {
+        validate_conditions(&conditions).expect("conditions should compile")
+    }
+
+    #[test]
+    fn in_operator_matches_listed_values() {
+        let listed = json!({"user_tier": ["premium", "enterprise"]});
+        let operator = json!({"user_tier": {"in": ["premium", "enterprise"]}});
+        for conditions in [listed, operator] {
+            let conditions = compile(conditions);
+            assert!(conditions.matches(&json!({"user_tier": "premium"})));
+            assert!(!conditions.matches(&json!({"user_tier": "free"})));
+            assert!(!conditions.matches(&json!({})));
+        }
+    }
+
+    #[test]
+    fn equals_operator_compares_numbers_by_value() {
+        for conditions in [json!({"seats": 30}), json!({"seats": {"equals": 30}})] {
+            let conditions = compile(conditions);
+            assert!(conditions.matches(&json!({"seats": 30.0})));
+            assert!(!conditions.matches(&json!({"seats": 31})));
+            assert!(!conditions.matches(&json!({"seats": "30"})));
+        }
+    }
+
+    #[test]
+    fn greater_than_operator_needs_a_larger_number() {
+        let conditions = compile(json!({"signup_age_days": {"greater_than": 30}}));
+        assert!(conditions.matches(&json!({"signup_age_days": 31})));
+        assert!(!conditions.matches(&json!({"signup_age_days": 30})));
+        assert!(!conditions.matches(&json!({"signup_age_days": "31"})));
+    }
+
+    #[test]
+    fn less_than_operator_needs_a_smaller_number() {
+        let conditions = compile(json!({"signup_age_days": {"less_than": 7}}));
+        assert!(conditions.matches(&json!({"signup_age_days": 6.5})));
+        assert!(!conditions.matches(&json!({"signup_age_days": 7})));
+        assert!(!conditions.matches(&json!({"signup_age_days": null})));
+    }
+
+    #[test]
+    fn matches_operator_applies_the_pattern_to_strings() {
+        let conditions = compile(json!({"email": {"matches": "@example\\.com$"}}));
+        assert!(conditions.matches(&json!({"email": "ops@example.com"})));
+        assert!(!conditions.matches(&json!({"email": "ops@example.org"})));
+        assert!(!conditions.matches(&json!({"email": 42})));
+    }
+
+    #[test]
+    fn every_operator_on_a_key_must_hold() {
+        let conditions = compile(json!({
+            "signup_age_days": {"greater_than": 7, "less_than": 30},
+            "start_at": "2020-01-01T00:00:00Z"
+        }));
+        assert!(conditions.matches(&json!({"signup_age_days": 10})));
+        assert!(!conditions.matches(&json!({"signup_age_days": 40})));
+    }
+
+    #[test]
+    fn invalid_patterns_and_unknown_operators_are_rejected() {
+        assert!(validate_conditions(&json!({"email": {"matches": "("}})).is_err());
+        assert!(validate_conditions(&json!({"email": {"matches": 1}})).is_err());
+        assert!(validate_conditions(&json!({"seats": {"at_least": 3}})).is_err());
+    }
+
+    #[tokio::test]
+    async fn loaded_flags_with_invalid_conditions_never_match() {
+        let now = OffsetDateTime::now_utc();
+        let flag = FeatureFlag {
+            name: "beta_search".to_string(),
+            enabled: true,
+            rollout_percentage: 100.0,
+            conditions: Some(json!({"email": {"matches": "("}})),
+            depends_on: Vec::new(),
+            variants: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        };
+        let service = InMemoryFeatureFlagService::new();
+        service.replace_all(vec![flag]).await;
+
+        assert!(!service.is_enabled("beta_search", Some("user-1"), Some(&json!({"email": "("}))).await);
+    }
+
+    fn flag(name: &str, enabled: bool, depends_on: &[&str]) -> FeatureFlag {
+        let now = OffsetDateTime::now_utc();
+        FeatureFlag {
+            name: name.to_string(),
+            enabled,
+            rollout_percentage: 100.0,
+            conditions: None,
+            depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+            variants: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn flag_map(flags: Vec<FeatureFlag>) -> HashMap<String, FeatureFlag> {
+        flags.into_iter().map(|flag| (flag.name.clone(), flag)).collect()
+    }
+
+    fn validation_message(result: Result<()>) -> String {
+        match result {
+            Err(ApiError::Validation(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dependencies_must_name_existing_flags() {
+        let flags = flag_map(vec![flag("a", true, &[]), flag("b", true, &["a"])]);
+
+        assert!(validate_dependencies(&flag("c", true, &["a", "b"]), &flags).is_ok());
+        assert_eq!(
+            validation_message(validate_dependencies(&flag("c", true, &["a", "missing"]), &flags)),
+            "Flag c depends on unknown flag missing"
+        );
+    }
+
+    #[test]
+    fn dependency_cycles_are_rejected() {
+        let flags = flag_map(vec![flag("a", true, &[]), flag("b", true, &["a"]), flag("c", true, &["b"])]);
+
+        assert_eq!(
+            validation_message(validate_dependencies(&flag("a", true, &["c"]), &flags)),
+            "Flag dependency cycle: a -> c -> b -> a"
+        );
+        assert_eq!(
+            validation_message(validate_dependencies(&flag("d", true, &["d"]), &flags)),
+            "Flag dependency cycle: d -> d"
+        );
+        // Replacing a flag checks its new dependencies, not the stored ones
+        let flags = flag_map(vec![flag("a", true, &["b"]), flag("b", true, &[])]);
+        assert!(validate_dependencies(&flag("a", true, &[]), &flags).is_ok());
+        assert!(validate_dependencies(&flag("b", true, &["a"]), &flags).is_err());
+    }
+
+    #[test]
+    fn shared_dependencies_are_not_cycles() {
+        let flags = flag_map(vec![
+            flag("base", true, &[]),
+            flag("left", true, &["base"]),
+            flag("right", true, &["base"]),
+        ]);
+        assert!(validate_dependencies(&flag("top", true, &["left", "right", "base"]), &flags).is_ok());
+    }
+
+    #[test]
+    fn dependency_graphs_list_prerequisites_and_dependents_by_name() {
+        let graph = dependency_graph(&[
+            flag("reports", true, &["analytics", "export"]),
+            flag("export", false, &[]),
+            flag("analytics", true, &["export"]),
+        ]);
+
+        let nodes: Vec<(&str, Vec<String>, Vec<String>)> = graph
+            .iter()
+            .map(|node| (node.name.as_str(), node.depends_on.clone(), node.dependents.clone()))
+            .collect();
+        let names = |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+        assert_eq!(
+            nodes,
+            [
+                ("analytics", names(&["export"]), names(&["reports"])),
+                ("export", names(&[]), names(&["analytics", "reports"])),
+                ("reports", names(&["analytics", "export"]), names(&[])),
+            ]
+        );
+        assert!(!graph[1].enabled);
+    }
+
+    #[tokio::test]
+    async fn flags_are_only_enabled_when_their_dependencies_are() {
+        let service = InMemoryFeatureFlagService::new();
+        service
+            .replace_all(vec![
+                flag("base", false, &[]),
+                flag("middle", true, &["base"]),
+                flag("top", true, &["middle"]),
+            ])
+            .await;
+        assert!(!service.is_enabled("middle", Some("user-1"), None).await);
+        assert!(!service.is_enabled("top", Some("user-1"), None).await);
+
+        service.set_flag(flag("base", true, &[])).await.unwrap();
+        assert!(service.is_enabled("top", Some("user-1"), None).await);
+
+        // A disabled dependency gates its dependents even though they are enabled
+        service.set_flag(flag("middle", false, &["base"])).await.unwrap();
+        assert!(!service.is_enabled("top", Some("user-1"), None).await);
+    }
+
+    #[tokio::test]
+    async fn cycles_loaded_from_a_store_evaluate_as_disabled() {
+        let service = InMemoryFeatureFlagService::new();
+        service.replace_all(vec![flag("a", true, &["b"]), flag("b", true, &["a"])]).await;
+
+        assert!(!service.is_enabled("a", Some("user-1"), None).await);
+    }
+
+    #[tokio::test]
+    async fn concurrent_creates_of_one_name_admit_exactly_one() {
+        let service = InMemoryFeatureFlagService::new();
+        let creates = (0..8).map(|i| {
+            let service = service.clone();
+            tokio::spawn(async move { service.create_flag(flag("new", i % 2 == 0, &[])).await.unwrap() })
+        });
+
+        let mut created = 0;
+        for create in creates {
+            created += create.await.unwrap() as usize;
+        }
+        assert_eq!(created, 1);
+        assert!(!service.create_flag(flag("new", true, &[])).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn flags_with_dependents_are_not_deleted() {
+        let service = InMemoryFeatureFlagService::new();
+        service
+            .replace_all(vec![flag("base", true, &[]), flag("b", true, &["base"]), flag("a", true, &["base"])])
+            .await;
+
+        match service.delete_flag("base").await {
+            Err(ApiError::Conflict(message)) => assert_eq!(message, "Feature flag base is required by: a, b"),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert!(service.get_flag("base").await.unwrap().is_some());
+
+        assert!(service.delete_flag("a").await.unwrap());
+        assert!(service.delete_flag("b").await.unwrap());
+        assert!(service.delete_flag("base").await.unwrap());
+        assert!(!service.delete_flag("base").await.unwrap());
+    }
+
+    #[sqlx::test(migrations = "../../migrations")]
+    async fn stored_flags_are_created_once_and_kept_while_depended_on(pool: PgPool) {
+        let service = DatabaseFeatureFlagService::new(pool);
+        let creates = (0..4).map(|_| {
+            let service = service.clone();
+            tokio::spawn(async move { service.create_flag(flag("base", true, &[])).await.unwrap() })
+        });
+        let mut created = 0;
+        for create in creates {
+            created += create.await.unwrap() as usize;
+        }
+        assert_eq!(created, 1);
+
+        // Whichever of the two runs second sees the other's outcome
+        let dependent = {
+            let service = service.clone();
+            tokio::spawn(async move { service.create_flag(flag("dependent", true, &["base"])).await })
+        };
+        let deleted = service.delete_flag("base").await;
+        let dependent = dependent.await.unwrap();
+
+        let stored = flag_map(service.list_flags().await.unwrap());
+        let kept = (stored.contains_key("base"), stored.contains_key("dependent"));
+        match (deleted, dependent) {
+            (Ok(true), Err(ApiError::Validation(_))) => assert_eq!(kept, (false, false)),
+            (Err(ApiError::Conflict(_)), Ok(true)) => assert_eq!(kept, (true, true)),
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+    }
+
+    fn default_flag(name: &str, rollout_percentage: f32, depends_on: &[&str]) -> FeatureFlagDefault {
+        FeatureFlagDefault {
+            name: name.to_string(),
+            enabled: true,
+            rollout_percentage,
+            conditions: None,
+            depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+            variants: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn seeding_skips_flags_already_in_the_store() {
+        let mut stored = flag("beta_features", false, &[]);
+        stored.rollout_percentage = 10.0;
+        let mut flags = flag_map(vec![stored]);
+
+        let seeded = seed_defaults(
+            &mut flags,
+            &[default_flag("beta_features", 100.0, &[]), default_flag("advanced_analytics", 50.0, &["beta_features"])],
+        )
+        .unwrap();
+
+        let seeded: Vec<&str> = seeded.iter().map(|flag| flag.name.as_str()).collect();
+        assert_eq!(seeded, ["advanced_analytics"]);
+        assert!(!flags["beta_features"].enabled);
+        assert_eq!(flags["beta_features"].rollout_percentage, 10.0);
+        assert_eq!(flags["advanced_analytics"].rollout_percentage, 50.0);
+    }
+
+    #[test]
+    fn seeded_defaults_may_depend_on_later_ones_but_not_on_unknown_flags() {
+        let mut flags = HashMap::new();
+        let seeded = seed_defaults(&mut flags, &[default_flag("a", 100.0, &["b"]), default_flag("b", 100.0, &[])]);
+        assert_eq!(seeded.unwrap().len(), 2);
+
+        let mut flags = HashMap::new();
+        assert!(seed_defaults(&mut flags, &[default_flag("a", 100.0, &["missing"])]).is_err());
+    }
+}
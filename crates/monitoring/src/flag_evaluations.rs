@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+use tracing::instrument;
+use uuid::Uuid;
+
+use app_core::{
+    enterprise::{FeatureFlag, FlagEvaluation, FlagEvaluationQuery},
+    error::Result,
+};
+use crate::feature_flags::FeatureFlagService;
+
+/// Evaluations held in memory between flushes; further ones are dropped until the next flush
+const MAX_BUFFERED_EVALUATIONS: usize = 10_000;
+
+/// Result of writing buffered evaluations to the database
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FlushOutcome {
+    pub written: usize,
+    /// Sampled evaluations discarded because the buffer was full
+    pub dropped: usize,
+}
+
+/// Sampled record of which flags were evaluated for which users, for answering "why
+/// did this user see feature X". Evaluations are buffered in memory and written in
+/// batches by the `flush_flag_evaluations` job, so recording never waits on the database.
+pub struct FlagEvaluationLog {
+    pool: PgPool,
+    sample_rate: f64,
+    buffer: Mutex<Vec<FlagEvaluation>>,
+    dropped: Mutex<usize>,
+}
+
+impl FlagEvaluationLog {
+    pub fn new(pool: PgPool, sample_rate: f64) -> Self {
+        Self {
+            pool,
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+            buffer: Mutex::new(Vec::new()),
+            dropped: Mutex::new(0),
+        }
+    }
+
+    /// Buffer the evaluation if it is sampled
+    pub fn record(&self, flag_name: &str, user_id: &str, enabled: bool) {
+        if rand::random::<f64>() >= self.sample_rate {
+            return;
+        }
+
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.len() >= MAX_BUFFERED_EVALUATIONS {
+            *self.dropped.lock().unwrap() += 1;
+            return;
+        }
+        buffer.push(FlagEvaluation {
+            flag_name: flag_name.to_string(),
+            user_id: user_id.to_string(),
+            enabled,
+            evaluated_at: OffsetDateTime::now_utc(),
+        });
+    }
+
+    /// Write every buffered evaluation in a single insert
+    #[instrument(skip(self))]
+    pub async fn flush(&self) -> Result<FlushOutcome> {
+        let evaluations = std::mem::take(&mut *self.buffer.lock().unwrap());
+        let dropped = std::mem::take(&mut *self.dropped.lock().unwrap());
+        if evaluations.is_empty() {
+            return Ok(FlushOutcome { written: 0, dropped });
+        }
+
+        let ids: Vec<Uuid> = evaluations.iter().map(|_| Uuid::new_v4()).collect();
+        let flag_names: Vec<String> = evaluations.iter().map(|e| e.flag_name.clone()).collect();
+        let user_ids: Vec<String> = evaluations.iter().map(|e| e.user_id.clone()).collect();
+        let enabled: Vec<bool> = evaluations.iter().map(|e| e.enabled).collect();
+        let evaluated_at: Vec<OffsetDateTime> = evaluations.iter().map(|e| e.evaluated_at).collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO flag_evaluations (id, flag_name, user_id, enabled, evaluated_at)
+            SELECT * FROM UNNEST($1::uuid[], $2::varchar[], $3::varchar[], $4::bool[], $5::timestamptz[])
+            "#,
+            &ids,
+            &flag_names,
+            &user_ids,
+            &enabled,
+            &evaluated_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(FlushOutcome { written: evaluations.len(), dropped })
+    }
+
+    /// Delete evaluations recorded before `before`, returning how many
+    #[instrument(skip(self))]
+    pub async fn purge(&self, before: OffsetDateTime) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM flag_evaluations WHERE evaluated_at < $1", before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Up to `limit` recorded evaluations for `user_id` within the query's range, newest first
+    #[instrument(skip(self))]
+    pub async fn history(&self, user_id: &str, query: &FlagEvaluationQuery, limit: i64) -> Result<Vec<FlagEvaluation>> {
+        let evaluations = sqlx::query_as!(
+            FlagEvaluation,
+            r#"
+            SELECT flag_name, user_id, enabled, evaluated_at
+            FROM flag_evaluations
+            WHERE user_id = $1 AND evaluated_at >= $2 AND evaluated_at < $3
+              AND ($4::text IS NULL OR flag_name = $4)
+            ORDER BY evaluated_at DESC
+            LIMIT $5
+            "#,
+            user_id,
+            query.from,
+            query.to,
+            query.flag_name,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(evaluations)
+    }
+}
+
+/// Records the result of every evaluation made for a user in a `FlagEvaluationLog`.
+/// Only the requested flag is recorded, not the prerequisites evaluated along with it.
+pub struct RecordingFeatureFlagService {
+    inner: Arc<dyn FeatureFlagService>,
+    log: Arc<FlagEvaluationLog>,
+}
+
+impl RecordingFeatureFlagService {
+    pub fn new(inner: Arc<dyn FeatureFlagService>, log: Arc<FlagEvaluationLog>) -> Self {
+        Self { inner, log }
+    }
+}
+
+#[async_trait]
+impl FeatureFlagService for RecordingFeatureFlagService {
+    async fn is_enabled(&self, flag_name: &str, user_id: Option<&str>, context: Option<&Value>) -> bool {
+        let enabled = self.inner.is_enabled(flag_name, user_id, context).await;
+        if let Some(user_id) = user_id {
+            self.log.record(flag_name, user_id, enabled);
+        }
+        enabled
+    }
+
+    async fn get_flag(&self, flag_name: &str) -> Result<Option<FeatureFlag>> {
+        self.inner.get_flag(flag_name).await
+    }
+
+    async fn set_flag(&self, flag: FeatureFlag) -> Result<()> {
+        self.inner.set_flag(flag).await
+    }
+
+    async fn create_flag(&self, flag: FeatureFlag) -> Result<bool> {
+        self.inner.create_flag(flag).await
+    }
+
+    async fn delete_flag(&self, flag_name: &str) -> Result<bool> {
+        self.inner.delete_flag(flag_name).await
+    }
+
+    async fn list_flags(&self) -> Result<Vec<FeatureFlag>> {
+        self.inner.list_flags().await
+    }
+}
@@ -3,9 +3,19 @@ pub mod tracing_config;
 pub mod circuit_breaker;
 pub mod audit;
 pub mod feature_flags;
+pub mod flag_evaluations;
+pub mod scheduler;
+pub mod sanitize;
+pub mod notifications;
+pub mod dependency_health;
+pub mod redis_connection;
 
 pub use service::MetricsService;
 pub use tracing_config::init_tracing;
-pub use circuit_breaker::CircuitBreaker;
-pub use audit::{AuditService, DatabaseAuditService};
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerRegistry, CircuitBreakerSnapshot};
+pub use audit::{audit_diff, AuditService, DatabaseAuditService, GatedAuditService};
 pub use feature_flags::{FeatureFlagService, InMemoryFeatureFlagService};
+pub use scheduler::{JobLock, JobLockGuard, JobStatus, Scheduler};
+pub use dependency_health::{DependencyHealthTracker, DependencyReport};
+pub use redis_connection::GuardedRedis;
+pub use notifications::{Mailer, Notification, NotificationService, Notifier};
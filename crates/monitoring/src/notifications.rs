@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox,
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, instrument, warn};
+
+use app_core::{config::NotificationConfig, error::Result};
+
+/// Timeout for a single outbound notification request
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Message for a human operator
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub subject: String,
+    pub body: String,
+}
+
+impl Notification {
+    pub fn new(subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+}
+
+/// Delivery transport for operator notifications
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Default transport: notifications only go to the application log
+#[derive(Clone, Default)]
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        info!(subject = %notification.subject, "Notification: {}", notification.body);
+        Ok(())
+    }
+}
+
+/// Sends each notification by SMTP (STARTTLS) to every configured recipient
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(
+        smtp_host: &str,
+        smtp_port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self> {
+        Ok(Self {
+            transport: smtp_transport(smtp_host, smtp_port, credentials)?,
+            from: parse_mailbox(from)?,
+            to: to.iter().map(|address| parse_mailbox(address)).collect::<Result<_>>()?,
+        })
+    }
+}
+
+fn smtp_transport(
+    smtp_host: &str,
+    smtp_port: u16,
+    credentials: Option<(String, String)>,
+) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)
+        .map_err(|e| anyhow::anyhow!("Invalid SMTP relay {}: {}", smtp_host, e))?
+        .port(smtp_port)
+        .timeout(Some(DELIVERY_TIMEOUT));
+    if let Some((username, password)) = credentials {
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+    Ok(builder.build())
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox> {
+    Ok(address
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid email address {}: {}", address, e))?)
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        for recipient in &self.to {
+            let message = Message::builder()
+                .from(self.from.clone())
+                .to(recipient.clone())
+                .subject(&notification.subject)
+                .body(notification.body.clone())
+                .map_err(|e| anyhow::anyhow!("Failed to build notification email: {}", e))?;
+
+            self.transport
+                .send(message)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to send notification email: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Posts notifications to a Slack incoming webhook
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: String) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Failed to build HTTP client: {}", e))?;
+
+        Ok(Self { client, webhook_url })
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let payload = serde_json::json!({
+            "text": format!("*{}*\n{}", notification.subject, notification.body),
+        });
+
+        self.client
+            .post(&self.webhook_url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow::anyhow!("Failed to post Slack notification: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Delivery of messages addressed to individual users (verification links, security notices)
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send_to(&self, to: &str, notification: &Notification) -> Result<()>;
+}
+
+/// Used unless SMTP is configured. Development only: bodies, including any
+/// verification links, are written to the log.
+#[derive(Clone, Default)]
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send_to(&self, to: &str, notification: &Notification) -> Result<()> {
+        info!(to = %to, subject = %notification.subject, "Email: {}", notification.body);
+        Ok(())
+    }
+}
+
+/// Sends user email over the same SMTP relay as operator notifications
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(
+        smtp_host: &str,
+        smtp_port: u16,
+        credentials: Option<(String, String)>,
+        from: &str,
+    ) -> Result<Self> {
+        Ok(Self {
+            transport: smtp_transport(smtp_host, smtp_port, credentials)?,
+            from: parse_mailbox(from)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send_to(&self, to: &str, notification: &Notification) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(parse_mailbox(to)?)
+            .subject(&notification.subject)
+            .body(notification.body.clone())
+            .map_err(|e| anyhow::anyhow!("Failed to build email: {}", e))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to send email: {}", e))?;
+        Ok(())
+    }
+}
+
+/// Build the user mailer: SMTP when the email channel is configured, otherwise the log
+pub fn mailer_from_config(config: &NotificationConfig) -> Result<Arc<dyn Mailer>> {
+    Ok(match config {
+        NotificationConfig::Email {
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            from,
+            ..
+        } => Arc::new(SmtpMailer::new(
+            smtp_host,
+            *smtp_port,
+            smtp_username.clone().zip(smtp_password.clone()),
+            from,
+        )?),
+        NotificationConfig::Log | NotificationConfig::Slack { .. } => Arc::new(LogMailer),
+    })
+}
+
+/// Build the notifier selected in config
+pub fn notifier_from_config(config: &NotificationConfig) -> Result<Arc<dyn Notifier>> {
+    Ok(match config {
+        NotificationConfig::Log => Arc::new(LogNotifier),
+        NotificationConfig::Email {
+            smtp_host,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            from,
+            to,
+        } => Arc::new(EmailNotifier::new(
+            smtp_host,
+            *smtp_port,
+            smtp_username.clone().zip(smtp_password.clone()),
+            from,
+            to,
+        )?),
+        NotificationConfig::Slack { webhook_url } => Arc::new(SlackNotifier::new(webhook_url.clone())?),
+    })
+}
+
+/// Non-blocking front for a `Notifier`: deliveries run in the background so callers
+/// never wait on (or fail because of) the transport
+#[derive(Clone)]
+pub struct NotificationService {
+    notifier: Arc<dyn Notifier>,
+    mailer: Arc<dyn Mailer>,
+}
+
+impl NotificationService {
+    pub fn new(notifier: Arc<dyn Notifier>, mailer: Arc<dyn Mailer>) -> Self {
+        Self { notifier, mailer }
+    }
+
+    /// Email `notification` to a single user in the background
+    #[instrument(skip(self, to, notification), fields(subject = %notification.subject))]
+    pub fn notify_user(&self, to: &str, notification: Notification) {
+        let mailer = self.mailer.clone();
+        let to = to.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = mailer.send_to(&to, &notification).await {
+                warn!("Failed to deliver email '{}': {}", notification.subject, e);
+            }
+        });
+    }
+
+    #[instrument(skip(self, notification), fields(subject = %notification.subject))]
+    pub fn notify(&self, notification: Notification) {
+        let notifier = self.notifier.clone();
+        tokio::spawn(async move {
+            if let Err(e) = notifier.send(&notification).await {
+                warn!("Failed to deliver notification '{}': {}", notification.subject, e);
+            }
+        });
+    }
+}
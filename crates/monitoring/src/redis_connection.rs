@@ -0,0 +1,88 @@
+use redis::{aio::ConnectionManager, Client, RedisResult};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::warn;
+
+use app_core::enterprise::CircuitBreakerConfig;
+use app_core::error::Result;
+
+use crate::{CircuitBreaker, DependencyHealthTracker};
+
+/// Connection to Redis for one of its uses, e.g. the token blacklist or the cache.
+///
+/// The connection is opened on first use so the API can start while Redis is down.
+/// Calls go through a circuit breaker of their own, time out after `timeout`, and are
+/// recorded in dependency health under the use's name.
+#[derive(Clone)]
+pub struct GuardedRedis {
+    client: Client,
+    connection: Arc<OnceCell<ConnectionManager>>,
+    breaker: Arc<CircuitBreaker>,
+    dependency_health: Arc<DependencyHealthTracker>,
+    dependency: &'static str,
+    timeout: Duration,
+}
+
+impl GuardedRedis {
+    pub fn new(
+        url: &str,
+        dependency: &'static str,
+        timeout: Duration,
+        dependency_health: Arc<DependencyHealthTracker>,
+    ) -> Result<Self> {
+        let client = Client::open(url).map_err(|e| anyhow::anyhow!("Invalid Redis URL: {}", e))?;
+        let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig::builder().build()?));
+        dependency_health.attach_breaker(dependency, breaker.clone());
+
+        Ok(Self {
+            client,
+            connection: Default::default(),
+            breaker,
+            dependency_health,
+            dependency,
+            timeout,
+        })
+    }
+
+    /// Run `operation` on the shared connection, within the breaker and timeout.
+    /// `None` when Redis is unavailable: the circuit is open, or the call failed or
+    /// timed out.
+    pub async fn call<T, F, Fut>(&self, operation: F) -> Option<T>
+    where
+        F: FnOnce(ConnectionManager) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        if !self.breaker.allow_request().await {
+            return None;
+        }
+
+        let started = Instant::now();
+        let result = tokio::time::timeout(self.timeout, async {
+            let connection = self
+                .connection
+                .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+                .await?
+                .clone();
+            operation(connection).await
+        })
+        .await;
+
+        let healthy = matches!(result, Ok(Ok(_)));
+        self.breaker.record_outcome(healthy).await;
+        self.dependency_health.record(self.dependency, started.elapsed(), healthy);
+
+        match result {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(e)) => {
+                warn!(dependency = self.dependency, "Redis call failed: {}", e);
+                None
+            }
+            Err(_) => {
+                warn!(dependency = self.dependency, "Redis call timed out after {:?}", self.timeout);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use serde_json::Value;
+
+/// Maximum number of characters kept from any single user-controlled string
+pub const MAX_FIELD_LENGTH: usize = 1024;
+
+const TRUNCATION_MARKER: &str = "...[truncated]";
+
+/// Escape control characters and cap length so user-controlled input can't
+/// forge log lines or bloat the audit trail
+pub fn sanitize_str(input: &str) -> String {
+    let mut sanitized = String::with_capacity(input.len().min(MAX_FIELD_LENGTH));
+
+    for (count, c) in input.chars().enumerate() {
+        if count >= MAX_FIELD_LENGTH {
+            sanitized.push_str(TRUNCATION_MARKER);
+            break;
+        }
+
+        if c.is_control() {
+            sanitized.extend(c.escape_default());
+        } else {
+            sanitized.push(c);
+        }
+    }
+
+    sanitized
+}
+
+/// Recursively sanitize every string (including object keys) in a JSON value
+pub fn sanitize_json(value: Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(sanitize_str(&s)),
+        Value::Array(items) => Value::Array(items.into_iter().map(sanitize_json).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (sanitize_str(&k), sanitize_json(v)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use app_core::error::Result;
+use crate::service::MetricsService;
+
+/// Shortest period a job may run at; shorter configured intervals are raised to it
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Intervals a job may go without completing a run before it is reported unhealthy
+const STALE_AFTER_INTERVALS: u32 = 2;
+
+/// Fleet-wide mutual exclusion for singleton jobs, e.g. Postgres advisory locks
+#[async_trait]
+pub trait JobLock: Send + Sync {
+    /// Take the lock for `job` without waiting; `None` while another instance holds it
+    async fn try_lock(&self, job: &str) -> Result<Option<Box<dyn JobLockGuard>>>;
+}
+
+/// A held job lock, released after the run. Implementations must also give the lock up
+/// if the holder disappears without releasing it.
+#[async_trait]
+pub trait JobLockGuard: Send {
+    async fn release(self: Box<Self>) -> Result<()>;
+}
+
+/// Latest state of a registered job, as reported by health checks
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub name: String,
+    pub interval_secs: u64,
+    /// Critical jobs that stall make the service unhealthy
+    pub critical: bool,
+    /// Singleton jobs run on one instance at a time
+    pub singleton: bool,
+    /// Whether the job completed a run within its expected window
+    pub healthy: bool,
+    #[serde(with = "app_core::timestamp::option")]
+    pub last_run_at: Option<OffsetDateTime>,
+    pub last_duration_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub running: bool,
+}
+
+struct JobState {
+    interval: Duration,
+    critical: bool,
+    singleton: bool,
+    registered_at: Instant,
+    last_finished: Option<Instant>,
+    last_run_at: Option<OffsetDateTime>,
+    last_duration: Option<Duration>,
+    last_error: Option<String>,
+    running: bool,
+}
+
+impl JobState {
+    fn status(&self, name: &str) -> JobStatus {
+        // A job that never finishes a run (hung or crashed) goes stale like one that stopped
+        let since = self.last_finished.unwrap_or(self.registered_at);
+        let healthy = since.elapsed() <= self.interval * STALE_AFTER_INTERVALS;
+
+        JobStatus {
+            name: name.to_string(),
+            interval_secs: self.interval.as_secs(),
+            critical: self.critical,
+            singleton: self.singleton,
+            healthy,
+            last_run_at: self.last_run_at,
+            last_duration_ms: self.last_duration.map(|d| d.as_millis() as u64),
+            last_error: self.last_error.clone(),
+            running: self.running,
+        }
+    }
+}
+
+/// Background job scheduler that runs named periodic jobs with panic isolation
+pub struct Scheduler {
+    metrics: MetricsService,
+    shutdown_tx: watch::Sender<bool>,
+    jobs: Mutex<Vec<(String, JoinHandle<()>)>>,
+    states: Arc<StdMutex<BTreeMap<String, JobState>>>,
+    job_lock: Option<Arc<dyn JobLock>>,
+}
+
+#[derive(Clone, Copy)]
+struct JobOptions {
+    critical: bool,
+    singleton: bool,
+}
+
+impl Scheduler {
+    pub fn new(metrics: MetricsService) -> Self {
+        let (shutdown_tx, _) = watch::channel(false);
+
+        Self {
+            metrics,
+            shutdown_tx,
+            jobs: Mutex::new(Vec::new()),
+            states: Arc::new(StdMutex::new(BTreeMap::new())),
+            job_lock: None,
+        }
+    }
+
+    /// Coordinate singleton jobs with other instances through `lock`. Without one,
+    /// singleton jobs run like any other.
+    pub fn with_job_lock(mut self, lock: Arc<dyn JobLock>) -> Self {
+        self.job_lock = Some(lock);
+        self
+    }
+
+    /// Register a named job that runs immediately and then once per `interval` (at least a second)
+    pub async fn register<F, Fut>(&self, name: &str, interval: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_job(name, interval, JobOptions { critical: false, singleton: false }, job).await;
+    }
+
+    /// Register a job whose stalling makes the service report itself unhealthy
+    pub async fn register_critical<F, Fut>(&self, name: &str, interval: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_job(name, interval, JobOptions { critical: true, singleton: false }, job).await;
+    }
+
+    /// Register a job that runs on only one instance at a time. A tick on which another
+    /// instance holds the job's lock is skipped and counts as a completed run for health.
+    pub async fn register_singleton<F, Fut>(&self, name: &str, interval: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_job(name, interval, JobOptions { critical: false, singleton: true }, job).await;
+    }
+
+    /// Register a critical job that runs on only one instance at a time
+    pub async fn register_critical_singleton<F, Fut>(&self, name: &str, interval: Duration, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.register_job(name, interval, JobOptions { critical: true, singleton: true }, job).await;
+    }
+
+    async fn register_job<F, Fut>(&self, name: &str, interval: Duration, options: JobOptions, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        // A zero period would make `tokio::time::interval` panic
+        let interval = if interval < MIN_INTERVAL {
+            warn!("Scheduled job '{}' interval {:?} is below the minimum, using {:?}", name, interval, MIN_INTERVAL);
+            MIN_INTERVAL
+        } else {
+            interval
+        };
+
+        let job_name = name.to_string();
+        let metrics = self.metrics.clone();
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        let states = self.states.clone();
+        let job_lock = self.job_lock.clone().filter(|_| options.singleton);
+
+        states.lock().unwrap().insert(job_name.clone(), JobState {
+            interval,
+            critical: options.critical,
+            singleton: options.singleton,
+            registered_at: Instant::now(),
+            last_finished: None,
+            last_run_at: None,
+            last_duration: None,
+            last_error: None,
+            running: false,
+        });
+
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown_rx.changed() => break,
+                }
+
+                let start = Instant::now();
+                let started_at = OffsetDateTime::now_utc();
+                if let Some(state) = states.lock().unwrap().get_mut(&job_name) {
+                    state.running = true;
+                }
+
+                // Singleton jobs only run on the instance that takes the job's lock
+                let lock = match &job_lock {
+                    Some(job_lock) => match job_lock.try_lock(&job_name).await {
+                        Ok(Some(guard)) => Ok(Some(guard)),
+                        Ok(None) => Err(("skipped", None)),
+                        Err(e) => {
+                            warn!("Could not take the lock for scheduled job '{}': {}", job_name, e);
+                            Err(("failure", Some(format!("lock: {}", e))))
+                        }
+                    },
+                    None => Ok(None),
+                };
+
+                let (outcome, run_error) = match lock {
+                    Ok(guard) => {
+                        // Each run gets its own task so a panic only fails this run
+                        let result = match tokio::spawn(job()).await {
+                            Ok(Ok(())) => ("success", None),
+                            Ok(Err(e)) => {
+                                warn!("Scheduled job '{}' failed: {}", job_name, e);
+                                ("failure", Some(e.to_string()))
+                            }
+                            Err(e) => {
+                                error!("Scheduled job '{}' panicked: {}", job_name, e);
+                                ("panic", Some(format!("panicked: {}", e)))
+                            }
+                        };
+                        if let Some(guard) = guard {
+                            if let Err(e) = guard.release().await {
+                                warn!("Could not release the lock for scheduled job '{}': {}", job_name, e);
+                            }
+                        }
+                        result
+                    }
+                    Err(skipped) => skipped,
+                };
+
+                if let Some(state) = states.lock().unwrap().get_mut(&job_name) {
+                    state.running = false;
+                    state.last_finished = Some(Instant::now());
+                    // Another instance ran it; this one's last run is unchanged
+                    if outcome != "skipped" {
+                        state.last_run_at = Some(started_at);
+                        state.last_duration = Some(start.elapsed());
+                        state.last_error = run_error;
+                    }
+                }
+
+                metrics.increment_counter(
+                    "scheduler_job_runs_total",
+                    &[("job", &job_name), ("outcome", outcome)],
+                );
+                if outcome != "skipped" {
+                    metrics.record_histogram(
+                        "scheduler_job_duration_milliseconds",
+                        start.elapsed().as_millis() as f64,
+                        &[("job", &job_name)],
+                    );
+                }
+            }
+
+            info!("Scheduled job '{}' stopped", job_name);
+        });
+
+        info!("Registered scheduled job '{}' every {:?}", name, interval);
+        self.jobs.lock().await.push((name.to_string(), handle));
+    }
+
+    /// Names of all registered jobs
+    pub async fn job_names(&self) -> Vec<String> {
+        self.jobs.lock().await.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Last-run details of every registered job, ordered by name
+    pub fn job_statuses(&self) -> Vec<JobStatus> {
+        self.states
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, state)| state.status(name))
+            .collect()
+    }
+
+    /// Signal all jobs to stop and wait for in-flight runs to finish
+    pub async fn shutdown(&self) {
+        let _ = self.shutdown_tx.send(true);
+
+        let jobs: Vec<_> = self.jobs.lock().await.drain(..).collect();
+        for (name, handle) in jobs {
+            if let Err(e) = handle.await {
+                error!("Scheduled job '{}' did not shut down cleanly: {}", name, e);
+            }
+        }
+
+        info!("Scheduler drained");
+    }
+}
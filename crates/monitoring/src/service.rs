@@ -1,4 +1,4 @@
-use metrics::{counter, histogram, Counter, Histogram};
+use metrics::{counter, gauge, histogram, Counter, Histogram};
 use metrics_exporter_prometheus::PrometheusBuilder;
 use std::collections::HashMap;
 use tracing::{error, info, instrument};
@@ -51,6 +51,15 @@ impl MetricsService {
         histogram!(name, labels).record(value);
     }
 
+    #[instrument(skip(self))]
+    pub fn set_gauge(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+        let labels: Vec<(String, String)> = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        gauge!(name.to_string(), &labels).set(value);
+    }
+
     #[instrument(skip(self))]
     pub async fn export_metrics(&self) -> Result<String> {
         // In a real implementation, you might want to return the current metrics
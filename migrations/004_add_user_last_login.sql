@@ -0,0 +1,4 @@
+-- Track last successful login for account inactivity policies
+ALTER TABLE users ADD COLUMN last_login_at TIMESTAMPTZ;
+
+CREATE INDEX idx_users_last_login_at ON users(last_login_at);
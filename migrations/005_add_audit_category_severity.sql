@@ -0,0 +1,11 @@
+-- Classify audit entries so security-relevant events can be filtered from routine ones
+CREATE TYPE audit_category AS ENUM ('security', 'admin', 'data_change', 'access', 'system');
+
+-- Declaration order doubles as severity order for range filters
+CREATE TYPE audit_severity AS ENUM ('info', 'warning', 'critical');
+
+ALTER TABLE audit_logs
+    ADD COLUMN category audit_category NOT NULL DEFAULT 'access',
+    ADD COLUMN severity audit_severity NOT NULL DEFAULT 'info';
+
+CREATE INDEX IF NOT EXISTS idx_audit_logs_category_severity ON audit_logs(category, severity, created_at);
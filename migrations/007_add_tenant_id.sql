@@ -0,0 +1,6 @@
+-- Multi-tenancy: rows with a NULL tenant_id belong to single-tenant deployments
+ALTER TABLE users ADD COLUMN tenant_id UUID;
+ALTER TABLE products ADD COLUMN tenant_id UUID;
+
+CREATE INDEX IF NOT EXISTS idx_users_tenant_id ON users(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_products_tenant_id ON products(tenant_id);
@@ -0,0 +1,13 @@
+-- Pending email changes; the primary email only switches once the new address is verified
+CREATE TABLE IF NOT EXISTS email_change_requests (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    new_email VARCHAR(255) NOT NULL,
+    -- SHA-256 of the emailed token; the token itself is never stored
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- At most one pending change per user
+CREATE UNIQUE INDEX IF NOT EXISTS idx_email_change_requests_user_id ON email_change_requests(user_id);
@@ -0,0 +1,13 @@
+-- Sampled feature flag evaluations, recorded only when evaluation logging is enabled
+-- and deleted once past the configured retention
+CREATE TABLE IF NOT EXISTS flag_evaluations (
+    id UUID PRIMARY KEY,
+    flag_name VARCHAR(255) NOT NULL,
+    -- The id flags were evaluated for, as passed to the flag service
+    user_id VARCHAR(255) NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    evaluated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_flag_evaluations_user_time ON flag_evaluations(user_id, evaluated_at);
+CREATE INDEX IF NOT EXISTS idx_flag_evaluations_evaluated_at ON flag_evaluations(evaluated_at);
@@ -0,0 +1,2 @@
+-- Named, weighted variants of multivariate flags, persisted by the database flag backend
+ALTER TABLE feature_flags ADD COLUMN IF NOT EXISTS variants JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,17 @@
+-- Stored emails predate normalization and may be mixed case, while new writes and lookups
+-- are lowercased. Where several accounts' addresses differ only in case, one takes the
+-- lowercase form: one already lowercase, then a live account, then the most recent login,
+-- then the oldest. The others keep their stored address until an admin changes it.
+WITH ranked AS (
+    SELECT
+        id,
+        ROW_NUMBER() OVER (
+            PARTITION BY lower(email)
+            ORDER BY (email = lower(email)) DESC, (deleted_at IS NULL) DESC, last_login_at DESC NULLS LAST, created_at
+        ) AS position
+    FROM users
+)
+UPDATE users
+SET email = lower(users.email)
+FROM ranked
+WHERE users.id = ranked.id AND ranked.position = 1 AND users.email <> lower(users.email);
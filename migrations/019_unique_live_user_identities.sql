@@ -0,0 +1,7 @@
+-- Soft-deleted users no longer hold on to their email and username: uniqueness applies
+-- only among live rows, matching the conflict checks, which ignore deleted users
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_username_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_live_key ON users(email) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS users_username_live_key ON users(username) WHERE deleted_at IS NULL;
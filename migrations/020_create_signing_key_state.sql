@@ -0,0 +1,9 @@
+-- The JWT signing key every instance should sign with, so a key promoted on one instance
+-- is picked up by the rest. Holds at most one row.
+CREATE TABLE IF NOT EXISTS signing_key_state (
+    singleton BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (singleton),
+    primary_key_id TEXT NOT NULL,
+    retiring_key_id TEXT NOT NULL,
+    retires_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
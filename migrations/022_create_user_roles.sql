@@ -0,0 +1,8 @@
+-- Roles granted to users on top of `user`, which every account has. Tokens carry them,
+-- so a change applies from the user's next login or token refresh.
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role)
+);